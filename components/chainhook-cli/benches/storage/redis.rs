@@ -7,8 +7,11 @@ fn criterion_benchmark(c: &mut Criterion) {
     let config = IndexerConfig {
         stacks_node_rpc_url: "http://0.0.0.0:20443".into(),
         bitcoind_rpc_url: "http://0.0.0.0:18443".into(),
+        bitcoind_fallback_rpc_urls: vec![],
+        bitcoind_rpc_rate_limits: Default::default(),
         bitcoind_rpc_username: "devnet".into(),
         bitcoind_rpc_password: "devnet".into(),    
+        bitcoind_rest_enabled: false,
     };
     c.bench_function("redis", |b| b.iter(|| start_ingesting("/Users/ludovic/Downloads/stacks-blockchain-api.tsv".into(), config.clone()).unwrap()));
 }
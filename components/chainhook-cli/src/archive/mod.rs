@@ -1,12 +1,17 @@
 use crate::config::Config;
+use chainhook_event_observer::bitcoincore_rpc::bitcoin::hashes::{sha256, Hash, HashEngine};
+use chainhook_event_observer::hord::db::import_hord_db_snapshot;
 use chainhook_event_observer::utils::Context;
 use chainhook_types::{BitcoinNetwork, StacksNetwork};
 use clarinet_files::FileLocation;
 use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use futures_util::StreamExt;
 use std::fs;
 use std::io::{self, Cursor};
 use std::io::{Read, Write};
+use std::path::PathBuf;
 
 pub fn default_tsv_file_path(network: &StacksNetwork) -> String {
     format!("{:?}-stacks-events.tsv", network).to_lowercase()
@@ -146,6 +151,170 @@ pub async fn download_sqlite_file(config: &Config) -> Result<(), String> {
     Ok(())
 }
 
+fn sha256_of_file(path: &PathBuf) -> Result<String, String> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| format!("unable to open {}: {}", path.display(), e.to_string()))?;
+    let mut engine = sha256::Hash::engine();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e.to_string()))?;
+        if read == 0 {
+            break;
+        }
+        engine.input(&buffer[..read]);
+    }
+    Ok(sha256::Hash::from_engine(engine).to_string())
+}
+
+/// Downloads `file_url` to `destination`, resuming from the bytes already present locally
+/// when the server supports range requests.
+async fn download_file_with_resume(file_url: &str, destination: &PathBuf) -> Result<(), String> {
+    let downloaded = fs::metadata(destination).map(|md| md.len()).unwrap_or(0);
+    let mut request = reqwest::Client::new().get(file_url);
+    if downloaded > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", downloaded));
+    }
+    let res = request
+        .send()
+        .await
+        .or(Err(format!("Failed to GET from '{}'", &file_url)))?;
+    let mut file = match res.status() {
+        reqwest::StatusCode::PARTIAL_CONTENT => {
+            fs::OpenOptions::new().append(true).open(destination)
+        }
+        reqwest::StatusCode::OK => fs::File::create(destination),
+        // The previous attempt was interrupted after the last chunk
+        reqwest::StatusCode::RANGE_NOT_SATISFIABLE if downloaded > 0 => return Ok(()),
+        status => return Err(format!("Failed to GET from '{}': {}", &file_url, status)),
+    }
+    .map_err(|e| {
+        format!(
+            "unable to open {}: {}",
+            destination.display(),
+            e.to_string()
+        )
+    })?;
+
+    let mut stream = res.bytes_stream();
+    while let Some(item) = stream.next().await {
+        let chunk = item.or(Err(format!("Error while downloading file")))?;
+        file.write_all(&chunk)
+            .map_err(|e| format!("unable to write file: {}", e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Seeds an empty cache with the hord db snapshot configured with `hord_snapshot_url`,
+/// verified against the sha256 published next to it. Interrupted downloads are resumed.
+pub async fn download_hord_snapshot_if_required(
+    config: &Config,
+    ctx: &Context,
+) -> Result<(), String> {
+    let file_url = match config.remote_hord_snapshot_url() {
+        Some(file_url) => file_url.clone(),
+        None => return Ok(()),
+    };
+    let cache_path = config.expected_cache_path();
+    if cache_path.join("hord.sqlite").exists() || cache_path.join("hord.rocksdb").exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(&cache_path).map_err(|e| {
+        format!(
+            "unable to create {}: {}",
+            cache_path.display(),
+            e.to_string()
+        )
+    })?;
+
+    let sha_url = config.expected_remote_hord_snapshot_sha256().unwrap();
+    let res = reqwest::get(&sha_url)
+        .await
+        .or(Err(format!("Failed to GET from '{}'", &sha_url)))?
+        .text()
+        .await
+        .or(Err(format!("Failed to GET from '{}'", &sha_url)))?;
+    // `sha256sum` format: the hash, optionally followed by the file name
+    let expected_sha256 = res
+        .split_whitespace()
+        .next()
+        .map(|sha| sha.to_lowercase())
+        .ok_or(format!("Empty checksum at '{}'", &sha_url))?;
+
+    let archive_path = cache_path.join("hord.snapshot.tar.gz");
+    info!(ctx.expect_logger(), "Downloading {}", file_url);
+    download_file_with_resume(&file_url, &archive_path).await?;
+    let sha256 = sha256_of_file(&archive_path)?;
+    if sha256 != expected_sha256 {
+        let _ = fs::remove_file(&archive_path);
+        return Err(format!(
+            "checksum mismatch for {} (expected {}, got {})",
+            file_url, expected_sha256, sha256
+        ));
+    }
+
+    let snapshot_dir = cache_path.join("hord.snapshot.tmp");
+    let _ = fs::remove_dir_all(&snapshot_dir);
+    let res = unpack_archive(&archive_path, &snapshot_dir).and_then(|_| {
+        import_hord_db_snapshot(&snapshot_dir, &cache_path, ctx).map_err(|e| e.to_string())
+    });
+    let _ = fs::remove_dir_all(&snapshot_dir);
+    let manifest = res?;
+    let _ = fs::remove_file(&archive_path);
+    info!(
+        ctx.expect_logger(),
+        "hord_db seeded from {} (last block inserted: #{})", file_url, manifest.last_block_inserted
+    );
+    Ok(())
+}
+
+/// Packs the content of `source` in a gzipped tarball.
+pub fn pack_directory(source: &PathBuf, archive_path: &PathBuf) -> Result<(), String> {
+    let file = fs::File::create(archive_path).map_err(|e| {
+        format!(
+            "unable to create {}: {}",
+            archive_path.display(),
+            e.to_string()
+        )
+    })?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder
+        .append_dir_all(".", source)
+        .map_err(|e| format!("unable to pack {}: {}", source.display(), e.to_string()))?;
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(|e| {
+            format!(
+                "unable to write {}: {}",
+                archive_path.display(),
+                e.to_string()
+            )
+        })?;
+    Ok(())
+}
+
+/// Unpacks a tarball produced by `pack_directory` in `destination`.
+pub fn unpack_archive(archive_path: &PathBuf, destination: &PathBuf) -> Result<(), String> {
+    let file = fs::File::open(archive_path).map_err(|e| {
+        format!(
+            "unable to open {}: {}",
+            archive_path.display(),
+            e.to_string()
+        )
+    })?;
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(destination)
+        .map_err(|e| {
+            format!(
+                "unable to unpack {}: {}",
+                archive_path.display(),
+                e.to_string()
+            )
+        })
+}
+
 // Wrap a channel into something that impls `io::Read`
 struct ChannelRead {
     rx: flume::Receiver<Vec<u8>>,
@@ -1,3 +1,4 @@
+use crate::archive::{download_hord_snapshot_if_required, pack_directory, unpack_archive};
 use crate::block::DigestingCommand;
 use crate::config::generator::generate_config;
 use crate::config::Config;
@@ -13,11 +14,13 @@ use chainhook_event_observer::chainhooks::types::{
     StacksPrintEventBasedPredicate,
 };
 use chainhook_event_observer::hord::db::{
-    delete_data_in_hord_db, fetch_and_cache_blocks_in_hord_db, find_block_at_block_height,
-    find_last_block_inserted, find_watched_satpoint_for_inscription, initialize_hord_db,
-    insert_entry_in_blocks, open_readonly_hord_db_conn, open_readonly_hord_db_conn_rocks_db,
-    open_readwrite_hord_db_conn, open_readwrite_hord_db_conn_rocks_db,
-    retrieve_satoshi_point_using_lazy_storage, LazyBlock,
+    apply_block_pruning_policy, check_hord_db_integrity, compact_blocks_db, delete_data_in_hord_db,
+    export_hord_db_snapshot, fetch_and_cache_blocks_in_hord_db, find_block_at_block_height,
+    find_genesis_satpoint, find_last_block_inserted, find_watched_satpoint_for_inscription,
+    import_hord_db_snapshot, initialize_hord_db, insert_entry_in_blocks, open_inscriptions_replica,
+    open_readonly_hord_db_conn, open_readonly_hord_db_conn_rocks_db, open_readwrite_hord_db_conn,
+    open_readwrite_hord_db_conn_rocks_db_with_options, repair_hord_db,
+    retrieve_satoshi_point_using_lazy_storage, verify_hord_db, LazyBlock, TXID_LEN,
 };
 use chainhook_event_observer::hord::{
     new_traversals_lazy_cache, retrieve_inscribed_satoshi_points_from_block,
@@ -25,20 +28,22 @@ use chainhook_event_observer::hord::{
 };
 use chainhook_event_observer::indexer;
 use chainhook_event_observer::indexer::bitcoin::{
-    download_and_parse_block_with_retry, retrieve_block_hash_with_retry,
+    download_and_parse_block_with_retry, retrieve_block_hash_with_retry, BitcoinRpcEndpoints,
 };
 use chainhook_event_observer::observer::BitcoinConfig;
 use chainhook_event_observer::utils::Context;
 use chainhook_types::{
     BitcoinBlockData, BitcoinNetwork, BlockIdentifier, StacksNetwork, TransactionIdentifier,
 };
-use clap::{Parser, Subcommand};
+use clap::{ArgEnum, Parser, Subcommand};
 use ctrlc;
 use hiro_system_kit;
+use hiro_system_kit::slog::{self, Drain, Logger};
 use std::collections::BTreeMap;
 use std::io::{BufReader, Read};
 use std::path::PathBuf;
 use std::process;
+use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 
@@ -47,6 +52,15 @@ use std::sync::Arc;
 struct Opts {
     #[clap(subcommand)]
     command: Command,
+    /// Format of the logs: `text`, or `json` (one object per line, see `Context`)
+    #[clap(long = "log-format", arg_enum, global = true, default_value = "text")]
+    log_format: LogFormat,
+}
+
+#[derive(ArgEnum, PartialEq, Clone, Debug)]
+enum LogFormat {
+    Text,
+    Json,
 }
 
 #[derive(Subcommand, PartialEq, Clone, Debug)]
@@ -213,12 +227,46 @@ enum DbCommand {
     /// Check integrity
     #[clap(name = "check", bin_name = "check")]
     Check(CheckHordDbCommand),
+    /// Verify blocks and inscriptions, optionally repairing corrupted blocks
+    #[clap(name = "verify", bin_name = "verify")]
+    Verify(VerifyHordDbCommand),
     /// Patch DB
     #[clap(name = "patch", bin_name = "patch")]
     Patch(PatchHordDbCommand),
     /// Migrate
     #[clap(name = "migrate", bin_name = "migrate")]
     Migrate(MigrateHordDbCommand),
+    /// Export / import snapshots of hord db
+    #[clap(subcommand)]
+    Snapshot(SnapshotHordDbCommand),
+}
+
+#[derive(Subcommand, PartialEq, Clone, Debug)]
+enum SnapshotHordDbCommand {
+    /// Export hord db in a single archive
+    #[clap(name = "export", bin_name = "export")]
+    Export(ExportSnapshotCommand),
+    /// Import an archive produced by `hord db snapshot export`
+    #[clap(name = "import", bin_name = "import")]
+    Import(ImportSnapshotCommand),
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct ExportSnapshotCommand {
+    /// Path of the archive (.tar.gz) to create
+    pub archive_path: String,
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: Option<String>,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct ImportSnapshotCommand {
+    /// Path of the archive (.tar.gz) to import
+    pub archive_path: String,
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: Option<String>,
 }
 
 #[derive(Subcommand, PartialEq, Clone, Debug)]
@@ -359,6 +407,19 @@ struct CheckHordDbCommand {
     pub config_path: Option<String>,
 }
 
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct VerifyHordDbCommand {
+    /// Number of stored blocks to compare with bitcoind
+    #[clap(long = "sample", default_value = "100")]
+    pub sample_size: usize,
+    /// Re-download missing, corrupted and mismatching blocks
+    #[clap(long = "repair")]
+    pub repair: bool,
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: Option<String>,
+}
+
 #[derive(Parser, PartialEq, Clone, Debug)]
 struct InitHordDbCommand {
     /// Load config file path
@@ -369,13 +430,6 @@ struct InitHordDbCommand {
 }
 
 pub fn main() {
-    let logger = hiro_system_kit::log::setup_logger();
-    let _guard = hiro_system_kit::log::setup_global_logger(logger.clone());
-    let ctx = Context {
-        logger: Some(logger),
-        tracer: false,
-    };
-
     let opts: Opts = match Opts::try_parse() {
         Ok(opts) => opts,
         Err(e) => {
@@ -384,7 +438,21 @@ pub fn main() {
         }
     };
 
-    match hiro_system_kit::nestable_block_on(handle_command(opts, ctx)) {
+    let logger = match opts.log_format {
+        LogFormat::Text => hiro_system_kit::log::setup_logger(),
+        LogFormat::Json => setup_json_logger(),
+    };
+    let _guard = hiro_system_kit::log::setup_global_logger(logger.clone());
+    let ctx = Context {
+        logger: Some(logger),
+        tracer: false,
+    };
+
+    let res = hiro_system_kit::nestable_block_on(handle_command(opts, ctx));
+    // Flushes the spans pending export
+    #[cfg(feature = "otel")]
+    opentelemetry::global::shutdown_tracer_provider();
+    match res {
         Err(e) => {
             println!("{e}");
             process::exit(1);
@@ -393,7 +461,36 @@ pub fn main() {
     }
 }
 
+/// Logs a JSON object per record on stdout: `ts`, `level` and `msg`, followed by the
+/// structured fields of the record.
+fn setup_json_logger() -> Logger {
+    let drain = slog_json::Json::new(std::io::stdout())
+        .add_default_keys()
+        .build()
+        .fuse();
+    let drain = slog_async::Async::new(drain).build().fuse();
+    Logger::root(drain, slog::o!())
+}
+
+/// Exports the spans of the indexing pipeline over OTLP, to the collector configured with
+/// the standard `OTEL_EXPORTER_OTLP_ENDPOINT` environment variable (`http://localhost:4317`
+/// by default). Installed from the runtime running the command, the export being batched
+/// on it.
+#[cfg(feature = "otel")]
+fn install_otel_tracer() -> Result<(), String> {
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic())
+        .install_batch(opentelemetry::runtime::Tokio)
+        .map(|_| ())
+        .map_err(|e| format!("unable to install OpenTelemetry tracer: {e}"))
+}
+
 async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
+    #[cfg(feature = "otel")]
+    if let Err(e) = install_otel_tracer() {
+        warn!(ctx.expect_logger(), "{e}");
+    }
     match opts.command {
         Command::Service(subcmd) => match subcmd {
             ServiceCommand::Start(cmd) => {
@@ -417,6 +514,8 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                         "Ordinal indexing is enabled by default hord, checking index... (use --no-hord to disable ordinals)"
                     );
 
+                    download_hord_snapshot_if_required(&config, &ctx).await?;
+
                     if let Some((start_block, end_block)) = should_sync_hord_db(&config, &ctx)? {
                         if start_block == 0 {
                             info!(
@@ -509,7 +608,7 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                                 start_block: Some(0),
                                 end_block: Some(100),
                                 predicate: BitcoinPredicateType::OrdinalsProtocol(
-                                    OrdinalOperations::InscriptionFeed,
+                                    OrdinalOperations::InscriptionFeed { rarity: None },
                                 ),
                                 expire_after_occurrence: None,
                                 action: HookAction::FileAppend(FileHook {
@@ -620,8 +719,7 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                     Config::default(cmd.devnet, cmd.testnet, cmd.mainnet, &cmd.config_path)?;
 
                 let hord_db_conn =
-                    open_readonly_hord_db_conn_rocks_db(&config.expected_cache_path(), &ctx)
-                        .unwrap();
+                    open_readonly_hord_db_conn_rocks_db(&config.expected_cache_path(), &ctx)?;
 
                 let tip_height = find_last_block_inserted(&hord_db_conn) as u64;
                 if cmd.block_height > tip_height {
@@ -643,9 +741,13 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                             &block_identifier,
                             &transaction_identifier,
                             0,
+                            0,
                             Arc::new(traversals_cache),
+                            None,
+                            None,
                             &ctx,
-                        )?;
+                        )
+                        .map_err(|e| e.to_string())?;
                         info!(
                             ctx.expect_logger(),
                             "Satoshi #{} was minted in block #{} at offset {} and was transferred {} times.",
@@ -664,6 +766,7 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                             &block,
                             None,
                             &config.expected_cache_path(),
+                            Some(&bitcoin_config),
                             &traversals_cache,
                             &ctx,
                         );
@@ -680,10 +783,15 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                     Config::default(cmd.devnet, cmd.testnet, cmd.mainnet, &cmd.config_path)?;
 
                 let inscriptions_db_conn =
-                    open_readonly_hord_db_conn(&config.expected_cache_path(), &ctx)?;
+                    open_readonly_hord_db_conn(&config.expected_cache_path(), &ctx)
+                        .map_err(|e| e.to_string())?;
 
-                let blocks_db_conn =
-                    open_readwrite_hord_db_conn_rocks_db(&config.expected_cache_path(), &ctx)?;
+                let blocks_db_conn = open_readwrite_hord_db_conn_rocks_db_with_options(
+                    &config.expected_cache_path(),
+                    &config.hord_db_options(false),
+                    &ctx,
+                )
+                .map_err(|e| e.to_string())?;
 
                 let tip_height = find_last_block_inserted(&blocks_db_conn) as u64;
                 let end_at = match cmd.block_height {
@@ -694,15 +802,18 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                     _ => tip_height,
                 };
 
-                let (start_at_height, watched_satpoint) = find_watched_satpoint_for_inscription(
-                    &cmd.inscription_id,
-                    &inscriptions_db_conn,
-                )?;
+                let (start_at_height, mut watched_satpoint) =
+                    find_watched_satpoint_for_inscription(
+                        &cmd.inscription_id,
+                        &inscriptions_db_conn,
+                    )
+                    .map_err(|e| e.to_string())?;
+                let genesis_satpoint =
+                    find_genesis_satpoint(&cmd.inscription_id, &inscriptions_db_conn)
+                        .map_err(|e| e.to_string())?;
+                watched_satpoint.offset = genesis_satpoint.offset;
                 let mut cache = BTreeMap::new();
-                cache.insert(
-                    watched_satpoint.get_genesis_satpoint(),
-                    vec![watched_satpoint],
-                );
+                cache.insert(genesis_satpoint.outpoint(), vec![watched_satpoint]);
                 let mut storage = Storage::Memory(cache);
 
                 let mut seq = vec![
@@ -767,10 +878,15 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                 let config = Config::default(false, false, false, &cmd.config_path)?;
                 // Delete data, if any
                 {
-                    let blocks_db_rw =
-                        open_readwrite_hord_db_conn_rocks_db(&config.expected_cache_path(), &ctx)?;
+                    let blocks_db_rw = open_readwrite_hord_db_conn_rocks_db_with_options(
+                        &config.expected_cache_path(),
+                        &config.hord_db_options(false),
+                        &ctx,
+                    )
+                    .map_err(|e| e.to_string())?;
                     let inscriptions_db_conn_rw =
-                        open_readwrite_hord_db_conn(&config.expected_cache_path(), &ctx)?;
+                        open_readwrite_hord_db_conn(&config.expected_cache_path(), &ctx)
+                            .map_err(|e| e.to_string())?;
 
                     delete_data_in_hord_db(
                         cmd.start_block,
@@ -778,7 +894,8 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                         &blocks_db_rw,
                         &inscriptions_db_conn_rw,
                         &ctx,
-                    )?;
+                    )
+                    .map_err(|e| e.to_string())?;
                 }
                 // Update data
                 perform_hord_db_update(
@@ -792,27 +909,106 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
             }
             DbCommand::Check(cmd) => {
                 let config = Config::default(false, false, false, &cmd.config_path)?;
-                // Delete data, if any
+                let blocks_db =
+                    open_readonly_hord_db_conn_rocks_db(&config.expected_cache_path(), &ctx)?;
+                let inscriptions_db_conn =
+                    open_readonly_hord_db_conn(&config.expected_cache_path(), &ctx)
+                        .map_err(|e| e.to_string())?;
+
+                let report = check_hord_db_integrity(&blocks_db, &inscriptions_db_conn, &ctx)
+                    .map_err(|e| e.to_string())?;
+                for block_height in report.missing_blocks.iter() {
+                    println!("Missing block #{block_height}");
+                }
+                for (inscription_id, block_height) in report.orphaned_inscriptions.iter() {
+                    println!(
+                        "Inscription {inscription_id} revealed in missing block #{block_height}"
+                    );
+                }
+                match report.first_gap() {
+                    Some(block_height) => println!(
+                        "hord_db inconsistent from block #{block_height} (last block inserted: #{})",
+                        report.last_block_inserted
+                    ),
+                    None => println!(
+                        "hord_db consistent (last block inserted: #{})",
+                        report.last_block_inserted
+                    ),
+                }
+            }
+            DbCommand::Verify(cmd) => {
+                let config = Config::default(false, false, false, &cmd.config_path)?;
+                let bitcoin_config = config.get_event_observer_config().get_bitcoin_config();
+                let blocks_db = if cmd.repair {
+                    open_readwrite_hord_db_conn_rocks_db_with_options(
+                        &config.expected_cache_path(),
+                        &config.hord_db_options(false),
+                        &ctx,
+                    )
+                    .map_err(|e| e.to_string())?
+                } else {
+                    open_readonly_hord_db_conn_rocks_db(&config.expected_cache_path(), &ctx)?
+                };
+                let inscriptions_db_conn =
+                    open_readonly_hord_db_conn(&config.expected_cache_path(), &ctx)
+                        .map_err(|e| e.to_string())?;
+
+                let report = verify_hord_db(
+                    &blocks_db,
+                    &inscriptions_db_conn,
+                    Some(&bitcoin_config),
+                    cmd.sample_size,
+                    &ctx,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+                for block_height in report.integrity.missing_blocks.iter() {
+                    println!("Missing block #{block_height}");
+                }
+                for (block_height, e) in report.corrupted_blocks.iter() {
+                    println!("Corrupted block #{block_height}: {e}");
+                }
+                for block_height in report.mismatching_blocks.iter() {
+                    println!("Block #{block_height} does not match bitcoind");
+                }
+                for (inscription_id, block_height) in report.integrity.orphaned_inscriptions.iter()
                 {
-                    let blocks_db_rw =
-                        open_readwrite_hord_db_conn_rocks_db(&config.expected_cache_path(), &ctx)?;
-
-                    let mut missing_blocks = vec![];
-                    for i in 1..=780000 {
-                        if find_block_at_block_height(i, 3, &blocks_db_rw).is_none() {
-                            println!("Missing block {i}");
-                            missing_blocks.push(i);
-                        }
-                    }
-                    println!("{:?}", missing_blocks);
+                    println!(
+                        "Inscription {inscription_id} revealed in missing block #{block_height}"
+                    );
+                }
+                if report.is_valid() {
+                    println!(
+                        "hord_db valid ({} blocks sampled, last block inserted: #{})",
+                        report.sampled_blocks.len(),
+                        report.integrity.last_block_inserted
+                    );
+                } else if cmd.repair {
+                    let repaired = repair_hord_db(&report, &blocks_db, &bitcoin_config, &ctx)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    info!(
+                        ctx.expect_logger(),
+                        "Repairing hord_db: {repaired} blocks re-downloaded"
+                    );
+                } else {
+                    println!(
+                        "hord_db invalid: {} blocks to repair (use --repair)",
+                        report.blocks_to_repair().len()
+                    );
                 }
             }
             DbCommand::Drop(cmd) => {
                 let config = Config::default(false, false, false, &cmd.config_path)?;
-                let blocks_db =
-                    open_readwrite_hord_db_conn_rocks_db(&config.expected_cache_path(), &ctx)?;
+                let blocks_db = open_readwrite_hord_db_conn_rocks_db_with_options(
+                    &config.expected_cache_path(),
+                    &config.hord_db_options(false),
+                    &ctx,
+                )
+                .map_err(|e| e.to_string())?;
                 let inscriptions_db_conn_rw =
-                    open_readwrite_hord_db_conn(&config.expected_cache_path(), &ctx)?;
+                    open_readwrite_hord_db_conn(&config.expected_cache_path(), &ctx)
+                        .map_err(|e| e.to_string())?;
 
                 delete_data_in_hord_db(
                     cmd.start_block,
@@ -820,7 +1016,8 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                     &blocks_db,
                     &inscriptions_db_conn_rw,
                     &ctx,
-                )?;
+                )
+                .map_err(|e| e.to_string())?;
                 info!(
                     ctx.expect_logger(),
                     "Cleaning hord_db: {} blocks dropped",
@@ -830,11 +1027,63 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
             DbCommand::Patch(cmd) => {
                 unimplemented!()
             }
+            DbCommand::Snapshot(SnapshotHordDbCommand::Export(cmd)) => {
+                let config = Config::default(false, false, false, &cmd.config_path)?;
+                let blocks_db = open_readwrite_hord_db_conn_rocks_db_with_options(
+                    &config.expected_cache_path(),
+                    &config.hord_db_options(false),
+                    &ctx,
+                )
+                .map_err(|e| e.to_string())?;
+                let inscriptions_db_conn =
+                    open_readonly_hord_db_conn(&config.expected_cache_path(), &ctx)
+                        .map_err(|e| e.to_string())?;
+
+                let archive_path = PathBuf::from(&cmd.archive_path);
+                let snapshot_dir = PathBuf::from(format!("{}.tmp", cmd.archive_path));
+                let manifest =
+                    export_hord_db_snapshot(&blocks_db, &inscriptions_db_conn, &snapshot_dir, &ctx)
+                        .map_err(|e| e.to_string())?;
+                let res = pack_directory(&snapshot_dir, &archive_path);
+                let _ = std::fs::remove_dir_all(&snapshot_dir);
+                res?;
+                info!(
+                    ctx.expect_logger(),
+                    "hord_db snapshot written to {} (last block inserted: #{}, {} inscriptions)",
+                    archive_path.display(),
+                    manifest.last_block_inserted,
+                    manifest.inscriptions_count
+                );
+            }
+            DbCommand::Snapshot(SnapshotHordDbCommand::Import(cmd)) => {
+                let config = Config::default(false, false, false, &cmd.config_path)?;
+                let archive_path = PathBuf::from(&cmd.archive_path);
+                let mut snapshot_dir = config.expected_cache_path();
+                snapshot_dir.push("hord.snapshot.tmp");
+                let _ = std::fs::remove_dir_all(&snapshot_dir);
+
+                let res = unpack_archive(&archive_path, &snapshot_dir).and_then(|_| {
+                    import_hord_db_snapshot(&snapshot_dir, &config.expected_cache_path(), &ctx)
+                        .map_err(|e| e.to_string())
+                });
+                let _ = std::fs::remove_dir_all(&snapshot_dir);
+                let manifest = res?;
+                info!(
+                    ctx.expect_logger(),
+                    "hord_db snapshot imported (last block inserted: #{}, {} inscriptions)",
+                    manifest.last_block_inserted,
+                    manifest.inscriptions_count
+                );
+            }
             DbCommand::Migrate(cmd) => {
                 let config = Config::default(false, false, false, &cmd.config_path)?;
 
-                let blocks_db_rw =
-                    open_readwrite_hord_db_conn_rocks_db(&config.expected_cache_path(), &ctx)?;
+                let blocks_db_rw = open_readwrite_hord_db_conn_rocks_db_with_options(
+                    &config.expected_cache_path(),
+                    &config.hord_db_options(false),
+                    &ctx,
+                )
+                .map_err(|e| e.to_string())?;
 
                 let tip = find_last_block_inserted(&blocks_db_rw);
 
@@ -845,7 +1094,7 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                             block
                                 .serialize_to_lazy_format(&mut bytes)
                                 .expect("unable to convert to lazy block");
-                            let lazy_block = LazyBlock::new(bytes);
+                            let lazy_block = LazyBlock::from_body(TXID_LEN, bytes);
                             insert_entry_in_blocks(i, &lazy_block, &blocks_db_rw, &ctx);
                             println!("Block #{} migrated to lazy block", i);
                         }
@@ -883,7 +1132,7 @@ pub fn should_sync_hord_db(config: &Config, ctx: &Context) -> Result<Option<(u64
     };
 
     if start_block == 0 {
-        let _ = initialize_hord_db(&config.expected_cache_path(), &ctx);
+        initialize_hord_db(&config.expected_cache_path(), &ctx).map_err(|e| e.to_string())?;
     }
 
     let end_block = match bitcoin_rpc.get_blockchain_info() {
@@ -919,24 +1168,50 @@ pub async fn perform_hord_db_update(
         username: config.network.bitcoind_rpc_username.clone(),
         password: config.network.bitcoind_rpc_password.clone(),
         rpc_url: config.network.bitcoind_rpc_url.clone(),
+        rpc_endpoints: BitcoinRpcEndpoints::new(
+            &config.network.bitcoind_rpc_url,
+            &config.network.bitcoind_fallback_rpc_urls,
+            &config.network.bitcoind_rpc_rate_limits,
+        ),
         network: config.network.bitcoin_network.clone(),
         bitcoin_block_signaling: config.network.bitcoin_block_signaling.clone(),
     };
 
-    let blocks_db = open_readwrite_hord_db_conn_rocks_db(&config.expected_cache_path(), &ctx)?;
-    let inscriptions_db_conn_rw = open_readwrite_hord_db_conn(&config.expected_cache_path(), &ctx)?;
-
+    let hord_db_options = config.hord_db_options(start_block == 0);
+    let blocks_db = open_readwrite_hord_db_conn_rocks_db_with_options(
+        &config.expected_cache_path(),
+        &hord_db_options,
+        &ctx,
+    )
+    .map_err(|e| e.to_string())?;
+    let inscriptions_db_conn_rw = open_readwrite_hord_db_conn(&config.expected_cache_path(), &ctx)
+        .map_err(|e| e.to_string())?;
+    let inscriptions_replica =
+        open_inscriptions_replica(config.storage.inscriptions_postgres_url.as_deref())
+            .map_err(|e| e.to_string())?;
+
+    let ordinal_computing_height = config.storage.hord_ordinal_computing_height;
     let _ = fetch_and_cache_blocks_in_hord_db(
         &bitcoin_config,
         &blocks_db,
         &inscriptions_db_conn_rw,
+        inscriptions_replica.as_deref(),
         start_block,
         end_block,
-        network_threads,
+        ordinal_computing_height,
+        &config.fetch_config(start_block, ordinal_computing_height, network_threads),
         &config.expected_cache_path(),
+        &Arc::new(AtomicBool::new(false)),
+        Some(&config.hord_sync_progress),
         &ctx,
     )
-    .await?;
+    .await
+    .map_err(|e| e.to_string())?;
+    if hord_db_options.prepare_for_bulk_load {
+        compact_blocks_db(&blocks_db);
+    }
+    apply_block_pruning_policy(&config.storage.hord_block_pruning, &blocks_db, &ctx)
+        .map_err(|e| e.to_string())?;
 
     Ok(())
 }
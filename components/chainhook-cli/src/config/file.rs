@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct ConfigFile {
     pub storage: StorageConfigFile,
@@ -11,6 +13,22 @@ pub struct StorageConfigFile {
     pub driver: String,
     pub redis_uri: String,
     pub cache_path: Option<String>,
+    pub hord_blocks_to_keep: Option<u32>,
+    pub hord_prune_below: Option<u32>,
+    pub hord_traversals_cache_size_mb: Option<usize>,
+    pub hord_ordinal_computing_height: Option<u64>,
+    pub inscriptions_postgres_url: Option<String>,
+    pub rocksdb: Option<RocksDbConfigFile>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RocksDbConfigFile {
+    pub compression: Option<String>,
+    pub block_cache_size_mb: Option<usize>,
+    pub write_buffer_size_mb: Option<usize>,
+    pub parallelism: Option<i32>,
+    pub max_open_files: Option<i32>,
+    pub bulk_load_during_initial_sync: Option<bool>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -21,20 +39,28 @@ pub struct EventSourceConfigFile {
     pub polling_delay: Option<u32>,
     pub tsv_file_path: Option<String>,
     pub tsv_file_url: Option<String>,
+    pub hord_snapshot_url: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ChainhooksConfigFile {
     pub max_stacks_registrations: Option<u16>,
     pub max_bitcoin_registrations: Option<u16>,
+    pub grpc_port: Option<u16>,
+    pub websocket_port: Option<u16>,
+    pub watch_bitcoin_mempool: Option<bool>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct NetworkConfigFile {
     pub mode: String,
     pub bitcoind_rpc_url: String,
+    pub bitcoind_fallback_rpc_urls: Option<Vec<String>>,
+    pub bitcoind_rpc_rate_limit: Option<u32>,
+    pub bitcoind_rpc_endpoint_rate_limits: Option<HashMap<String, u32>>,
     pub bitcoind_rpc_username: String,
     pub bitcoind_rpc_password: String,
     pub bitcoind_zmq_url: Option<String>,
+    pub bitcoind_rest_enabled: Option<bool>,
     pub stacks_node_rpc_url: String,
 }
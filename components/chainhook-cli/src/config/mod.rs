@@ -1,14 +1,23 @@
 pub mod file;
 pub mod generator;
 
+use chainhook_event_observer::hord::db::{
+    get_default_ordinal_computing_height, BlockPruningPolicy, FetchConfig, HordDbOptions,
+};
+use chainhook_event_observer::hord::sync_progress::SyncProgressTracker;
+use chainhook_event_observer::hord::traversals_cache::DEFAULT_TRAVERSALS_CACHE_SIZE;
+use chainhook_event_observer::indexer::bitcoin::BitcoinRpcRateLimits;
 pub use chainhook_event_observer::indexer::IndexerConfig;
 use chainhook_event_observer::observer::EventObserverConfig;
+use chainhook_event_observer::rocksdb::DBCompressionType;
 use chainhook_types::{BitcoinBlockSignaling, BitcoinNetwork, StacksNetwork};
 pub use file::ConfigFile;
+use file::RocksDbConfigFile;
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use crate::service::{DEFAULT_CONTROL_PORT, DEFAULT_INGESTION_PORT};
 
@@ -22,6 +31,8 @@ const DEFAULT_MAINNET_ORDINALS_SQLITE_ARCHIVE: &str =
 #[derive(Clone, Debug)]
 pub struct Config {
     pub storage: StorageConfig,
+    /// Progress of the hord db syncs, shared by the clones of the config.
+    pub hord_sync_progress: Arc<SyncProgressTracker>,
     pub event_sources: Vec<EventSourceConfig>,
     pub chainhooks: ChainhooksConfig,
     pub network: IndexerConfig,
@@ -31,6 +42,23 @@ pub struct Config {
 pub struct StorageConfig {
     pub driver: StorageDriver,
     pub cache_path: String,
+    pub hord_block_pruning: BlockPruningPolicy,
+    /// Memory budget of the traversals cache, in bytes.
+    pub hord_traversals_cache_size: usize,
+    /// Height from which the ordinal numbers of the inscriptions are computed, defaults to
+    /// `get_default_ordinal_computing_height` of the network.
+    pub hord_ordinal_computing_height: u64,
+    /// Postgres database the inscriptions index is replicated to, requires the `postgres`
+    /// feature.
+    pub inscriptions_postgres_url: Option<String>,
+    pub rocksdb: RocksDbConfig,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RocksDbConfig {
+    pub options: HordDbOptions,
+    /// Open hord.rocksdb in bulk-load mode when syncing from genesis.
+    pub bulk_load_during_initial_sync: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -56,6 +84,7 @@ pub enum EventSourceConfig {
     StacksTsvUrl(UrlConfig),
     OrdinalsSqlitePath(PathConfig),
     OrdinalsSqliteUrl(UrlConfig),
+    HordSnapshotUrl(UrlConfig),
 }
 
 #[derive(Clone, Debug)]
@@ -73,6 +102,12 @@ pub struct ChainhooksConfig {
     pub max_stacks_registrations: u16,
     pub max_bitcoin_registrations: u16,
     pub enable_http_api: bool,
+    /// Port of the gRPC ordinals service, requires the `grpc` feature.
+    pub grpc_port: Option<u16>,
+    /// Port of the WebSocket subscriptions server, requires the `websocket` feature.
+    pub websocket_port: Option<u16>,
+    /// Deliver the unconfirmed transactions matching the bitcoin predicates.
+    pub watch_bitcoin_mempool: bool,
 }
 
 impl Config {
@@ -106,6 +141,8 @@ impl Config {
             bitcoind_rpc_username: self.network.bitcoind_rpc_username.clone(),
             bitcoind_rpc_password: self.network.bitcoind_rpc_password.clone(),
             bitcoind_rpc_url: self.network.bitcoind_rpc_url.clone(),
+            bitcoind_fallback_rpc_urls: self.network.bitcoind_fallback_rpc_urls.clone(),
+            bitcoind_rpc_rate_limits: self.network.bitcoind_rpc_rate_limits.clone(),
             stacks_node_rpc_url: self.network.stacks_node_rpc_url.clone(),
             bitcoin_block_signaling: self.network.bitcoin_block_signaling.clone(),
             operators: HashSet::new(),
@@ -113,6 +150,16 @@ impl Config {
             cache_path: self.storage.cache_path.clone(),
             bitcoin_network: self.network.bitcoin_network.clone(),
             stacks_network: self.network.stacks_network.clone(),
+            hord_block_pruning: self.storage.hord_block_pruning.clone(),
+            hord_db_options: self.hord_db_options(false),
+            hord_traversals_cache_size: self.storage.hord_traversals_cache_size,
+            hord_sync_progress: self.hord_sync_progress.clone(),
+            inscriptions_postgres_url: self.storage.inscriptions_postgres_url.clone(),
+            #[cfg(feature = "grpc")]
+            grpc_port: self.chainhooks.grpc_port,
+            #[cfg(feature = "websocket")]
+            websocket_port: self.chainhooks.websocket_port,
+            watch_bitcoin_mempool: self.chainhooks.watch_bitcoin_mempool,
         }
     }
 
@@ -136,15 +183,71 @@ impl Config {
                 event_sources.push(EventSourceConfig::StacksTsvUrl(UrlConfig { file_url }));
                 continue;
             }
+            if let Some(file_url) = source.hord_snapshot_url.take() {
+                event_sources.push(EventSourceConfig::HordSnapshotUrl(UrlConfig { file_url }));
+                continue;
+            }
         }
 
+        let hord_block_pruning = match (
+            config_file.storage.hord_blocks_to_keep,
+            config_file.storage.hord_prune_below,
+        ) {
+            (None, None) => BlockPruningPolicy::KeepAll,
+            (Some(blocks), None) => BlockPruningPolicy::KeepLast(blocks),
+            (None, Some(height)) => BlockPruningPolicy::PruneBelow(height),
+            (Some(_), Some(_)) => return Err(
+                "storage.hord_blocks_to_keep and storage.hord_prune_below are mutually exclusive"
+                    .to_string(),
+            ),
+        };
+
+        if config_file.network.bitcoind_rpc_url.trim().is_empty()
+            || config_file
+                .network
+                .bitcoind_fallback_rpc_urls
+                .iter()
+                .flatten()
+                .any(|url| url.trim().is_empty())
+        {
+            return Err(
+                "network.bitcoind_rpc_url and network.bitcoind_fallback_rpc_urls can not be empty"
+                    .to_string(),
+            );
+        }
+
+        if config_file.storage.inscriptions_postgres_url.is_some() && !cfg!(feature = "postgres") {
+            return Err(
+                "storage.inscriptions_postgres_url requires chainhook to be built with the `postgres` feature"
+                    .to_string(),
+            );
+        }
+
+        let rocksdb = match config_file.storage.rocksdb {
+            Some(rocksdb_config_file) => RocksDbConfig::from_config_file(rocksdb_config_file)?,
+            None => RocksDbConfig::default(),
+        };
+
         let config = Config {
             storage: StorageConfig {
                 driver: StorageDriver::Redis(RedisConfig {
                     uri: config_file.storage.redis_uri.to_string(),
                 }),
                 cache_path: config_file.storage.cache_path.unwrap_or("cache".into()),
+                hord_block_pruning,
+                hord_traversals_cache_size: config_file
+                    .storage
+                    .hord_traversals_cache_size_mb
+                    .map(|mb| mb * 1024 * 1024)
+                    .unwrap_or(DEFAULT_TRAVERSALS_CACHE_SIZE),
+                hord_ordinal_computing_height: config_file
+                    .storage
+                    .hord_ordinal_computing_height
+                    .unwrap_or(get_default_ordinal_computing_height(&bitcoin_network)),
+                inscriptions_postgres_url: config_file.storage.inscriptions_postgres_url,
+                rocksdb,
             },
+            hord_sync_progress: Arc::new(SyncProgressTracker::default()),
             event_sources,
             chainhooks: ChainhooksConfig {
                 max_stacks_registrations: config_file
@@ -156,12 +259,30 @@ impl Config {
                     .max_bitcoin_registrations
                     .unwrap_or(100),
                 enable_http_api: true,
+                grpc_port: config_file.chainhooks.grpc_port,
+                websocket_port: config_file.chainhooks.websocket_port,
+                watch_bitcoin_mempool: config_file
+                    .chainhooks
+                    .watch_bitcoin_mempool
+                    .unwrap_or(false),
             },
             network: IndexerConfig {
                 stacks_node_rpc_url: config_file.network.stacks_node_rpc_url.to_string(),
                 bitcoind_rpc_url: config_file.network.bitcoind_rpc_url.to_string(),
+                bitcoind_fallback_rpc_urls: config_file
+                    .network
+                    .bitcoind_fallback_rpc_urls
+                    .unwrap_or_default(),
+                bitcoind_rpc_rate_limits: BitcoinRpcRateLimits {
+                    default: config_file.network.bitcoind_rpc_rate_limit,
+                    endpoints: config_file
+                        .network
+                        .bitcoind_rpc_endpoint_rate_limits
+                        .unwrap_or_default(),
+                },
                 bitcoind_rpc_username: config_file.network.bitcoind_rpc_username.to_string(),
                 bitcoind_rpc_password: config_file.network.bitcoind_rpc_password.to_string(),
+                bitcoind_rest_enabled: config_file.network.bitcoind_rest_enabled.unwrap_or(false),
                 bitcoin_block_signaling: match config_file.network.bitcoind_zmq_url {
                     Some(ref zmq_url) => BitcoinBlockSignaling::ZeroMQ(zmq_url.clone()),
                     None => BitcoinBlockSignaling::Stacks(
@@ -231,6 +352,33 @@ impl Config {
         panic!("expected local-tsv source")
     }
 
+    /// Options used when opening hord.rocksdb for writing. `initial_sync` enables the
+    /// bulk-load mode if `bulk_load_during_initial_sync` is set.
+    pub fn hord_db_options(&self, initial_sync: bool) -> HordDbOptions {
+        let mut options = self.storage.rocksdb.options.clone();
+        if initial_sync && self.storage.rocksdb.bulk_load_during_initial_sync {
+            options.prepare_for_bulk_load = true;
+        }
+        options
+    }
+
+    pub fn fetch_config(
+        &self,
+        start_block: u64,
+        ordinal_computing_height: u64,
+        network_threads: usize,
+    ) -> FetchConfig {
+        let rpc_urls = std::iter::once(&self.network.bitcoind_rpc_url)
+            .chain(self.network.bitcoind_fallback_rpc_urls.iter())
+            .map(|url| url.as_str());
+        FetchConfig {
+            rest_interface: self.network.bitcoind_rest_enabled,
+            traversals_cache_size: self.storage.hord_traversals_cache_size,
+            ..FetchConfig::default_for(start_block, ordinal_computing_height, network_threads)
+        }
+        .capped_to_rate(self.network.bitcoind_rpc_rate_limits.total_rate(rpc_urls))
+    }
+
     pub fn expected_cache_path(&self) -> PathBuf {
         let mut destination_path = PathBuf::new();
         destination_path.push(&self.storage.cache_path);
@@ -271,6 +419,21 @@ impl Config {
         format!("{}.gz", self.expected_remote_ordinals_sqlite_base_url())
     }
 
+    /// Archive produced by `hord db snapshot export`, used to seed an empty cache.
+    pub fn remote_hord_snapshot_url(&self) -> Option<&String> {
+        for source in self.event_sources.iter() {
+            if let EventSourceConfig::HordSnapshotUrl(config) = source {
+                return Some(&config.file_url);
+            }
+        }
+        None
+    }
+
+    pub fn expected_remote_hord_snapshot_sha256(&self) -> Option<String> {
+        self.remote_hord_snapshot_url()
+            .map(|url| format!("{}.sha256", url))
+    }
+
     pub fn rely_on_remote_stacks_tsv(&self) -> bool {
         for source in self.event_sources.iter() {
             if let EventSourceConfig::StacksTsvUrl(_config) = source {
@@ -340,18 +503,32 @@ impl Config {
                     uri: "redis://localhost:6379/".into(),
                 }),
                 cache_path: default_cache_path(),
+                hord_block_pruning: BlockPruningPolicy::KeepAll,
+                hord_traversals_cache_size: DEFAULT_TRAVERSALS_CACHE_SIZE,
+                hord_ordinal_computing_height: get_default_ordinal_computing_height(
+                    &BitcoinNetwork::Regtest,
+                ),
+                inscriptions_postgres_url: None,
+                rocksdb: RocksDbConfig::default(),
             },
+            hord_sync_progress: Arc::new(SyncProgressTracker::default()),
             event_sources: vec![],
             chainhooks: ChainhooksConfig {
                 max_stacks_registrations: 50,
                 max_bitcoin_registrations: 50,
                 enable_http_api: true,
+                grpc_port: None,
+                websocket_port: None,
+                watch_bitcoin_mempool: false,
             },
             network: IndexerConfig {
                 stacks_node_rpc_url: "http://0.0.0.0:20443".into(),
                 bitcoind_rpc_url: "http://0.0.0.0:18443".into(),
+                bitcoind_fallback_rpc_urls: vec![],
+                bitcoind_rpc_rate_limits: BitcoinRpcRateLimits::default(),
                 bitcoind_rpc_username: "devnet".into(),
                 bitcoind_rpc_password: "devnet".into(),
+                bitcoind_rest_enabled: false,
                 bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(
                     "http://0.0.0.0:20443".into(),
                 ),
@@ -368,7 +545,15 @@ impl Config {
                     uri: "redis://localhost:6379/".into(),
                 }),
                 cache_path: default_cache_path(),
+                hord_block_pruning: BlockPruningPolicy::KeepAll,
+                hord_traversals_cache_size: DEFAULT_TRAVERSALS_CACHE_SIZE,
+                hord_ordinal_computing_height: get_default_ordinal_computing_height(
+                    &BitcoinNetwork::Testnet,
+                ),
+                inscriptions_postgres_url: None,
+                rocksdb: RocksDbConfig::default(),
             },
+            hord_sync_progress: Arc::new(SyncProgressTracker::default()),
             event_sources: vec![EventSourceConfig::StacksTsvUrl(UrlConfig {
                 file_url: DEFAULT_TESTNET_STACKS_TSV_ARCHIVE.into(),
             })],
@@ -376,12 +561,18 @@ impl Config {
                 max_stacks_registrations: 10,
                 max_bitcoin_registrations: 10,
                 enable_http_api: true,
+                grpc_port: None,
+                websocket_port: None,
+                watch_bitcoin_mempool: false,
             },
             network: IndexerConfig {
                 stacks_node_rpc_url: "http://0.0.0.0:20443".into(),
                 bitcoind_rpc_url: "http://0.0.0.0:18332".into(),
+                bitcoind_fallback_rpc_urls: vec![],
+                bitcoind_rpc_rate_limits: BitcoinRpcRateLimits::default(),
                 bitcoind_rpc_username: "devnet".into(),
                 bitcoind_rpc_password: "devnet".into(),
+                bitcoind_rest_enabled: false,
                 bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(
                     "http://0.0.0.0:20443".into(),
                 ),
@@ -398,7 +589,15 @@ impl Config {
                     uri: "redis://localhost:6379/".into(),
                 }),
                 cache_path: default_cache_path(),
+                hord_block_pruning: BlockPruningPolicy::KeepAll,
+                hord_traversals_cache_size: DEFAULT_TRAVERSALS_CACHE_SIZE,
+                hord_ordinal_computing_height: get_default_ordinal_computing_height(
+                    &BitcoinNetwork::Mainnet,
+                ),
+                inscriptions_postgres_url: None,
+                rocksdb: RocksDbConfig::default(),
             },
+            hord_sync_progress: Arc::new(SyncProgressTracker::default()),
             event_sources: vec![
                 EventSourceConfig::StacksTsvUrl(UrlConfig {
                     file_url: DEFAULT_MAINNET_STACKS_TSV_ARCHIVE.into(),
@@ -411,12 +610,18 @@ impl Config {
                 max_stacks_registrations: 10,
                 max_bitcoin_registrations: 10,
                 enable_http_api: true,
+                grpc_port: None,
+                websocket_port: None,
+                watch_bitcoin_mempool: false,
             },
             network: IndexerConfig {
                 stacks_node_rpc_url: "http://0.0.0.0:20443".into(),
                 bitcoind_rpc_url: "http://0.0.0.0:8332".into(),
+                bitcoind_fallback_rpc_urls: vec![],
+                bitcoind_rpc_rate_limits: BitcoinRpcRateLimits::default(),
                 bitcoind_rpc_username: "devnet".into(),
                 bitcoind_rpc_password: "devnet".into(),
+                bitcoind_rest_enabled: false,
                 bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(
                     "http://0.0.0.0:20443".into(),
                 ),
@@ -427,8 +632,64 @@ impl Config {
     }
 }
 
+impl RocksDbConfig {
+    fn from_config_file(config_file: RocksDbConfigFile) -> Result<RocksDbConfig, String> {
+        let mut options = HordDbOptions::default();
+        if let Some(compression) = config_file.compression {
+            options.compression = Some(parse_rocksdb_compression(&compression)?);
+        }
+        if let Some(max_open_files) = config_file.max_open_files {
+            options.max_open_files = max_open_files;
+        }
+        options.parallelism = config_file.parallelism;
+        options.block_cache_size = config_file.block_cache_size_mb.map(|mb| mb * 1024 * 1024);
+        options.write_buffer_size = config_file.write_buffer_size_mb.map(|mb| mb * 1024 * 1024);
+        Ok(RocksDbConfig {
+            options,
+            bulk_load_during_initial_sync: config_file
+                .bulk_load_during_initial_sync
+                .unwrap_or(false),
+        })
+    }
+}
+
+fn parse_rocksdb_compression(value: &str) -> Result<DBCompressionType, String> {
+    match value {
+        "none" => Ok(DBCompressionType::None),
+        "snappy" => Ok(DBCompressionType::Snappy),
+        "zlib" => Ok(DBCompressionType::Zlib),
+        "bz2" => Ok(DBCompressionType::Bz2),
+        "lz4" => Ok(DBCompressionType::Lz4),
+        "lz4hc" => Ok(DBCompressionType::Lz4hc),
+        "zstd" => Ok(DBCompressionType::Zstd),
+        _ => Err(format!(
+            "storage.rocksdb.compression: unsupported value {value}"
+        )),
+    }
+}
+
 pub fn default_cache_path() -> String {
     let mut cache_path = std::env::current_dir().expect("unable to get current dir");
     cache_path.push("cache");
     format!("{}", cache_path.display())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordinal_computing_height_defaults_to_the_network_and_can_be_overridden() {
+        let config_file: ConfigFile = toml::from_str(&generator::generate_config()).unwrap();
+        let config = Config::from_config_file(config_file.clone()).unwrap();
+        assert_eq!(
+            config.storage.hord_ordinal_computing_height,
+            get_default_ordinal_computing_height(&BitcoinNetwork::Mainnet)
+        );
+
+        let mut config_file = config_file;
+        config_file.storage.hord_ordinal_computing_height = Some(800000);
+        let config = Config::from_config_file(config_file).unwrap();
+        assert_eq!(config.storage.hord_ordinal_computing_height, 800000);
+    }
+}
@@ -9,13 +9,18 @@ use chainhook_event_observer::chainhooks::bitcoin::{
 use chainhook_event_observer::chainhooks::types::{
     BitcoinChainhookSpecification, BitcoinPredicateType,
 };
+#[cfg(feature = "brc20")]
+use chainhook_event_observer::hord::brc20::find_brc20_operations_at_block_height;
 use chainhook_event_observer::hord::db::{
     fetch_and_cache_blocks_in_hord_db, find_all_inscriptions, find_block_at_block_height,
-    find_last_block_inserted, open_readonly_hord_db_conn, open_readonly_hord_db_conn_rocks_db,
-    open_readwrite_hord_db_conn, open_readwrite_hord_db_conn_rocks_db,
+    find_last_block_inserted, initialize_hord_db, open_inscriptions_replica,
+    open_readonly_hord_db_conn, open_readonly_hord_db_conn_rocks_db, open_readwrite_hord_db_conn,
+    open_readwrite_hord_db_conn_rocks_db_with_options,
 };
+use chainhook_event_observer::hord::runes::find_rune_operations_at_block_height;
 use chainhook_event_observer::hord::{
-    get_inscriptions_revealed_in_block,
+    augment_bitcoin_block_with_sat_movements, get_inscriptions_revealed_in_block,
+    traversals_cache::TraversalsLruCache,
     update_storage_and_augment_bitcoin_block_with_inscription_reveal_data,
     update_storage_and_augment_bitcoin_block_with_inscription_transfer_data, Storage,
 };
@@ -24,9 +29,17 @@ use chainhook_event_observer::indexer::bitcoin::{
     download_and_parse_block_with_retry, retrieve_block_hash_with_retry,
 };
 use chainhook_event_observer::observer::{gather_proofs, EventObserverConfig};
-use chainhook_event_observer::utils::{file_append, send_request, Context};
+use chainhook_event_observer::utils::amqp::publish_amqp_message;
+use chainhook_event_observer::utils::kafka::produce_kafka_message;
+use chainhook_event_observer::utils::nats::publish_nats_message;
+use chainhook_event_observer::utils::pubsub::publish_pubsub_message;
+use chainhook_event_observer::utils::redis_streams::append_to_redis_stream;
+use chainhook_event_observer::utils::{file_append, send_webhook_request, Context};
 use chainhook_types::{BitcoinChainEvent, BitcoinChainUpdatedWithBlocksData};
 use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
 
 pub async fn scan_bitcoin_chainstate_via_http_using_predicate(
     predicate_spec: &BitcoinChainhookSpecification,
@@ -76,7 +89,12 @@ pub async fn scan_bitcoin_chainstate_via_http_using_predicate(
     let mut is_predicate_evaluating_ordinals = false;
     let mut hord_blocks_requires_update = false;
 
-    if let BitcoinPredicateType::OrdinalsProtocol(_) = &predicate_spec.predicate {
+    if let BitcoinPredicateType::OrdinalsProtocol(_)
+    | BitcoinPredicateType::Brc20(_)
+    | BitcoinPredicateType::RunesEtched
+    | BitcoinPredicateType::RuneTransfer(_)
+    | BitcoinPredicateType::RareSats(_) = &predicate_spec.predicate
+    {
         is_predicate_evaluating_ordinals = true;
         if let Ok(inscriptions_db_conn) =
             open_readonly_hord_db_conn(&config.expected_cache_path(), &ctx)
@@ -104,8 +122,12 @@ pub async fn scan_bitcoin_chainstate_via_http_using_predicate(
             // TODO: make sure that we have a contiguous chain
             // check_compacted_blocks_chain_integrity(&hord_db_conn);
 
-            let blocks_db_rw =
-                open_readwrite_hord_db_conn_rocks_db(&config.expected_cache_path(), ctx)?;
+            let blocks_db_rw = open_readwrite_hord_db_conn_rocks_db_with_options(
+                &config.expected_cache_path(),
+                &config.hord_db_options(false),
+                ctx,
+            )
+            .map_err(|e| e.to_string())?;
 
             let start_block = find_last_block_inserted(&blocks_db_rw) as u64;
             if start_block < end_block {
@@ -116,24 +138,39 @@ pub async fn scan_bitcoin_chainstate_via_http_using_predicate(
                 );
 
                 let inscriptions_db_conn_rw =
-                    open_readwrite_hord_db_conn(&config.expected_cache_path(), ctx)?;
+                    open_readwrite_hord_db_conn(&config.expected_cache_path(), ctx)
+                        .map_err(|e| e.to_string())?;
+                let inscriptions_replica =
+                    open_inscriptions_replica(config.storage.inscriptions_postgres_url.as_deref())
+                        .map_err(|e| e.to_string())?;
+                let ordinal_computing_height = config.storage.hord_ordinal_computing_height;
                 fetch_and_cache_blocks_in_hord_db(
                     &config.get_event_observer_config().get_bitcoin_config(),
                     &blocks_db_rw,
                     &inscriptions_db_conn_rw,
+                    inscriptions_replica.as_deref(),
                     start_block,
                     end_block,
-                    8,
+                    ordinal_computing_height,
+                    &config.fetch_config(start_block, ordinal_computing_height, 8),
                     &config.expected_cache_path(),
+                    &Arc::new(AtomicBool::new(false)),
+                    Some(&config.hord_sync_progress),
                     &ctx,
                 )
-                .await?;
+                .await
+                .map_err(|e| e.to_string())?;
 
                 inscriptions_cache = find_all_inscriptions(&inscriptions_db_conn_rw);
             }
         }
     }
 
+    // The hord db sync above logs as a component of its own
+    let ctx = &ctx
+        .with_component("predicate_scan")
+        .with_predicate_uuid(&predicate_spec.uuid);
+    let scan_started_at = Instant::now();
     info!(
         ctx.expect_logger(),
         "Starting predicate evaluation on Bitcoin blocks",
@@ -147,9 +184,21 @@ pub async fn scan_bitcoin_chainstate_via_http_using_predicate(
     let bitcoin_config = event_observer_config.get_bitcoin_config();
     let mut traversals = HashMap::new();
     if is_predicate_evaluating_ordinals {
-        let hord_db_conn = open_readonly_hord_db_conn(&config.expected_cache_path(), ctx)?;
+        let hord_db_conn = match open_readonly_hord_db_conn(&config.expected_cache_path(), ctx) {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(
+                    ctx.expect_logger(),
+                    "{e}, initializing a new hord.sqlite database"
+                );
+                initialize_hord_db(&config.expected_cache_path(), ctx).map_err(|e| e.to_string())?
+            }
+        };
 
         let mut storage = Storage::Memory(BTreeMap::new());
+        let traversals_cache = Arc::new(TraversalsLruCache::new(
+            config.storage.hord_traversals_cache_size,
+        ));
         let mut cursor = start_block.saturating_sub(1);
         while cursor <= end_block {
             cursor += 1;
@@ -197,6 +246,66 @@ pub async fn scan_bitcoin_chainstate_via_http_using_predicate(
                 &ctx,
             );
 
+            // BRC-20 operations are retrieved from the ledger built while indexing hord.sqlite
+            #[cfg(feature = "brc20")]
+            if let BitcoinPredicateType::Brc20(_) = &predicate_spec.predicate {
+                match find_brc20_operations_at_block_height(cursor, &hord_db_conn) {
+                    Ok(mut operations) => {
+                        for tx in block.transactions.iter_mut() {
+                            if let Some(brc20_operations) =
+                                operations.remove(&tx.transaction_identifier.hash)
+                            {
+                                tx.metadata.brc20_operations = brc20_operations;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        warn!(
+                            ctx.expect_logger(),
+                            "Unable to retrieve BRC-20 operations of block #{}: {}", cursor, e
+                        );
+                    }
+                }
+            }
+
+            // Rune operations are retrieved from the ledger built while indexing hord.sqlite
+            if let BitcoinPredicateType::RunesEtched | BitcoinPredicateType::RuneTransfer(_) =
+                &predicate_spec.predicate
+            {
+                match find_rune_operations_at_block_height(cursor, &hord_db_conn) {
+                    Ok(mut operations) => {
+                        for tx in block.transactions.iter_mut() {
+                            if let Some(rune_operations) =
+                                operations.remove(&tx.transaction_identifier.hash)
+                            {
+                                tx.metadata.rune_operations = rune_operations;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        warn!(
+                            ctx.expect_logger(),
+                            "Unable to retrieve rune operations of block #{}: {}", cursor, e
+                        );
+                    }
+                }
+            }
+
+            // Spent sats are traversed using the blocks stored in hord.sqlite
+            if let BitcoinPredicateType::RareSats(_) = &predicate_spec.predicate {
+                if let Err(e) = augment_bitcoin_block_with_sat_movements(
+                    &mut block,
+                    &config.expected_cache_path(),
+                    &traversals_cache,
+                    ctx,
+                ) {
+                    warn!(
+                        ctx.expect_logger(),
+                        "Unable to retrieve the sats spent in block #{}: {}", cursor, e
+                    );
+                }
+            }
+
             let inscriptions_revealed = get_inscriptions_revealed_in_block(&block)
                 .iter()
                 .map(|d| d.inscription_number.to_string())
@@ -219,7 +328,8 @@ pub async fn scan_bitcoin_chainstate_via_http_using_predicate(
                 "Processing block #{} through {} predicate (inscriptions revealed: [{}])",
                 cursor,
                 predicate_spec.uuid,
-                inscriptions_revealed.join(", ")
+                inscriptions_revealed.join(", ");
+                "block_height" => cursor
             );
 
             match execute_predicates_action(hits, &event_observer_config, &ctx).await {
@@ -303,7 +413,8 @@ pub async fn scan_bitcoin_chainstate_via_http_using_predicate(
     }
     info!(
         ctx.expect_logger(),
-        "{blocks_scanned} blocks scanned, {actions_triggered} actions triggered"
+        "{blocks_scanned} blocks scanned, {actions_triggered} actions triggered";
+        "duration_ms" => scan_started_at.elapsed().as_millis() as u64
     );
 
     Ok(())
@@ -328,12 +439,30 @@ pub async fn execute_predicates_action<'a>(
                 actions_triggered += 1;
                 match action {
                     BitcoinChainhookOccurrence::Http(request) => {
-                        send_request(request, 3, 1, &ctx).await?
+                        send_webhook_request(request, 3, 1, &ctx).await?
                     }
                     BitcoinChainhookOccurrence::File(path, bytes) => {
                         file_append(path, bytes, &ctx)?
                     }
+                    BitcoinChainhookOccurrence::Kafka(message) => {
+                        produce_kafka_message(message, &ctx).await?
+                    }
+                    BitcoinChainhookOccurrence::Nats(messages) => {
+                        for message in messages.into_iter() {
+                            publish_nats_message(message, &ctx).await?
+                        }
+                    }
+                    BitcoinChainhookOccurrence::Amqp(message) => {
+                        publish_amqp_message(message, &ctx).await?
+                    }
+                    BitcoinChainhookOccurrence::RedisStream(message) => {
+                        append_to_redis_stream(message, &ctx).await?
+                    }
+                    BitcoinChainhookOccurrence::PubSub(message) => {
+                        publish_pubsub_message(message, &ctx).await?
+                    }
                     BitcoinChainhookOccurrence::Data(_payload) => unreachable!(),
+                    BitcoinChainhookOccurrence::WebSocket(..) => unreachable!(),
                 };
             }
         }
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
 
 use crate::{
     archive::download_stacks_dataset_if_required,
@@ -15,7 +16,11 @@ use chainhook_event_observer::{
         stacks::{handle_stacks_hook_action, StacksChainhookOccurrence, StacksTriggerChainhook},
         types::StacksChainhookSpecification,
     },
-    utils::{file_append, send_request, AbstractStacksBlock},
+    utils::{
+        amqp::publish_amqp_message, file_append, kafka::produce_kafka_message,
+        nats::publish_nats_message, pubsub::publish_pubsub_message,
+        redis_streams::append_to_redis_stream, send_webhook_request, AbstractStacksBlock,
+    },
 };
 use chainhook_types::BlockIdentifier;
 
@@ -24,6 +29,10 @@ pub async fn scan_stacks_chainstate_via_csv_using_predicate(
     config: &mut Config,
     ctx: &Context,
 ) -> Result<BlockIdentifier, String> {
+    let ctx = &ctx
+        .with_component("predicate_scan")
+        .with_predicate_uuid(&predicate_spec.uuid);
+    let scan_started_at = Instant::now();
     let start_block = match predicate_spec.start_block {
         Some(start_block) => start_block,
         None => {
@@ -168,10 +177,30 @@ pub async fn scan_stacks_chainstate_via_csv_using_predicate(
                 actions_triggered += 1;
                 let res = match action {
                     StacksChainhookOccurrence::Http(request) => {
-                        send_request(request, 3, 1, &ctx).await
+                        send_webhook_request(request, 3, 1, &ctx).await
                     }
                     StacksChainhookOccurrence::File(path, bytes) => file_append(path, bytes, &ctx),
+                    StacksChainhookOccurrence::Kafka(message) => {
+                        produce_kafka_message(message, &ctx).await
+                    }
+                    StacksChainhookOccurrence::Nats(messages) => {
+                        let mut res = Ok(());
+                        for message in messages.into_iter() {
+                            res = res.and(publish_nats_message(message, &ctx).await);
+                        }
+                        res
+                    }
+                    StacksChainhookOccurrence::Amqp(message) => {
+                        publish_amqp_message(message, &ctx).await
+                    }
+                    StacksChainhookOccurrence::RedisStream(message) => {
+                        append_to_redis_stream(message, &ctx).await
+                    }
+                    StacksChainhookOccurrence::PubSub(message) => {
+                        publish_pubsub_message(message, &ctx).await
+                    }
                     StacksChainhookOccurrence::Data(_payload) => unreachable!(),
+                    StacksChainhookOccurrence::WebSocket(..) => unreachable!(),
                 };
                 if res.is_err() {
                     err_count += 1;
@@ -187,7 +216,8 @@ pub async fn scan_stacks_chainstate_via_csv_using_predicate(
     }
     info!(
         ctx.expect_logger(),
-        "{blocks_scanned} blocks scanned, {actions_triggered} actions triggered"
+        "{blocks_scanned} blocks scanned, {actions_triggered} actions triggered";
+        "duration_ms" => scan_started_at.elapsed().as_millis() as u64
     );
 
     Ok(last_block_scanned)
@@ -0,0 +1,286 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use bitcoincore_rpc::bitcoin::secp256k1::{Secp256k1, Verification, VerifyOnly};
+use bitcoincore_rpc::bitcoin::util::bip32::{ChildNumber, ExtendedPubKey};
+use bitcoincore_rpc::bitcoin::{PublicKey, Script};
+use chainhook_types::BitcoinTransactionData;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScriptType {
+    P2pkh,
+    P2wpkh,
+    P2shP2wpkh,
+    P2tr,
+}
+
+/// Keys designated by `xpub/<path>/*`, or the single key designated by `xpub/<path>`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DescriptorChain {
+    pub script_type: ScriptType,
+    /// Parent of the keys designated by the wildcard, or the key itself when not ranged.
+    pub xpub: ExtendedPubKey,
+    pub ranged: bool,
+}
+
+/// Parses a `pkh`, `wpkh`, `sh(wpkh)` or `tr` descriptor of a single extended public key.
+/// Key origins and checksums are ignored, hardened derivations can not be performed.
+/// A bare extended public key is watched as `wpkh(xpub/0/*)` and `wpkh(xpub/1/*)`.
+pub fn parse_descriptor(descriptor: &str) -> Result<Vec<DescriptorChain>, String> {
+    let secp = Secp256k1::verification_only();
+    let descriptor = descriptor.trim();
+    let descriptor = match descriptor.split_once('#') {
+        Some((descriptor, _checksum)) => descriptor,
+        None => descriptor,
+    };
+
+    if let Ok(xpub) = ExtendedPubKey::from_str(descriptor) {
+        return [0, 1]
+            .iter()
+            .map(|chain| {
+                Ok(DescriptorChain {
+                    script_type: ScriptType::P2wpkh,
+                    xpub: derive_child(&secp, &xpub, *chain)?,
+                    ranged: true,
+                })
+            })
+            .collect();
+    }
+
+    let functions = [
+        ("sh(wpkh(", "))", ScriptType::P2shP2wpkh),
+        ("wpkh(", ")", ScriptType::P2wpkh),
+        ("pkh(", ")", ScriptType::P2pkh),
+        ("tr(", ")", ScriptType::P2tr),
+    ];
+    let (key, script_type) = functions
+        .iter()
+        .find_map(|(prefix, suffix, script_type)| {
+            descriptor
+                .strip_prefix(prefix)
+                .and_then(|key| key.strip_suffix(suffix))
+                .map(|key| (key, *script_type))
+        })
+        .ok_or_else(|| format!("unsupported descriptor {descriptor}"))?;
+
+    let key = match key.strip_prefix('[') {
+        Some(key) => match key.split_once(']') {
+            Some((_origin, key)) => key,
+            None => return Err(format!("malformed key origin in {descriptor}")),
+        },
+        None => key,
+    };
+    let mut steps = key.split('/');
+    let mut xpub = steps
+        .next()
+        .and_then(|xpub| ExtendedPubKey::from_str(xpub).ok())
+        .ok_or_else(|| format!("invalid extended public key in {descriptor}"))?;
+    let mut steps = steps.collect::<Vec<_>>();
+    let ranged = steps.last() == Some(&"*");
+    if ranged {
+        steps.pop();
+    }
+    for step in steps {
+        let index = step
+            .parse::<u32>()
+            .map_err(|_| format!("unable to derive step {step} of {descriptor}"))?;
+        xpub = derive_child(&secp, &xpub, index)?;
+    }
+    Ok(vec![DescriptorChain {
+        script_type,
+        xpub,
+        ranged,
+    }])
+}
+
+fn derive_child<C: Verification>(
+    secp: &Secp256k1<C>,
+    xpub: &ExtendedPubKey,
+    index: u32,
+) -> Result<ExtendedPubKey, String> {
+    let child = ChildNumber::from_normal_idx(index).map_err(|e| e.to_string())?;
+    xpub.derive_pub(secp, &[child]).map_err(|e| e.to_string())
+}
+
+fn script_pubkey<C: Verification>(
+    secp: &Secp256k1<C>,
+    script_type: ScriptType,
+    xpub: &ExtendedPubKey,
+) -> Script {
+    let public_key = PublicKey::new(xpub.public_key);
+    let p2wpkh = || {
+        Script::new_v0_p2wpkh(
+            &public_key
+                .wpubkey_hash()
+                .expect("derived public keys are compressed"),
+        )
+    };
+    match script_type {
+        ScriptType::P2pkh => Script::new_p2pkh(&public_key.pubkey_hash()),
+        ScriptType::P2wpkh => p2wpkh(),
+        ScriptType::P2shP2wpkh => Script::new_p2sh(&p2wpkh().script_hash()),
+        ScriptType::P2tr => {
+            let (internal_key, _parity) = xpub.public_key.x_only_public_key();
+            Script::new_v1_p2tr(secp, internal_key, None)
+        }
+    }
+}
+
+/// Scripts derived from a descriptor, along with the outputs they received.
+#[derive(Debug)]
+struct WalletScripts {
+    secp: Secp256k1<VerifyOnly>,
+    chains: Vec<DescriptorChain>,
+    /// Number of keys derived, per chain.
+    derived: Vec<u32>,
+    /// Hex encoded scripts, with the chain and the index of their key.
+    scripts: HashMap<String, (usize, u32)>,
+    /// Unspent outputs received by the wallet, formatted as `txid:vout`.
+    outpoints: HashSet<String>,
+}
+
+impl WalletScripts {
+    fn new(descriptor: &str, gap_limit: u32) -> Result<WalletScripts, String> {
+        let chains = parse_descriptor(descriptor)?;
+        let mut wallet = WalletScripts {
+            secp: Secp256k1::verification_only(),
+            derived: vec![0; chains.len()],
+            chains,
+            scripts: HashMap::new(),
+            outpoints: HashSet::new(),
+        };
+        for chain in 0..wallet.chains.len() {
+            wallet.extend(chain, gap_limit)?;
+        }
+        Ok(wallet)
+    }
+
+    /// Derives the keys of the chain up to `count`.
+    fn extend(&mut self, chain: usize, count: u32) -> Result<(), String> {
+        let descriptor_chain = &self.chains[chain];
+        if !descriptor_chain.ranged {
+            if self.derived[chain] == 0 {
+                let script = script_pubkey(
+                    &self.secp,
+                    descriptor_chain.script_type,
+                    &descriptor_chain.xpub,
+                );
+                self.scripts
+                    .insert(hex::encode(script.as_bytes()), (chain, 0));
+                self.derived[chain] = 1;
+            }
+            return Ok(());
+        }
+        for index in self.derived[chain]..count {
+            let xpub = derive_child(&self.secp, &descriptor_chain.xpub, index)?;
+            let script = script_pubkey(&self.secp, descriptor_chain.script_type, &xpub);
+            self.scripts
+                .insert(hex::encode(script.as_bytes()), (chain, index));
+            self.derived[chain] = index + 1;
+        }
+        Ok(())
+    }
+}
+
+/// Wallet watched by a `descriptor` predicate, lazily derived on the first evaluation and
+/// extended as its addresses are getting used. Shared by the clones of the predicate.
+#[derive(Clone, Debug, Default)]
+pub struct WatchedWallet(Arc<Mutex<Option<WalletScripts>>>);
+
+impl PartialEq for WatchedWallet {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl WatchedWallet {
+    /// Is the transaction paying the wallet, or spending an output previously received?
+    /// Outputs received before the first evaluation are unknown, their spendings are missed.
+    pub fn evaluate_transaction(
+        &self,
+        tx: &BitcoinTransactionData,
+        descriptor: &str,
+        gap_limit: u32,
+    ) -> bool {
+        let mut guard = match self.0.lock() {
+            Ok(guard) => guard,
+            Err(_) => return false,
+        };
+        if guard.is_none() {
+            match WalletScripts::new(descriptor, gap_limit) {
+                Ok(wallet) => *guard = Some(wallet),
+                Err(_) => return false,
+            }
+        }
+        let wallet = match guard.as_mut() {
+            Some(wallet) => wallet,
+            None => return false,
+        };
+
+        let mut matched = false;
+        for input in tx.metadata.inputs.iter() {
+            let txid = &input.previous_output.txid;
+            let outpoint = format!(
+                "{}:{}",
+                txid.strip_prefix("0x").unwrap_or(txid),
+                input.previous_output.vout
+            );
+            matched |= wallet.outpoints.remove(&outpoint);
+        }
+
+        let txid = &tx.transaction_identifier.hash;
+        let txid = txid.strip_prefix("0x").unwrap_or(txid);
+        for (vout, output) in tx.metadata.outputs.iter().enumerate() {
+            let script = &output.script_pubkey;
+            let script = script.strip_prefix("0x").unwrap_or(script);
+            if let Some((chain, index)) = wallet.scripts.get(script).cloned() {
+                wallet.outpoints.insert(format!("{txid}:{vout}"));
+                let _ = wallet.extend(chain, index + 1 + gap_limit);
+                matched = true;
+            }
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP-84 test vector, account 0 of "abandon abandon ... about"
+    const XPUB: &str = "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V";
+
+    #[test]
+    fn derive_wpkh_scripts() {
+        let wallet =
+            WalletScripts::new(&format!("wpkh([73c5da0a/84'/0'/0']{XPUB}/0/*)#checksum"), 2)
+                .unwrap();
+        assert_eq!(wallet.scripts.len(), 2);
+        assert_eq!(
+            wallet
+                .scripts
+                .get("0014c0cebcd6c3d3ca8c75dc5ec62ebe55330ef910e2"),
+            Some(&(0, 0))
+        );
+
+        let wallet = WalletScripts::new(XPUB, 1).unwrap();
+        assert_eq!(
+            wallet
+                .scripts
+                .get("00143e34985dca6fddc9fb369940e4c7d8e2873f529c"),
+            Some(&(1, 0))
+        );
+    }
+
+    #[test]
+    fn reject_unsupported_descriptors() {
+        assert!(parse_descriptor(&format!("wpkh({XPUB}/0'/*)")).is_err());
+        assert!(parse_descriptor(&format!("wsh({XPUB}/0/*)")).is_err());
+        assert!(parse_descriptor("wpkh(xpub/0/*)").is_err());
+        assert_eq!(
+            parse_descriptor(&format!("tr({XPUB}/0/*)")).unwrap()[0].script_type,
+            ScriptType::P2tr
+        );
+    }
+}
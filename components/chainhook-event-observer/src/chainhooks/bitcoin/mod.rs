@@ -1,30 +1,70 @@
+pub mod descriptor;
+
 use super::types::{
-    BitcoinChainhookSpecification, BitcoinPredicateType, ExactMatchingRule, HookAction,
-    InputPredicate, MatchingRule, OrdinalOperations, OutputPredicate, StacksOperations,
+    BitcoinChainhookSpecification, BitcoinPredicateType, Brc20Predicate, ExactMatchingRule,
+    FeeRatePredicate, HookAction, InputPredicate, MatchingRule, OpReturnPredicate,
+    OrdinalOperations, OutputPredicate, RuneTransferPredicate, StacksOperations, StampsPredicate,
+    TaprootScriptPathPredicate,
+};
+use crate::indexer::bitcoin::get_op_return_data;
+use crate::utils::amqp::{render_amqp_template, AmqpMessage, AmqpTemplateValues};
+use crate::utils::kafka::KafkaMessage;
+use crate::utils::nats::{nats_message_id, NatsMessage};
+use crate::utils::pubsub::PubSubMessage;
+use crate::utils::redis_streams::RedisStreamMessage;
+use crate::utils::webhooks::{
+    authorize_webhook_request, build_webhook_client, sign_webhook_request, WebhookRequest,
 };
 use crate::utils::Context;
 
 use bitcoincore_rpc::bitcoin::util::address::Payload;
 use bitcoincore_rpc::bitcoin::Address;
 use chainhook_types::{
-    BitcoinBlockData, BitcoinChainEvent, BitcoinTransactionData, OrdinalOperation,
-    StacksBaseChainOperation, TransactionIdentifier,
+    BitcoinBlockData, BitcoinChainEvent, BitcoinTransactionData, BlockIdentifier, Brc20Operation,
+    OrdinalOperation, RuneOperation, StacksBaseChainOperation, StampOperation,
+    TransactionIdentifier,
 };
 use clarity_repl::clarity::util::hash::to_hex;
+use regex::Regex;
 
-use reqwest::{Client, Method};
+use reqwest::Method;
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
 use std::str::FromStr;
 
-use reqwest::RequestBuilder;
-
 pub struct BitcoinTriggerChainhook<'a> {
     pub chainhook: &'a BitcoinChainhookSpecification,
     pub apply: Vec<(Vec<&'a BitcoinTransactionData>, &'a BitcoinBlockData)>,
     pub rollback: Vec<(Vec<&'a BitcoinTransactionData>, &'a BitcoinBlockData)>,
 }
 
+impl<'a> BitcoinTriggerChainhook<'a> {
+    /// Last block applied, or rolled back when none is applied.
+    pub fn tip_block_identifier(&self) -> Option<&BlockIdentifier> {
+        self.apply
+            .last()
+            .or(self.rollback.last())
+            .map(|(_, block)| &block.block_identifier)
+    }
+}
+
+/// Unconfirmed transactions matched by a chainhook, either admitted in the mempool or dropped
+/// from it without being confirmed.
+pub struct BitcoinMempoolTriggerChainhook<'a> {
+    pub chainhook: &'a BitcoinChainhookSpecification,
+    pub apply: Vec<&'a BitcoinTransactionData>,
+    pub rollback: Vec<(&'a BitcoinTransactionData, &'a BitcoinMempoolDropReason)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BitcoinMempoolDropReason {
+    /// Conflicting with a transaction spending some of the same outputs, either bumping the
+    /// fees (RBF) or double-spending them.
+    Replaced(TransactionIdentifier),
+    /// Expired or evicted from the mempool.
+    Evicted,
+}
+
 #[derive(Clone, Debug)]
 pub struct BitcoinApplyTransactionPayload {
     pub block: BitcoinBlockData,
@@ -48,8 +88,15 @@ pub struct BitcoinChainhookOccurrencePayload {
 }
 
 pub enum BitcoinChainhookOccurrence {
-    Http(RequestBuilder),
+    Http(WebhookRequest),
     File(String, Vec<u8>),
+    Kafka(KafkaMessage),
+    Nats(Vec<NatsMessage>),
+    Amqp(AmqpMessage),
+    RedisStream(RedisStreamMessage),
+    PubSub(PubSubMessage),
+    /// Payload streamed to the WebSocket the predicate (uuid) was registered over.
+    WebSocket(String, Vec<u8>),
     Data(BitcoinChainhookOccurrencePayload),
 }
 
@@ -126,6 +173,31 @@ pub fn evaluate_bitcoin_chainhooks_on_chain_event<'a>(
     triggered_chainhooks
 }
 
+pub fn evaluate_bitcoin_chainhooks_on_mempool_transactions<'a>(
+    transactions: &'a Vec<BitcoinTransactionData>,
+    active_chainhooks: Vec<&'a BitcoinChainhookSpecification>,
+    ctx: &Context,
+) -> Vec<BitcoinMempoolTriggerChainhook<'a>> {
+    let mut triggered_chainhooks = vec![];
+    for chainhook in active_chainhooks.into_iter() {
+        if !chainhook.predicate.is_evaluable_on_mempool() {
+            continue;
+        }
+        let apply = transactions
+            .iter()
+            .filter(|tx| chainhook.predicate.evaluate_transaction_predicate(tx, ctx))
+            .collect::<Vec<_>>();
+        if !apply.is_empty() {
+            triggered_chainhooks.push(BitcoinMempoolTriggerChainhook {
+                chainhook,
+                apply,
+                rollback: vec![],
+            })
+        }
+    }
+    triggered_chainhooks
+}
+
 pub fn serialize_bitcoin_payload_to_json<'a>(
     trigger: BitcoinTriggerChainhook<'a>,
     proofs: &HashMap<&'a TransactionIdentifier, String>,
@@ -157,6 +229,47 @@ pub fn serialize_bitcoin_payload_to_json<'a>(
     })
 }
 
+pub fn serialize_bitcoin_mempool_payload_to_json<'a>(
+    trigger: BitcoinMempoolTriggerChainhook<'a>,
+) -> JsonValue {
+    let predicate_spec = trigger.chainhook;
+    let proofs = HashMap::new();
+    let mut apply = vec![];
+    if !trigger.apply.is_empty() {
+        apply.push(json!({
+            "transactions": serialize_bitcoin_transactions_to_json(predicate_spec, &trigger.apply, &proofs),
+        }));
+    }
+    let rollback = trigger
+        .rollback
+        .into_iter()
+        .map(|(transaction, reason)| {
+            let transactions =
+                serialize_bitcoin_transactions_to_json(predicate_spec, &vec![transaction], &proofs);
+            match reason {
+                BitcoinMempoolDropReason::Replaced(replaced_by) => json!({
+                    "event": "transaction_replaced",
+                    "replaced_by": replaced_by,
+                    "transactions": transactions,
+                }),
+                BitcoinMempoolDropReason::Evicted => json!({
+                    "event": "transaction_evicted",
+                    "transactions": transactions,
+                }),
+            }
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "unconfirmed": true,
+        "apply": apply,
+        "rollback": rollback,
+        "chainhook": {
+            "uuid": trigger.chainhook.uuid,
+            "predicate": trigger.chainhook.predicate,
+        }
+    })
+}
+
 pub fn serialize_bitcoin_transactions_to_json<'a>(
     predicate_spec: &BitcoinChainhookSpecification,
     transactions: &Vec<&BitcoinTransactionData>,
@@ -198,10 +311,43 @@ pub fn serialize_bitcoin_transactions_to_json<'a>(
                     json!(transaction.metadata.ordinal_operations),
                 );
             }
+            if !transaction.metadata.brc20_operations.is_empty() {
+                metadata.insert(
+                    "brc20_operations".into(),
+                    json!(transaction.metadata.brc20_operations),
+                );
+            }
+            if !transaction.metadata.rune_operations.is_empty() {
+                metadata.insert(
+                    "rune_operations".into(),
+                    json!(transaction.metadata.rune_operations),
+                );
+            }
+            if !transaction.metadata.stamp_operations.is_empty() {
+                metadata.insert(
+                    "stamp_operations".into(),
+                    json!(transaction.metadata.stamp_operations),
+                );
+            }
+            if !transaction.metadata.sat_movements.is_empty() {
+                metadata.insert(
+                    "sat_movements".into(),
+                    json!(transaction.metadata.sat_movements),
+                );
+            }
+            if !transaction.metadata.taproot_spends.is_empty() {
+                metadata.insert(
+                    "taproot_spends".into(),
+                    json!(transaction.metadata.taproot_spends),
+                );
+            }
             metadata.insert(
                 "proof".into(),
                 json!(proofs.get(&transaction.transaction_identifier)),
             );
+            metadata.insert("fee".into(), json!(transaction.metadata.fee));
+            metadata.insert("vsize".into(), json!(transaction.metadata.vsize));
+            metadata.insert("fee_rate".into(), json!(transaction.metadata.fee_rate));
             json!({
                 "transaction_identifier": transaction.transaction_identifier,
                 "operations": transaction.operations,
@@ -217,20 +363,24 @@ pub fn handle_bitcoin_hook_action<'a>(
 ) -> Result<BitcoinChainhookOccurrence, String> {
     match &trigger.chainhook.action {
         HookAction::HttpPost(http) => {
-            let client = Client::builder()
-                .build()
-                .map_err(|e| format!("unable to build http client: {}", e.to_string()))?;
+            let client = build_webhook_client(http)?;
             let host = format!("{}", http.url);
             let method = Method::POST;
             let body = serde_json::to_vec(&serialize_bitcoin_payload_to_json(trigger, proofs))
                 .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
-            Ok(BitcoinChainhookOccurrence::Http(
-                client
-                    .request(method, &host)
-                    .header("Content-Type", "application/json")
-                    .header("Authorization", http.authorization_header.clone())
-                    .body(body),
-            ))
+            let mut request = client
+                .request(method, &host)
+                .header("Content-Type", "application/json");
+            if http.auth.is_none() && !http.authorization_header.is_empty() {
+                request = request.header("Authorization", http.authorization_header.clone());
+            }
+            if let Some(ref secret) = http.signing_secret {
+                request = sign_webhook_request(request, secret, &body);
+            }
+            Ok(BitcoinChainhookOccurrence::Http(authorize_webhook_request(
+                request.body(body),
+                http,
+            )))
         }
         HookAction::FileAppend(disk) => {
             let bytes = serde_json::to_vec(&serialize_bitcoin_payload_to_json(trigger, proofs))
@@ -240,6 +390,115 @@ pub fn handle_bitcoin_hook_action<'a>(
                 bytes,
             ))
         }
+        HookAction::Kafka(kafka) => {
+            let key = trigger
+                .tip_block_identifier()
+                .map(|block| block.hash.clone())
+                .unwrap_or_default();
+            let payload = serde_json::to_vec(&serialize_bitcoin_payload_to_json(trigger, proofs))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(BitcoinChainhookOccurrence::Kafka(KafkaMessage {
+                hook: kafka.clone(),
+                key,
+                payload,
+            }))
+        }
+        HookAction::Nats(nats) => {
+            let chainhook = trigger.chainhook;
+            let mut messages = vec![];
+            let entries = trigger
+                .rollback
+                .into_iter()
+                .map(|entry| (entry, true))
+                .chain(trigger.apply.into_iter().map(|entry| (entry, false)));
+            for ((transactions, block), rollback) in entries {
+                let transactions = if transactions.is_empty() {
+                    vec![None]
+                } else {
+                    transactions.into_iter().map(Some).collect()
+                };
+                for transaction in transactions {
+                    let id = nats_message_id(
+                        &chainhook.uuid,
+                        &block.block_identifier.hash,
+                        transaction.map(|tx| tx.transaction_identifier.hash.as_str()),
+                        rollback,
+                    );
+                    let entry = vec![(transaction.into_iter().collect::<Vec<_>>(), block)];
+                    let trigger = BitcoinTriggerChainhook {
+                        chainhook,
+                        apply: if rollback { vec![] } else { entry.clone() },
+                        rollback: if rollback { entry } else { vec![] },
+                    };
+                    let payload =
+                        serde_json::to_vec(&serialize_bitcoin_payload_to_json(trigger, proofs))
+                            .map_err(|e| {
+                                format!("unable to serialize payload {}", e.to_string())
+                            })?;
+                    messages.push(NatsMessage {
+                        hook: nats.clone(),
+                        id,
+                        payload,
+                    });
+                }
+            }
+            Ok(BitcoinChainhookOccurrence::Nats(messages))
+        }
+        HookAction::Amqp(amqp) => {
+            let values = AmqpTemplateValues {
+                predicate_uuid: &trigger.chainhook.uuid,
+                predicate_name: &trigger.chainhook.name,
+                chain: "bitcoin",
+                network: format!("{:?}", trigger.chainhook.network).to_lowercase(),
+                block: trigger.tip_block_identifier(),
+            };
+            let exchange = render_amqp_template(&amqp.exchange, &values);
+            let routing_key = render_amqp_template(&amqp.routing_key, &values);
+            let payload = serde_json::to_vec(&serialize_bitcoin_payload_to_json(trigger, proofs))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(BitcoinChainhookOccurrence::Amqp(AmqpMessage {
+                hook: amqp.clone(),
+                exchange,
+                routing_key,
+                payload,
+            }))
+        }
+        HookAction::RedisStream(redis) => {
+            let predicate_uuid = trigger.chainhook.uuid.clone();
+            let block_hash = trigger
+                .tip_block_identifier()
+                .map(|block| block.hash.clone())
+                .unwrap_or_default();
+            let payload = serde_json::to_vec(&serialize_bitcoin_payload_to_json(trigger, proofs))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(BitcoinChainhookOccurrence::RedisStream(
+                RedisStreamMessage {
+                    hook: redis.clone(),
+                    predicate_uuid,
+                    block_hash,
+                    payload,
+                },
+            ))
+        }
+        HookAction::PubSub(pubsub) => {
+            let ordering_key = trigger.chainhook.uuid.clone();
+            let payload = serde_json::to_vec(&serialize_bitcoin_payload_to_json(trigger, proofs))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(BitcoinChainhookOccurrence::PubSub(PubSubMessage {
+                hook: pubsub.clone(),
+                ordering_key,
+                payload,
+            }))
+        }
+        HookAction::WebSocket => {
+            let predicate_uuid = trigger.chainhook.uuid.clone();
+            let payload = serde_json::to_vec(&serialize_bitcoin_payload_to_json(trigger, proofs))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(BitcoinChainhookOccurrence::WebSocket(
+                predicate_uuid,
+                payload,
+            ))
+        }
         HookAction::Noop => Ok(BitcoinChainhookOccurrence::Data(
             BitcoinChainhookOccurrencePayload {
                 apply: trigger
@@ -274,7 +533,169 @@ pub fn handle_bitcoin_hook_action<'a>(
     }
 }
 
+/// Unconfirmed transactions are not delivered to the chainhooks consuming their payloads
+/// in-process (`noop` action).
+pub fn handle_bitcoin_mempool_hook_action<'a>(
+    trigger: BitcoinMempoolTriggerChainhook<'a>,
+) -> Result<Option<BitcoinChainhookOccurrence>, String> {
+    match &trigger.chainhook.action {
+        HookAction::HttpPost(http) => {
+            let client = build_webhook_client(http)?;
+            let body = serde_json::to_vec(&serialize_bitcoin_mempool_payload_to_json(trigger))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            let mut request = client
+                .request(Method::POST, &http.url)
+                .header("Content-Type", "application/json");
+            if http.auth.is_none() && !http.authorization_header.is_empty() {
+                request = request.header("Authorization", http.authorization_header.clone());
+            }
+            if let Some(ref secret) = http.signing_secret {
+                request = sign_webhook_request(request, secret, &body);
+            }
+            Ok(Some(BitcoinChainhookOccurrence::Http(
+                authorize_webhook_request(request.body(body), http),
+            )))
+        }
+        HookAction::FileAppend(disk) => {
+            let bytes = serde_json::to_vec(&serialize_bitcoin_mempool_payload_to_json(trigger))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(Some(BitcoinChainhookOccurrence::File(
+                disk.path.to_string(),
+                bytes,
+            )))
+        }
+        HookAction::Kafka(kafka) => {
+            let key = trigger
+                .apply
+                .first()
+                .copied()
+                .or(trigger.rollback.first().map(|(tx, _)| *tx))
+                .map(|tx| tx.transaction_identifier.hash.clone())
+                .unwrap_or_default();
+            let payload = serde_json::to_vec(&serialize_bitcoin_mempool_payload_to_json(trigger))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(Some(BitcoinChainhookOccurrence::Kafka(KafkaMessage {
+                hook: kafka.clone(),
+                key,
+                payload,
+            })))
+        }
+        HookAction::Nats(nats) => {
+            let chainhook = trigger.chainhook;
+            let mut messages = vec![];
+            for (transaction, reason) in trigger.rollback.into_iter() {
+                let trigger = BitcoinMempoolTriggerChainhook {
+                    chainhook,
+                    apply: vec![],
+                    rollback: vec![(transaction, reason)],
+                };
+                let payload =
+                    serde_json::to_vec(&serialize_bitcoin_mempool_payload_to_json(trigger))
+                        .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+                messages.push(NatsMessage {
+                    hook: nats.clone(),
+                    id: nats_message_id(
+                        &chainhook.uuid,
+                        "mempool",
+                        Some(transaction.transaction_identifier.hash.as_str()),
+                        true,
+                    ),
+                    payload,
+                });
+            }
+            for transaction in trigger.apply.into_iter() {
+                let trigger = BitcoinMempoolTriggerChainhook {
+                    chainhook,
+                    apply: vec![transaction],
+                    rollback: vec![],
+                };
+                let payload =
+                    serde_json::to_vec(&serialize_bitcoin_mempool_payload_to_json(trigger))
+                        .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+                messages.push(NatsMessage {
+                    hook: nats.clone(),
+                    id: nats_message_id(
+                        &chainhook.uuid,
+                        "mempool",
+                        Some(transaction.transaction_identifier.hash.as_str()),
+                        false,
+                    ),
+                    payload,
+                });
+            }
+            Ok(Some(BitcoinChainhookOccurrence::Nats(messages)))
+        }
+        HookAction::Amqp(amqp) => {
+            let values = AmqpTemplateValues {
+                predicate_uuid: &trigger.chainhook.uuid,
+                predicate_name: &trigger.chainhook.name,
+                chain: "bitcoin",
+                network: format!("{:?}", trigger.chainhook.network).to_lowercase(),
+                block: None,
+            };
+            let exchange = render_amqp_template(&amqp.exchange, &values);
+            let routing_key = render_amqp_template(&amqp.routing_key, &values);
+            let payload = serde_json::to_vec(&serialize_bitcoin_mempool_payload_to_json(trigger))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(Some(BitcoinChainhookOccurrence::Amqp(AmqpMessage {
+                hook: amqp.clone(),
+                exchange,
+                routing_key,
+                payload,
+            })))
+        }
+        HookAction::RedisStream(redis) => {
+            let predicate_uuid = trigger.chainhook.uuid.clone();
+            let payload = serde_json::to_vec(&serialize_bitcoin_mempool_payload_to_json(trigger))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(Some(BitcoinChainhookOccurrence::RedisStream(
+                RedisStreamMessage {
+                    hook: redis.clone(),
+                    predicate_uuid,
+                    block_hash: "mempool".into(),
+                    payload,
+                },
+            )))
+        }
+        HookAction::PubSub(pubsub) => {
+            let ordering_key = trigger.chainhook.uuid.clone();
+            let payload = serde_json::to_vec(&serialize_bitcoin_mempool_payload_to_json(trigger))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(Some(BitcoinChainhookOccurrence::PubSub(PubSubMessage {
+                hook: pubsub.clone(),
+                ordering_key,
+                payload,
+            })))
+        }
+        HookAction::WebSocket => {
+            let predicate_uuid = trigger.chainhook.uuid.clone();
+            let payload = serde_json::to_vec(&serialize_bitcoin_mempool_payload_to_json(trigger))
+                .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(Some(BitcoinChainhookOccurrence::WebSocket(
+                predicate_uuid,
+                payload,
+            )))
+        }
+        HookAction::Noop => Ok(None),
+    }
+}
+
 impl BitcoinPredicateType {
+    /// Scopes relying on the hord database, on the state of a wallet or matching every
+    /// transaction are only evaluated against confirmed blocks.
+    pub fn is_evaluable_on_mempool(&self) -> bool {
+        !matches!(
+            self,
+            BitcoinPredicateType::Block
+                | BitcoinPredicateType::OrdinalsProtocol(_)
+                | BitcoinPredicateType::Brc20(_)
+                | BitcoinPredicateType::RunesEtched
+                | BitcoinPredicateType::RuneTransfer(_)
+                | BitcoinPredicateType::RareSats(_)
+                | BitcoinPredicateType::Descriptor(_)
+        )
+    }
+
     pub fn evaluate_transaction_predicate(
         &self,
         tx: &BitcoinTransactionData,
@@ -413,17 +834,180 @@ impl BitcoinPredicateType {
                 }
                 false
             }
-            BitcoinPredicateType::OrdinalsProtocol(OrdinalOperations::InscriptionFeed) => {
+            BitcoinPredicateType::OrdinalsProtocol(OrdinalOperations::InscriptionFeed {
+                rarity,
+            }) => {
                 for op in tx.metadata.ordinal_operations.iter() {
-                    if let OrdinalOperation::InscriptionRevealed(_) = op {
-                        return true;
+                    match (op, rarity) {
+                        (_, None) => return true,
+                        (OrdinalOperation::InscriptionRevealed(data), Some(rarity)) => {
+                            if rarity
+                                .iter()
+                                .any(|rarity| rarity.to_string() == data.ordinal_rarity)
+                            {
+                                return true;
+                            }
+                        }
+                        (OrdinalOperation::InscriptionTransferred(_), Some(_)) => {}
+                    }
+                }
+                false
+            }
+            BitcoinPredicateType::OrdinalsProtocol(OrdinalOperations::InscriptionRevealed {
+                content_type,
+                max_size,
+            }) => tx.metadata.ordinal_operations.iter().any(|op| match op {
+                OrdinalOperation::InscriptionRevealed(data) => {
+                    content_type.as_ref().map_or(true, |content_type| {
+                        OrdinalOperations::matches_content_type(content_type, &data.content_type)
+                    }) && max_size.map_or(true, |max_size| data.content_length as u64 <= max_size)
+                }
+                OrdinalOperation::InscriptionTransferred(_) => false,
+            }),
+            BitcoinPredicateType::OrdinalsProtocol(OrdinalOperations::InscriptionTransferred {
+                inscription_ids,
+                parent_inscription_ids,
+            }) => tx.metadata.ordinal_operations.iter().any(|op| match op {
+                OrdinalOperation::InscriptionTransferred(data) => {
+                    match (inscription_ids, parent_inscription_ids) {
+                        (None, None) => true,
+                        _ => {
+                            inscription_ids
+                                .iter()
+                                .flatten()
+                                .any(|id| id.eq(&data.inscription_id))
+                                || parent_inscription_ids
+                                    .iter()
+                                    .flatten()
+                                    .any(|id| data.parent_inscription_id.as_ref() == Some(id))
+                        }
+                    }
+                }
+                OrdinalOperation::InscriptionRevealed(_) => false,
+            }),
+            BitcoinPredicateType::Brc20(Brc20Predicate { ticker }) => {
+                for op in tx.metadata.brc20_operations.iter() {
+                    let tick = match op {
+                        Brc20Operation::TokenDeployed(data) => &data.tick,
+                        Brc20Operation::TokenMinted(data)
+                        | Brc20Operation::TransferInscribed(data) => &data.tick,
+                        Brc20Operation::TransferSent(data) => &data.tick,
+                    };
+                    match ticker {
+                        Some(ticker) if !ticker.eq_ignore_ascii_case(tick) => {}
+                        _ => return true,
+                    }
+                }
+                false
+            }
+            BitcoinPredicateType::RunesEtched => tx
+                .metadata
+                .rune_operations
+                .iter()
+                .any(|op| matches!(op, RuneOperation::RuneEtched(_))),
+            BitcoinPredicateType::RuneTransfer(RuneTransferPredicate { rune_id }) => {
+                tx.metadata.rune_operations.iter().any(|op| match op {
+                    RuneOperation::RuneTransferred(data) => data.rune_id.eq(rune_id),
+                    RuneOperation::RuneBurned(data) => data.rune_id.eq(rune_id),
+                    _ => false,
+                })
+            }
+            BitcoinPredicateType::Stamps(StampsPredicate { ticker }) => tx
+                .metadata
+                .stamp_operations
+                .iter()
+                .any(|op| match (op, ticker) {
+                    (_, None) => true,
+                    (StampOperation::Src20(data), Some(ticker)) => {
+                        ticker.eq_ignore_ascii_case(&data.tick)
                     }
-                    if let OrdinalOperation::InscriptionTransferred(_) = op {
+                    (StampOperation::StampRevealed(_), Some(_)) => false,
+                }),
+            BitcoinPredicateType::OpReturn(rule) => {
+                let hex_rule = |hex_bytes: &String| {
+                    hex::decode(hex_bytes.strip_prefix("0x").unwrap_or(hex_bytes)).ok()
+                };
+                let regex = match rule {
+                    OpReturnPredicate::Regex(pattern) => match Regex::new(pattern) {
+                        Ok(regex) => Some(regex),
+                        Err(_) => return false,
+                    },
+                    _ => None,
+                };
+                for output in tx.metadata.outputs.iter() {
+                    let data = match get_op_return_data(&output.script_pubkey) {
+                        Some(data) => data,
+                        None => continue,
+                    };
+                    let matched = match (rule, &regex) {
+                        (OpReturnPredicate::StartsWith(prefix), _) => {
+                            hex_rule(prefix).map_or(false, |prefix| data.starts_with(&prefix))
+                        }
+                        (OpReturnPredicate::Hex(expected), _) => {
+                            hex_rule(expected).map_or(false, |expected| data == expected)
+                        }
+                        (OpReturnPredicate::Regex(_), Some(regex)) => {
+                            regex.is_match(&String::from_utf8_lossy(&data))
+                        }
+                        (OpReturnPredicate::Regex(_), None) => false,
+                    };
+                    if matched {
                         return true;
                     }
                 }
                 false
             }
+            BitcoinPredicateType::OutputValue(predicate) => {
+                let address_bytes = match &predicate.address {
+                    Some(encoded_address) => match Address::from_str(encoded_address) {
+                        Ok(address) => Some(to_hex(address.script_pubkey().as_bytes())),
+                        Err(_) => return false,
+                    },
+                    None => None,
+                };
+                let mut values = tx
+                    .metadata
+                    .outputs
+                    .iter()
+                    .filter(|output| match &address_bytes {
+                        Some(address_bytes) => output.script_pubkey[2..].eq(address_bytes),
+                        None => true,
+                    })
+                    .map(|output| output.value)
+                    .peekable();
+                if predicate.total {
+                    values.peek().is_some() && predicate.matches_value(values.sum())
+                } else {
+                    values.any(|value| predicate.matches_value(value))
+                }
+            }
+            BitcoinPredicateType::FeeRate(FeeRatePredicate { above, below }) => {
+                !tx.metadata.inputs.is_empty()
+                    && above.map_or(true, |above| tx.metadata.fee_rate > above)
+                    && below.map_or(true, |below| tx.metadata.fee_rate < below)
+            }
+            BitcoinPredicateType::TaprootScriptPath(TaprootScriptPathPredicate {
+                tapleaf_hash,
+            }) => {
+                let expected = tapleaf_hash.strip_prefix("0x").unwrap_or(tapleaf_hash);
+                tx.metadata
+                    .taproot_spends
+                    .iter()
+                    .any(|spend| match &spend.tapleaf_hash {
+                        Some(hash) => hash[2..].eq_ignore_ascii_case(expected),
+                        None => false,
+                    })
+            }
+            BitcoinPredicateType::Descriptor(predicate) => predicate.wallet.evaluate_transaction(
+                tx,
+                &predicate.descriptor,
+                predicate.gap_limit,
+            ),
+            BitcoinPredicateType::RareSats(predicate) => tx
+                .metadata
+                .sat_movements
+                .iter()
+                .any(|movement| predicate.matches(movement.ordinal_number, &movement.rarity)),
         }
     }
 }
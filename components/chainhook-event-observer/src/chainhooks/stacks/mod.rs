@@ -1,3 +1,11 @@
+use crate::utils::amqp::{render_amqp_template, AmqpMessage, AmqpTemplateValues};
+use crate::utils::kafka::KafkaMessage;
+use crate::utils::nats::{nats_message_id, NatsMessage};
+use crate::utils::pubsub::PubSubMessage;
+use crate::utils::redis_streams::RedisStreamMessage;
+use crate::utils::webhooks::{
+    authorize_webhook_request, build_webhook_client, sign_webhook_request, WebhookRequest,
+};
 use crate::utils::{AbstractStacksBlock, Context};
 
 use super::types::{
@@ -12,13 +20,11 @@ use clarity_repl::clarity::codec::StacksMessageCodec;
 use clarity_repl::clarity::util::hash::hex_bytes;
 use clarity_repl::clarity::vm::types::{CharType, SequenceData, Value as ClarityValue};
 use hiro_system_kit::slog;
-use reqwest::{Client, Method};
+use reqwest::Method;
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
 use std::io::Cursor;
 
-use reqwest::RequestBuilder;
-
 pub struct StacksTriggerChainhook<'a> {
     pub chainhook: &'a StacksChainhookSpecification,
     pub apply: Vec<(Vec<&'a StacksTransactionData>, &'a dyn AbstractStacksBlock)>,
@@ -49,8 +55,15 @@ pub struct StacksChainhookOccurrencePayload {
     pub chainhook: StacksChainhookPayload,
 }
 pub enum StacksChainhookOccurrence {
-    Http(RequestBuilder),
+    Http(WebhookRequest),
     File(String, Vec<u8>),
+    Kafka(KafkaMessage),
+    Nats(Vec<NatsMessage>),
+    Amqp(AmqpMessage),
+    RedisStream(RedisStreamMessage),
+    PubSub(PubSubMessage),
+    /// Payload streamed to the WebSocket the predicate (uuid) was registered over.
+    WebSocket(String, Vec<u8>),
     Data(StacksChainhookOccurrencePayload),
 }
 
@@ -686,19 +699,21 @@ pub fn handle_stacks_hook_action<'a>(
 ) -> Result<StacksChainhookOccurrence, String> {
     match &trigger.chainhook.action {
         HookAction::HttpPost(http) => {
-            let client = Client::builder()
-                .build()
-                .map_err(|e| format!("unable to build http client: {}", e.to_string()))?;
+            let client = build_webhook_client(http)?;
             let host = format!("{}", http.url);
             let method = Method::POST;
             let body = serde_json::to_vec(&serialize_stacks_payload_to_json(trigger, proofs, ctx))
                 .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
-            Ok(StacksChainhookOccurrence::Http(
-                client
-                    .request(method, &host)
-                    .header("Content-Type", "application/json")
-                    .body(body),
-            ))
+            let mut request = client
+                .request(method, &host)
+                .header("Content-Type", "application/json");
+            if let Some(ref secret) = http.signing_secret {
+                request = sign_webhook_request(request, secret, &body);
+            }
+            Ok(StacksChainhookOccurrence::Http(authorize_webhook_request(
+                request.body(body),
+                http,
+            )))
         }
         HookAction::FileAppend(disk) => {
             let bytes = serde_json::to_vec(&serialize_stacks_payload_to_json(trigger, proofs, ctx))
@@ -708,6 +723,126 @@ pub fn handle_stacks_hook_action<'a>(
                 bytes,
             ))
         }
+        HookAction::Kafka(kafka) => {
+            let key = trigger
+                .apply
+                .last()
+                .or(trigger.rollback.last())
+                .map(|(_, block)| block.get_identifier().hash.clone())
+                .unwrap_or_default();
+            let payload =
+                serde_json::to_vec(&serialize_stacks_payload_to_json(trigger, proofs, ctx))
+                    .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(StacksChainhookOccurrence::Kafka(KafkaMessage {
+                hook: kafka.clone(),
+                key,
+                payload,
+            }))
+        }
+        HookAction::Nats(nats) => {
+            let chainhook = trigger.chainhook;
+            let mut messages = vec![];
+            let entries = trigger
+                .rollback
+                .into_iter()
+                .map(|entry| (entry, true))
+                .chain(trigger.apply.into_iter().map(|entry| (entry, false)));
+            for ((transactions, block), rollback) in entries {
+                let transactions = if transactions.is_empty() {
+                    vec![None]
+                } else {
+                    transactions.into_iter().map(Some).collect()
+                };
+                for transaction in transactions {
+                    let id = nats_message_id(
+                        &chainhook.uuid,
+                        &block.get_identifier().hash,
+                        transaction.map(|tx| tx.transaction_identifier.hash.as_str()),
+                        rollback,
+                    );
+                    let entry = vec![(transaction.into_iter().collect::<Vec<_>>(), block)];
+                    let trigger = StacksTriggerChainhook {
+                        chainhook,
+                        apply: if rollback { vec![] } else { entry.clone() },
+                        rollback: if rollback { entry } else { vec![] },
+                    };
+                    let payload =
+                        serde_json::to_vec(&serialize_stacks_payload_to_json(trigger, proofs, ctx))
+                            .map_err(|e| {
+                                format!("unable to serialize payload {}", e.to_string())
+                            })?;
+                    messages.push(NatsMessage {
+                        hook: nats.clone(),
+                        id,
+                        payload,
+                    });
+                }
+            }
+            Ok(StacksChainhookOccurrence::Nats(messages))
+        }
+        HookAction::Amqp(amqp) => {
+            let values = AmqpTemplateValues {
+                predicate_uuid: &trigger.chainhook.uuid,
+                predicate_name: &trigger.chainhook.name,
+                chain: "stacks",
+                network: format!("{:?}", trigger.chainhook.network).to_lowercase(),
+                block: trigger
+                    .apply
+                    .last()
+                    .or(trigger.rollback.last())
+                    .map(|(_, block)| block.get_identifier()),
+            };
+            let exchange = render_amqp_template(&amqp.exchange, &values);
+            let routing_key = render_amqp_template(&amqp.routing_key, &values);
+            let payload =
+                serde_json::to_vec(&serialize_stacks_payload_to_json(trigger, proofs, ctx))
+                    .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(StacksChainhookOccurrence::Amqp(AmqpMessage {
+                hook: amqp.clone(),
+                exchange,
+                routing_key,
+                payload,
+            }))
+        }
+        HookAction::RedisStream(redis) => {
+            let predicate_uuid = trigger.chainhook.uuid.clone();
+            let block_hash = trigger
+                .apply
+                .last()
+                .or(trigger.rollback.last())
+                .map(|(_, block)| block.get_identifier().hash.clone())
+                .unwrap_or_default();
+            let payload =
+                serde_json::to_vec(&serialize_stacks_payload_to_json(trigger, proofs, ctx))
+                    .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(StacksChainhookOccurrence::RedisStream(RedisStreamMessage {
+                hook: redis.clone(),
+                predicate_uuid,
+                block_hash,
+                payload,
+            }))
+        }
+        HookAction::PubSub(pubsub) => {
+            let ordering_key = trigger.chainhook.uuid.clone();
+            let payload =
+                serde_json::to_vec(&serialize_stacks_payload_to_json(trigger, proofs, ctx))
+                    .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(StacksChainhookOccurrence::PubSub(PubSubMessage {
+                hook: pubsub.clone(),
+                ordering_key,
+                payload,
+            }))
+        }
+        HookAction::WebSocket => {
+            let predicate_uuid = trigger.chainhook.uuid.clone();
+            let payload =
+                serde_json::to_vec(&serialize_stacks_payload_to_json(trigger, proofs, ctx))
+                    .map_err(|e| format!("unable to serialize payload {}", e.to_string()))?;
+            Ok(StacksChainhookOccurrence::WebSocket(
+                predicate_uuid,
+                payload,
+            ))
+        }
         HookAction::Noop => Ok(StacksChainhookOccurrence::Data(
             StacksChainhookOccurrencePayload {
                 apply: trigger
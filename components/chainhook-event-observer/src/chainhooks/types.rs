@@ -1,15 +1,21 @@
 use std::collections::BTreeMap;
+use std::str::FromStr;
 
 use clarity_repl::clarity::util::hash::hex_bytes;
+use regex::Regex;
 use reqwest::Url;
 use serde::ser::{SerializeSeq, Serializer};
 use serde::{Deserialize, Serialize};
 
+use bitcoincore_rpc::bitcoin::Address;
 use chainhook_types::{BitcoinNetwork, StacksNetwork};
 
 use schemars::JsonSchema;
 
+use crate::chainhooks::bitcoin::descriptor::{parse_descriptor, WatchedWallet};
+use crate::hord::ord::rarity::Rarity;
 use crate::observer::ApiKey;
+use crate::utils::amqp::validate_amqp_template;
 
 #[derive(Clone, Debug)]
 pub struct ChainhookConfig {
@@ -175,6 +181,13 @@ impl ChainhookSpecification {
         }
     }
 
+    pub fn action(&self) -> &HookAction {
+        match &self {
+            Self::Bitcoin(data) => &data.action,
+            Self::Stacks(data) => &data.action,
+        }
+    }
+
     pub fn set_owner_uuid(&mut self, api_key: &ApiKey) {
         match self {
             Self::Bitcoin(ref mut data) => {
@@ -221,6 +234,7 @@ impl ChainhookSpecification {
         match &self {
             Self::Bitcoin(data) => {
                 let _ = data.action.validate()?;
+                let _ = data.predicate.validate()?;
             }
             Self::Stacks(data) => {
                 let _ = data.action.validate()?;
@@ -266,6 +280,7 @@ impl ChainhookFullSpecification {
             Self::Bitcoin(data) => {
                 for (_, spec) in data.networks.iter() {
                     let _ = spec.action.validate()?;
+                    let _ = spec.predicate.validate()?;
                 }
             }
             Self::Stacks(data) => {
@@ -408,6 +423,16 @@ pub struct StacksChainhookNetworkSpecification {
 pub enum HookAction {
     HttpPost(HttpHook),
     FileAppend(FileHook),
+    Kafka(KafkaHook),
+    Nats(NatsHook),
+    Amqp(AmqpHook),
+    RedisStream(RedisStreamHook),
+    #[serde(rename = "pubsub")]
+    PubSub(PubSubHook),
+    /// Occurrences streamed to the WebSocket the predicate was registered over. Set by the
+    /// WebSocket server, predicates can't be registered with this action otherwise.
+    #[serde(rename = "websocket", skip_deserializing)]
+    WebSocket,
     Noop,
 }
 
@@ -415,10 +440,103 @@ impl HookAction {
     pub fn validate(&self) -> Result<(), String> {
         match &self {
             HookAction::HttpPost(spec) => {
-                let _ = Url::parse(&spec.url)
+                let url = Url::parse(&spec.url)
                     .map_err(|e| format!("hook action url invalid ({})", e.to_string()))?;
+                if let Some(ref tls) = spec.tls {
+                    if url.scheme() != "https" {
+                        return Err("http_post action tls requires an https url".into());
+                    }
+                    if tls.client_certificate_path.is_some() != tls.client_key_path.is_some() {
+                        return Err(
+                            "http_post action tls requires both client_certificate_path and client_key_path".into(),
+                        );
+                    }
+                }
+                match spec.auth {
+                    Some(_) if !spec.authorization_header.is_empty() => {
+                        return Err(
+                            "http_post action can not specify both auth and authorization_header"
+                                .into(),
+                        );
+                    }
+                    Some(HttpAuth::Bearer { ref token }) if token.is_empty() => {
+                        return Err("http_post action bearer token can not be empty".into());
+                    }
+                    Some(HttpAuth::OAuth2ClientCredentials(ref credentials)) => {
+                        let _ = Url::parse(&credentials.token_url)
+                            .map_err(|e| format!("oauth2 token url invalid ({})", e.to_string()))?;
+                        if credentials.client_id.is_empty() {
+                            return Err("oauth2 client_id can not be empty".into());
+                        }
+                    }
+                    _ => {}
+                }
+                if let Some(ref secret) = spec.signing_secret {
+                    if secret.is_empty() {
+                        return Err("http_post action signing_secret can not be empty".into());
+                    }
+                }
             }
             HookAction::FileAppend(_) => {}
+            HookAction::Kafka(spec) => {
+                if !cfg!(feature = "kafka") {
+                    return Err(
+                        "kafka actions require chainhook to be built with the kafka feature".into(),
+                    );
+                }
+                if spec.brokers.is_empty() {
+                    return Err("kafka action requires at least one broker".into());
+                }
+                if spec.topic.is_empty() {
+                    return Err("kafka action requires a topic".into());
+                }
+            }
+            HookAction::Nats(spec) => {
+                if !cfg!(feature = "nats") {
+                    return Err(
+                        "nats actions require chainhook to be built with the nats feature".into(),
+                    );
+                }
+                if spec.subject.is_empty() {
+                    return Err("nats action requires a subject".into());
+                }
+            }
+            HookAction::Amqp(spec) => {
+                if !cfg!(feature = "amqp") {
+                    return Err(
+                        "amqp actions require chainhook to be built with the amqp feature".into(),
+                    );
+                }
+                validate_amqp_template(&spec.exchange)?;
+                validate_amqp_template(&spec.routing_key)?;
+            }
+            HookAction::RedisStream(spec) => {
+                if !cfg!(feature = "redis_streams") {
+                    return Err(
+                        "redis_stream actions require chainhook to be built with the redis_streams feature"
+                            .into(),
+                    );
+                }
+                if spec.stream.is_empty() {
+                    return Err("redis_stream action requires a stream".into());
+                }
+            }
+            HookAction::PubSub(spec) => {
+                if !cfg!(feature = "pubsub") {
+                    return Err(
+                        "pubsub actions require chainhook to be built with the pubsub feature"
+                            .into(),
+                    );
+                }
+                if spec.project_id.is_empty() || spec.topic.is_empty() {
+                    return Err("pubsub action requires a project_id and a topic".into());
+                }
+                if let Some(ref endpoint) = spec.endpoint {
+                    let _ = Url::parse(endpoint)
+                        .map_err(|e| format!("pubsub endpoint invalid ({})", e.to_string()))?;
+                }
+            }
+            HookAction::WebSocket => {}
             HookAction::Noop => {}
         }
         Ok(())
@@ -429,7 +547,53 @@ impl HookAction {
 #[serde(rename_all = "snake_case")]
 pub struct HttpHook {
     pub url: String,
+    /// Value of the `Authorization` header of the bitcoin deliveries, superseded by `auth`.
+    #[serde(default)]
     pub authorization_header: String,
+    /// Secret the deliveries are signed with, see `utils::webhooks::webhook_signature`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_secret: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<HttpTlsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<HttpAuth>,
+}
+
+/// Bearer token the deliveries of an `http_post` action are authorized with.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpAuth {
+    Bearer {
+        token: String,
+    },
+    /// Token requested with the client credentials grant, and refreshed before it expires.
+    #[serde(rename = "oauth2_client_credentials")]
+    OAuth2ClientCredentials(OAuth2ClientCredentials),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct OAuth2ClientCredentials {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audience: Option<String>,
+}
+
+/// Certificate authorities and client certificate of an `http_post` action, for deliveries to
+/// endpoints requiring mutual TLS. Paths of PEM files on the chainhook host.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct HttpTlsConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_certificate_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key_path: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
@@ -438,6 +602,136 @@ pub struct FileHook {
     pub path: String,
 }
 
+/// Occurrences are produced to `topic`, keyed by the hash of the block they apply, for the
+/// occurrences of a block to land on the same partition.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct KafkaHook {
+    pub brokers: Vec<String>,
+    pub topic: String,
+    #[serde(default)]
+    pub acks: KafkaAcks,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sasl: Option<KafkaSaslConfig>,
+    #[serde(default)]
+    pub tls: bool,
+}
+
+impl KafkaHook {
+    pub fn security_protocol(&self) -> &'static str {
+        match (self.sasl.is_some(), self.tls) {
+            (true, true) => "sasl_ssl",
+            (true, false) => "sasl_plaintext",
+            (false, true) => "ssl",
+            (false, false) => "plaintext",
+        }
+    }
+}
+
+/// Acknowledgements the leader of a partition waits for before a message is considered
+/// produced.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaAcks {
+    None,
+    Leader,
+    All,
+}
+
+impl Default for KafkaAcks {
+    fn default() -> Self {
+        KafkaAcks::All
+    }
+}
+
+impl KafkaAcks {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KafkaAcks::None => "0",
+            KafkaAcks::Leader => "1",
+            KafkaAcks::All => "all",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct KafkaSaslConfig {
+    pub mechanism: KafkaSaslMechanism,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub enum KafkaSaslMechanism {
+    #[serde(rename = "PLAIN")]
+    Plain,
+    #[serde(rename = "SCRAM-SHA-256")]
+    ScramSha256,
+    #[serde(rename = "SCRAM-SHA-512")]
+    ScramSha512,
+}
+
+impl KafkaSaslMechanism {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KafkaSaslMechanism::Plain => "PLAIN",
+            KafkaSaslMechanism::ScramSha256 => "SCRAM-SHA-256",
+            KafkaSaslMechanism::ScramSha512 => "SCRAM-SHA-512",
+        }
+    }
+}
+
+/// Occurrences are published to a JetStream `subject`, one message per transaction, with a
+/// `Nats-Msg-Id` header for the stream to drop the messages published twice.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct NatsHook {
+    pub url: String,
+    pub subject: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials_file: Option<String>,
+}
+
+/// Occurrences are published to `exchange` with `routing_key`, both templates rendered per
+/// occurrence with the placeholders listed in `AMQP_TEMPLATE_PLACEHOLDERS`, e.g.
+/// `{chain}.{network}.{predicate_name}`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AmqpHook {
+    pub url: String,
+    #[serde(default)]
+    pub exchange: String,
+    pub routing_key: String,
+}
+
+/// Occurrences are appended to `stream`, trimmed to about `max_len` entries.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct RedisStreamHook {
+    pub url: String,
+    pub stream: String,
+    #[serde(default = "default_redis_stream_max_len")]
+    pub max_len: u64,
+}
+
+fn default_redis_stream_max_len() -> u64 {
+    10_000
+}
+
+/// Occurrences are published to `topic` with the uuid of the predicate as ordering key, for
+/// the subscriptions with message ordering enabled to receive the occurrences of a predicate
+/// in order. Ordering is only guaranteed within a region: `endpoint` can point to a regional
+/// endpoint, e.g. `https://us-east1-pubsub.googleapis.com`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PubSubHook {
+    pub project_id: String,
+    pub topic: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct ScriptTemplate {
     pub instructions: Vec<ScriptInstruction>,
@@ -503,6 +797,57 @@ pub enum BitcoinPredicateType {
     Outputs(OutputPredicate),
     StacksProtocol(StacksOperations),
     OrdinalsProtocol(OrdinalOperations),
+    Brc20(Brc20Predicate),
+    RunesEtched,
+    RuneTransfer(RuneTransferPredicate),
+    Stamps(StampsPredicate),
+    RareSats(RareSatsPredicate),
+    OpReturn(OpReturnPredicate),
+    OutputValue(OutputValuePredicate),
+    Descriptor(DescriptorPredicate),
+    TaprootScriptPath(TaprootScriptPathPredicate),
+    FeeRate(FeeRatePredicate),
+}
+
+impl BitcoinPredicateType {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self {
+            BitcoinPredicateType::OpReturn(OpReturnPredicate::StartsWith(hex_bytes))
+            | BitcoinPredicateType::OpReturn(OpReturnPredicate::Hex(hex_bytes)) => {
+                let _ = hex::decode(hex_bytes.strip_prefix("0x").unwrap_or(hex_bytes))
+                    .map_err(|e| format!("op_return predicate invalid ({})", e.to_string()))?;
+            }
+            BitcoinPredicateType::OpReturn(OpReturnPredicate::Regex(pattern)) => {
+                let _ = Regex::new(pattern)
+                    .map_err(|e| format!("op_return predicate invalid ({})", e.to_string()))?;
+            }
+            BitcoinPredicateType::OutputValue(OutputValuePredicate {
+                address: Some(address),
+                ..
+            }) => {
+                let _ = Address::from_str(address)
+                    .map_err(|e| format!("output_value predicate invalid ({})", e.to_string()))?;
+            }
+            BitcoinPredicateType::TaprootScriptPath(TaprootScriptPathPredicate {
+                tapleaf_hash,
+            }) => {
+                let hash = hex::decode(tapleaf_hash.strip_prefix("0x").unwrap_or(tapleaf_hash))
+                    .map_err(|e| format!("taproot_script_path predicate invalid ({})", e))?;
+                if hash.len() != 32 {
+                    return Err(
+                        "taproot_script_path predicate invalid (tapleaf hash should be 32 bytes)"
+                            .into(),
+                    );
+                }
+            }
+            BitcoinPredicateType::Descriptor(predicate) => {
+                let _ = parse_descriptor(&predicate.descriptor)
+                    .map_err(|e| format!("descriptor predicate invalid ({})", e))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
@@ -535,7 +880,166 @@ pub enum StacksOperations {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case", tag = "operation")]
 pub enum OrdinalOperations {
-    InscriptionFeed,
+    InscriptionFeed {
+        /// Only match the inscriptions revealed on sats of these rarities.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rarity: Option<Vec<Rarity>>,
+    },
+    /// Matches the inscription reveals only, transfers are ignored.
+    InscriptionRevealed {
+        /// Mime type of the content, either exact (`text/plain`) or of a whole type (`image/*`).
+        /// Parameters such as `;charset=utf-8` are ignored.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content_type: Option<String>,
+        /// Maximum content length, in bytes.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_size: Option<u64>,
+    },
+    /// Matches the transfers of the listed inscriptions, or of the children of the listed
+    /// parents. Every transfer is matched when both lists are omitted.
+    InscriptionTransferred {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        inscription_ids: Option<Vec<String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parent_inscription_ids: Option<Vec<String>>,
+    },
+}
+
+impl OrdinalOperations {
+    pub fn matches_content_type(pattern: &str, content_type: &str) -> bool {
+        let essence = |mime: &str| mime.split(';').next().unwrap_or("").trim().to_lowercase();
+        let (pattern, content_type) = (essence(pattern), essence(content_type));
+        match pattern.strip_suffix("/*") {
+            Some(top_level_type) => content_type
+                .split_once('/')
+                .map_or(false, |(t, _)| t == top_level_type),
+            None => pattern == content_type,
+        }
+    }
+}
+
+/// Matches the transactions applying BRC-20 operations, requires the BRC-20 indexing.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Brc20Predicate {
+    /// Only match the operations on this ticker (case insensitive).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ticker: Option<String>,
+}
+
+/// Matches the transactions transferring or burning a rune.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct RuneTransferPredicate {
+    /// Id of the rune, formatted as `block:tx`.
+    pub rune_id: String,
+}
+
+/// Matches the transactions embedding a stamp.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct StampsPredicate {
+    /// Only match the SRC-20 operations on this ticker (case insensitive).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ticker: Option<String>,
+}
+
+/// Matches the transactions with an `OP_RETURN` output, on the data it is pushing.
+/// Unlike `outputs.op_return`, the opcodes are not part of the matched bytes.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OpReturnPredicate {
+    /// Hex encoded prefix of the data.
+    StartsWith(String),
+    /// Hex encoded data.
+    Hex(String),
+    /// Regular expression, matched against the data decoded as UTF-8 (lossy).
+    Regex(String),
+}
+
+/// Matches the transactions with an output whose value is above and / or below the given amounts,
+/// in satoshis. Bounds are excluded.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct OutputValuePredicate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub above: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub below: Option<u64>,
+    /// Compare the total value of the outputs, instead of the value of each output.
+    #[serde(default)]
+    pub total: bool,
+    /// Only consider the outputs paying this address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+impl OutputValuePredicate {
+    pub fn matches_value(&self, value: u64) -> bool {
+        self.above.map_or(true, |above| value > above)
+            && self.below.map_or(true, |below| value < below)
+    }
+}
+
+/// Matches the transactions paying a fee rate above and / or below the given rates, in
+/// satoshis per vbyte. Bounds are excluded, coinbase transactions are ignored.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct FeeRatePredicate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub above: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub below: Option<f64>,
+}
+
+/// Matches the transactions spending a taproot output through a given tapleaf script.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct TaprootScriptPathPredicate {
+    /// Hex encoded tapleaf hash, as defined by BIP-341.
+    pub tapleaf_hash: String,
+}
+
+/// Matches the transactions paying the addresses derived from a descriptor, or spending the
+/// outputs they received. Addresses are derived up to `gap_limit` past the last one used.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct DescriptorPredicate {
+    /// `pkh`, `wpkh`, `sh(wpkh)` or `tr` descriptor of an extended public key, such as
+    /// `wpkh([d34db33f/84'/0'/0']xpub.../0/*)`, or a bare extended public key.
+    pub descriptor: String,
+    #[serde(default = "default_gap_limit")]
+    pub gap_limit: u32,
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub wallet: WatchedWallet,
+}
+
+fn default_gap_limit() -> u32 {
+    20
+}
+
+/// Matches the transactions spending an outpoint whose first sat is of one of the listed
+/// rarities, or belongs to one of the listed ranges. Any sat rarer than common is matched when
+/// both filters are omitted. Requires the hord block store, for traversing the spent sats.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct RareSatsPredicate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rarity: Option<Vec<Rarity>>,
+    /// Ranges of ordinal numbers, formatted as `[start, end]`, `end` being excluded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sat_ranges: Option<Vec<(u64, u64)>>,
+}
+
+impl RareSatsPredicate {
+    pub fn matches(&self, ordinal_number: u64, rarity: &str) -> bool {
+        match (&self.rarity, &self.sat_ranges) {
+            (None, None) => rarity != Rarity::Common.to_string(),
+            (rarities, sat_ranges) => {
+                rarities
+                    .iter()
+                    .flatten()
+                    .any(|expected| expected.to_string() == rarity)
+                    || sat_ranges
+                        .iter()
+                        .flatten()
+                        .any(|(start, end)| (*start..*end).contains(&ordinal_number))
+            }
+        }
+    }
 }
 
 pub fn get_stacks_canonical_magic_bytes(network: &BitcoinNetwork) -> [u8; 2] {
@@ -0,0 +1,1034 @@
+use std::collections::HashMap;
+
+use chainhook_types::{
+    BitcoinBlockData, Brc20BalanceData, Brc20Operation, Brc20TokenDeployData, Brc20TransferData,
+    OrdinalInscriptionRevealData, OrdinalInscriptionTransferData, OrdinalOperation,
+};
+use hiro_system_kit::slog;
+use rusqlite::{Connection, OptionalExtension, Row};
+
+use crate::utils::Context;
+
+use super::db::{decode_content_bytes, update_brc20_totals, HordDbError};
+
+/// Largest number of decimals of a token, also used by the deploys omitting `dec`.
+const MAX_DECIMALS: u8 = 18;
+/// Largest amount, in whole tokens.
+const MAX_AMOUNT: u64 = u64::MAX;
+
+/// Amounts are scaled by `10^dec`, `dec` being the decimals of their token.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedBrc20Operation {
+    Deploy {
+        tick: String,
+        max: u128,
+        lim: u128,
+        dec: u8,
+    },
+    /// Amounts are validated once the decimals of the token are known.
+    Mint {
+        tick: String,
+        amt: String,
+    },
+    Transfer {
+        tick: String,
+        amt: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct Brc20Payload {
+    p: String,
+    op: String,
+    tick: String,
+    max: Option<String>,
+    lim: Option<String>,
+    dec: Option<String>,
+    amt: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Brc20Balance {
+    /// Balance that can be inscribed in transfer inscriptions.
+    pub avail_balance: u128,
+    /// Balance locked in transfer inscriptions that were not sent yet.
+    pub trans_balance: u128,
+}
+
+/// Balance changes are signed: amounts never exceed `MAX_AMOUNT * 10^MAX_DECIMALS`, which fits
+/// in an i128.
+struct Brc20LedgerEntry<'a> {
+    inscription_id: &'a str,
+    block_height: u64,
+    tx_id: &'a str,
+    tick: &'a str,
+    address: &'a str,
+    avail_balance: i128,
+    trans_balance: i128,
+    operation: &'a str,
+}
+
+/// BRC-20 operation carried by a text inscription, None if its content is not a well formed
+/// deploy, mint or transfer payload. Tickers are case insensitive and returned in lowercase.
+pub fn parse_brc20_operation(
+    inscription: &OrdinalInscriptionRevealData,
+) -> Option<ParsedBrc20Operation> {
+    if !inscription.content_type.starts_with("text/plain")
+        && !inscription.content_type.starts_with("application/json")
+    {
+        return None;
+    }
+    let content = decode_content_bytes(&inscription.content_bytes)?;
+    let payload: Brc20Payload = serde_json::from_slice(&content).ok()?;
+    if payload.p != "brc-20" || payload.tick.chars().count() != 4 {
+        return None;
+    }
+    let tick = payload.tick.to_lowercase();
+    match payload.op.as_str() {
+        "deploy" => {
+            let dec = match payload.dec {
+                Some(dec) => dec.parse::<u8>().ok().filter(|dec| *dec <= MAX_DECIMALS)?,
+                None => MAX_DECIMALS,
+            };
+            let max = parse_brc20_amount(&payload.max?, dec)?;
+            let lim = match payload.lim {
+                Some(lim) => parse_brc20_amount(&lim, dec)?,
+                None => max,
+            };
+            Some(ParsedBrc20Operation::Deploy {
+                tick,
+                max,
+                lim,
+                dec,
+            })
+        }
+        "mint" => Some(ParsedBrc20Operation::Mint {
+            tick,
+            amt: payload.amt?,
+        }),
+        "transfer" => Some(ParsedBrc20Operation::Transfer {
+            tick,
+            amt: payload.amt?,
+        }),
+        _ => None,
+    }
+}
+
+/// Positive decimal amount using at most `dec` decimals, e.g. "21000000" or "0.5", scaled by
+/// `10^dec`. Amounts above `MAX_AMOUNT` are rejected.
+fn parse_brc20_amount(value: &str, dec: u8) -> Option<u128> {
+    let (integer, fraction) = match value.split_once('.') {
+        Some((_, "")) => return None,
+        Some((integer, fraction)) => (integer, fraction),
+        None => (value, ""),
+    };
+    if integer.is_empty()
+        || fraction.len() > dec as usize
+        || !integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let scale = 10u128.pow(dec as u32);
+    let fraction_scale = 10u128.pow((dec as usize - fraction.len()) as u32);
+    let amount = integer
+        .chars()
+        .chain(fraction.chars())
+        .try_fold(0u128, |amount, c| {
+            amount.checked_mul(10)?.checked_add(c.to_digit(10)? as u128)
+        })?
+        .checked_mul(fraction_scale)?;
+    if amount > 0 && amount <= MAX_AMOUNT as u128 * scale {
+        Some(amount)
+    } else {
+        None
+    }
+}
+
+/// Applies the BRC-20 operations of the block to the ledger, in the order of its transactions,
+/// and attaches the valid ones to their transaction.
+pub fn update_brc20_ledger_and_augment_bitcoin_block(
+    block: &mut BitcoinBlockData,
+    inscriptions_db_conn_rw: &Connection,
+    ctx: &Context,
+) -> Result<(), HordDbError> {
+    let block_height = block.block_identifier.index;
+    for tx in block.transactions.iter_mut().skip(1) {
+        let mut brc20_operations = vec![];
+        for ordinal_event in tx.metadata.ordinal_operations.iter() {
+            let operation = match ordinal_event {
+                OrdinalOperation::InscriptionRevealed(inscription) => apply_brc20_reveal(
+                    inscription,
+                    block_height,
+                    &tx.transaction_identifier.hash,
+                    inscriptions_db_conn_rw,
+                )?,
+                OrdinalOperation::InscriptionTransferred(transfer) => apply_brc20_transfer(
+                    transfer,
+                    block_height,
+                    &tx.transaction_identifier.hash,
+                    inscriptions_db_conn_rw,
+                )?,
+            };
+            if let Some(operation) = operation {
+                ctx.try_log(|logger| {
+                    slog::info!(
+                        logger,
+                        "BRC-20 operation {:?} detected in transaction {} (block {})",
+                        operation,
+                        tx.transaction_identifier.hash,
+                        block_height,
+                    )
+                });
+                brc20_operations.push(operation);
+            }
+        }
+        tx.metadata.brc20_operations = brc20_operations;
+    }
+    Ok(())
+}
+
+fn apply_brc20_reveal(
+    inscription: &OrdinalInscriptionRevealData,
+    block_height: u64,
+    tx_id: &str,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<Option<Brc20Operation>, HordDbError> {
+    // Cursed inscriptions are ignored by the protocol
+    if inscription.curse_type.is_some() {
+        return Ok(None);
+    }
+    let (operation, address) = match (
+        parse_brc20_operation(inscription),
+        &inscription.inscriber_address,
+    ) {
+        (Some(operation), Some(address)) => (operation, address),
+        _ => return Ok(None),
+    };
+    match operation {
+        ParsedBrc20Operation::Deploy {
+            tick,
+            max,
+            lim,
+            dec,
+        } => {
+            if find_brc20_token(&tick, inscriptions_db_conn_rw)?.is_some() {
+                return Ok(None);
+            }
+            let token = Brc20TokenDeployData {
+                tick,
+                max,
+                lim,
+                dec,
+                address: address.clone(),
+                inscription_id: inscription.inscription_id.clone(),
+            };
+            insert_brc20_token(&token, block_height, tx_id, inscriptions_db_conn_rw)?;
+            Ok(Some(Brc20Operation::TokenDeployed(token)))
+        }
+        ParsedBrc20Operation::Mint { tick, amt } => {
+            let token = match find_brc20_token(&tick, inscriptions_db_conn_rw)? {
+                Some(token) => token,
+                None => return Ok(None),
+            };
+            let amt = match parse_brc20_amount(&amt, token.dec) {
+                Some(amt) if amt <= token.lim => amt,
+                _ => return Ok(None),
+            };
+            let remaining_supply = token
+                .max
+                .saturating_sub(find_brc20_minted_supply(&tick, inscriptions_db_conn_rw)?);
+            if remaining_supply == 0 {
+                return Ok(None);
+            }
+            // The last mint is capped to the remaining supply
+            let amt = amt.min(remaining_supply);
+            insert_brc20_ledger_entry(
+                &Brc20LedgerEntry {
+                    inscription_id: &inscription.inscription_id,
+                    block_height,
+                    tx_id,
+                    tick: &tick,
+                    address,
+                    avail_balance: amt as i128,
+                    trans_balance: 0,
+                    operation: "mint",
+                },
+                inscriptions_db_conn_rw,
+            )?;
+            Ok(Some(Brc20Operation::TokenMinted(Brc20BalanceData {
+                tick,
+                amt,
+                address: address.clone(),
+                inscription_id: inscription.inscription_id.clone(),
+            })))
+        }
+        ParsedBrc20Operation::Transfer { tick, amt } => {
+            let token = match find_brc20_token(&tick, inscriptions_db_conn_rw)? {
+                Some(token) => token,
+                None => return Ok(None),
+            };
+            let amt = match parse_brc20_amount(&amt, token.dec) {
+                Some(amt) => amt,
+                None => return Ok(None),
+            };
+            let balance = find_brc20_balance(&tick, address, inscriptions_db_conn_rw)?;
+            if balance.avail_balance < amt {
+                return Ok(None);
+            }
+            insert_brc20_ledger_entry(
+                &Brc20LedgerEntry {
+                    inscription_id: &inscription.inscription_id,
+                    block_height,
+                    tx_id,
+                    tick: &tick,
+                    address,
+                    avail_balance: -(amt as i128),
+                    trans_balance: amt as i128,
+                    operation: "transfer",
+                },
+                inscriptions_db_conn_rw,
+            )?;
+            Ok(Some(Brc20Operation::TransferInscribed(Brc20BalanceData {
+                tick,
+                amt,
+                address: address.clone(),
+                inscription_id: inscription.inscription_id.clone(),
+            })))
+        }
+    }
+}
+
+/// Only the first transfer of a transfer inscription moves its amount.
+fn apply_brc20_transfer(
+    transfer: &OrdinalInscriptionTransferData,
+    block_height: u64,
+    tx_id: &str,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<Option<Brc20Operation>, HordDbError> {
+    let (tick, amt, sender_address) =
+        match find_unsent_brc20_transfer(&transfer.inscription_id, inscriptions_db_conn_rw)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+    // Transfer inscriptions spent as fees, or sent to an output without address, are
+    // returned to their sender
+    let receiver_address = transfer
+        .updated_address
+        .clone()
+        .unwrap_or_else(|| sender_address.clone());
+    insert_brc20_ledger_entry(
+        &Brc20LedgerEntry {
+            inscription_id: &transfer.inscription_id,
+            block_height,
+            tx_id,
+            tick: &tick,
+            address: &sender_address,
+            avail_balance: 0,
+            trans_balance: -(amt as i128),
+            operation: "transfer_send",
+        },
+        inscriptions_db_conn_rw,
+    )?;
+    insert_brc20_ledger_entry(
+        &Brc20LedgerEntry {
+            inscription_id: &transfer.inscription_id,
+            block_height,
+            tx_id,
+            tick: &tick,
+            address: &receiver_address,
+            avail_balance: amt as i128,
+            trans_balance: 0,
+            operation: "transfer_receive",
+        },
+        inscriptions_db_conn_rw,
+    )?;
+    Ok(Some(Brc20Operation::TransferSent(Brc20TransferData {
+        tick,
+        amt,
+        sender_address,
+        receiver_address,
+        inscription_id: transfer.inscription_id.clone(),
+    })))
+}
+
+fn insert_brc20_token(
+    token: &Brc20TokenDeployData,
+    block_height: u64,
+    tx_id: &str,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<(), HordDbError> {
+    inscriptions_db_conn_rw
+        .execute(
+            "INSERT INTO brc20_tokens (tick, max, lim, dec, address, inscription_id, block_height, tx_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                &token.tick,
+                &token.max.to_string(),
+                &token.lim.to_string(),
+                &token.dec,
+                &token.address,
+                &token.inscription_id,
+                &block_height,
+                &tx_id
+            ],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to insert brc20 token".into(), e))?;
+    Ok(())
+}
+
+fn insert_brc20_ledger_entry(
+    entry: &Brc20LedgerEntry,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<(), HordDbError> {
+    inscriptions_db_conn_rw
+        .execute(
+            "INSERT INTO brc20_ledger (inscription_id, block_height, tx_id, tick, address, avail_balance, trans_balance, operation) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                &entry.inscription_id,
+                &entry.block_height,
+                &entry.tx_id,
+                &entry.tick,
+                &entry.address,
+                &entry.avail_balance.to_string(),
+                &entry.trans_balance.to_string(),
+                &entry.operation
+            ],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to insert brc20 balance change".into(), e))?;
+    let minted = if entry.operation == "mint" {
+        entry.avail_balance
+    } else {
+        0
+    };
+    update_brc20_totals(
+        entry.tick,
+        entry.address,
+        entry.avail_balance,
+        entry.trans_balance,
+        minted,
+        inscriptions_db_conn_rw,
+    )
+}
+
+pub fn find_brc20_token(
+    tick: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<Option<Brc20TokenDeployData>, HordDbError> {
+    inscriptions_db_conn
+        .query_row(
+            "SELECT tick, max, lim, dec, address, inscription_id FROM brc20_tokens WHERE tick = ?",
+            rusqlite::params![&tick.to_lowercase()],
+            |row| {
+                Ok(Brc20TokenDeployData {
+                    tick: row.get(0)?,
+                    max: get_amount(row, 1)?,
+                    lim: get_amount(row, 2)?,
+                    dec: row.get(3)?,
+                    address: row.get(4)?,
+                    inscription_id: row.get(5)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_tokens table".into(), e))
+}
+
+/// Read from the running totals of the ledger, kept in `brc20_balances`.
+pub fn find_brc20_balance(
+    tick: &str,
+    address: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<Brc20Balance, HordDbError> {
+    let balance = inscriptions_db_conn
+        .query_row(
+            "SELECT avail_balance, trans_balance FROM brc20_balances WHERE tick = ?1 AND address = ?2",
+            rusqlite::params![&tick.to_lowercase(), &address],
+            |row| {
+                Ok(Brc20Balance {
+                    avail_balance: get_amount(row, 0)?,
+                    trans_balance: get_amount(row, 1)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_balances table".into(), e))?;
+    Ok(balance.unwrap_or_default())
+}
+
+/// Supply minted so far, kept along with the token as mints are recorded.
+pub fn find_brc20_minted_supply(
+    tick: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<u128, HordDbError> {
+    let minted = inscriptions_db_conn
+        .query_row(
+            "SELECT minted FROM brc20_tokens WHERE tick = ?",
+            rusqlite::params![&tick.to_lowercase()],
+            |row| get_amount(row, 0),
+        )
+        .optional()
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_tokens table".into(), e))?;
+    Ok(minted.unwrap_or(0))
+}
+
+/// (tick, amount, owner address) of a transfer inscription that was not sent yet.
+fn find_unsent_brc20_transfer(
+    inscription_id: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<Option<(String, u128, String)>, HordDbError> {
+    inscriptions_db_conn
+        .query_row(
+            "SELECT tick, trans_balance, address FROM brc20_ledger WHERE inscription_id = ?1 AND operation = 'transfer'
+            AND NOT EXISTS (SELECT 1 FROM brc20_ledger WHERE inscription_id = ?1 AND operation = 'transfer_send')",
+            rusqlite::params![&inscription_id],
+            |row| Ok((row.get(0)?, get_amount(row, 1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_ledger table".into(), e))
+}
+
+/// BRC-20 operations applied by the block, keyed by transaction hash, e.g. for evaluating
+/// predicates against blocks indexed earlier.
+pub fn find_brc20_operations_at_block_height(
+    block_height: u64,
+    inscriptions_db_conn: &Connection,
+) -> Result<HashMap<String, Vec<Brc20Operation>>, HordDbError> {
+    let mut operations: HashMap<String, Vec<Brc20Operation>> = HashMap::new();
+
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT tick, max, lim, dec, address, inscription_id, tx_id FROM brc20_tokens WHERE block_height = ? ORDER BY rowid ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_tokens table".into(), e))?;
+    let tokens = stmt
+        .query_map(rusqlite::params![&block_height], |row| {
+            Ok((
+                row.get::<_, String>(6)?,
+                Brc20TokenDeployData {
+                    tick: row.get(0)?,
+                    max: get_amount(row, 1)?,
+                    lim: get_amount(row, 2)?,
+                    dec: row.get(3)?,
+                    address: row.get(4)?,
+                    inscription_id: row.get(5)?,
+                },
+            ))
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_tokens table".into(), e))?;
+    for (tx_id, token) in tokens.into_iter() {
+        operations
+            .entry(tx_id)
+            .or_default()
+            .push(Brc20Operation::TokenDeployed(token));
+    }
+
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT inscription_id, tx_id, tick, address, avail_balance, trans_balance, operation FROM brc20_ledger WHERE block_height = ? ORDER BY rowid ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_ledger table".into(), e))?;
+    let entries = stmt
+        .query_map(rusqlite::params![&block_height], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                get_amount::<u128>(row, 4)?,
+                get_amount::<u128>(row, 5)?,
+                row.get::<_, String>(6)?,
+            ))
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_ledger table".into(), e))?;
+    // A transfer_send entry is always directly followed by its transfer_receive entry
+    let mut sender_address = None;
+    for (inscription_id, tx_id, tick, address, avail_balance, trans_balance, operation) in
+        entries.into_iter()
+    {
+        let operation = match operation.as_str() {
+            "mint" => Brc20Operation::TokenMinted(Brc20BalanceData {
+                tick,
+                amt: avail_balance,
+                address,
+                inscription_id,
+            }),
+            "transfer" => Brc20Operation::TransferInscribed(Brc20BalanceData {
+                tick,
+                amt: trans_balance,
+                address,
+                inscription_id,
+            }),
+            "transfer_send" => {
+                sender_address = Some(address);
+                continue;
+            }
+            "transfer_receive" => match sender_address.take() {
+                Some(sender_address) => Brc20Operation::TransferSent(Brc20TransferData {
+                    tick,
+                    amt: avail_balance,
+                    sender_address,
+                    receiver_address: address,
+                    inscription_id,
+                }),
+                None => {
+                    return Err(HordDbError::Corrupted(format!(
+                        "brc20 transfer {inscription_id} received without being sent"
+                    )))
+                }
+            },
+            _ => {
+                return Err(HordDbError::Corrupted(format!(
+                    "unknown brc20 operation {operation}"
+                )))
+            }
+        };
+        operations.entry(tx_id).or_default().push(operation);
+    }
+    Ok(operations)
+}
+
+/// Amounts and balance changes are stored as decimal strings, SQLite integers being 64 bits.
+fn get_amount<T>(row: &Row, index: usize) -> rusqlite::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let amount: String = row.get(index)?;
+    amount.parse::<T>().map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chainhook_types::{
+        BitcoinBlockMetadata, BitcoinTransactionData, BitcoinTransactionMetadata, BlockIdentifier,
+        TransactionIdentifier,
+    };
+
+    use super::*;
+    use crate::hord::db::{delete_brc20_entries_in_block_range, migrate_hord_db};
+
+    /// One token of 18 decimals.
+    const ONE: u128 = 1_000_000_000_000_000_000;
+
+    fn reveal(index: u64, content: &str, address: &str) -> OrdinalInscriptionRevealData {
+        OrdinalInscriptionRevealData {
+            content_bytes: format!("0x{}", hex::encode(content)),
+            content_type: "text/plain;charset=utf-8".into(),
+            content_length: content.len(),
+            inscription_number: index as i64,
+            inscription_fee: 0,
+            inscription_output_value: 10_000,
+            inscription_id: format!("{:064x}i0", index),
+            inscriber_address: Some(address.into()),
+            ordinal_number: 1_000_000 + index,
+            ordinal_block_height: 0,
+            ordinal_offset: 0,
+            ordinal_rarity: "common".into(),
+            transfers_pre_inscription: 0,
+            satpoint_post_inscription: format!("{:064x}:0:0", index),
+            metadata: None,
+            parent_inscription_id: None,
+            curse_type: None,
+        }
+    }
+
+    fn transfer(index: u64, updated_address: Option<&str>) -> OrdinalInscriptionTransferData {
+        OrdinalInscriptionTransferData {
+            inscription_number: index as i64,
+            inscription_id: format!("{:064x}i0", index),
+            ordinal_number: 1_000_000 + index,
+            updated_address: updated_address.map(|address| address.to_string()),
+            satpoint_pre_transfer: format!("{:064x}:0:0", index),
+            satpoint_post_transfer: format!("{:064x}:0:0", index + 1000),
+            post_transfer_output_value: Some(10_000),
+            parent_inscription_id: None,
+        }
+    }
+
+    fn block(height: u64, ordinal_operations: Vec<OrdinalOperation>) -> BitcoinBlockData {
+        let mut transactions = vec![];
+        // The first transaction is the coinbase
+        for (i, operation) in [None]
+            .into_iter()
+            .chain(ordinal_operations.into_iter().map(Some))
+            .enumerate()
+        {
+            transactions.push(BitcoinTransactionData {
+                transaction_identifier: TransactionIdentifier {
+                    hash: format!("0x{:064x}", height * 1000 + i as u64),
+                },
+                operations: vec![],
+                metadata: BitcoinTransactionMetadata {
+                    inputs: vec![],
+                    outputs: vec![],
+                    stacks_operations: vec![],
+                    ordinal_operations: operation.into_iter().collect(),
+                    brc20_operations: vec![],
+                    rune_operations: vec![],
+                    stamp_operations: vec![],
+                    sat_movements: vec![],
+                    taproot_spends: vec![],
+                    proof: None,
+                    fee: 0,
+                    vsize: 0,
+                    fee_rate: 0.0,
+                },
+            });
+        }
+        BitcoinBlockData {
+            block_identifier: BlockIdentifier {
+                index: height,
+                hash: format!("0x{:064x}", height),
+            },
+            parent_block_identifier: BlockIdentifier {
+                index: height - 1,
+                hash: format!("0x{:064x}", height - 1),
+            },
+            timestamp: 0,
+            transactions,
+            metadata: BitcoinBlockMetadata {},
+        }
+    }
+
+    fn brc20_operations(block: &BitcoinBlockData) -> Vec<Brc20Operation> {
+        block
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.metadata.brc20_operations.clone())
+            .collect()
+    }
+
+    #[test]
+    fn parse_deploy_mint_and_transfer() {
+        assert_eq!(
+            parse_brc20_operation(&reveal(
+                1,
+                r#"{"p":"brc-20","op":"deploy","tick":"ORDI","max":"21000000","lim":"1000"}"#,
+                "bc1qdeployer"
+            )),
+            Some(ParsedBrc20Operation::Deploy {
+                tick: "ordi".into(),
+                max: 21_000_000 * ONE,
+                lim: 1000 * ONE,
+                dec: 18,
+            })
+        );
+        assert_eq!(
+            parse_brc20_operation(&reveal(
+                1,
+                r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"100.5","dec":"1"}"#,
+                "bc1qdeployer"
+            )),
+            Some(ParsedBrc20Operation::Deploy {
+                tick: "ordi".into(),
+                max: 1005,
+                lim: 1005,
+                dec: 1,
+            })
+        );
+        assert_eq!(
+            parse_brc20_operation(&reveal(
+                1,
+                r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1000"}"#,
+                "bc1qminter"
+            )),
+            Some(ParsedBrc20Operation::Mint {
+                tick: "ordi".into(),
+                amt: "1000".into(),
+            })
+        );
+        assert_eq!(
+            parse_brc20_operation(&reveal(
+                1,
+                r#"{"p":"brc-20","op":"transfer","tick":"ordi","amt":"1.5"}"#,
+                "bc1qminter"
+            )),
+            Some(ParsedBrc20Operation::Transfer {
+                tick: "ordi".into(),
+                amt: "1.5".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_rejects_malformed_payloads() {
+        for content in [
+            "not json",
+            r#"{"p":"brc-21","op":"mint","tick":"ordi","amt":"1"}"#,
+            r#"{"p":"brc-20","op":"burn","tick":"ordi","amt":"1"}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordinals","amt":"1"}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":1}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"ordi","lim":"1"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"1.25","dec":"1"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"1","dec":"19"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"0"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"-5"}"#,
+        ] {
+            assert_eq!(
+                parse_brc20_operation(&reveal(1, content, "bc1q")),
+                None,
+                "{content}"
+            );
+        }
+
+        let mut inscription = reveal(
+            1,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1"}"#,
+            "bc1q",
+        );
+        inscription.content_type = "image/png".into();
+        assert_eq!(parse_brc20_operation(&inscription), None);
+    }
+
+    #[test]
+    fn amounts_are_validated_against_decimals() {
+        assert_eq!(parse_brc20_amount("10", 0), Some(10));
+        assert_eq!(parse_brc20_amount("0.25", 2), Some(25));
+        assert_eq!(parse_brc20_amount("0.2", 2), Some(20));
+        assert_eq!(parse_brc20_amount("0.25", 1), None);
+        assert_eq!(parse_brc20_amount("1.", 18), None);
+        assert_eq!(parse_brc20_amount(".5", 18), None);
+        assert_eq!(parse_brc20_amount("1e3", 18), None);
+        assert_eq!(parse_brc20_amount("0.0", 18), None);
+    }
+
+    #[test]
+    fn amounts_are_exact_up_to_18_decimals_and_max_supply() {
+        assert_eq!(parse_brc20_amount("0.000000000000000001", 18), Some(1));
+        assert_eq!(parse_brc20_amount("0.0000000000000000001", 18), None);
+        assert_eq!(
+            parse_brc20_amount("20999999.999999999999999999", 18),
+            Some(21_000_000 * ONE - 1)
+        );
+        assert_eq!(
+            parse_brc20_amount("18446744073709551615", 18),
+            Some(u64::MAX as u128 * ONE)
+        );
+        assert_eq!(
+            parse_brc20_amount("18446744073709551615.000000000000000000", 18),
+            Some(u64::MAX as u128 * ONE)
+        );
+        assert_eq!(
+            parse_brc20_amount("18446744073709551615.000000000000000001", 18),
+            None
+        );
+        assert_eq!(parse_brc20_amount("18446744073709551616", 18), None);
+        assert_eq!(parse_brc20_amount("18446744073709551615.1", 1), None);
+        assert_eq!(
+            parse_brc20_amount("340282366920938463463374607431768211456", 0),
+            None
+        );
+        assert_eq!(
+            parse_brc20_operation(&reveal(
+                1,
+                r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"18446744073709551615","lim":"0.000000000000000001"}"#,
+                "bc1qdeployer"
+            )),
+            Some(ParsedBrc20Operation::Deploy {
+                tick: "ordi".into(),
+                max: u64::MAX as u128 * ONE,
+                lim: 1,
+                dec: 18,
+            })
+        );
+    }
+
+    #[test]
+    fn amounts_are_serialized_as_decimal_strings() {
+        let data = Brc20BalanceData {
+            tick: "ordi".into(),
+            amt: u64::MAX as u128 * ONE,
+            address: "bc1qalice".into(),
+            inscription_id: format!("{:064x}i0", 1),
+        };
+        let value = serde_json::to_value(&data).unwrap();
+        assert_eq!(
+            value["amt"],
+            serde_json::json!("18446744073709551615000000000000000000")
+        );
+        assert_eq!(
+            serde_json::from_value::<Brc20BalanceData>(value).unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn ledger_tracks_balances_through_transfers() {
+        let ctx = Context::empty();
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_hord_db(&conn, &ctx).unwrap();
+
+        let mut first_block = block(
+            800_000,
+            vec![
+                OrdinalOperation::InscriptionRevealed(reveal(
+                    1,
+                    r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"1500","lim":"1000"}"#,
+                    "bc1qdeployer",
+                )),
+                // Re-deploying an existing ticker is ignored
+                OrdinalOperation::InscriptionRevealed(reveal(
+                    2,
+                    r#"{"p":"brc-20","op":"deploy","tick":"ORDI","max":"1","lim":"1"}"#,
+                    "bc1qdeployer",
+                )),
+                OrdinalOperation::InscriptionRevealed(reveal(
+                    3,
+                    r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1000"}"#,
+                    "bc1qalice",
+                )),
+                // Above the limit per mint
+                OrdinalOperation::InscriptionRevealed(reveal(
+                    4,
+                    r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1001"}"#,
+                    "bc1qalice",
+                )),
+                // Capped to the remaining supply
+                OrdinalOperation::InscriptionRevealed(reveal(
+                    5,
+                    r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1000"}"#,
+                    "bc1qbob",
+                )),
+                OrdinalOperation::InscriptionRevealed(reveal(
+                    6,
+                    r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1"}"#,
+                    "bc1qbob",
+                )),
+                OrdinalOperation::InscriptionRevealed(reveal(
+                    7,
+                    r#"{"p":"brc-20","op":"transfer","tick":"ordi","amt":"300"}"#,
+                    "bc1qalice",
+                )),
+                // Above the available balance
+                OrdinalOperation::InscriptionRevealed(reveal(
+                    8,
+                    r#"{"p":"brc-20","op":"transfer","tick":"ordi","amt":"701"}"#,
+                    "bc1qalice",
+                )),
+            ],
+        );
+        update_brc20_ledger_and_augment_bitcoin_block(&mut first_block, &conn, &ctx).unwrap();
+
+        assert_eq!(
+            brc20_operations(&first_block)
+                .iter()
+                .map(|operation| match operation {
+                    Brc20Operation::TokenDeployed(data) => data.inscription_id.clone(),
+                    Brc20Operation::TokenMinted(data) => data.inscription_id.clone(),
+                    Brc20Operation::TransferInscribed(data) => data.inscription_id.clone(),
+                    Brc20Operation::TransferSent(data) => data.inscription_id.clone(),
+                })
+                .collect::<Vec<_>>(),
+            [1, 3, 5, 7]
+                .iter()
+                .map(|index| format!("{:064x}i0", index))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(find_brc20_minted_supply("ordi", &conn).unwrap(), 1500 * ONE);
+        assert_eq!(
+            find_brc20_balance("ordi", "bc1qalice", &conn).unwrap(),
+            Brc20Balance {
+                avail_balance: 700 * ONE,
+                trans_balance: 300 * ONE
+            }
+        );
+        assert_eq!(
+            find_brc20_balance("ORDI", "bc1qbob", &conn).unwrap(),
+            Brc20Balance {
+                avail_balance: 500 * ONE,
+                trans_balance: 0
+            }
+        );
+
+        let mut second_block = block(
+            800_001,
+            vec![
+                OrdinalOperation::InscriptionTransferred(transfer(7, Some("bc1qbob"))),
+                // Transfer inscriptions are only effective once
+                OrdinalOperation::InscriptionTransferred(transfer(7, Some("bc1qcarol"))),
+            ],
+        );
+        update_brc20_ledger_and_augment_bitcoin_block(&mut second_block, &conn, &ctx).unwrap();
+
+        let sent = Brc20Operation::TransferSent(Brc20TransferData {
+            tick: "ordi".into(),
+            amt: 300 * ONE,
+            sender_address: "bc1qalice".into(),
+            receiver_address: "bc1qbob".into(),
+            inscription_id: format!("{:064x}i0", 7),
+        });
+        assert_eq!(brc20_operations(&second_block), vec![sent.clone()]);
+        assert_eq!(
+            find_brc20_balance("ordi", "bc1qalice", &conn).unwrap(),
+            Brc20Balance {
+                avail_balance: 700 * ONE,
+                trans_balance: 0
+            }
+        );
+        assert_eq!(
+            find_brc20_balance("ordi", "bc1qbob", &conn).unwrap(),
+            Brc20Balance {
+                avail_balance: 800 * ONE,
+                trans_balance: 0
+            }
+        );
+        assert_eq!(
+            find_brc20_balance("ordi", "bc1qcarol", &conn).unwrap(),
+            Brc20Balance::default()
+        );
+
+        let operations = find_brc20_operations_at_block_height(800_001, &conn).unwrap();
+        assert_eq!(
+            operations.get(&second_block.transactions[1].transaction_identifier.hash),
+            Some(&vec![sent])
+        );
+        let operations = find_brc20_operations_at_block_height(800_000, &conn).unwrap();
+        for tx in first_block.transactions.iter() {
+            assert_eq!(
+                operations
+                    .get(&tx.transaction_identifier.hash)
+                    .cloned()
+                    .unwrap_or_default(),
+                tx.metadata.brc20_operations
+            );
+        }
+
+        // Reverting the blocks reverts the balances and the tokens
+        delete_brc20_entries_in_block_range(800_001, 800_001, &conn, &ctx).unwrap();
+        assert_eq!(
+            find_brc20_balance("ordi", "bc1qalice", &conn).unwrap(),
+            Brc20Balance {
+                avail_balance: 700 * ONE,
+                trans_balance: 300 * ONE
+            }
+        );
+        delete_brc20_entries_in_block_range(800_000, 800_000, &conn, &ctx).unwrap();
+        assert_eq!(find_brc20_token("ordi", &conn).unwrap(), None);
+        assert_eq!(find_brc20_minted_supply("ordi", &conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn cursed_inscriptions_are_ignored() {
+        let ctx = Context::empty();
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_hord_db(&conn, &ctx).unwrap();
+
+        let mut inscription = reveal(
+            1,
+            r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"1500"}"#,
+            "bc1qdeployer",
+        );
+        inscription.curse_type =
+            Some(chainhook_types::OrdinalInscriptionCurseType::NotInFirstInput);
+        let mut cursed_block = block(
+            800_000,
+            vec![OrdinalOperation::InscriptionRevealed(inscription)],
+        );
+        update_brc20_ledger_and_augment_bitcoin_block(&mut cursed_block, &conn, &ctx).unwrap();
+        assert!(brc20_operations(&cursed_block).is_empty());
+        assert_eq!(find_brc20_token("ordi", &conn).unwrap(), None);
+    }
+}
@@ -1,34 +1,48 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     hash::BuildHasherDefault,
     path::PathBuf,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
 };
 
 use chainhook_types::{
-    BitcoinBlockData, BlockIdentifier, OrdinalInscriptionRevealData, TransactionIdentifier,
+    BitcoinBlockData, BitcoinNetwork, BlockIdentifier, OrdinalInscriptionRevealData,
+    OrdinalOperation, TransactionIdentifier,
 };
 use dashmap::DashMap;
 use fxhash::FxHasher;
 use hiro_system_kit::slog;
 
+use bitcoincore_rpc::bitcoin::hashes::{sha256, Hash, HashEngine};
 use rocksdb::DB;
-use rusqlite::{Connection, OpenFlags, ToSql};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, ToSql};
 use std::io::Cursor;
 use threadpool::ThreadPool;
 
 use crate::{
     indexer::bitcoin::{
-        download_block_with_retry, retrieve_block_hash_with_retry, standardize_bitcoin_block,
-        BitcoinBlockFullBreakdown,
+        download_block_with_retry, download_rest_block_with_retry, retrieve_block_hash_with_retry,
+        standardize_bitcoin_block, BitcoinBlockFullBreakdown, PrevoutCache,
+        DEFAULT_PREVOUT_CACHE_CAPACITY,
     },
     observer::BitcoinConfig,
-    utils::Context,
+    utils::{
+        telemetry::{PipelineStage, StageSpan},
+        Context,
+    },
 };
 
 use super::{
-    new_traversals_lazy_cache,
-    ord::{height::Height, sat::Sat},
+    executor::execute_in_parallel,
+    ord::{height::Height, rarity::Rarity, sat::Sat},
+    sync_progress::SyncProgressTracker,
+    traversals_cache::{
+        PersistedTraversalsCache, TraversalsLruCache, DEFAULT_TRAVERSALS_CACHE_SIZE,
+        TRAVERSALS_CACHE_WARMING_DEPTH,
+    },
     update_hord_db_and_augment_bitcoin_block,
 };
 
@@ -38,23 +52,434 @@ fn get_default_hord_db_file_path(base_dir: &PathBuf) -> PathBuf {
     destination_path
 }
 
-pub fn open_readonly_hord_db_conn(base_dir: &PathBuf, ctx: &Context) -> Result<Connection, String> {
+const BLOCKS_BATCH_SIZE: usize = 256;
+
+/// Tuning knobs for the hord databases (RocksDB blocks store and hord.sqlite).
+/// `HordDbOptions::default()` matches the settings historically used by the
+/// functions that don't take options, except for hord.sqlite being opened in WAL mode
+/// (see `SqliteTuning::default()`).
+#[derive(Debug, Clone)]
+pub struct HordDbOptions {
+    /// Compression applied to the RocksDB blocks store.
+    pub compression: Option<rocksdb::DBCompressionType>,
+    /// Maximum number of files kept open by RocksDB (-1 means unlimited).
+    pub max_open_files: i32,
+    /// Number of background threads used by RocksDB for flushes and compactions.
+    pub parallelism: Option<i32>,
+    /// Tune RocksDB for ingesting large amount of data (mirrors `prepare_for_bulk_load`).
+    pub prepare_for_bulk_load: bool,
+    /// Size of the LRU cache of the blocks column family, in bytes.
+    pub block_cache_size: Option<usize>,
+    /// Size of the memtables, in bytes.
+    pub write_buffer_size: Option<usize>,
+    pub sqlite: SqliteTuning,
+    /// Retries performed when hord.sqlite can't be opened.
+    pub open_retry: OpenRetry,
+}
+
+/// Bounded retries, with a delay doubling after every failed attempt up to `max_delay`.
+#[derive(Debug, Clone)]
+pub struct OpenRetry {
+    /// Attempts made before giving up, including the first one.
+    pub max_attempts: u8,
+    pub initial_delay: std::time::Duration,
+    pub max_delay: std::time::Duration,
+}
+
+impl Default for OpenRetry {
+    /// 5 attempts, 1 second apart.
+    fn default() -> Self {
+        OpenRetry {
+            max_attempts: 5,
+            initial_delay: std::time::Duration::from_secs(1),
+            max_delay: std::time::Duration::from_secs(1),
+        }
+    }
+}
+
+impl OpenRetry {
+    fn delay_after_attempt(&self, attempt: u8) -> std::time::Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1) as u32);
+        factor
+            .and_then(|factor| self.initial_delay.checked_mul(factor))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+/// Pragmas applied to hord.sqlite when opened for writing. `None` keeps SQLite's default.
+#[derive(Debug, Clone)]
+pub struct SqliteTuning {
+    /// `journal_mode` pragma: `WAL` lets readers proceed while the indexer is writing.
+    pub journal_mode: Option<String>,
+    /// `synchronous` pragma (`OFF`, `NORMAL`, `FULL`, `EXTRA`).
+    pub synchronous: Option<String>,
+    /// `mmap_size` pragma, in bytes.
+    pub mmap_size: Option<i64>,
+    /// `page_size` pragma, in bytes. Only effective before the database is populated.
+    pub page_size: Option<i64>,
+    /// `cache_size` pragma: pages if positive, KiB if negative.
+    pub cache_size: Option<i64>,
+    /// How long a connection waits on a locked database before returning `SQLITE_BUSY`.
+    pub busy_timeout: Option<std::time::Duration>,
+}
+
+/// Also applied to the read-only connections.
+const DEFAULT_SQLITE_BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+impl Default for SqliteTuning {
+    fn default() -> Self {
+        SqliteTuning {
+            journal_mode: Some("WAL".into()),
+            synchronous: None,
+            mmap_size: None,
+            page_size: None,
+            cache_size: None,
+            busy_timeout: Some(DEFAULT_SQLITE_BUSY_TIMEOUT),
+        }
+    }
+}
+
+impl SqliteTuning {
+    fn apply(&self, conn: &Connection) -> Result<(), HordDbError> {
+        // Must precede the switch to WAL, which freezes the page size
+        if let Some(page_size) = self.page_size {
+            conn.pragma_update(None, "page_size", page_size)
+                .map_err(|e| HordDbError::Sqlite("unable to set page_size".into(), e))?;
+        }
+        if let Some(ref journal_mode) = self.journal_mode {
+            conn.pragma_update_and_check(None, "journal_mode", journal_mode, |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(|e| HordDbError::Sqlite("unable to set journal_mode".into(), e))?;
+        }
+        if let Some(ref synchronous) = self.synchronous {
+            conn.pragma_update(None, "synchronous", synchronous)
+                .map_err(|e| HordDbError::Sqlite("unable to set synchronous".into(), e))?;
+        }
+        if let Some(mmap_size) = self.mmap_size {
+            conn.pragma_update(None, "mmap_size", mmap_size)
+                .map_err(|e| HordDbError::Sqlite("unable to set mmap_size".into(), e))?;
+        }
+        if let Some(cache_size) = self.cache_size {
+            conn.pragma_update(None, "cache_size", cache_size)
+                .map_err(|e| HordDbError::Sqlite("unable to set cache_size".into(), e))?;
+        }
+        if let Some(busy_timeout) = self.busy_timeout {
+            conn.busy_timeout(busy_timeout)
+                .map_err(|e| HordDbError::Sqlite("unable to set busy_timeout".into(), e))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for HordDbOptions {
+    fn default() -> Self {
+        HordDbOptions {
+            compression: None,
+            max_open_files: 2048,
+            parallelism: None,
+            prepare_for_bulk_load: false,
+            block_cache_size: None,
+            write_buffer_size: None,
+            sqlite: SqliteTuning::default(),
+            open_retry: OpenRetry::default(),
+        }
+    }
+}
+
+impl HordDbOptions {
+    /// Preset suited for seeding a fresh database.
+    pub fn bulk_load() -> Self {
+        let parallelism = std::thread::available_parallelism()
+            .map(|n| n.get() as i32)
+            .unwrap_or(4);
+        HordDbOptions {
+            compression: Some(rocksdb::DBCompressionType::Lz4),
+            max_open_files: -1,
+            parallelism: Some(parallelism),
+            prepare_for_bulk_load: true,
+            block_cache_size: None,
+            write_buffer_size: None,
+            sqlite: SqliteTuning {
+                synchronous: Some("NORMAL".into()),
+                mmap_size: Some(256 * 1024 * 1024),
+                page_size: Some(16384),
+                ..SqliteTuning::default()
+            },
+            open_retry: OpenRetry::default(),
+        }
+    }
+}
+
+/// Errors returned by the hord db functions. `Display` renders the messages
+/// historically returned as `String`s.
+#[derive(Debug)]
+pub enum HordDbError {
+    /// The database file is missing.
+    NotFound(PathBuf),
+    /// The block is missing from the blocks store.
+    BlockNotFound(u32),
+    InscriptionNotFound(String),
+    /// A hord.sqlite operation (described by the first field) failed.
+    Sqlite(String, rusqlite::Error),
+    /// A blocks store operation (described by the first field) failed.
+    RocksDb(String, rocksdb::Error),
+    /// A filesystem or stream operation (described by the first field) failed.
+    Io(String, std::io::Error),
+    /// Stored data that can't be decoded, or that is inconsistent with the rest of the db.
+    Corrupted(String),
+    /// Arguments or imported data that can't be processed.
+    InvalidInput(String),
+    /// A traversal is spending a txid prefix matching several transactions of the block, whose
+    /// longer prefixes only collide on the spent one.
+    AmbiguousTxid {
+        block_height: u32,
+        txid: String,
+        candidates: Vec<String>,
+    },
+    /// The sync was interrupted by bitcoind errors, after storing blocks up to `last_completed_block`.
+    SyncAborted {
+        last_completed_block: Option<u64>,
+        error: String,
+    },
+    /// A Postgres inscriptions store operation (described by the first field) failed.
+    #[cfg(feature = "postgres")]
+    Postgres(String, ::postgres::Error),
+    Other(String),
+}
+
+impl std::fmt::Display for HordDbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HordDbError::NotFound(path) => write!(f, "unable to find {}", path.display()),
+            HordDbError::BlockNotFound(block_height) => {
+                write!(f, "block #{block_height} not in database")
+            }
+            HordDbError::InscriptionNotFound(inscription_id) => {
+                write!(f, "unable to find inscription with id {inscription_id}")
+            }
+            HordDbError::Sqlite(context, e) => write!(f, "{context}: {}", e.to_string()),
+            HordDbError::RocksDb(context, e) => write!(f, "{context}: {}", e.to_string()),
+            HordDbError::Io(context, e) => write!(f, "{context}: {}", e.to_string()),
+            #[cfg(feature = "postgres")]
+            HordDbError::Postgres(context, e) => write!(f, "{context}: {}", e.to_string()),
+            HordDbError::AmbiguousTxid {
+                block_height,
+                txid,
+                candidates,
+            } => write!(
+                f,
+                "txid prefix {txid} matches transactions {} of block #{block_height}",
+                candidates.join(", ")
+            ),
+            HordDbError::SyncAborted {
+                last_completed_block,
+                error,
+            } => write!(
+                f,
+                "hord db sync aborted (last contiguous block: {:?}): {error}",
+                last_completed_block
+            ),
+            HordDbError::Corrupted(message)
+            | HordDbError::InvalidInput(message)
+            | HordDbError::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for HordDbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HordDbError::Sqlite(_, e) => Some(e),
+            HordDbError::RocksDb(_, e) => Some(e),
+            HordDbError::Io(_, e) => Some(e),
+            #[cfg(feature = "postgres")]
+            HordDbError::Postgres(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Underlying errors are compared on their description, `std::io::Error` not being comparable.
+impl PartialEq for HordDbError {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+            && self.to_string() == other.to_string()
+    }
+}
+
+pub fn open_readonly_hord_db_conn(
+    base_dir: &PathBuf,
+    ctx: &Context,
+) -> Result<Connection, HordDbError> {
     let path = get_default_hord_db_file_path(&base_dir);
-    let conn = open_existing_readonly_db(&path, ctx);
+    let conn = open_existing_readonly_db(&path, ctx)?;
     Ok(conn)
 }
 
+/// Pool of read-only connections to hord.sqlite, letting concurrent readers (HTTP handlers,
+/// predicate evaluation) run inscription lookups without serializing on one connection.
+/// Connections are opened lazily, up to `max_size`; `get` blocks when all of them are in use.
+pub struct ReadonlyHordDbPool {
+    path: PathBuf,
+    max_size: usize,
+    state: std::sync::Mutex<ReadonlyHordDbPoolState>,
+    released: std::sync::Condvar,
+}
+
+#[derive(Default)]
+struct ReadonlyHordDbPoolState {
+    idle: Vec<Connection>,
+    open: usize,
+}
+
+impl ReadonlyHordDbPool {
+    /// Opens a first connection, so that a missing database is reported right away.
+    pub fn new(
+        base_dir: &PathBuf,
+        max_size: usize,
+        ctx: &Context,
+    ) -> Result<ReadonlyHordDbPool, HordDbError> {
+        if max_size == 0 {
+            return Err(HordDbError::InvalidInput(
+                "pool size must be greater than 0".into(),
+            ));
+        }
+        let path = get_default_hord_db_file_path(base_dir);
+        let conn = open_existing_readonly_db(&path, ctx)?;
+        Ok(ReadonlyHordDbPool {
+            path,
+            max_size,
+            state: std::sync::Mutex::new(ReadonlyHordDbPoolState {
+                idle: vec![conn],
+                open: 1,
+            }),
+            released: std::sync::Condvar::new(),
+        })
+    }
+
+    pub fn get(&self, ctx: &Context) -> Result<PooledHordDbConn<'_>, HordDbError> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(conn) = state.idle.pop() {
+                return Ok(PooledHordDbConn {
+                    pool: self,
+                    conn: Some(conn),
+                });
+            }
+            if state.open < self.max_size {
+                break;
+            }
+            state = self.released.wait(state).unwrap();
+        }
+        // The slot is reserved before opening, so that the lock isn't held during the open.
+        state.open += 1;
+        drop(state);
+        match open_existing_readonly_db(&self.path, ctx) {
+            Ok(conn) => Ok(PooledHordDbConn {
+                pool: self,
+                conn: Some(conn),
+            }),
+            Err(e) => {
+                self.state.lock().unwrap().open -= 1;
+                self.released.notify_one();
+                Err(e)
+            }
+        }
+    }
+
+    /// Number of connections currently opened, idle or in use.
+    pub fn open_connections(&self) -> usize {
+        self.state.lock().unwrap().open
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+}
+
+/// Connection borrowed from a `ReadonlyHordDbPool`, returned to the pool on drop.
+pub struct PooledHordDbConn<'a> {
+    pool: &'a ReadonlyHordDbPool,
+    conn: Option<Connection>,
+}
+
+impl std::ops::Deref for PooledHordDbConn<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledHordDbConn<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.state.lock().unwrap().idle.push(conn);
+            self.pool.released.notify_one();
+        }
+    }
+}
+
+pub fn open_readonly_hord_db_pool(
+    base_dir: &PathBuf,
+    max_size: usize,
+    ctx: &Context,
+) -> Result<ReadonlyHordDbPool, HordDbError> {
+    ReadonlyHordDbPool::new(base_dir, max_size, ctx)
+}
+
 pub fn open_readwrite_hord_db_conn(
     base_dir: &PathBuf,
     ctx: &Context,
-) -> Result<Connection, String> {
-    let conn = create_or_open_readwrite_db(&base_dir, ctx);
+) -> Result<Connection, HordDbError> {
+    open_readwrite_hord_db_conn_with_options(base_dir, &HordDbOptions::default(), ctx)
+}
+
+pub fn open_readwrite_hord_db_conn_with_options(
+    base_dir: &PathBuf,
+    options: &HordDbOptions,
+    ctx: &Context,
+) -> Result<Connection, HordDbError> {
+    let conn = create_or_open_readwrite_db(&base_dir, options, ctx)?;
+    migrate_hord_db(&conn, ctx)?;
+    Ok(conn)
+}
+
+pub fn initialize_hord_db(path: &PathBuf, ctx: &Context) -> Result<Connection, HordDbError> {
+    let conn = create_or_open_readwrite_db(path, &HordDbOptions::default(), ctx)?;
+    migrate_hord_db(&conn, ctx)?;
     Ok(conn)
 }
 
-pub fn initialize_hord_db(path: &PathBuf, ctx: &Context) -> Connection {
-    let conn = create_or_open_readwrite_db(path, ctx);
-    if let Err(e) = conn.execute(
+/// Current version of the hord.sqlite schema, bumped with every new entry in `HORD_DB_MIGRATIONS`.
+pub const HORD_SCHEMA_VERSION: u32 = 16;
+
+/// Ordered schema migrations: the step at index `i` brings a database from version `i` to `i + 1`.
+/// Steps must be idempotent, databases created before versioning was introduced are
+/// re-migrated from version 1.
+const HORD_DB_MIGRATIONS: [fn(&Connection) -> Result<(), HordDbError>;
+    HORD_SCHEMA_VERSION as usize] = [
+    create_hord_db_tables_v1,
+    add_transfers_column_to_inscriptions,
+    add_unique_index_on_inscription_number,
+    add_content_columns_to_inscriptions,
+    create_transfers_table_with_outpoints,
+    add_content_hash_column_to_inscriptions,
+    create_inscription_number_reservations_table,
+    create_inscription_contents_table,
+    create_locations_table,
+    add_metadata_column_to_inscriptions,
+    create_brc20_tables,
+    create_runes_tables,
+    add_address_column_to_inscriptions,
+    add_parent_column_to_inscriptions,
+    store_brc20_amounts_as_text,
+    create_brc20_totals,
+];
+
+fn create_hord_db_tables_v1(conn: &Connection) -> Result<(), HordDbError> {
+    conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS inscriptions (
             inscription_id TEXT NOT NULL PRIMARY KEY,
             block_height INTEGER NOT NULL,
@@ -63,43 +488,604 @@ pub fn initialize_hord_db(path: &PathBuf, ctx: &Context) -> Connection {
             ordinal_number INTEGER NOT NULL,
             inscription_number INTEGER NOT NULL,
             offset INTEGER NOT NULL
-        )",
+        );
+        CREATE TABLE IF NOT EXISTS transfers (
+            block_height INTEGER NOT NULL PRIMARY KEY
+        );
+        CREATE INDEX IF NOT EXISTS index_inscriptions_on_outpoint_to_watch ON inscriptions(outpoint_to_watch);
+        CREATE INDEX IF NOT EXISTS index_inscriptions_on_ordinal_number ON inscriptions(ordinal_number);
+        CREATE INDEX IF NOT EXISTS index_inscriptions_on_block_height ON inscriptions(block_height);",
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create tables".into(), e))
+}
+
+fn add_transfers_column_to_inscriptions(conn: &Connection) -> Result<(), HordDbError> {
+    if table_has_column(conn, "inscriptions", "transfers")? {
+        return Ok(());
+    }
+    conn.execute(
+        "ALTER TABLE inscriptions ADD COLUMN transfers INTEGER NOT NULL DEFAULT 0",
         [],
-    ) {
-        ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to add column transfers".into(), e))?;
+    Ok(())
+}
+
+/// Number of duplicated inscription numbers listed by `add_unique_index_on_inscription_number`.
+const MAX_DUPLICATE_INSCRIPTION_NUMBERS_REPORTED: usize = 10;
+
+/// Fails without creating the index when inscription numbers were assigned several times,
+/// listing the conflicting numbers: the inscriptions of their blocks have to be rebuilt.
+fn add_unique_index_on_inscription_number(conn: &Connection) -> Result<(), HordDbError> {
+    let duplicates = find_duplicate_inscription_numbers(conn)?;
+    if !duplicates.is_empty() {
+        let listed = duplicates
+            .iter()
+            .take(MAX_DUPLICATE_INSCRIPTION_NUMBERS_REPORTED)
+            .map(|(inscription_number, block_heights)| {
+                format!("#{inscription_number} (blocks {block_heights})")
+            })
+            .collect::<Vec<_>>();
+        let others = duplicates.len().saturating_sub(listed.len());
+        return Err(HordDbError::Corrupted(format!(
+            "unable to create index on inscription_number: inscription numbers {}{} are assigned to several inscriptions. Move hord.sqlite aside and rebuild it with `chainhook hord db rewrite`",
+            listed.join(", "),
+            if others > 0 {
+                format!(" and {others} others")
+            } else {
+                String::new()
+            }
+        )));
+    }
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS index_inscriptions_on_inscription_number ON inscriptions(inscription_number);",
+        [],
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create index on inscription_number".into(), e))?;
+    Ok(())
+}
+
+/// Inscription numbers assigned to several inscriptions, along with the heights of the
+/// blocks of these inscriptions.
+fn find_duplicate_inscription_numbers(
+    conn: &Connection,
+) -> Result<Vec<(i64, String)>, HordDbError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT inscription_number, GROUP_CONCAT(block_height) FROM (
+                SELECT DISTINCT inscription_number, block_height FROM inscriptions
+                WHERE inscription_number IN (
+                    SELECT inscription_number FROM inscriptions
+                    GROUP BY inscription_number HAVING COUNT(*) > 1
+                )
+                ORDER BY inscription_number, block_height
+            ) GROUP BY inscription_number ORDER BY inscription_number",
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to prepare query".into(), e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| {
+            HordDbError::Sqlite("unable to query duplicate inscription numbers".into(), e)
+        })?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| HordDbError::Sqlite("unable to query duplicate inscription numbers".into(), e))
+}
+
+fn add_content_columns_to_inscriptions(conn: &Connection) -> Result<(), HordDbError> {
+    for (column, column_type) in [("content_type", "TEXT"), ("content_length", "INTEGER")] {
+        if table_has_column(conn, "inscriptions", column)? {
+            continue;
+        }
+        conn.execute(
+            &format!("ALTER TABLE inscriptions ADD COLUMN {column} {column_type}"),
+            [],
+        )
+        .map_err(|e| HordDbError::Sqlite(format!("unable to add column {column}"), e))?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS index_inscriptions_on_content_type ON inscriptions(content_type);",
+        [],
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create index on content_type".into(), e))?;
+    Ok(())
+}
+
+/// The original `transfers` table was never written to, it is replaced by a table
+/// recording every transfer.
+fn create_transfers_table_with_outpoints(conn: &Connection) -> Result<(), HordDbError> {
+    if table_exists(conn, "transfers")? && !table_has_column(conn, "transfers", "inscription_id")? {
+        conn.execute("DROP TABLE transfers", [])
+            .map_err(|e| HordDbError::Sqlite("unable to drop table transfers".into(), e))?;
     }
-    if let Err(e) = conn.execute(
+    conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS transfers (
-            block_height INTEGER NOT NULL PRIMARY KEY
+            inscription_id TEXT NOT NULL,
+            block_height INTEGER NOT NULL,
+            block_hash TEXT NOT NULL,
+            outpoint_pre_transfer TEXT NOT NULL,
+            outpoint_post_transfer TEXT NOT NULL,
+            offset INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS index_transfers_on_block_height ON transfers(block_height);
+        CREATE INDEX IF NOT EXISTS index_transfers_on_inscription_id ON transfers(inscription_id);",
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create table transfers".into(), e))
+}
+
+/// Inscriptions stored before this migration keep a NULL `content_hash`.
+fn add_content_hash_column_to_inscriptions(conn: &Connection) -> Result<(), HordDbError> {
+    if !table_has_column(conn, "inscriptions", "content_hash")? {
+        conn.execute("ALTER TABLE inscriptions ADD COLUMN content_hash TEXT", [])
+            .map_err(|e| HordDbError::Sqlite("unable to add column content_hash".into(), e))?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS index_inscriptions_on_content_hash ON inscriptions(content_hash);",
+        [],
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create index on content_hash".into(), e))?;
+    Ok(())
+}
+
+/// Runs of inscription numbers handed out by `reserve_inscription_numbers`.
+fn create_inscription_number_reservations_table(conn: &Connection) -> Result<(), HordDbError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS inscription_number_reservations (
+            block_height INTEGER NOT NULL PRIMARY KEY,
+            first_inscription_number INTEGER NOT NULL,
+            count INTEGER NOT NULL
         )",
         [],
-    ) {
-        ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+    )
+    .map_err(|e| {
+        HordDbError::Sqlite(
+            "unable to create table inscription_number_reservations".into(),
+            e,
+        )
+    })?;
+    Ok(())
+}
+
+/// Bodies of the inscriptions, addressed by `content_hash` so that duplicates are stored once.
+/// Inscriptions stored before this migration have no content.
+fn create_inscription_contents_table(conn: &Connection) -> Result<(), HordDbError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS inscription_contents (
+            content_hash TEXT NOT NULL PRIMARY KEY,
+            content BLOB NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create table inscription_contents".into(), e))?;
+    Ok(())
+}
+
+/// Every location held by the inscriptions, from their reveal to their latest transfer.
+/// Inscriptions stored before this migration have no history.
+fn create_locations_table(conn: &Connection) -> Result<(), HordDbError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS locations (
+            inscription_id TEXT NOT NULL,
+            block_height INTEGER NOT NULL,
+            block_hash TEXT NOT NULL,
+            tx_id TEXT NOT NULL,
+            satpoint TEXT NOT NULL,
+            address TEXT
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS index_locations_on_inscription_id_and_satpoint ON locations(inscription_id, satpoint);
+        CREATE INDEX IF NOT EXISTS index_locations_on_block_height ON locations(block_height);",
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create table locations".into(), e))
+}
+
+/// JSON encoded metadata of the inscriptions. Inscriptions stored before this migration
+/// keep a NULL `metadata`.
+fn add_metadata_column_to_inscriptions(conn: &Connection) -> Result<(), HordDbError> {
+    if !table_has_column(conn, "inscriptions", "metadata")? {
+        conn.execute("ALTER TABLE inscriptions ADD COLUMN metadata TEXT", [])
+            .map_err(|e| HordDbError::Sqlite("unable to add column metadata".into(), e))?;
     }
+    Ok(())
+}
+
+/// Deployed BRC-20 tokens, and the balance changes applied by every BRC-20 operation:
+/// balances are the sum of the changes recorded for a (tick, address).
+/// Only populated when the BRC-20 indexing is enabled.
+fn create_brc20_tables(conn: &Connection) -> Result<(), HordDbError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS brc20_tokens (
+            tick TEXT NOT NULL PRIMARY KEY,
+            max REAL NOT NULL,
+            lim REAL NOT NULL,
+            dec INTEGER NOT NULL,
+            address TEXT NOT NULL,
+            inscription_id TEXT NOT NULL,
+            block_height INTEGER NOT NULL,
+            tx_id TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS index_brc20_tokens_on_block_height ON brc20_tokens(block_height);
+        CREATE TABLE IF NOT EXISTS brc20_ledger (
+            inscription_id TEXT NOT NULL,
+            block_height INTEGER NOT NULL,
+            tx_id TEXT NOT NULL,
+            tick TEXT NOT NULL,
+            address TEXT NOT NULL,
+            avail_balance REAL NOT NULL,
+            trans_balance REAL NOT NULL,
+            operation TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS index_brc20_ledger_on_tick_and_address ON brc20_ledger(tick, address);
+        CREATE INDEX IF NOT EXISTS index_brc20_ledger_on_inscription_id ON brc20_ledger(inscription_id);
+        CREATE INDEX IF NOT EXISTS index_brc20_ledger_on_block_height ON brc20_ledger(block_height);",
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create brc20 tables".into(), e))
+}
+
+/// Etched runes, their mints, the rune balances held by the outpoints and the runes burned.
+/// Amounts are u128 stored as TEXT. Spent balances are kept until their block is reverted.
+fn create_runes_tables(conn: &Connection) -> Result<(), HordDbError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS runes (
+            rune_id TEXT NOT NULL PRIMARY KEY,
+            name TEXT NOT NULL,
+            spacers INTEGER NOT NULL,
+            divisibility INTEGER NOT NULL,
+            symbol TEXT,
+            premine TEXT NOT NULL,
+            cap TEXT,
+            amount TEXT,
+            height_start INTEGER,
+            height_end INTEGER,
+            offset_start INTEGER,
+            offset_end INTEGER,
+            turbo INTEGER NOT NULL,
+            cenotaph INTEGER NOT NULL,
+            block_height INTEGER NOT NULL,
+            tx_id TEXT NOT NULL
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS index_runes_on_name ON runes(name);
+        CREATE INDEX IF NOT EXISTS index_runes_on_block_height ON runes(block_height);
+        CREATE TABLE IF NOT EXISTS rune_mints (
+            rune_id TEXT NOT NULL,
+            amount TEXT NOT NULL,
+            block_height INTEGER NOT NULL,
+            tx_id TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS index_rune_mints_on_rune_id ON rune_mints(rune_id);
+        CREATE INDEX IF NOT EXISTS index_rune_mints_on_block_height ON rune_mints(block_height);
+        CREATE TABLE IF NOT EXISTS rune_balances (
+            outpoint TEXT NOT NULL,
+            rune_id TEXT NOT NULL,
+            amount TEXT NOT NULL,
+            address TEXT,
+            block_height INTEGER NOT NULL,
+            tx_id TEXT NOT NULL,
+            spent_block_height INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS index_rune_balances_on_outpoint ON rune_balances(outpoint);
+        CREATE INDEX IF NOT EXISTS index_rune_balances_on_block_height ON rune_balances(block_height);
+        CREATE INDEX IF NOT EXISTS index_rune_balances_on_spent_block_height ON rune_balances(spent_block_height);
+        CREATE TABLE IF NOT EXISTS rune_burns (
+            rune_id TEXT NOT NULL,
+            amount TEXT NOT NULL,
+            block_height INTEGER NOT NULL,
+            tx_id TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS index_rune_burns_on_block_height ON rune_burns(block_height);",
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create runes tables".into(), e))
+}
 
-    if let Err(e) = conn.execute(
-        "CREATE INDEX IF NOT EXISTS index_inscriptions_on_outpoint_to_watch ON inscriptions(outpoint_to_watch);",
+/// Address of the output holding the inscription, NULL when it can't be resolved. Inscriptions
+/// stored before this migration are backfilled from their current location, when recorded.
+fn add_address_column_to_inscriptions(conn: &Connection) -> Result<(), HordDbError> {
+    if !table_has_column(conn, "inscriptions", "address")? {
+        conn.execute_batch(
+            "ALTER TABLE inscriptions ADD COLUMN address TEXT;
+            UPDATE inscriptions SET address = (
+                SELECT l.address FROM locations l
+                WHERE l.inscription_id = inscriptions.inscription_id
+                AND l.satpoint = inscriptions.outpoint_to_watch || ':' || inscriptions.offset
+            );",
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to add column address".into(), e))?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS index_inscriptions_on_address ON inscriptions(address)",
         [],
-    ) {
-        ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create index on address".into(), e))?;
+    Ok(())
+}
+
+/// Parent of the inscription, for collections. Inscriptions stored before this migration are
+/// left without parent.
+fn add_parent_column_to_inscriptions(conn: &Connection) -> Result<(), HordDbError> {
+    if !table_has_column(conn, "inscriptions", "parent_inscription_id")? {
+        conn.execute(
+            "ALTER TABLE inscriptions ADD COLUMN parent_inscription_id TEXT",
+            [],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to add column parent_inscription_id".into(), e))?;
     }
-    if let Err(e) = conn.execute(
-        "CREATE INDEX IF NOT EXISTS index_inscriptions_on_ordinal_number ON inscriptions(ordinal_number);",
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS index_inscriptions_on_parent_inscription_id ON inscriptions(parent_inscription_id)",
         [],
-    ) {
-        ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+    )
+    .map_err(|e| {
+        HordDbError::Sqlite("unable to create index on parent_inscription_id".into(), e)
+    })?;
+    Ok(())
+}
+
+/// BRC-20 amounts were first stored as REAL (see `create_brc20_tables`), losing precision
+/// beyond 2^53. The tables are rebuilt with the amounts scaled by the decimals of their token,
+/// as TEXT, the amounts stored so far being rounded to these decimals.
+fn store_brc20_amounts_as_text(conn: &Connection) -> Result<(), HordDbError> {
+    if table_column_type(conn, "brc20_tokens", "max")?.as_deref() != Some("REAL") {
+        return Ok(());
+    }
+    conn.execute_batch(
+        "ALTER TABLE brc20_tokens RENAME TO brc20_tokens_real;
+        ALTER TABLE brc20_ledger RENAME TO brc20_ledger_real;
+        DROP INDEX IF EXISTS index_brc20_tokens_on_block_height;
+        DROP INDEX IF EXISTS index_brc20_ledger_on_tick_and_address;
+        DROP INDEX IF EXISTS index_brc20_ledger_on_inscription_id;
+        DROP INDEX IF EXISTS index_brc20_ledger_on_block_height;",
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to rename brc20 tables".into(), e))?;
+    conn.execute_batch(
+        "CREATE TABLE brc20_tokens (
+            tick TEXT NOT NULL PRIMARY KEY,
+            max TEXT NOT NULL,
+            lim TEXT NOT NULL,
+            dec INTEGER NOT NULL,
+            address TEXT NOT NULL,
+            inscription_id TEXT NOT NULL,
+            block_height INTEGER NOT NULL,
+            tx_id TEXT NOT NULL
+        );
+        CREATE INDEX index_brc20_tokens_on_block_height ON brc20_tokens(block_height);
+        CREATE TABLE brc20_ledger (
+            inscription_id TEXT NOT NULL,
+            block_height INTEGER NOT NULL,
+            tx_id TEXT NOT NULL,
+            tick TEXT NOT NULL,
+            address TEXT NOT NULL,
+            avail_balance TEXT NOT NULL,
+            trans_balance TEXT NOT NULL,
+            operation TEXT NOT NULL
+        );
+        CREATE INDEX index_brc20_ledger_on_tick_and_address ON brc20_ledger(tick, address);
+        CREATE INDEX index_brc20_ledger_on_inscription_id ON brc20_ledger(inscription_id);
+        CREATE INDEX index_brc20_ledger_on_block_height ON brc20_ledger(block_height);",
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create brc20 tables".into(), e))?;
+
+    let to_text = |value: f64, dec: u8| -> rusqlite::Result<String> {
+        // Formatting with `dec` decimals gives the scaled amount, once the point is removed
+        format!("{:.*}", dec as usize, value)
+            .replace('.', "")
+            .parse::<i128>()
+            .map(|amount| amount.to_string())
+            .map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Real,
+                    Box::new(e),
+                )
+            })
+    };
+    conn.prepare(
+        "SELECT tick, max, lim, dec, address, inscription_id, block_height, tx_id FROM brc20_tokens_real ORDER BY rowid ASC",
+    )
+    .and_then(|mut stmt| {
+        let tokens = stmt
+            .query_map([], |row| {
+                let dec: u8 = row.get(3)?;
+                Ok((
+                    row.get::<_, String>(0)?,
+                    to_text(row.get(1)?, dec)?,
+                    to_text(row.get(2)?, dec)?,
+                    dec,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, u64>(6)?,
+                    row.get::<_, String>(7)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        for token in tokens.iter() {
+            conn.execute(
+                "INSERT INTO brc20_tokens (tick, max, lim, dec, address, inscription_id, block_height, tx_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                rusqlite::params![
+                    &token.0, &token.1, &token.2, &token.3, &token.4, &token.5, &token.6, &token.7
+                ],
+            )?;
+        }
+        Ok(())
+    })
+    .map_err(|e| HordDbError::Sqlite("unable to migrate brc20 tokens".into(), e))?;
+    // Entries keep their order, operations being listed in the order of the ledger
+    conn.prepare(
+        "SELECT l.inscription_id, l.block_height, l.tx_id, l.tick, l.address, l.avail_balance, l.trans_balance, l.operation, t.dec
+        FROM brc20_ledger_real AS l INNER JOIN brc20_tokens_real AS t ON t.tick = l.tick ORDER BY l.rowid ASC",
+    )
+    .and_then(|mut stmt| {
+        let entries = stmt
+            .query_map([], |row| {
+                let dec: u8 = row.get(8)?;
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, u64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    to_text(row.get(5)?, dec)?,
+                    to_text(row.get(6)?, dec)?,
+                    row.get::<_, String>(7)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        for entry in entries.iter() {
+            conn.execute(
+                "INSERT INTO brc20_ledger (inscription_id, block_height, tx_id, tick, address, avail_balance, trans_balance, operation) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                rusqlite::params![
+                    &entry.0, &entry.1, &entry.2, &entry.3, &entry.4, &entry.5, &entry.6, &entry.7
+                ],
+            )?;
+        }
+        Ok(())
+    })
+    .map_err(|e| HordDbError::Sqlite("unable to migrate brc20 ledger".into(), e))?;
+    conn.execute_batch("DROP TABLE brc20_ledger_real; DROP TABLE brc20_tokens_real;")
+        .map_err(|e| HordDbError::Sqlite("unable to drop brc20 tables".into(), e))
+}
+
+/// Running totals of the BRC-20 ledger, for balances and minted supplies not to be summed
+/// from every entry: `brc20_balances` holds the balance of every (tick, address), and
+/// `brc20_tokens.minted` the supply minted so far. Both are updated along with the ledger.
+fn create_brc20_totals(conn: &Connection) -> Result<(), HordDbError> {
+    if !table_has_column(conn, "brc20_tokens", "minted")? {
+        conn.execute(
+            "ALTER TABLE brc20_tokens ADD COLUMN minted TEXT NOT NULL DEFAULT '0'",
+            [],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to add column minted".into(), e))?;
     }
-    if let Err(e) = conn.execute(
-        "CREATE INDEX IF NOT EXISTS index_inscriptions_on_block_height ON inscriptions(block_height);",
+    if table_exists(conn, "brc20_balances")? {
+        return Ok(());
+    }
+    conn.execute(
+        "CREATE TABLE brc20_balances (
+            tick TEXT NOT NULL,
+            address TEXT NOT NULL,
+            avail_balance TEXT NOT NULL,
+            trans_balance TEXT NOT NULL,
+            PRIMARY KEY (tick, address)
+        )",
         [],
-    ) {
-        ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to create table brc20_balances".into(), e))?;
+    apply_brc20_ledger_entries_to_totals(
+        "SELECT tick, address, avail_balance, trans_balance, operation FROM brc20_ledger",
+        [],
+        1,
+        conn,
+    )
+}
+
+fn table_has_column(conn: &Connection, table: &str, column: &str) -> Result<bool, HordDbError> {
+    let columns = conn
+        .prepare(&format!("PRAGMA table_info({table})"))
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get::<_, String>(1))
+                .and_then(|rows| rows.collect::<Result<Vec<String>, _>>())
+        })
+        .map_err(|e| HordDbError::Sqlite(format!("unable to retrieve columns of {table}"), e))?;
+    Ok(columns.iter().any(|c| c.eq(column)))
+}
+
+/// Declared type of the column, None if the table has no such column.
+fn table_column_type(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+) -> Result<Option<String>, HordDbError> {
+    let columns = conn
+        .prepare(&format!("PRAGMA table_info({table})"))
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<(String, String)>, _>>())
+        })
+        .map_err(|e| HordDbError::Sqlite(format!("unable to retrieve columns of {table}"), e))?;
+    Ok(columns
+        .into_iter()
+        .find(|(name, _)| name.eq(column))
+        .map(|(_, column_type)| column_type))
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, HordDbError> {
+    conn.query_row(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?",
+        rusqlite::params![table],
+        |_| Ok(()),
+    )
+    .optional()
+    .map(|res| res.is_some())
+    .map_err(|e| HordDbError::Sqlite(format!("unable to lookup table {table}"), e))
+}
+
+pub fn find_hord_db_schema_version(conn: &Connection) -> Result<u32, HordDbError> {
+    if table_exists(conn, "schema_version")? {
+        let version = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get::<_, u32>(0)
+            })
+            .optional()
+            .map_err(|e| HordDbError::Sqlite("unable to retrieve schema version".into(), e))?;
+        if let Some(version) = version {
+            return Ok(version);
+        }
+    }
+    // Databases created before the schema was versioned
+    if table_exists(conn, "inscriptions")? {
+        Ok(1)
+    } else {
+        Ok(0)
+    }
+}
+
+/// Fails if hord.sqlite was migrated by a more recent version of chainhook.
+fn check_hord_db_schema_is_supported(conn: &Connection) -> Result<u32, HordDbError> {
+    let current_version = find_hord_db_schema_version(conn)?;
+    if current_version > HORD_SCHEMA_VERSION {
+        return Err(HordDbError::Other(format!(
+            "hord.sqlite schema version {current_version} is more recent than the supported version {HORD_SCHEMA_VERSION}"
+        )));
     }
+    Ok(current_version)
+}
+
+/// Brings the schema of hord.sqlite up to `HORD_SCHEMA_VERSION`, within a single transaction.
+pub fn migrate_hord_db(conn: &Connection, ctx: &Context) -> Result<(), HordDbError> {
+    let current_version = check_hord_db_schema_is_supported(conn)?;
+    if current_version == HORD_SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let db_tx = conn
+        .unchecked_transaction()
+        .map_err(|e| HordDbError::Sqlite("unable to begin transaction".into(), e))?;
+    for version in current_version..HORD_SCHEMA_VERSION {
+        HORD_DB_MIGRATIONS[version as usize](&db_tx)?;
+    }
+    db_tx
+        .execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);
+            DELETE FROM schema_version;
+            INSERT INTO schema_version (version) VALUES ({HORD_SCHEMA_VERSION});"
+        ))
+        .map_err(|e| HordDbError::Sqlite("unable to update schema version".into(), e))?;
+    db_tx
+        .commit()
+        .map_err(|e| HordDbError::Sqlite("unable to commit migration".into(), e))?;
 
-    conn
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "hord.sqlite schema migrated from version {current_version} to {HORD_SCHEMA_VERSION}"
+        )
+    });
+    Ok(())
 }
 
-fn create_or_open_readwrite_db(cache_path: &PathBuf, ctx: &Context) -> Connection {
+fn create_or_open_readwrite_db(
+    cache_path: &PathBuf,
+    options: &HordDbOptions,
+    ctx: &Context,
+) -> Result<Connection, HordDbError> {
     let path = get_default_hord_db_file_path(&cache_path);
     let open_flags = match std::fs::metadata(&path) {
         Err(e) => {
@@ -112,7 +1098,10 @@ fn create_or_open_readwrite_db(cache_path: &PathBuf, ctx: &Context) -> Connectio
                 }
                 OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
             } else {
-                panic!("FATAL: could not stat {}", path.display());
+                return Err(HordDbError::Io(
+                    format!("unable to stat {}", path.display()),
+                    e,
+                ));
             }
         }
         Ok(_md) => {
@@ -121,32 +1110,22 @@ fn create_or_open_readwrite_db(cache_path: &PathBuf, ctx: &Context) -> Connectio
         }
     };
 
-    let conn = loop {
-        match Connection::open_with_flags(&path, open_flags) {
-            Ok(conn) => break conn,
-            Err(e) => {
-                ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
-            }
-        };
-        std::thread::sleep(std::time::Duration::from_secs(1));
-    };
+    let conn = open_db_with_retry(&path, open_flags, &options.open_retry, ctx)?;
     // db.profile(Some(trace_profile));
-    // db.busy_handler(Some(tx_busy_handler))?;
-    // let mmap_size: i64 = 256 * 1024 * 1024;
-    // let page_size: i64 = 16384;
-    // conn.pragma_update(None, "mmap_size", mmap_size).unwrap();
-    // conn.pragma_update(None, "page_size", page_size).unwrap();
-    // conn.pragma_update(None, "synchronous", &"NORMAL").unwrap();
-    conn
+    options.sqlite.apply(&conn)?;
+    Ok(conn)
 }
 
-fn open_existing_readonly_db(path: &PathBuf, ctx: &Context) -> Connection {
+fn open_existing_readonly_db(path: &PathBuf, ctx: &Context) -> Result<Connection, HordDbError> {
     let open_flags = match std::fs::metadata(path) {
         Err(e) => {
             if e.kind() == std::io::ErrorKind::NotFound {
-                panic!("FATAL: could not find {}", path.display());
+                return Err(HordDbError::NotFound(path.clone()));
             } else {
-                panic!("FATAL: could not stat {}", path.display());
+                return Err(HordDbError::Io(
+                    format!("unable to stat {}", path.display()),
+                    e,
+                ));
             }
         }
         Ok(_md) => {
@@ -155,19 +1134,40 @@ fn open_existing_readonly_db(path: &PathBuf, ctx: &Context) -> Connection {
         }
     };
 
-    let conn = loop {
+    let conn = open_db_with_retry(path, open_flags, &OpenRetry::default(), ctx)?;
+    conn.busy_timeout(DEFAULT_SQLITE_BUSY_TIMEOUT)
+        .map_err(|e| HordDbError::Sqlite("unable to set busy_timeout".into(), e))?;
+    // Readers can't migrate, but must not misread a schema written by a more recent version.
+    check_hord_db_schema_is_supported(&conn)?;
+    Ok(conn)
+}
+
+fn open_db_with_retry(
+    path: &PathBuf,
+    open_flags: OpenFlags,
+    retry: &OpenRetry,
+    ctx: &Context,
+) -> Result<Connection, HordDbError> {
+    let mut attempt = 0;
+    loop {
         match Connection::open_with_flags(path, open_flags) {
-            Ok(conn) => break conn,
+            Ok(conn) => return Ok(conn),
             Err(e) => {
-                ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+                attempt += 1;
+                if attempt >= retry.max_attempts {
+                    return Err(HordDbError::Sqlite(
+                        format!("unable to open {} after {attempt} attempts", path.display()),
+                        e,
+                    ));
+                }
+                ctx.try_log(|logger| slog::warn!(logger, "{}", e.to_string()));
             }
         };
-        std::thread::sleep(std::time::Duration::from_secs(1));
-    };
-    return conn;
+        std::thread::sleep(retry.delay_after_attempt(attempt));
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[repr(C)]
 pub struct CompactedBlock(
     pub  (
@@ -176,8 +1176,17 @@ pub struct CompactedBlock(
     ),
 );
 
-use std::io::{Read, Write};
-
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// A `CompactedBlock` is persisted using the layout of legacy `LazyBlock`s (8 bytes txid prefixes):
+///
+/// - tx_len (u16): number of transactions in the block (not including coinbase)
+/// - for each transaction: inputs_len (u16), outputs_len (u16)
+/// - coinbase txid (8 bytes) and coinbase value (u64)
+/// - for each transaction: txid (8 bytes), then for each input: txin (8 bytes),
+///   block height (u32), vout (u16), value (u64), then for each output: value (u64)
+///
+/// All the integers are big endian.
 impl CompactedBlock {
     fn empty() -> CompactedBlock {
         CompactedBlock((([0, 0, 0, 0, 0, 0, 0, 0], 0), vec![]))
@@ -191,20 +1200,20 @@ impl CompactedBlock {
     pub fn serialize_to_lazy_format<W: Write>(&self, fd: &mut W) -> std::io::Result<()> {
         // Number of transactions in the block (not including coinbase)
         let tx_len = self.0 .1.len() as u16;
-        fd.write(&tx_len.to_be_bytes())?;
+        fd.write_all(&tx_len.to_be_bytes())?;
         // For each transaction:
         for (_, inputs, outputs) in self.0 .1.iter() {
             let inputs_len = inputs.len() as u16;
             let outputs_len = outputs.len() as u16;
             // Number of inputs
-            fd.write(&inputs_len.to_be_bytes())?;
+            fd.write_all(&inputs_len.to_be_bytes())?;
             // Number of outputs
-            fd.write(&outputs_len.to_be_bytes())?;
+            fd.write_all(&outputs_len.to_be_bytes())?;
         }
-        // Coinbase transaction
+        // Coinbase transaction txid
         fd.write_all(&self.0 .0 .0)?;
-        //
-        fd.write(&self.0 .0 .1.to_be_bytes())?;
+        // Coinbase transaction value
+        fd.write_all(&self.0 .0 .1.to_be_bytes())?;
         // For each transaction
         for (id, inputs, outputs) in self.0 .1.iter() {
             // Transaction id
@@ -214,38 +1223,52 @@ impl CompactedBlock {
                 // Txin id
                 fd.write_all(txid)?;
                 // Block height
-                fd.write(&block.to_be_bytes())?;
+                fd.write_all(&block.to_be_bytes())?;
                 // Vout
-                fd.write(&vout.to_be_bytes())?;
+                fd.write_all(&vout.to_be_bytes())?;
                 // Value
-                fd.write(&value.to_be_bytes())?;
+                fd.write_all(&value.to_be_bytes())?;
             }
             for value in outputs.iter() {
-                fd.write(&value.to_be_bytes())?;
+                fd.write_all(&value.to_be_bytes())?;
             }
         }
         Ok(())
     }
 
-    fn deserialize<R: Read>(fd: &mut R) -> std::io::Result<CompactedBlock> {
+    pub fn to_lazy(&self) -> LazyBlock {
+        LazyBlock::from_compacted(self)
+    }
+
+    pub fn deserialize<R: Read>(fd: &mut R) -> std::io::Result<CompactedBlock> {
+        // Number of transactions in the block (not including coinbase)
+        let tx_len = {
+            let mut bytes = [0u8; 2];
+            fd.read_exact(&mut bytes)?;
+            u16::from_be_bytes(bytes) as usize
+        };
+        // For each transaction: number of inputs and outputs
+        let mut formats = Vec::with_capacity(tx_len);
+        for _ in 0..tx_len {
+            let mut inputs_len = [0u8; 2];
+            fd.read_exact(&mut inputs_len)?;
+            let mut outputs_len = [0u8; 2];
+            fd.read_exact(&mut outputs_len)?;
+            formats.push((
+                u16::from_be_bytes(inputs_len) as usize,
+                u16::from_be_bytes(outputs_len) as usize,
+            ));
+        }
+        // Coinbase transaction
         let mut ci = [0u8; 8];
         fd.read_exact(&mut ci)?;
         let mut cv = [0u8; 8];
         fd.read_exact(&mut cv)?;
-        let tx_len = {
-            let mut bytes = [0u8; 8];
-            fd.read_exact(&mut bytes).expect("corrupted data");
-            usize::from_be_bytes(bytes)
-        };
+        // For each transaction
         let mut txs = Vec::with_capacity(tx_len);
-        for _ in 0..tx_len {
+        for (inputs_len, outputs_len) in formats.into_iter() {
             let mut txid = [0u8; 8];
             fd.read_exact(&mut txid)?;
-            let inputs_len = {
-                let mut bytes = [0u8; 8];
-                fd.read_exact(&mut bytes).expect("corrupted data");
-                usize::from_be_bytes(bytes)
-            };
             let mut inputs = Vec::with_capacity(inputs_len);
             for _ in 0..inputs_len {
                 let mut txin = [0u8; 8];
@@ -263,11 +1286,6 @@ impl CompactedBlock {
                     u64::from_be_bytes(value),
                 ))
             }
-            let outputs_len = {
-                let mut bytes = [0u8; 8];
-                fd.read_exact(&mut bytes).expect("corrupted data");
-                usize::from_be_bytes(bytes)
-            };
             let mut outputs = Vec::with_capacity(outputs_len);
             for _ in 0..outputs_len {
                 let mut v = [0u8; 8];
@@ -286,13 +1304,45 @@ fn get_default_hord_db_file_path_rocks_db(base_dir: &PathBuf) -> PathBuf {
     destination_path
 }
 
+/// Blocks, keyed by their big-endian height.
+const BLOCKS_CF: &str = "blocks";
+/// Bookkeeping entries (`last_insert`, `pruned_below`).
+const METADATA_CF: &str = "metadata";
+/// Traversal results, keyed by the txid of the reveal transaction.
+const TRAVERSAL_CACHE_CF: &str = "traversal_cache";
+/// Transactions decoded by the traversals, keyed by their big-endian block height followed
+/// by their txid prefix (see `txid_cache_key`).
+const TRAVERSAL_TRANSACTIONS_CF: &str = "traversal_transactions";
+/// Blocks downloaded by an interrupted sync but not processed for inscriptions yet, keyed
+/// by their big-endian height (JSON encoded `BitcoinBlockFullBreakdown`).
+const SYNC_INBOX_CF: &str = "sync_inbox";
+
+const LAST_INSERT_KEY: &[u8] = b"last_insert";
+/// Last block of the contiguous range completed by `fetch_and_cache_blocks_in_hord_db`.
+const SYNC_CURSOR_KEY: &[u8] = b"sync_cursor";
+const PRUNED_BELOW_KEY: &[u8] = b"pruned_below";
+
 fn rocks_db_default_options() -> rocksdb::Options {
+    rocks_db_options(&HordDbOptions::default())
+}
+
+fn rocks_db_options(options: &HordDbOptions) -> rocksdb::Options {
     let mut opts = rocksdb::Options::default();
     opts.create_if_missing(true);
-    // opts.prepare_for_bulk_load();
-    // opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
-    // opts.set_blob_compression_type(rocksdb::DBCompressionType::Lz4);
-    // opts.increase_parallelism(parallelism)
+    opts.create_missing_column_families(true);
+    if options.prepare_for_bulk_load {
+        opts.prepare_for_bulk_load();
+    }
+    if let Some(compression) = options.compression {
+        opts.set_compression_type(compression);
+        opts.set_blob_compression_type(compression);
+    }
+    if let Some(parallelism) = options.parallelism {
+        opts.increase_parallelism(parallelism);
+    }
+    if let Some(write_buffer_size) = options.write_buffer_size {
+        opts.set_write_buffer_size(write_buffer_size);
+    }
     // Per rocksdb's documentation:
     // If cache_index_and_filter_blocks is false (which is default),
     // the number of index/filter blocks is controlled by option max_open_files.
@@ -300,29 +1350,199 @@ fn rocks_db_default_options() -> rocksdb::Options {
     // we recommend setting max_open_files to -1, which means infinity.
     // This option will preload all filter and index blocks and will not need to maintain LRU of files.
     // Setting max_open_files to -1 will get you the best possible performance.
-    opts.set_max_open_files(2048);
+    opts.set_max_open_files(options.max_open_files);
     opts
 }
 
+/// Blocks are large and only appended, metadata and traversal cache entries are small
+/// and looked up by key: each dataset gets its own column family so that compacting
+/// (or iterating over) the blocks doesn't evict the others from the block cache.
+fn rocks_db_column_families(
+    options: &HordDbOptions,
+) -> Result<Vec<rocksdb::ColumnFamilyDescriptor>, HordDbError> {
+    let mut blocks_opts = rocks_db_options(options);
+    let mut block_based_opts = rocksdb::BlockBasedOptions::default();
+    block_based_opts.set_block_size(64 * 1024);
+    if let Some(block_cache_size) = options.block_cache_size {
+        let cache = rocksdb::Cache::new_lru_cache(block_cache_size)
+            .map_err(|e| HordDbError::RocksDb("unable to create block cache".into(), e))?;
+        block_based_opts.set_block_cache(&cache);
+    }
+    blocks_opts.set_block_based_table_factory(&block_based_opts);
+    blocks_opts.set_level_compaction_dynamic_level_bytes(true);
+
+    let mut metadata_opts = rocksdb::Options::default();
+    metadata_opts.optimize_for_point_lookup(1);
+
+    let mut traversal_cache_opts = rocksdb::Options::default();
+    traversal_cache_opts.optimize_for_point_lookup(64);
+    traversal_cache_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+
+    // Also iterated by height when warming up the in-memory traversals cache
+    let mut traversal_transactions_opts = rocksdb::Options::default();
+    traversal_transactions_opts.optimize_for_point_lookup(64);
+    traversal_transactions_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+
+    let mut sync_inbox_opts = rocksdb::Options::default();
+    sync_inbox_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+
+    Ok(vec![
+        rocksdb::ColumnFamilyDescriptor::new(BLOCKS_CF, blocks_opts),
+        rocksdb::ColumnFamilyDescriptor::new(METADATA_CF, metadata_opts),
+        rocksdb::ColumnFamilyDescriptor::new(TRAVERSAL_CACHE_CF, traversal_cache_opts),
+        rocksdb::ColumnFamilyDescriptor::new(
+            TRAVERSAL_TRANSACTIONS_CF,
+            traversal_transactions_opts,
+        ),
+        rocksdb::ColumnFamilyDescriptor::new(SYNC_INBOX_CF, sync_inbox_opts),
+    ])
+}
+
+/// Every `DB` returned by the `open_*_hord_db_conn_rocks_db` functions has the column families.
+fn column_family<'a>(blocks_db: &'a DB, name: &str) -> &'a rocksdb::ColumnFamily {
+    blocks_db
+        .cf_handle(name)
+        .unwrap_or_else(|| panic!("blocks_db opened without the {name} column family"))
+}
+
+fn blocks_cf(blocks_db: &DB) -> &rocksdb::ColumnFamily {
+    column_family(blocks_db, BLOCKS_CF)
+}
+
+fn metadata_cf(blocks_db: &DB) -> &rocksdb::ColumnFamily {
+    column_family(blocks_db, METADATA_CF)
+}
+
+fn traversal_cache_cf(blocks_db: &DB) -> &rocksdb::ColumnFamily {
+    column_family(blocks_db, TRAVERSAL_CACHE_CF)
+}
+
+fn sync_inbox_cf(blocks_db: &DB) -> &rocksdb::ColumnFamily {
+    column_family(blocks_db, SYNC_INBOX_CF)
+}
+
+/// Missing from the databases created before it was introduced, when opened read-only.
+fn traversal_transactions_cf(blocks_db: &DB) -> Option<&rocksdb::ColumnFamily> {
+    blocks_db.cf_handle(TRAVERSAL_TRANSACTIONS_CF)
+}
+
+/// Databases created before column families were introduced keep everything in the
+/// default column family, metadata keys being prefixed with `metadata::`.
+fn is_legacy_blocks_db(blocks_db: &DB) -> bool {
+    matches!(
+        blocks_db.get_cf(metadata_cf(blocks_db), LAST_INSERT_KEY),
+        Ok(None)
+    ) && matches!(blocks_db.get(b"metadata::last_insert"), Ok(Some(_)))
+}
+
+/// Moves the entries of a legacy database to their column families, in batches.
+/// Blocks are sorted before the `metadata::` entries, so an interrupted migration is
+/// resumed on the next open.
+fn migrate_legacy_blocks_db(blocks_db: &DB, ctx: &Context) -> Result<(), HordDbError> {
+    const ENTRIES_PER_BATCH: usize = 10_000;
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "Moving blocks_db entries to dedicated column families"
+        )
+    });
+    let mut moved = 0;
+    loop {
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut entries = 0;
+        for entry in blocks_db.iterator(rocksdb::IteratorMode::Start) {
+            let (key, value) =
+                entry.map_err(|e| HordDbError::RocksDb("unable to read legacy entry".into(), e))?;
+            if key.len() == 4 {
+                batch.put_cf(blocks_cf(blocks_db), &key, &value);
+            } else if let Some(metadata_key) = key.strip_prefix(b"metadata::") {
+                batch.put_cf(metadata_cf(blocks_db), metadata_key, &value);
+            }
+            batch.delete(&key);
+            entries += 1;
+            if entries == ENTRIES_PER_BATCH {
+                break;
+            }
+        }
+        if entries == 0 {
+            break;
+        }
+        blocks_db
+            .write(batch)
+            .map_err(|e| HordDbError::RocksDb("unable to migrate legacy entries".into(), e))?;
+        moved += entries;
+    }
+    blocks_db.compact_range::<&[u8], &[u8]>(None, None);
+    ctx.try_log(|logger| slog::info!(logger, "{moved} blocks_db entries moved"));
+    Ok(())
+}
+
+/// Flushes the memtables of the blocks and metadata column families.
+pub fn flush_blocks_db(blocks_db_rw: &DB) -> Result<(), HordDbError> {
+    for cf in [blocks_cf(blocks_db_rw), metadata_cf(blocks_db_rw)] {
+        blocks_db_rw
+            .flush_cf(cf)
+            .map_err(|e| HordDbError::RocksDb("unable to flush blocks_db".into(), e))?;
+    }
+    Ok(())
+}
+
+/// Compacts the blocks column family, e.g. once blocks were written with
+/// `HordDbOptions::prepare_for_bulk_load` (auto compactions being disabled).
+pub fn compact_blocks_db(blocks_db_rw: &DB) {
+    blocks_db_rw.compact_range_cf::<&[u8], &[u8]>(blocks_cf(blocks_db_rw), None, None);
+}
+
 pub fn open_readonly_hord_db_conn_rocks_db(
     base_dir: &PathBuf,
     _ctx: &Context,
-) -> Result<DB, String> {
+) -> Result<DB, HordDbError> {
     let path = get_default_hord_db_file_path_rocks_db(&base_dir);
     let opts = rocks_db_default_options();
-    let db = DB::open_for_read_only(&opts, path, false)
-        .map_err(|e| format!("unable to open blocks_db: {}", e.to_string()))?;
+    let column_families = DB::list_cf(&opts, &path)
+        .map_err(|e| HordDbError::RocksDb("unable to open blocks_db".into(), e))?;
+    if !column_families.iter().any(|cf| cf == BLOCKS_CF) {
+        return Err(HordDbError::Other(
+            "blocks_db must be opened in read-write mode once, to be migrated to column families"
+                .into(),
+        ));
+    }
+    let db = DB::open_cf_for_read_only(
+        &opts,
+        path,
+        [
+            BLOCKS_CF,
+            METADATA_CF,
+            TRAVERSAL_CACHE_CF,
+            TRAVERSAL_TRANSACTIONS_CF,
+        ]
+        .into_iter()
+        .filter(|name| column_families.iter().any(|cf| cf == name)),
+        false,
+    )
+    .map_err(|e| HordDbError::RocksDb("unable to open blocks_db".into(), e))?;
     Ok(db)
 }
 
 pub fn open_readwrite_hord_db_conn_rocks_db(
     base_dir: &PathBuf,
-    _ctx: &Context,
-) -> Result<DB, String> {
+    ctx: &Context,
+) -> Result<DB, HordDbError> {
+    open_readwrite_hord_db_conn_rocks_db_with_options(base_dir, &HordDbOptions::default(), ctx)
+}
+
+pub fn open_readwrite_hord_db_conn_rocks_db_with_options(
+    base_dir: &PathBuf,
+    options: &HordDbOptions,
+    ctx: &Context,
+) -> Result<DB, HordDbError> {
     let path = get_default_hord_db_file_path_rocks_db(&base_dir);
-    let opts = rocks_db_default_options();
-    let db = DB::open(&opts, path)
-        .map_err(|e| format!("unable to open blocks_db: {}", e.to_string()))?;
+    let opts = rocks_db_options(options);
+    let db = DB::open_cf_descriptors(&opts, path, rocks_db_column_families(options)?)
+        .map_err(|e| HordDbError::RocksDb("unable to open blocks_db".into(), e))?;
+    if is_legacy_blocks_db(&db) {
+        migrate_legacy_blocks_db(&db, ctx)?;
+    }
     Ok(db)
 }
 
@@ -344,15 +1564,50 @@ pub fn insert_entry_in_blocks(
 ) {
     let block_height_bytes = block_height.to_be_bytes();
     blocks_db_rw
-        .put(&block_height_bytes, &lazy_block.bytes)
+        .put_cf(
+            blocks_cf(blocks_db_rw),
+            &block_height_bytes,
+            &lazy_block.bytes,
+        )
         .expect("unable to insert blocks");
     blocks_db_rw
-        .put(b"metadata::last_insert", block_height_bytes)
+        .put_cf(
+            metadata_cf(blocks_db_rw),
+            LAST_INSERT_KEY,
+            block_height_bytes,
+        )
         .expect("unable to insert metadata");
 }
 
+pub fn insert_entries_in_blocks(
+    entries: &[(u32, LazyBlock)],
+    blocks_db_rw: &DB,
+    _ctx: &Context,
+) -> Result<(), HordDbError> {
+    let last_insert = match entries.iter().map(|(block_height, _)| *block_height).max() {
+        Some(block_height) => block_height,
+        None => return Ok(()),
+    };
+    let mut batch = rocksdb::WriteBatch::default();
+    for (block_height, lazy_block) in entries.iter() {
+        batch.put_cf(
+            blocks_cf(blocks_db_rw),
+            &block_height.to_be_bytes(),
+            &lazy_block.bytes,
+        );
+    }
+    batch.put_cf(
+        metadata_cf(blocks_db_rw),
+        LAST_INSERT_KEY,
+        last_insert.to_be_bytes(),
+    );
+    blocks_db_rw
+        .write(batch)
+        .map_err(|e| HordDbError::RocksDb("unable to insert blocks".into(), e))
+}
+
 pub fn find_last_block_inserted(blocks_db: &DB) -> u32 {
-    match blocks_db.get(b"metadata::last_insert") {
+    match blocks_db.get_cf(metadata_cf(blocks_db), LAST_INSERT_KEY) {
         Ok(Some(bytes)) => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
         _ => 0,
     }
@@ -362,104 +1617,1316 @@ pub fn find_block_at_block_height(
     block_height: u32,
     retry: u8,
     blocks_db: &DB,
+) -> Option<CompactedBlock> {
+    find_block_at_block_height_with_backoff(
+        block_height,
+        retry,
+        std::time::Duration::from_secs(1),
+        blocks_db,
+    )
+}
+
+/// Retry `retry` times, sleeping `delay` between attempts: only useful when racing
+/// a concurrent writer.
+pub fn find_block_at_block_height_with_backoff(
+    block_height: u32,
+    retry: u8,
+    delay: std::time::Duration,
+    blocks_db: &DB,
 ) -> Option<CompactedBlock> {
     let mut attempt = 0;
-    let mut read_options = rocksdb::ReadOptions::default();
-    read_options.fill_cache(true);
-    read_options.set_verify_checksums(false);
     loop {
-        match blocks_db.get_pinned_opt(block_height.to_be_bytes(), &read_options) {
-            Ok(Some(ref res)) => {
-                let res = CompactedBlock::deserialize(&mut std::io::Cursor::new(&res)).unwrap();
-                return Some(res);
-            }
-            _ => {
+        match try_find_block_at_block_height(block_height, blocks_db) {
+            Some(res) => return Some(res),
+            None => {
                 attempt += 1;
-                std::thread::sleep(std::time::Duration::from_secs(1));
                 if attempt > retry {
                     return None;
                 }
+                std::thread::sleep(delay);
+            }
+        }
+    }
+}
+
+/// Single, non-blocking lookup.
+pub fn try_find_block_at_block_height(block_height: u32, blocks_db: &DB) -> Option<CompactedBlock> {
+    let mut read_options = rocksdb::ReadOptions::default();
+    read_options.fill_cache(true);
+    read_options.set_verify_checksums(false);
+    match blocks_db.get_pinned_cf_opt(
+        blocks_cf(blocks_db),
+        block_height.to_be_bytes(),
+        &read_options,
+    ) {
+        Ok(Some(ref res)) => {
+            let lazy_block = LazyBlock::new(res.to_vec());
+            let res =
+                CompactedBlock::deserialize(&mut std::io::Cursor::new(lazy_block.body())).unwrap();
+            Some(res)
+        }
+        _ => None,
+    }
+}
+
+/// Storage of lazy blocks, indexed by block height.
+pub trait BlockStore {
+    fn get_lazy_block(&self, block_height: u32) -> Option<LazyBlock>;
+    fn put_block(&self, block_height: u32, lazy_block: &LazyBlock) -> Result<(), HordDbError>;
+    fn delete_block(&self, block_height: u32) -> Result<(), HordDbError>;
+    fn last_inserted(&self) -> u32;
+
+    /// Transaction persisted by `put_traversal_transactions`, not supported by default.
+    fn get_traversal_transaction(
+        &self,
+        _block_height: u32,
+        _txid: &[u8],
+    ) -> Option<LazyBlockTransaction> {
+        None
+    }
+
+    /// Persists transactions decoded by traversals, along with their block height.
+    fn put_traversal_transactions(
+        &self,
+        _transactions: &[(u32, LazyBlockTransaction)],
+    ) -> Result<(), HordDbError> {
+        Ok(())
+    }
+}
+
+impl BlockStore for DB {
+    fn get_lazy_block(&self, block_height: u32) -> Option<LazyBlock> {
+        match self.get_cf(blocks_cf(self), block_height.to_be_bytes()) {
+            Ok(Some(res)) => Some(LazyBlock::new(res)),
+            _ => None,
+        }
+    }
+
+    fn put_block(&self, block_height: u32, lazy_block: &LazyBlock) -> Result<(), HordDbError> {
+        let mut batch = rocksdb::WriteBatch::default();
+        batch.put_cf(
+            blocks_cf(self),
+            &block_height.to_be_bytes(),
+            &lazy_block.bytes,
+        );
+        batch.put_cf(
+            metadata_cf(self),
+            LAST_INSERT_KEY,
+            block_height.to_be_bytes(),
+        );
+        self.write(batch)
+            .map_err(|e| HordDbError::RocksDb("unable to insert block".into(), e))
+    }
+
+    fn delete_block(&self, block_height: u32) -> Result<(), HordDbError> {
+        self.delete_cf(blocks_cf(self), block_height.to_be_bytes())
+            .map_err(|e| HordDbError::RocksDb("unable to delete block".into(), e))
+    }
+
+    fn last_inserted(&self) -> u32 {
+        find_last_block_inserted(self)
+    }
+
+    fn get_traversal_transaction(
+        &self,
+        block_height: u32,
+        txid: &[u8],
+    ) -> Option<LazyBlockTransaction> {
+        let key = traversal_transaction_key(block_height, txid);
+        match self.get_cf(traversal_transactions_cf(self)?, key) {
+            Ok(Some(bytes)) => deserialize_traversal_transaction(&bytes)
+                .filter(|transaction| txid_identifies(&transaction.txid, txid)),
+            _ => None,
+        }
+    }
+
+    fn put_traversal_transactions(
+        &self,
+        transactions: &[(u32, LazyBlockTransaction)],
+    ) -> Result<(), HordDbError> {
+        let traversal_transactions_cf = match traversal_transactions_cf(self) {
+            Some(cf) => cf,
+            None => return Ok(()),
+        };
+        let mut batch = rocksdb::WriteBatch::default();
+        for (block_height, transaction) in transactions.iter() {
+            batch.put_cf(
+                traversal_transactions_cf,
+                traversal_transaction_key(*block_height, &transaction.txid),
+                serialize_traversal_transaction(transaction),
+            );
+        }
+        // Entries can be decoded again from the blocks: no need for the write-ahead log
+        let mut write_options = rocksdb::WriteOptions::default();
+        write_options.disable_wal(true);
+        self.write_opt(batch, &write_options)
+            .map_err(|e| HordDbError::RocksDb("unable to persist traversals cache".into(), e))
+    }
+}
+
+/// In-memory `BlockStore`, convenient for tests.
+#[derive(Debug, Default)]
+pub struct MemoryBlockStore {
+    blocks: std::sync::RwLock<HashMap<u32, Vec<u8>>>,
+    last_insert: std::sync::RwLock<u32>,
+}
+
+impl BlockStore for MemoryBlockStore {
+    fn get_lazy_block(&self, block_height: u32) -> Option<LazyBlock> {
+        let blocks = self.blocks.read().ok()?;
+        blocks
+            .get(&block_height)
+            .map(|bytes| LazyBlock::new(bytes.clone()))
+    }
+
+    fn put_block(&self, block_height: u32, lazy_block: &LazyBlock) -> Result<(), HordDbError> {
+        let mut blocks = self.blocks.write().map_err(|e| {
+            HordDbError::Other(format!("unable to insert block: {}", e.to_string()))
+        })?;
+        let mut last_insert = self.last_insert.write().map_err(|e| {
+            HordDbError::Other(format!("unable to insert block: {}", e.to_string()))
+        })?;
+        blocks.insert(block_height, lazy_block.bytes.clone());
+        *last_insert = block_height;
+        Ok(())
+    }
+
+    fn delete_block(&self, block_height: u32) -> Result<(), HordDbError> {
+        let mut blocks = self.blocks.write().map_err(|e| {
+            HordDbError::Other(format!("unable to delete block: {}", e.to_string()))
+        })?;
+        blocks.remove(&block_height);
+        Ok(())
+    }
+
+    fn last_inserted(&self) -> u32 {
+        self.last_insert.read().map(|h| *h).unwrap_or(0)
+    }
+}
+
+/// `BlockStore` falling back to `fetch_block` for the blocks missing from `blocks_db`,
+/// typically because they were pruned (see `BlockPruningPolicy`). Fetched blocks are
+/// not written back.
+pub struct RefetchingBlockStore<'a> {
+    blocks_db: &'a dyn BlockStore,
+    fetch_block: Box<dyn Fn(u32) -> Option<LazyBlock> + 'a>,
+}
+
+impl<'a> RefetchingBlockStore<'a> {
+    pub fn new(
+        blocks_db: &'a dyn BlockStore,
+        fetch_block: impl Fn(u32) -> Option<LazyBlock> + 'a,
+    ) -> RefetchingBlockStore<'a> {
+        RefetchingBlockStore {
+            blocks_db,
+            fetch_block: Box::new(fetch_block),
+        }
+    }
+
+    /// Missing blocks are re-downloaded from bitcoind.
+    pub fn with_bitcoind(
+        blocks_db: &'a dyn BlockStore,
+        bitcoin_config: &'a BitcoinConfig,
+        ctx: &'a Context,
+    ) -> RefetchingBlockStore<'a> {
+        RefetchingBlockStore::new(blocks_db, move |block_height| {
+            ctx.try_log(|logger| {
+                slog::info!(
+                    logger,
+                    "Block #{block_height} not stored, fetching from bitcoind"
+                )
+            });
+            match hiro_system_kit::nestable_block_on(download_lazy_block(
+                block_height,
+                bitcoin_config,
+                ctx,
+            )) {
+                Ok((_, lazy_block)) => Some(lazy_block),
+                Err(e) => {
+                    ctx.try_log(|logger| {
+                        slog::error!(logger, "Unable to fetch block #{block_height}: {e}")
+                    });
+                    None
+                }
+            }
+        })
+    }
+}
+
+impl<'a> BlockStore for RefetchingBlockStore<'a> {
+    fn get_lazy_block(&self, block_height: u32) -> Option<LazyBlock> {
+        self.blocks_db
+            .get_lazy_block(block_height)
+            .or_else(|| (self.fetch_block)(block_height))
+    }
+
+    fn put_block(&self, block_height: u32, lazy_block: &LazyBlock) -> Result<(), HordDbError> {
+        self.blocks_db.put_block(block_height, lazy_block)
+    }
+
+    fn delete_block(&self, block_height: u32) -> Result<(), HordDbError> {
+        self.blocks_db.delete_block(block_height)
+    }
+
+    fn last_inserted(&self) -> u32 {
+        self.blocks_db.last_inserted()
+    }
+
+    fn get_traversal_transaction(
+        &self,
+        block_height: u32,
+        txid: &[u8],
+    ) -> Option<LazyBlockTransaction> {
+        self.blocks_db.get_traversal_transaction(block_height, txid)
+    }
+
+    fn put_traversal_transactions(
+        &self,
+        transactions: &[(u32, LazyBlockTransaction)],
+    ) -> Result<(), HordDbError> {
+        self.blocks_db.put_traversal_transactions(transactions)
+    }
+}
+
+/// Read-through cache of the most recently used blocks of a `BlockStore`, bounded by
+/// a number of entries and, optionally, by the total size of the cached blocks.
+pub struct LruBlockCache<'a> {
+    blocks_db: &'a dyn BlockStore,
+    max_entries: usize,
+    max_bytes: usize,
+    entries: std::sync::Mutex<LruBlockCacheEntries>,
+}
+
+#[derive(Default)]
+struct LruBlockCacheEntries {
+    blocks: HashMap<u32, Vec<u8>>,
+    // Least recently used block first
+    usage: std::collections::VecDeque<u32>,
+    total_bytes: usize,
+}
+
+impl LruBlockCacheEntries {
+    fn touch(&mut self, block_height: u32) {
+        self.usage.retain(|h| *h != block_height);
+        self.usage.push_back(block_height);
+    }
+}
+
+impl<'a> LruBlockCache<'a> {
+    pub fn new(blocks_db: &'a dyn BlockStore, max_entries: usize) -> LruBlockCache<'a> {
+        LruBlockCache {
+            blocks_db,
+            max_entries,
+            max_bytes: usize::MAX,
+            entries: std::sync::Mutex::new(LruBlockCacheEntries::default()),
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> LruBlockCache<'a> {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Blocks missing from the store are not cached, `retry` is only used on misses.
+    pub fn get_or_fetch(&self, block_height: u32, retry: u8) -> Option<LazyBlock> {
+        if let Ok(mut entries) = self.entries.lock() {
+            if let Some(bytes) = entries.blocks.get(&block_height) {
+                let lazy_block = LazyBlock::new(bytes.clone());
+                entries.touch(block_height);
+                return Some(lazy_block);
+            }
+        }
+        let lazy_block = find_lazy_block_at_block_height_with_backoff(
+            block_height,
+            retry,
+            std::time::Duration::from_secs(1),
+            self.blocks_db,
+        )?;
+        self.insert(block_height, &lazy_block);
+        Some(lazy_block)
+    }
+
+    fn insert(&self, block_height: u32, lazy_block: &LazyBlock) {
+        let len = lazy_block.bytes.len();
+        if self.max_entries == 0 || len > self.max_bytes {
+            return;
+        }
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        // The block could have been inserted by a concurrent lookup
+        if let Some(previous) = entries
+            .blocks
+            .insert(block_height, lazy_block.bytes.clone())
+        {
+            entries.total_bytes -= previous.len();
+        }
+        entries.touch(block_height);
+        entries.total_bytes += len;
+        while entries.blocks.len() > self.max_entries || entries.total_bytes > self.max_bytes {
+            let evicted = match entries.usage.pop_front() {
+                Some(evicted) => evicted,
+                None => break,
+            };
+            if let Some(bytes) = entries.blocks.remove(&evicted) {
+                entries.total_bytes -= bytes.len();
             }
         }
     }
+
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.blocks.len())
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
+/// With `validate`, blocks failing `LazyBlock::validate` are reported as errors.
 pub fn find_lazy_block_at_block_height(
     block_height: u32,
     retry: u8,
-    blocks_db: &DB,
+    validate: bool,
+    blocks_db: &impl BlockStore,
+) -> Result<Option<LazyBlock>, HordDbError> {
+    let lazy_block = match find_lazy_block_at_block_height_with_backoff(
+        block_height,
+        retry,
+        std::time::Duration::from_secs(1),
+        blocks_db,
+    ) {
+        Some(lazy_block) => lazy_block,
+        None => return Ok(None),
+    };
+    if validate {
+        lazy_block
+            .validate()
+            .map_err(|e| HordDbError::Corrupted(format!("block #{block_height} corrupted: {e}")))?;
+    }
+    Ok(Some(lazy_block))
+}
+
+/// Retry `retry` times, sleeping `delay` between attempts: only useful when racing
+/// a concurrent writer.
+pub fn find_lazy_block_at_block_height_with_backoff(
+    block_height: u32,
+    retry: u8,
+    delay: std::time::Duration,
+    blocks_db: &(impl BlockStore + ?Sized),
 ) -> Option<LazyBlock> {
     let mut attempt = 0;
-    // let mut read_options = rocksdb::ReadOptions::default();
-    // read_options.fill_cache(true);
-    // read_options.set_verify_checksums(false);
     loop {
-        match blocks_db.get(block_height.to_be_bytes()) {
-            Ok(Some(res)) => return Some(LazyBlock::new(res)),
-            _ => {
+        match try_find_lazy_block_at_block_height(block_height, blocks_db) {
+            Some(res) => return Some(res),
+            None => {
                 attempt += 1;
-                std::thread::sleep(std::time::Duration::from_secs(1));
                 if attempt > retry {
                     return None;
                 }
+                std::thread::sleep(delay);
             }
         }
     }
 }
 
+/// Single, non-blocking lookup.
+pub fn try_find_lazy_block_at_block_height(
+    block_height: u32,
+    blocks_db: &(impl BlockStore + ?Sized),
+) -> Option<LazyBlock> {
+    blocks_db.get_lazy_block(block_height)
+}
+
+/// Transactions persisted by the traversals for the block are removed along with it.
 pub fn remove_entry_from_blocks(block_height: u32, blocks_db_rw: &DB, ctx: &Context) {
-    if let Err(e) = blocks_db_rw.delete(block_height.to_be_bytes()) {
+    let mut batch = rocksdb::WriteBatch::default();
+    batch.delete_cf(blocks_cf(blocks_db_rw), block_height.to_be_bytes());
+    delete_traversal_transactions_in_range(&mut batch, block_height, block_height, blocks_db_rw);
+    // An inboxed block at that height would be replayed from the rolled back fork
+    batch.delete_cf(sync_inbox_cf(blocks_db_rw), block_height.to_be_bytes());
+    if let Err(e) = blocks_db_rw.write(batch) {
         ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
     }
 }
 
-pub fn delete_blocks_in_block_range(
+fn delete_traversal_transactions_in_range(
+    batch: &mut rocksdb::WriteBatch,
     start_block: u32,
     end_block: u32,
     blocks_db_rw: &DB,
-    ctx: &Context,
 ) {
+    if let Some(traversal_transactions_cf) = traversal_transactions_cf(blocks_db_rw) {
+        batch.delete_range_cf(
+            traversal_transactions_cf,
+            start_block.to_be_bytes(),
+            end_block.saturating_add(1).to_be_bytes(),
+        );
+    }
+}
+
+/// Removes the blocks in `start_block..=end_block` and resets `last_insert`
+/// to `start_block - 1`, in a single write.
+pub fn delete_blocks_in_block_range(
+    start_block: u32,
+    end_block: u32,
+    blocks_db_rw: &DB,
+    _ctx: &Context,
+) -> Result<(), HordDbError> {
+    let mut batch = rocksdb::WriteBatch::default();
     for block_height in start_block..=end_block {
-        remove_entry_from_blocks(block_height, blocks_db_rw, ctx);
+        batch.delete_cf(blocks_cf(blocks_db_rw), block_height.to_be_bytes());
     }
-    let start_block_bytes = (start_block - 1).to_be_bytes();
+    delete_traversal_transactions_in_range(&mut batch, start_block, end_block, blocks_db_rw);
+    batch.put_cf(
+        metadata_cf(blocks_db_rw),
+        LAST_INSERT_KEY,
+        start_block.saturating_sub(1).to_be_bytes(),
+    );
     blocks_db_rw
-        .put(b"metadata::last_insert", start_block_bytes)
-        .expect("unable to insert metadata");
+        .write(batch)
+        .map_err(|e| HordDbError::RocksDb("unable to delete blocks".into(), e))
 }
 
-pub fn store_new_inscription(
-    inscription_data: &OrdinalInscriptionRevealData,
-    block_identifier: &BlockIdentifier,
-    hord_db_conn: &Connection,
+/// Drops the blocks stored below `height`, leaving the inscriptions table untouched.
+/// The floor is persisted under the `pruned_below` metadata key so that integrity checks
+/// don't report the pruned range as missing.
+pub fn prune_blocks_below(
+    height: u32,
+    blocks_db_rw: &DB,
     ctx: &Context,
-) {
-    if let Err(e) = hord_db_conn.execute(
-        "INSERT INTO inscriptions (inscription_id, outpoint_to_watch, ordinal_number, inscription_number, offset, block_height, block_hash) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        rusqlite::params![&inscription_data.inscription_id, &inscription_data.satpoint_post_inscription[0..inscription_data.satpoint_post_inscription.len()-2], &inscription_data.ordinal_number, &inscription_data.inscription_number, 0, &block_identifier.index, &block_identifier.hash],
-    ) {
-        ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+) -> Result<(), HordDbError> {
+    let pruned_height = find_pruned_height(blocks_db_rw);
+    if height <= pruned_height {
+        return Ok(());
     }
+    let mut batch = rocksdb::WriteBatch::default();
+    batch.delete_range_cf(
+        blocks_cf(blocks_db_rw),
+        0u32.to_be_bytes(),
+        height.to_be_bytes(),
+    );
+    batch.put_cf(
+        metadata_cf(blocks_db_rw),
+        PRUNED_BELOW_KEY,
+        height.to_be_bytes(),
+    );
+    blocks_db_rw
+        .write(batch)
+        .map_err(|e| HordDbError::RocksDb("unable to prune blocks".into(), e))?;
+    blocks_db_rw.compact_range_cf(
+        blocks_cf(blocks_db_rw),
+        Some(0u32.to_be_bytes()),
+        Some(height.to_be_bytes()),
+    );
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "Blocks #{pruned_height} to #{} pruned from hord db",
+            height - 1
+        )
+    });
+    Ok(())
 }
 
-pub fn update_transfered_inscription(
-    inscription_id: &str,
-    outpoint_post_transfer: &str,
+/// Which blocks are dropped from the blocks store once they have been indexed. Pruned
+/// blocks needed by a traversal can be re-downloaded with `RefetchingBlockStore`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockPruningPolicy {
+    KeepAll,
+    /// Keep the last N blocks inserted.
+    KeepLast(u32),
+    /// Drop the blocks below a height.
+    PruneBelow(u32),
+}
+
+impl Default for BlockPruningPolicy {
+    fn default() -> Self {
+        BlockPruningPolicy::KeepAll
+    }
+}
+
+impl BlockPruningPolicy {
+    /// Floor to pass to `prune_blocks_below`, if any. The last block inserted is never pruned.
+    pub fn pruning_height(&self, last_block_inserted: u32) -> Option<u32> {
+        let height = match self {
+            BlockPruningPolicy::KeepAll => return None,
+            BlockPruningPolicy::KeepLast(blocks) => {
+                last_block_inserted.saturating_sub(blocks.saturating_sub(1))
+            }
+            BlockPruningPolicy::PruneBelow(height) => *height,
+        };
+        match height.min(last_block_inserted) {
+            0 => None,
+            height => Some(height),
+        }
+    }
+}
+
+/// Prunes `blocks_db_rw` according to `policy`. No-op when the floor didn't move.
+pub fn apply_block_pruning_policy(
+    policy: &BlockPruningPolicy,
+    blocks_db_rw: &DB,
+    ctx: &Context,
+) -> Result<(), HordDbError> {
+    match policy.pruning_height(find_last_block_inserted(blocks_db_rw)) {
+        Some(height) => prune_blocks_below(height, blocks_db_rw, ctx),
+        None => Ok(()),
+    }
+}
+
+/// Height below which blocks were dropped by `prune_blocks_below` (0 if never pruned).
+pub fn find_pruned_height(blocks_db: &DB) -> u32 {
+    match blocks_db.get_cf(metadata_cf(blocks_db), PRUNED_BELOW_KEY) {
+        Ok(Some(bytes)) => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        _ => 0,
+    }
+}
+
+/// Heights of the blocks stored in `blocks_db`, in ascending order.
+pub fn iter_block_heights(blocks_db: &DB) -> impl Iterator<Item = u32> + '_ {
+    blocks_db
+        .iterator_cf(blocks_cf(blocks_db), rocksdb::IteratorMode::Start)
+        .filter_map(|entry| match entry {
+            Ok((key, _)) if key.len() == 4 => {
+                Some(u32::from_be_bytes([key[0], key[1], key[2], key[3]]))
+            }
+            _ => None,
+        })
+}
+
+/// Ranges `(start, end)` (inclusive) of the heights missing between the lowest and
+/// the highest block stored in `blocks_db`.
+pub fn find_block_height_gaps(blocks_db: &DB) -> Vec<(u32, u32)> {
+    let mut gaps = vec![];
+    let mut previous: Option<u32> = None;
+    for block_height in iter_block_heights(blocks_db) {
+        if let Some(previous) = previous {
+            if block_height > previous + 1 {
+                gaps.push((previous + 1, block_height - 1));
+            }
+        }
+        previous = Some(block_height);
+    }
+    gaps
+}
+
+/// Lowest block height referenced by the inscriptions table, i.e. the highest safe
+/// floor for `prune_blocks_below`.
+pub fn minimum_traversable_height(inscriptions_db_conn: &Connection) -> Option<u32> {
+    inscriptions_db_conn
+        .query_row("SELECT MIN(block_height) FROM inscriptions", [], |row| {
+            row.get::<_, Option<u32>>(0)
+        })
+        .ok()
+        .flatten()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InscriptionStoreOutcome {
+    Inserted,
+    Updated,
+}
+
+/// Hex encoded sha256 of the body of an inscription, given its `0x` prefixed hex encoded
+/// `content_bytes`. None if `content_bytes` can not be decoded.
+pub fn compute_content_hash(content_bytes: &str) -> Option<String> {
+    let bytes = decode_content_bytes(content_bytes)?;
+    Some(sha256::Hash::hash(&bytes).to_string())
+}
+
+pub(crate) fn decode_content_bytes(content_bytes: &str) -> Option<Vec<u8>> {
+    hex::decode(content_bytes.strip_prefix("0x").unwrap_or(content_bytes)).ok()
+}
+
+/// Stores the body of an inscription in `inscription_contents`, unless already known.
+/// Returns its `content_hash`, None if `content_bytes` can not be decoded.
+fn store_inscription_content(
+    content_bytes: &str,
+    hord_db_conn: &Connection,
+) -> Result<Option<String>, HordDbError> {
+    let bytes = match decode_content_bytes(content_bytes) {
+        Some(bytes) => bytes,
+        None => return Ok(None),
+    };
+    let content_hash = sha256::Hash::hash(&bytes).to_string();
+    hord_db_conn
+        .prepare_cached(
+            "INSERT OR IGNORE INTO inscription_contents (content_hash, content) VALUES (?1, ?2)",
+        )
+        .and_then(|mut stmt| stmt.execute(rusqlite::params![&content_hash, &bytes]))
+        .map_err(|e| HordDbError::Sqlite(format!("unable to store content {content_hash}"), e))?;
+    Ok(Some(content_hash))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InscriptionContent {
+    pub content_type: Option<String>,
+    pub content_length: Option<u64>,
+    pub content_hash: String,
+    pub content: Vec<u8>,
+}
+
+/// Body of the inscription `inscription_id`, None if the inscription is unknown or was
+/// stored before contents were (see `create_inscription_contents_table`).
+pub fn find_inscription_content(
+    inscription_id: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<Option<InscriptionContent>, HordDbError> {
+    inscriptions_db_conn
+        .query_row(
+            "SELECT i.content_type, i.content_length, c.content_hash, c.content FROM inscriptions i
+                INNER JOIN inscription_contents c ON c.content_hash = i.content_hash
+                WHERE i.inscription_id = ?1",
+            rusqlite::params![&inscription_id],
+            |row| {
+                Ok(InscriptionContent {
+                    content_type: row.get(0)?,
+                    content_length: row.get(1)?,
+                    content_hash: row.get(2)?,
+                    content: row.get(3)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| {
+            HordDbError::Sqlite(
+                format!("unable to retrieve content of inscription {inscription_id}"),
+                e,
+            )
+        })
+}
+
+/// Metadata of the inscription `inscription_id`, None if the inscription is unknown or
+/// has no metadata.
+pub fn find_inscription_metadata(
+    inscription_id: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<Option<serde_json::Value>, HordDbError> {
+    let metadata: Option<String> = inscriptions_db_conn
+        .query_row(
+            "SELECT metadata FROM inscriptions WHERE inscription_id = ?1",
+            rusqlite::params![&inscription_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| {
+            HordDbError::Sqlite(
+                format!("unable to retrieve metadata of inscription {inscription_id}"),
+                e,
+            )
+        })?
+        .flatten();
+    metadata
+        .map(|metadata| serde_json::from_str(&metadata))
+        .transpose()
+        .map_err(|e| {
+            HordDbError::Corrupted(format!(
+                "invalid metadata for inscription {inscription_id}: {e}"
+            ))
+        })
+}
+
+/// Opens the transaction grouping the inscriptions mutations of a block. The returned
+/// transaction can be passed wherever a `&Connection` is expected: mutations are persisted
+/// by `commit_block_write`, and rolled back if the transaction is dropped instead.
+pub fn begin_block_write(
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<rusqlite::Transaction<'_>, HordDbError> {
+    inscriptions_db_conn_rw
+        .unchecked_transaction()
+        .map_err(|e| HordDbError::Sqlite("unable to begin block write".into(), e))
+}
+
+pub fn commit_block_write(block_write: rusqlite::Transaction<'_>) -> Result<(), HordDbError> {
+    block_write
+        .commit()
+        .map_err(|e| HordDbError::Sqlite("unable to commit block write".into(), e))
+}
+
+/// Runs `f` atomically, whether or not `conn` is already in a transaction (e.g. a block
+/// write), where `unchecked_transaction` would fail.
+fn with_savepoint<T>(
+    conn: &Connection,
+    f: impl FnOnce() -> rusqlite::Result<T>,
+) -> rusqlite::Result<T> {
+    conn.execute_batch("SAVEPOINT hord_db_write")?;
+    match f() {
+        Ok(res) => {
+            conn.execute_batch("RELEASE hord_db_write")?;
+            Ok(res)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO hord_db_write; RELEASE hord_db_write");
+            Err(e)
+        }
+    }
+}
+
+/// Stores an inscription, overwriting any existing row with the same `inscription_id`
+/// (e.g. when a block is being re-indexed).
+pub fn store_new_inscription(
+    inscription_data: &OrdinalInscriptionRevealData,
+    block_identifier: &BlockIdentifier,
+    hord_db_conn: &Connection,
+    _ctx: &Context,
+) -> Result<InscriptionStoreOutcome, HordDbError> {
+    let already_stored = hord_db_conn
+        .query_row(
+            "SELECT 1 FROM inscriptions WHERE inscription_id = ?",
+            rusqlite::params![&inscription_data.inscription_id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(|e| {
+            HordDbError::Sqlite(
+                format!(
+                    "unable to lookup inscription {}",
+                    inscription_data.inscription_id
+                ),
+                e,
+            )
+        })?
+        .is_some();
+    let satpoint_post_inscription = SatPoint::parse(&inscription_data.satpoint_post_inscription)
+        .map_err(HordDbError::InvalidInput)?;
+    let outpoint_to_watch = satpoint_post_inscription.outpoint();
+    let content_hash = store_inscription_content(&inscription_data.content_bytes, hord_db_conn)?;
+
+    let metadata = inscription_data.metadata.as_ref().map(|m| m.to_string());
+
+    hord_db_conn.execute(
+        "INSERT INTO inscriptions (inscription_id, outpoint_to_watch, ordinal_number, inscription_number, offset, block_height, block_hash, transfers, content_type, content_length, content_hash, metadata, address, parent_inscription_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+            ON CONFLICT(inscription_id) DO UPDATE SET outpoint_to_watch = excluded.outpoint_to_watch, ordinal_number = excluded.ordinal_number, inscription_number = excluded.inscription_number, offset = excluded.offset, block_height = excluded.block_height, block_hash = excluded.block_hash, transfers = excluded.transfers, content_type = excluded.content_type, content_length = excluded.content_length, content_hash = excluded.content_hash, metadata = excluded.metadata, address = excluded.address, parent_inscription_id = excluded.parent_inscription_id",
+        rusqlite::params![&inscription_data.inscription_id, &outpoint_to_watch, &inscription_data.ordinal_number, &inscription_data.inscription_number, &satpoint_post_inscription.offset, &block_identifier.index, &block_identifier.hash, &inscription_data.transfers_pre_inscription, &inscription_data.content_type, &inscription_data.content_length, &content_hash, &metadata, &inscription_data.inscriber_address, &inscription_data.parent_inscription_id],
+    )
+    .map_err(|e| HordDbError::Sqlite(format!("unable to store inscription {}", inscription_data.inscription_id), e))?;
+    store_inscription_location(
+        &InscriptionLocation {
+            inscription_id: inscription_data.inscription_id.clone(),
+            block_height: block_identifier.index,
+            block_hash: block_identifier.hash.clone(),
+            tx_id: satpoint_post_inscription.txid,
+            satpoint: inscription_data.satpoint_post_inscription.clone(),
+            address: inscription_data.inscriber_address.clone(),
+        },
+        hord_db_conn,
+    )?;
+
+    Ok(if already_stored {
+        InscriptionStoreOutcome::Updated
+    } else {
+        InscriptionStoreOutcome::Inserted
+    })
+}
+
+pub fn store_new_inscriptions(
+    inscriptions: &[(OrdinalInscriptionRevealData, BlockIdentifier)],
+    hord_db_conn: &Connection,
+    _ctx: &Context,
+) -> Result<(), HordDbError> {
+    let db_tx = hord_db_conn
+        .unchecked_transaction()
+        .map_err(|e| HordDbError::Sqlite("unable to begin transaction".into(), e))?;
+    {
+        let mut stmt = db_tx
+            .prepare_cached("INSERT INTO inscriptions (inscription_id, outpoint_to_watch, ordinal_number, inscription_number, offset, block_height, block_hash, transfers, content_type, content_length, content_hash, metadata, address, parent_inscription_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)")
+            .map_err(|e| HordDbError::Sqlite("unable to prepare statement".into(), e))?;
+        for (inscription_data, block_identifier) in inscriptions.iter() {
+            let satpoint_post_inscription =
+                SatPoint::parse(&inscription_data.satpoint_post_inscription)
+                    .map_err(HordDbError::InvalidInput)?;
+            let outpoint_to_watch = satpoint_post_inscription.outpoint();
+            let content_hash = store_inscription_content(&inscription_data.content_bytes, &db_tx)?;
+            stmt.execute(rusqlite::params![
+                &inscription_data.inscription_id,
+                &outpoint_to_watch,
+                &inscription_data.ordinal_number,
+                &inscription_data.inscription_number,
+                &satpoint_post_inscription.offset,
+                &block_identifier.index,
+                &block_identifier.hash,
+                &inscription_data.transfers_pre_inscription,
+                &inscription_data.content_type,
+                &inscription_data.content_length,
+                &content_hash,
+                &inscription_data.metadata.as_ref().map(|m| m.to_string()),
+                &inscription_data.inscriber_address,
+                &inscription_data.parent_inscription_id
+            ])
+            .map_err(|e| {
+                HordDbError::Sqlite(
+                    format!(
+                        "unable to insert inscription {}",
+                        inscription_data.inscription_id
+                    ),
+                    e,
+                )
+            })?;
+            store_inscription_location(
+                &InscriptionLocation {
+                    inscription_id: inscription_data.inscription_id.clone(),
+                    block_height: block_identifier.index,
+                    block_hash: block_identifier.hash.clone(),
+                    tx_id: satpoint_post_inscription.txid,
+                    satpoint: inscription_data.satpoint_post_inscription.clone(),
+                    address: inscription_data.inscriber_address.clone(),
+                },
+                &db_tx,
+            )?;
+        }
+    }
+    db_tx
+        .commit()
+        .map_err(|e| HordDbError::Sqlite("unable to commit transaction".into(), e))?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InscriptionTransferRecord {
+    pub inscription_id: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    pub outpoint_pre_transfer: String,
+    pub outpoint_post_transfer: String,
+    pub offset: u64,
+}
+
+/// Moves the inscription to its new outpoint, owned by `updated_address`, and records the
+/// transfer in the `transfers` table.
+pub fn update_transfered_inscription(
+    inscription_id: &str,
+    outpoint_pre_transfer: &str,
+    outpoint_post_transfer: &str,
     offset: u64,
+    updated_address: Option<&str>,
+    block_identifier: &BlockIdentifier,
     inscriptions_db_conn_rw: &Connection,
     ctx: &Context,
 ) {
-    if let Err(e) = inscriptions_db_conn_rw.execute(
-        "UPDATE inscriptions SET outpoint_to_watch = ?, offset = ? WHERE inscription_id = ?",
-        rusqlite::params![&outpoint_post_transfer, &offset, &inscription_id],
+    if let Err(e) = try_update_transfered_inscription(
+        inscription_id,
+        outpoint_pre_transfer,
+        outpoint_post_transfer,
+        offset,
+        updated_address,
+        block_identifier,
+        inscriptions_db_conn_rw,
+    ) {
+        ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+    }
+}
+
+fn try_update_transfered_inscription(
+    inscription_id: &str,
+    outpoint_pre_transfer: &str,
+    outpoint_post_transfer: &str,
+    offset: u64,
+    updated_address: Option<&str>,
+    block_identifier: &BlockIdentifier,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<(), HordDbError> {
+    with_savepoint(inscriptions_db_conn_rw, || {
+        inscriptions_db_conn_rw.execute(
+            "UPDATE inscriptions SET outpoint_to_watch = ?, offset = ?, address = ?, transfers = transfers + 1 WHERE inscription_id = ?",
+            rusqlite::params![&outpoint_post_transfer, &offset, &updated_address, &inscription_id],
+        )?;
+        inscriptions_db_conn_rw.execute(
+            "INSERT INTO transfers (inscription_id, block_height, block_hash, outpoint_pre_transfer, outpoint_post_transfer, offset) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![&inscription_id, &block_identifier.index, &block_identifier.hash, &outpoint_pre_transfer, &outpoint_post_transfer, &offset],
+        )?;
+        Ok(())
+    })
+    .map_err(|e| HordDbError::Sqlite(format!("unable to transfer inscription {inscription_id}"), e))
+}
+
+pub fn find_transfers_at_block_height(
+    block_height: u64,
+    inscriptions_db_conn: &Connection,
+) -> Result<Vec<InscriptionTransferRecord>, HordDbError> {
+    let args: &[&dyn ToSql] = &[&block_height.to_sql().unwrap()];
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT inscription_id, block_height, block_hash, outpoint_pre_transfer, outpoint_post_transfer, offset FROM transfers WHERE block_height = ? ORDER BY rowid ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query transfers table".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query transfers table".into(), e))?;
+    let mut results = vec![];
+    while let Ok(Some(row)) = rows.next() {
+        results.push(InscriptionTransferRecord {
+            inscription_id: row.get(0).unwrap(),
+            block_height: row.get(1).unwrap(),
+            block_hash: row.get(2).unwrap(),
+            outpoint_pre_transfer: row.get(3).unwrap(),
+            outpoint_post_transfer: row.get(4).unwrap(),
+            offset: row.get(5).unwrap(),
+        });
+    }
+    Ok(results)
+}
+
+pub fn delete_transfers_in_block_range(
+    start_block: u32,
+    end_block: u32,
+    inscriptions_db_conn_rw: &Connection,
+    _ctx: &Context,
+) -> Result<(), HordDbError> {
+    inscriptions_db_conn_rw
+        .execute(
+            "DELETE FROM transfers WHERE block_height >= ?1 AND block_height <= ?2",
+            rusqlite::params![&start_block, &end_block],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to delete transfers".into(), e))?;
+    Ok(())
+}
+
+/// A location held by an inscription: where it was revealed, or where a transfer moved it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InscriptionLocation {
+    pub inscription_id: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    /// Transaction that revealed or moved the inscription.
+    pub tx_id: String,
+    pub satpoint: String,
+    /// None when the inscription was lost to fees, or the script is not an address.
+    pub address: Option<String>,
+}
+
+/// Records a location in the `locations` table. Storing the same location twice
+/// (e.g. when a block is being re-indexed) only refreshes it.
+pub fn store_inscription_location(
+    location: &InscriptionLocation,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<(), HordDbError> {
+    inscriptions_db_conn_rw
+        .prepare_cached(
+            "INSERT OR REPLACE INTO locations (inscription_id, block_height, block_hash, tx_id, satpoint, address) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .and_then(|mut stmt| {
+            stmt.execute(rusqlite::params![
+                &location.inscription_id,
+                &location.block_height,
+                &location.block_hash,
+                &location.tx_id,
+                &location.satpoint,
+                &location.address,
+            ])
+        })
+        .map_err(|e| {
+            HordDbError::Sqlite(
+                format!(
+                    "unable to store location {} of inscription {}",
+                    location.satpoint, location.inscription_id
+                ),
+                e,
+            )
+        })?;
+    Ok(())
+}
+
+/// Locations held by the inscription, oldest first. The first entry is its reveal.
+pub fn find_transfer_history_for_inscription(
+    inscription_id: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<Vec<InscriptionLocation>, HordDbError> {
+    find_locations(
+        "SELECT inscription_id, block_height, block_hash, tx_id, satpoint, address FROM locations WHERE inscription_id = ? ORDER BY block_height ASC, rowid ASC",
+        &inscription_id,
+        inscriptions_db_conn,
+    )
+}
+
+/// Locations reached in the block, e.g. to build a feed of the inscriptions moved.
+pub fn find_locations_at_block_height(
+    block_height: u64,
+    inscriptions_db_conn: &Connection,
+) -> Result<Vec<InscriptionLocation>, HordDbError> {
+    find_locations(
+        "SELECT inscription_id, block_height, block_hash, tx_id, satpoint, address FROM locations WHERE block_height = ? ORDER BY rowid ASC",
+        &block_height,
+        inscriptions_db_conn,
+    )
+}
+
+fn find_locations(
+    query: &str,
+    arg: &dyn ToSql,
+    inscriptions_db_conn: &Connection,
+) -> Result<Vec<InscriptionLocation>, HordDbError> {
+    let mut stmt = inscriptions_db_conn
+        .prepare(query)
+        .map_err(|e| HordDbError::Sqlite("unable to query locations table".into(), e))?;
+    let args: &[&dyn ToSql] = &[arg];
+    let rows = stmt
+        .query_map(args, |row| {
+            Ok(InscriptionLocation {
+                inscription_id: row.get(0)?,
+                block_height: row.get(1)?,
+                block_hash: row.get(2)?,
+                tx_id: row.get(3)?,
+                satpoint: row.get(4)?,
+                address: row.get(5)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| HordDbError::Sqlite("unable to query locations table".into(), e))?;
+    Ok(rows)
+}
+
+pub fn delete_locations_in_block_range(
+    start_block: u32,
+    end_block: u32,
+    inscriptions_db_conn_rw: &Connection,
+    _ctx: &Context,
+) -> Result<(), HordDbError> {
+    inscriptions_db_conn_rw
+        .execute(
+            "DELETE FROM locations WHERE block_height >= ?1 AND block_height <= ?2",
+            rusqlite::params![&start_block, &end_block],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to delete locations".into(), e))?;
+    Ok(())
+}
+
+/// Adds the balance changes of a BRC-20 ledger entry to the running totals (see
+/// `create_brc20_totals`), along with the supply it minted.
+pub fn update_brc20_totals(
+    tick: &str,
+    address: &str,
+    avail_change: i128,
+    trans_change: i128,
+    minted_change: i128,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<(), HordDbError> {
+    let parse_amount = |index: usize, amount: String| {
+        amount.parse::<i128>().map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Text,
+                Box::new(e),
+            )
+        })
+    };
+    let (avail_balance, trans_balance) = inscriptions_db_conn_rw
+        .query_row(
+            "SELECT avail_balance, trans_balance FROM brc20_balances WHERE tick = ?1 AND address = ?2",
+            rusqlite::params![&tick, &address],
+            |row| Ok((parse_amount(0, row.get(0)?)?, parse_amount(1, row.get(1)?)?)),
+        )
+        .optional()
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_balances table".into(), e))?
+        .unwrap_or((0, 0));
+    let (avail_balance, trans_balance) =
+        (avail_balance + avail_change, trans_balance + trans_change);
+    if avail_balance < 0 || trans_balance < 0 {
+        return Err(HordDbError::Corrupted(format!(
+            "negative brc20 balance of {tick} for {address}"
+        )));
+    }
+    inscriptions_db_conn_rw
+        .execute(
+            "INSERT OR REPLACE INTO brc20_balances (tick, address, avail_balance, trans_balance) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                &tick,
+                &address,
+                &avail_balance.to_string(),
+                &trans_balance.to_string()
+            ],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to update brc20 balance".into(), e))?;
+    if minted_change == 0 {
+        return Ok(());
+    }
+    let minted = inscriptions_db_conn_rw
+        .query_row(
+            "SELECT minted FROM brc20_tokens WHERE tick = ?",
+            rusqlite::params![&tick],
+            |row| parse_amount(0, row.get(0)?),
+        )
+        .optional()
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_tokens table".into(), e))?;
+    if let Some(minted) = minted {
+        inscriptions_db_conn_rw
+            .execute(
+                "UPDATE brc20_tokens SET minted = ?1 WHERE tick = ?2",
+                rusqlite::params![&(minted + minted_change).to_string(), &tick],
+            )
+            .map_err(|e| HordDbError::Sqlite("unable to update brc20 minted supply".into(), e))?;
+    }
+    Ok(())
+}
+
+/// Adds (`sign` = 1) or subtracts (`sign` = -1) the ledger entries selected by `query`
+/// to the running totals, one update per (tick, address).
+fn apply_brc20_ledger_entries_to_totals<P: rusqlite::Params>(
+    query: &str,
+    params: P,
+    sign: i128,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<(), HordDbError> {
+    let parse_amount = |row: &rusqlite::Row, index: usize| {
+        row.get::<_, String>(index)?.parse::<i128>().map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Text,
+                Box::new(e),
+            )
+        })
+    };
+    let entries = inscriptions_db_conn_rw
+        .prepare(query)
+        .and_then(|mut stmt| {
+            stmt.query_map(params, |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    parse_amount(row, 2)?,
+                    parse_amount(row, 3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        })
+        .map_err(|e| HordDbError::Sqlite("unable to query brc20_ledger table".into(), e))?;
+    let mut totals: BTreeMap<(String, String), (i128, i128, i128)> = BTreeMap::new();
+    for (tick, address, avail_change, trans_change, operation) in entries.into_iter() {
+        let total = totals.entry((tick, address)).or_default();
+        total.0 += avail_change;
+        total.1 += trans_change;
+        if operation == "mint" {
+            total.2 += avail_change;
+        }
+    }
+    for ((tick, address), (avail_change, trans_change, minted_change)) in totals.iter() {
+        update_brc20_totals(
+            tick,
+            address,
+            sign * avail_change,
+            sign * trans_change,
+            sign * minted_change,
+            inscriptions_db_conn_rw,
+        )?;
+    }
+    Ok(())
+}
+
+/// The running totals are reverted along with the entries.
+pub fn delete_brc20_entries_in_block_range(
+    start_block: u32,
+    end_block: u32,
+    inscriptions_db_conn_rw: &Connection,
+    _ctx: &Context,
+) -> Result<(), HordDbError> {
+    apply_brc20_ledger_entries_to_totals(
+        "SELECT tick, address, avail_balance, trans_balance, operation FROM brc20_ledger WHERE block_height >= ?1 AND block_height <= ?2",
+        rusqlite::params![&start_block, &end_block],
+        -1,
+        inscriptions_db_conn_rw,
+    )?;
+    inscriptions_db_conn_rw
+        .execute(
+            "DELETE FROM brc20_ledger WHERE block_height >= ?1 AND block_height <= ?2",
+            rusqlite::params![&start_block, &end_block],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to delete brc20 balance changes".into(), e))?;
+    inscriptions_db_conn_rw
+        .execute(
+            "DELETE FROM brc20_tokens WHERE block_height >= ?1 AND block_height <= ?2",
+            rusqlite::params![&start_block, &end_block],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to delete brc20 tokens".into(), e))?;
+    Ok(())
+}
+
+pub fn delete_runes_entries_in_block_range(
+    start_block: u32,
+    end_block: u32,
+    inscriptions_db_conn_rw: &Connection,
+    _ctx: &Context,
+) -> Result<(), HordDbError> {
+    for (query, error) in [
+        (
+            "DELETE FROM rune_balances WHERE block_height >= ?1 AND block_height <= ?2",
+            "unable to delete rune balances",
+        ),
+        (
+            "UPDATE rune_balances SET spent_block_height = NULL WHERE spent_block_height >= ?1 AND spent_block_height <= ?2",
+            "unable to restore rune balances",
+        ),
+        (
+            "DELETE FROM rune_burns WHERE block_height >= ?1 AND block_height <= ?2",
+            "unable to delete rune burns",
+        ),
+        (
+            "DELETE FROM rune_mints WHERE block_height >= ?1 AND block_height <= ?2",
+            "unable to delete rune mints",
+        ),
+        (
+            "DELETE FROM runes WHERE block_height >= ?1 AND block_height <= ?2",
+            "unable to delete runes",
+        ),
+    ] {
+        inscriptions_db_conn_rw
+            .execute(query, rusqlite::params![&start_block, &end_block])
+            .map_err(|e| HordDbError::Sqlite(error.into(), e))?;
+    }
+    Ok(())
+}
+
+pub fn revert_transfered_inscription(
+    inscription_id: &str,
+    outpoint_pre_transfer: &str,
+    offset: u64,
+    inscriptions_db_conn_rw: &Connection,
+    ctx: &Context,
+) {
+    if let Err(e) = try_revert_transfered_inscription(
+        inscription_id,
+        outpoint_pre_transfer,
+        offset,
+        inscriptions_db_conn_rw,
     ) {
         ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
     }
 }
 
+/// The address is restored from the location of the inscription before the transfer.
+fn try_revert_transfered_inscription(
+    inscription_id: &str,
+    outpoint_pre_transfer: &str,
+    offset: u64,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<(), HordDbError> {
+    let satpoint_pre_transfer = format!("{outpoint_pre_transfer}:{offset}");
+    inscriptions_db_conn_rw
+        .execute(
+            "UPDATE inscriptions SET outpoint_to_watch = ?1, offset = ?2, transfers = MAX(transfers - 1, 0),
+                address = (SELECT address FROM locations WHERE inscription_id = ?3 AND satpoint = ?4)
+            WHERE inscription_id = ?3",
+            rusqlite::params![
+                &outpoint_pre_transfer,
+                &offset,
+                &inscription_id,
+                &satpoint_pre_transfer
+            ],
+        )
+        .map_err(|e| {
+            HordDbError::Sqlite(
+                format!("unable to revert transfer of inscription {inscription_id}"),
+                e,
+            )
+        })?;
+    Ok(())
+}
+
 pub fn patch_inscription_number(
     inscription_id: &str,
-    inscription_number: u64,
+    inscription_number: i64,
     inscriptions_db_conn_rw: &Connection,
     ctx: &Context,
 ) {
@@ -474,7 +2941,7 @@ pub fn patch_inscription_number(
 pub fn find_latest_inscription_block_height(
     inscriptions_db_conn: &Connection,
     _ctx: &Context,
-) -> Result<Option<u64>, String> {
+) -> Result<Option<u64>, HordDbError> {
     let args: &[&dyn ToSql] = &[];
     let mut stmt = inscriptions_db_conn
         .prepare("SELECT block_height FROM inscriptions ORDER BY block_height DESC LIMIT 1")
@@ -487,308 +2954,2243 @@ pub fn find_latest_inscription_block_height(
     Ok(None)
 }
 
+/// Cursed inscriptions (negative numbers) are ignored.
 pub fn find_latest_inscription_number_at_block_height(
     block_height: &u64,
     inscriptions_db_conn: &Connection,
     _ctx: &Context,
-) -> Result<Option<u64>, String> {
+) -> Result<Option<i64>, HordDbError> {
     let args: &[&dyn ToSql] = &[&block_height.to_sql().unwrap()];
     let mut stmt = inscriptions_db_conn
         .prepare(
-            "SELECT inscription_number FROM inscriptions WHERE block_height < ? ORDER BY inscription_number DESC LIMIT 1",
+            "SELECT inscription_number FROM inscriptions WHERE block_height < ? AND inscription_number >= 0 ORDER BY inscription_number DESC LIMIT 1",
         )
-        .map_err(|e| format!("unable to query inscriptions: {}", e.to_string()))?;
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
     let mut rows = stmt
         .query(args)
-        .map_err(|e| format!("unable to query inscriptions: {}", e.to_string()))?;
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
     while let Ok(Some(row)) = rows.next() {
-        let inscription_number: u64 = row.get(0).unwrap();
+        let inscription_number: i64 = row.get(0).unwrap();
         return Ok(Some(inscription_number));
     }
     Ok(None)
 }
 
+/// Cursed inscriptions (negative numbers) are ignored.
 pub fn find_latest_inscription_number(
     inscriptions_db_conn: &Connection,
     _ctx: &Context,
-) -> Result<Option<u64>, String> {
+) -> Result<Option<i64>, HordDbError> {
     let args: &[&dyn ToSql] = &[];
     let mut stmt = inscriptions_db_conn
         .prepare(
-            "SELECT inscription_number FROM inscriptions ORDER BY inscription_number DESC LIMIT 1",
+            "SELECT inscription_number FROM inscriptions WHERE inscription_number >= 0 ORDER BY inscription_number DESC LIMIT 1",
         )
         .unwrap();
     let mut rows = stmt.query(args).unwrap();
     while let Ok(Some(row)) = rows.next() {
-        let inscription_number: u64 = row.get(0).unwrap();
+        let inscription_number: i64 = row.get(0).unwrap();
         return Ok(Some(inscription_number));
     }
     Ok(None)
 }
 
-pub fn find_inscription_with_ordinal_number(
-    ordinal_number: &u64,
+/// Reserves `count` contiguous inscription numbers for the inscriptions revealed in
+/// `block_height`, and returns the first one: numbers stored or reserved by the previous
+/// blocks are skipped. Reserving numbers for a block again (e.g. after a reorg) replaces
+/// its previous reservation. The lookup and the reservation are performed in a single
+/// write transaction, so that concurrent writers can not be handed the same numbers.
+pub fn reserve_inscription_numbers(
+    block_height: u64,
+    count: u64,
+    inscriptions_db_conn_rw: &Connection,
+    ctx: &Context,
+) -> Result<u64, HordDbError> {
+    let db_tx = rusqlite::Transaction::new_unchecked(
+        inscriptions_db_conn_rw,
+        rusqlite::TransactionBehavior::Immediate,
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to begin transaction".into(), e))?;
+    let latest_inscription_number =
+        find_latest_inscription_number_at_block_height(&block_height, &db_tx, ctx)?;
+    let latest_reserved_number: Option<i64> = db_tx
+        .query_row(
+            "SELECT MAX(first_inscription_number + count - 1) FROM inscription_number_reservations WHERE block_height < ?",
+            rusqlite::params![block_height],
+            |row| row.get(0),
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to query reservations".into(), e))?;
+    let first_inscription_number = latest_inscription_number
+        .max(latest_reserved_number)
+        .map_or(0, |inscription_number| inscription_number as u64 + 1);
+    db_tx
+        .execute(
+            "INSERT OR REPLACE INTO inscription_number_reservations (block_height, first_inscription_number, count) VALUES (?1, ?2, ?3)",
+            rusqlite::params![block_height, first_inscription_number, count],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to store reservation".into(), e))?;
+    db_tx
+        .commit()
+        .map_err(|e| HordDbError::Sqlite("unable to commit transaction".into(), e))?;
+    Ok(first_inscription_number)
+}
+
+/// Same as `find_latest_cursed_inscription_number`, only considering the inscriptions
+/// revealed before `block_height`.
+pub fn find_latest_cursed_inscription_number_at_block_height(
+    block_height: &u64,
     inscriptions_db_conn: &Connection,
     _ctx: &Context,
-) -> Option<String> {
-    let args: &[&dyn ToSql] = &[&ordinal_number.to_sql().unwrap()];
+) -> Result<Option<i64>, HordDbError> {
+    let args: &[&dyn ToSql] = &[&block_height.to_sql().unwrap()];
     let mut stmt = inscriptions_db_conn
-        .prepare("SELECT inscription_id FROM inscriptions WHERE ordinal_number = ?")
-        .unwrap();
-    let mut rows = stmt.query(args).unwrap();
+        .prepare(
+            "SELECT inscription_number FROM inscriptions WHERE block_height < ? AND inscription_number < 0 ORDER BY inscription_number ASC LIMIT 1",
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
     while let Ok(Some(row)) = rows.next() {
-        let inscription_id: String = row.get(0).unwrap();
-        return Some(inscription_id);
+        let inscription_number: i64 = row.get(0).unwrap();
+        return Ok(Some(inscription_number));
     }
-    return None;
+    Ok(None)
 }
 
-pub fn find_inscription_with_id(
-    inscription_id: &str,
-    block_hash: &str,
+/// Returns the most negative inscription number, cursed inscriptions being numbered
+/// from -1 downwards.
+pub fn find_latest_cursed_inscription_number(
     inscriptions_db_conn: &Connection,
     _ctx: &Context,
-) -> Option<TraversalResult> {
-    let args: &[&dyn ToSql] = &[&inscription_id.to_sql().unwrap()];
+) -> Result<Option<i64>, HordDbError> {
+    let args: &[&dyn ToSql] = &[];
     let mut stmt = inscriptions_db_conn
-        .prepare("SELECT inscription_number, ordinal_number, block_hash FROM inscriptions WHERE inscription_id = ?")
-        .unwrap();
-    let mut rows = stmt.query(args).unwrap();
+        .prepare(
+            "SELECT inscription_number FROM inscriptions WHERE inscription_number < 0 ORDER BY inscription_number ASC LIMIT 1",
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
     while let Ok(Some(row)) = rows.next() {
-        let inscription_block_hash: String = row.get(2).unwrap();
-        if block_hash.eq(&inscription_block_hash) {
-            let inscription_number: u64 = row.get(0).unwrap();
-            let ordinal_number: u64 = row.get(1).unwrap();
-            let traversal = TraversalResult {
-                inscription_number,
-                ordinal_number,
-                transfers: 0,
-            };
-            return Some(traversal);
-        }
+        let inscription_number: i64 = row.get(0).unwrap();
+        return Ok(Some(inscription_number));
     }
-    return None;
+    Ok(None)
 }
 
-pub fn find_all_inscriptions(
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HordDbStats {
+    pub inscriptions_count: u64,
+    pub min_block_height: Option<u64>,
+    pub max_block_height: Option<u64>,
+    pub max_inscription_number: Option<i64>,
+    pub distinct_outpoints_count: u64,
+}
+
+/// Aggregates computed in a single pass over the inscriptions table, without loading rows.
+pub fn get_hord_db_stats(conn: &Connection) -> Result<HordDbStats, HordDbError> {
+    conn.query_row(
+        "SELECT COUNT(*), MIN(block_height), MAX(block_height), MAX(inscription_number), COUNT(DISTINCT outpoint_to_watch) FROM inscriptions",
+        [],
+        |row| {
+            Ok(HordDbStats {
+                inscriptions_count: row.get(0)?,
+                min_block_height: row.get(1)?,
+                max_block_height: row.get(2)?,
+                max_inscription_number: row.get(3)?,
+                distinct_outpoints_count: row.get(4)?,
+            })
+        },
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))
+}
+
+pub fn count_inscriptions_in_block(height: u64, conn: &Connection) -> Result<u64, HordDbError> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM inscriptions WHERE block_height = ?",
+        rusqlite::params![height],
+        |row| row.get(0),
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))
+}
+
+pub fn find_inscription_with_ordinal_number(
+    ordinal_number: &u64,
     inscriptions_db_conn: &Connection,
-) -> BTreeMap<u64, Vec<(TransactionIdentifier, TraversalResult)>> {
-    let args: &[&dyn ToSql] = &[];
+    _ctx: &Context,
+) -> Option<String> {
+    let args: &[&dyn ToSql] = &[&ordinal_number.to_sql().unwrap()];
     let mut stmt = inscriptions_db_conn
-        .prepare("SELECT inscription_number, ordinal_number, block_height, inscription_id FROM inscriptions ORDER BY inscription_number ASC")
+        .prepare("SELECT inscription_id FROM inscriptions WHERE ordinal_number = ?")
         .unwrap();
-    let mut results: BTreeMap<u64, Vec<(TransactionIdentifier, TraversalResult)>> = BTreeMap::new();
     let mut rows = stmt.query(args).unwrap();
     while let Ok(Some(row)) = rows.next() {
-        let inscription_number: u64 = row.get(0).unwrap();
+        let inscription_id: String = row.get(0).unwrap();
+        return Some(inscription_id);
+    }
+    return None;
+}
+
+pub fn find_inscription_with_number(
+    inscription_number: i64,
+    inscriptions_db_conn: &Connection,
+    _ctx: &Context,
+) -> Result<Option<(String, TraversalResult)>, HordDbError> {
+    let args: &[&dyn ToSql] = &[&inscription_number.to_sql().unwrap()];
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT inscription_id, ordinal_number, transfers FROM inscriptions WHERE inscription_number = ?")
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
+    while let Ok(Some(row)) = rows.next() {
+        let inscription_id: String = row.get(0).unwrap();
         let ordinal_number: u64 = row.get(1).unwrap();
-        let block_height: u64 = row.get(2).unwrap();
-        let transaction_id = {
-            let inscription_id: String = row.get(3).unwrap();
-            TransactionIdentifier {
-                hash: format!("0x{}", &inscription_id[0..inscription_id.len() - 2]),
-            }
-        };
+        let transfers: u32 = row.get(2).unwrap();
         let traversal = TraversalResult {
             inscription_number,
             ordinal_number,
-            transfers: 0,
+            transfers,
         };
-        results
-            .entry(block_height)
-            .and_modify(|v| v.push((transaction_id.clone(), traversal.clone())))
-            .or_insert(vec![(transaction_id, traversal)]);
+        return Ok(Some((inscription_id, traversal)));
     }
-    return results;
+    Ok(None)
 }
 
-#[derive(Clone, Debug)]
-pub struct WatchedSatpoint {
-    pub inscription_id: String,
-    pub inscription_number: u64,
-    pub ordinal_number: u64,
-    pub offset: u64,
+pub fn find_inscriptions_by_content_type(
+    content_type: &str,
+    limit: u64,
+    inscriptions_db_conn: &Connection,
+) -> Result<Vec<TraversalResult>, HordDbError> {
+    let args: &[&dyn ToSql] = &[&content_type.to_sql().unwrap(), &limit.to_sql().unwrap()];
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT inscription_number, ordinal_number, transfers FROM inscriptions WHERE content_type = ? ORDER BY inscription_number ASC LIMIT ?")
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
+    let mut results = vec![];
+    while let Ok(Some(row)) = rows.next() {
+        let inscription_number: i64 = row.get(0).unwrap();
+        let ordinal_number: u64 = row.get(1).unwrap();
+        let transfers: u32 = row.get(2).unwrap();
+        results.push(TraversalResult {
+            inscription_number,
+            ordinal_number,
+            transfers,
+        });
+    }
+    Ok(results)
 }
 
-impl WatchedSatpoint {
-    pub fn get_genesis_satpoint(&self) -> String {
-        format!(
-            "{}:0",
-            &self.inscription_id[0..self.inscription_id.len() - 2]
-        )
+/// Inscriptions whose body hashes to `content_hash` (see `compute_content_hash`).
+pub fn find_inscriptions_by_content_hash(
+    content_hash: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<Vec<TraversalResult>, HordDbError> {
+    let args: &[&dyn ToSql] = &[&content_hash.to_sql().unwrap()];
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT inscription_number, ordinal_number, transfers FROM inscriptions WHERE content_hash = ? ORDER BY inscription_number ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))?;
+    let mut results = vec![];
+    while let Ok(Some(row)) = rows.next() {
+        let inscription_number: i64 = row.get(0).unwrap();
+        let ordinal_number: u64 = row.get(1).unwrap();
+        let transfers: u32 = row.get(2).unwrap();
+        results.push(TraversalResult {
+            inscription_number,
+            ordinal_number,
+            transfers,
+        });
     }
+    Ok(results)
 }
 
-pub fn find_watched_satpoint_for_inscription(
+/// Number of distinct contents inscribed more than once.
+pub fn count_duplicate_content_groups(conn: &Connection) -> Result<u64, HordDbError> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM (
+            SELECT content_hash FROM inscriptions WHERE content_hash IS NOT NULL
+            GROUP BY content_hash HAVING COUNT(*) > 1
+        )",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| HordDbError::Sqlite("unable to query inscriptions".into(), e))
+}
+
+pub fn find_inscription_with_id(
     inscription_id: &str,
+    block_hash: &str,
     inscriptions_db_conn: &Connection,
-) -> Result<(u64, WatchedSatpoint), String> {
+    _ctx: &Context,
+) -> Option<TraversalResult> {
     let args: &[&dyn ToSql] = &[&inscription_id.to_sql().unwrap()];
     let mut stmt = inscriptions_db_conn
-        .prepare("SELECT inscription_id, inscription_number, ordinal_number, offset, block_height FROM inscriptions WHERE inscription_id = ? ORDER BY offset ASC")
-        .map_err(|e| format!("unable to query inscriptions table: {}", e.to_string()))?;
-    let mut rows = stmt
-        .query(args)
-        .map_err(|e| format!("unable to query inscriptions table: {}", e.to_string()))?;
+        .prepare("SELECT inscription_number, ordinal_number, block_hash, transfers FROM inscriptions WHERE inscription_id = ?")
+        .unwrap();
+    let mut rows = stmt.query(args).unwrap();
     while let Ok(Some(row)) = rows.next() {
-        let inscription_id: String = row.get(0).unwrap();
-        let inscription_number: u64 = row.get(1).unwrap();
-        let ordinal_number: u64 = row.get(2).unwrap();
-        let offset: u64 = row.get(3).unwrap();
-        let block_height: u64 = row.get(4).unwrap();
-        return Ok((
-            block_height,
-            WatchedSatpoint {
-                inscription_id,
+        let inscription_block_hash: String = row.get(2).unwrap();
+        if block_hash.eq(&inscription_block_hash) {
+            let inscription_number: i64 = row.get(0).unwrap();
+            let ordinal_number: u64 = row.get(1).unwrap();
+            let transfers: u32 = row.get(3).unwrap();
+            let traversal = TraversalResult {
                 inscription_number,
                 ordinal_number,
-                offset,
-            },
-        ));
+                transfers,
+            };
+            return Some(traversal);
+        }
     }
-    return Err(format!(
-        "unable to find inscription with id {}",
-        inscription_id
-    ));
+    return None;
 }
 
-pub fn find_inscriptions_at_wached_outpoint(
+/// Loads every inscription of the database in memory, grouped by block height.
+/// This is unbounded: on a fully indexed chain, prefer `find_inscriptions_paginated`
+/// or `stream_all_inscriptions`.
+pub fn find_all_inscriptions(
+    inscriptions_db_conn: &Connection,
+) -> BTreeMap<u64, Vec<(TransactionIdentifier, TraversalResult)>> {
+    let mut results: BTreeMap<u64, Vec<(TransactionIdentifier, TraversalResult)>> = BTreeMap::new();
+    let _ = stream_all_inscriptions(
+        inscriptions_db_conn,
+        |block_height, transaction_id, traversal| {
+            results
+                .entry(block_height)
+                .and_modify(|v| v.push((transaction_id.clone(), traversal.clone())))
+                .or_insert(vec![(transaction_id, traversal)]);
+        },
+    );
+    return results;
+}
+
+fn parse_inscription_row(row: &rusqlite::Row) -> (u64, TransactionIdentifier, TraversalResult) {
+    let inscription_number: i64 = row.get(0).unwrap();
+    let ordinal_number: u64 = row.get(1).unwrap();
+    let block_height: u64 = row.get(2).unwrap();
+    let transaction_id = {
+        let inscription_id: String = row.get(3).unwrap();
+        // Malformed ids are kept as is rather than truncated
+        let txid = match SatPoint::genesis_of_inscription(&inscription_id) {
+            Ok(satpoint) => satpoint.txid,
+            Err(_) => inscription_id,
+        };
+        TransactionIdentifier {
+            hash: format!("0x{txid}"),
+        }
+    };
+    let transfers: u32 = row.get(4).unwrap();
+    let traversal = TraversalResult {
+        inscription_number,
+        ordinal_number,
+        transfers,
+    };
+    (block_height, transaction_id, traversal)
+}
+
+pub fn find_inscriptions_paginated(
+    offset: u64,
+    limit: u64,
+    inscriptions_db_conn: &Connection,
+) -> Result<Vec<(u64, TransactionIdentifier, TraversalResult)>, HordDbError> {
+    let args: &[&dyn ToSql] = &[&limit.to_sql().unwrap(), &offset.to_sql().unwrap()];
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT inscription_number, ordinal_number, block_height, inscription_id, transfers FROM inscriptions ORDER BY inscription_number ASC LIMIT ? OFFSET ?")
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut results = vec![];
+    while let Ok(Some(row)) = rows.next() {
+        results.push(parse_inscription_row(row));
+    }
+    Ok(results)
+}
+
+pub fn find_inscriptions_in_block_height_range(
+    start_block: u64,
+    end_block: u64,
+    inscriptions_db_conn: &Connection,
+) -> Result<BTreeMap<u64, Vec<(TransactionIdentifier, TraversalResult)>>, HordDbError> {
+    let args: &[&dyn ToSql] = &[&start_block.to_sql().unwrap(), &end_block.to_sql().unwrap()];
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT inscription_number, ordinal_number, block_height, inscription_id, transfers FROM inscriptions WHERE block_height >= ?1 AND block_height <= ?2 ORDER BY inscription_number ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut results: BTreeMap<u64, Vec<(TransactionIdentifier, TraversalResult)>> = BTreeMap::new();
+    while let Ok(Some(row)) = rows.next() {
+        let (block_height, transaction_id, traversal) = parse_inscription_row(row);
+        results
+            .entry(block_height)
+            .or_default()
+            .push((transaction_id, traversal));
+    }
+    Ok(results)
+}
+
+/// Page of inscriptions, ordered by inscription number. Pages are keyed by inscription number
+/// rather than offset, so that a page stays consistent while new inscriptions are indexed:
+/// the next page starts after the last inscription number returned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InscriptionRowsQuery {
+    pub after_inscription_number: Option<i64>,
+    pub from_block_height: Option<u64>,
+    pub to_block_height: Option<u64>,
+    pub limit: u64,
+}
+
+pub fn find_inscription_rows(
+    query: &InscriptionRowsQuery,
+    inscriptions_db_conn: &Connection,
+) -> Result<Vec<InscriptionRow>, HordDbError> {
+    let mut stmt = inscriptions_db_conn
+        .prepare(&format!(
+            "SELECT {} FROM inscriptions WHERE inscription_number > ?1 AND block_height >= ?2 AND block_height <= ?3 ORDER BY inscription_number ASC LIMIT ?4",
+            INSCRIPTION_ROW_COLUMNS.join(", ")
+        ))
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut rows = stmt
+        .query(rusqlite::params![
+            query.after_inscription_number.unwrap_or(i64::MIN),
+            query.from_block_height.unwrap_or(0),
+            query.to_block_height.unwrap_or(i64::MAX as u64),
+            query.limit,
+        ])
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut results = vec![];
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?
+    {
+        results.push(
+            InscriptionRow::from_row(row)
+                .map_err(|e| HordDbError::Sqlite("unable to read inscription row".into(), e))?,
+        );
+    }
+    Ok(results)
+}
+
+pub fn find_inscription_row_with_id(
+    inscription_id: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<Option<InscriptionRow>, HordDbError> {
+    inscriptions_db_conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM inscriptions WHERE inscription_id = ?",
+                INSCRIPTION_ROW_COLUMNS.join(", ")
+            ),
+            rusqlite::params![&inscription_id],
+            InscriptionRow::from_row,
+        )
+        .optional()
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))
+}
+
+/// Inscriptions held by the outpoint `{txid}:{vout}`, ordered by offset.
+pub fn find_inscription_rows_at_outpoint(
     outpoint: &str,
-    hord_db_conn: &Connection,
-) -> Result<Vec<WatchedSatpoint>, String> {
-    let args: &[&dyn ToSql] = &[&outpoint.to_sql().unwrap()];
-    let mut stmt = hord_db_conn
-        .prepare("SELECT inscription_id, inscription_number, ordinal_number, offset FROM inscriptions WHERE outpoint_to_watch = ? ORDER BY offset ASC")
-        .map_err(|e| format!("unable to query inscriptions table: {}", e.to_string()))?;
+    inscriptions_db_conn: &Connection,
+) -> Result<Vec<InscriptionRow>, HordDbError> {
+    let mut stmt = inscriptions_db_conn
+        .prepare(&format!(
+            "SELECT {} FROM inscriptions WHERE outpoint_to_watch = ? ORDER BY offset ASC",
+            INSCRIPTION_ROW_COLUMNS.join(", ")
+        ))
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut rows = stmt
+        .query(rusqlite::params![&outpoint])
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
     let mut results = vec![];
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?
+    {
+        results.push(
+            InscriptionRow::from_row(row)
+                .map_err(|e| HordDbError::Sqlite("unable to read inscription row".into(), e))?,
+        );
+    }
+    Ok(results)
+}
+
+/// Invokes `callback` for each inscription of the database, ordered by inscription number,
+/// without materializing the whole table.
+pub fn stream_all_inscriptions<F>(
+    inscriptions_db_conn: &Connection,
+    mut callback: F,
+) -> Result<(), HordDbError>
+where
+    F: FnMut(u64, TransactionIdentifier, TraversalResult),
+{
+    let args: &[&dyn ToSql] = &[];
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT inscription_number, ordinal_number, block_height, inscription_id, transfers FROM inscriptions ORDER BY inscription_number ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
     let mut rows = stmt
         .query(args)
-        .map_err(|e| format!("unable to query inscriptions table: {}", e.to_string()))?;
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
     while let Ok(Some(row)) = rows.next() {
-        let inscription_id: String = row.get(0).unwrap();
-        let inscription_number: u64 = row.get(1).unwrap();
-        let ordinal_number: u64 = row.get(2).unwrap();
-        let offset: u64 = row.get(3).unwrap();
-        results.push(WatchedSatpoint {
-            inscription_id,
-            inscription_number,
-            ordinal_number,
-            offset,
-        });
+        let (block_height, transaction_id, traversal) = parse_inscription_row(row);
+        callback(block_height, transaction_id, traversal);
     }
-    return Ok(results);
+    Ok(())
 }
 
-pub fn delete_inscriptions_in_block_range(
-    start_block: u32,
-    end_block: u32,
-    inscriptions_db_conn_rw: &Connection,
+/// A raw row of the `inscriptions` table, as exported by `export_inscriptions`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InscriptionRow {
+    pub inscription_id: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    pub outpoint_to_watch: String,
+    pub ordinal_number: u64,
+    pub inscription_number: i64,
+    pub offset: u64,
+    pub transfers: u32,
+    pub content_type: Option<String>,
+    pub content_length: Option<u64>,
+    pub content_hash: Option<String>,
+    pub metadata: Option<String>,
+    pub address: Option<String>,
+    pub parent_inscription_id: Option<String>,
+}
+
+const INSCRIPTION_ROW_COLUMNS: [&str; 14] = [
+    "inscription_id",
+    "block_height",
+    "block_hash",
+    "outpoint_to_watch",
+    "ordinal_number",
+    "inscription_number",
+    "offset",
+    "transfers",
+    "content_type",
+    "content_length",
+    "content_hash",
+    "metadata",
+    "address",
+    "parent_inscription_id",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON object per line.
+    Json,
+    /// A header line followed by one record per inscription. Empty fields stand for missing
+    /// values.
+    Csv,
+}
+
+impl InscriptionRow {
+    fn from_row(row: &rusqlite::Row) -> Result<InscriptionRow, rusqlite::Error> {
+        Ok(InscriptionRow {
+            inscription_id: row.get(0)?,
+            block_height: row.get(1)?,
+            block_hash: row.get(2)?,
+            outpoint_to_watch: row.get(3)?,
+            ordinal_number: row.get(4)?,
+            inscription_number: row.get(5)?,
+            offset: row.get(6)?,
+            transfers: row.get(7)?,
+            content_type: row.get(8)?,
+            content_length: row.get(9)?,
+            content_hash: row.get(10)?,
+            metadata: row.get(11)?,
+            address: row.get(12)?,
+            parent_inscription_id: row.get(13)?,
+        })
+    }
+}
+
+fn next_inscription_row(rows: &mut rusqlite::Rows) -> Result<Option<InscriptionRow>, HordDbError> {
+    match rows
+        .next()
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?
+    {
+        Some(row) => InscriptionRow::from_row(row)
+            .map(Some)
+            .map_err(|e| HordDbError::Sqlite("unable to read inscription row".into(), e)),
+        None => Ok(None),
+    }
+}
+
+/// Streams every row of the `inscriptions` table to `writer`, ordered by inscription number.
+/// Returns the number of inscriptions exported.
+pub fn export_inscriptions(
+    inscriptions_db_conn: &Connection,
+    mut writer: impl Write,
+    format: ExportFormat,
+) -> Result<u64, HordDbError> {
+    let args: &[&dyn ToSql] = &[];
+    let mut stmt = inscriptions_db_conn
+        .prepare(&format!(
+            "SELECT {} FROM inscriptions ORDER BY inscription_number ASC",
+            INSCRIPTION_ROW_COLUMNS.join(", ")
+        ))
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut exported = 0;
+    match format {
+        ExportFormat::Json => {
+            while let Some(inscription) = next_inscription_row(&mut rows)? {
+                serde_json::to_writer(&mut writer, &inscription).map_err(|e| {
+                    HordDbError::Other(format!(
+                        "unable to serialize inscription: {}",
+                        e.to_string()
+                    ))
+                })?;
+                writeln!(writer)
+                    .map_err(|e| HordDbError::Io("unable to write export".into(), e))?;
+                exported += 1;
+            }
+        }
+        ExportFormat::Csv => {
+            // The header is written by hand, for exports without inscriptions to have one
+            let mut csv_writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(&mut writer);
+            csv_writer
+                .write_record(INSCRIPTION_ROW_COLUMNS)
+                .map_err(|e| HordDbError::Io("unable to write export".into(), e.into()))?;
+            while let Some(inscription) = next_inscription_row(&mut rows)? {
+                csv_writer
+                    .serialize(&inscription)
+                    .map_err(|e| HordDbError::Io("unable to write export".into(), e.into()))?;
+                exported += 1;
+            }
+            csv_writer
+                .flush()
+                .map_err(|e| HordDbError::Io("unable to write export".into(), e))?;
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| HordDbError::Io("unable to write export".into(), e))?;
+    Ok(exported)
+}
+
+/// Loads inscriptions produced by `export_inscriptions` into the `inscriptions` table,
+/// in a single transaction. Returns the number of inscriptions imported.
+pub fn import_inscriptions(
+    inscriptions_db_conn: &Connection,
+    reader: impl Read,
+    format: ExportFormat,
+) -> Result<u64, HordDbError> {
+    let db_tx = inscriptions_db_conn
+        .unchecked_transaction()
+        .map_err(|e| HordDbError::Sqlite("unable to begin transaction".into(), e))?;
+    let mut imported = 0;
+    {
+        let mut stmt = db_tx
+            .prepare_cached("INSERT INTO inscriptions (inscription_id, block_height, block_hash, outpoint_to_watch, ordinal_number, inscription_number, offset, transfers, content_type, content_length, content_hash, metadata, address, parent_inscription_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)")
+            .map_err(|e| HordDbError::Sqlite("unable to prepare statement".into(), e))?;
+        let mut insert_inscription = |inscription: InscriptionRow| {
+            stmt.execute(rusqlite::params![
+                &inscription.inscription_id,
+                &inscription.block_height,
+                &inscription.block_hash,
+                &inscription.outpoint_to_watch,
+                &inscription.ordinal_number,
+                &inscription.inscription_number,
+                &inscription.offset,
+                &inscription.transfers,
+                &inscription.content_type,
+                &inscription.content_length,
+                &inscription.content_hash,
+                &inscription.metadata,
+                &inscription.address,
+                &inscription.parent_inscription_id
+            ])
+            .map_err(|e| {
+                HordDbError::Sqlite(
+                    format!(
+                        "unable to insert inscription {}",
+                        inscription.inscription_id
+                    ),
+                    e,
+                )
+            })?;
+            imported += 1;
+            Ok::<(), HordDbError>(())
+        };
+        match format {
+            ExportFormat::Json => {
+                for line in BufReader::new(reader).lines() {
+                    let line =
+                        line.map_err(|e| HordDbError::Io("unable to read import".into(), e))?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let inscription =
+                        serde_json::from_str::<InscriptionRow>(&line).map_err(|e| {
+                            HordDbError::InvalidInput(format!(
+                                "unable to parse inscription: {}",
+                                e.to_string()
+                            ))
+                        })?;
+                    insert_inscription(inscription)?;
+                }
+            }
+            ExportFormat::Csv => {
+                let mut csv_reader = csv::Reader::from_reader(reader);
+                let header = csv_reader.headers().map_err(|e| {
+                    HordDbError::InvalidInput(format!("unable to read csv header: {e}"))
+                })?;
+                if !header.iter().eq(INSCRIPTION_ROW_COLUMNS) {
+                    return Err(HordDbError::InvalidInput(
+                        "unexpected csv header".to_string(),
+                    ));
+                }
+                for inscription in csv_reader.deserialize::<InscriptionRow>() {
+                    let inscription = inscription.map_err(|e| {
+                        HordDbError::InvalidInput(format!("unable to parse inscription: {e}"))
+                    })?;
+                    insert_inscription(inscription)?;
+                }
+            }
+        }
+    }
+    db_tx
+        .commit()
+        .map_err(|e| HordDbError::Sqlite("unable to commit transaction".into(), e))?;
+    Ok(imported)
+}
+
+const SNAPSHOT_MANIFEST_FILE: &str = "manifest.json";
+
+/// Describes a snapshot produced by `export_hord_db_snapshot`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HordDbSnapshotManifest {
+    pub schema_version: u32,
+    pub last_block_inserted: u32,
+    pub pruned_height: u32,
+    pub inscriptions_count: u64,
+    pub max_inscription_number: Option<i64>,
+    /// Files of the snapshot, relative to its directory.
+    pub files: Vec<HordDbSnapshotFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HordDbSnapshotFile {
+    pub path: String,
+    pub size: u64,
+    /// Hex encoded sha256 of the file.
+    pub sha256: String,
+}
+
+fn hash_snapshot_file(path: &PathBuf) -> Result<(u64, String), HordDbError> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| HordDbError::Io(format!("unable to open {}", path.display()), e))?;
+    let mut engine = sha256::Hash::engine();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let mut size = 0;
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| HordDbError::Io(format!("unable to read {}", path.display()), e))?;
+        if read == 0 {
+            break;
+        }
+        engine.input(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((size, sha256::Hash::from_engine(engine).to_string()))
+}
+
+fn list_snapshot_files(dir: &PathBuf, prefix: &str) -> Result<Vec<String>, HordDbError> {
+    let mut files = vec![];
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| HordDbError::Io(format!("unable to read {}", dir.display()), e))?;
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| HordDbError::Io(format!("unable to read {}", dir.display()), e))?;
+        let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        if entry.path().is_dir() {
+            files.append(&mut list_snapshot_files(
+                &entry.path(),
+                &format!("{name}/"),
+            )?);
+        } else {
+            files.push(name);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Writes a consistent copy of the hord databases in `destination` (which must not exist):
+/// a RocksDB checkpoint of the blocks store (`hord.rocksdb`), a copy of hord.sqlite made
+/// with `VACUUM INTO`, and a `manifest.json` listing heights and checksums.
+/// Blocks inserted while the snapshot is taken are not included.
+pub fn export_hord_db_snapshot(
+    blocks_db: &DB,
+    inscriptions_db_conn: &Connection,
+    destination: &PathBuf,
     ctx: &Context,
-) {
-    if let Err(e) = inscriptions_db_conn_rw.execute(
-        "DELETE FROM inscriptions WHERE block_height >= ?1 AND block_height <= ?2",
-        rusqlite::params![&start_block, &end_block],
-    ) {
-        ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+) -> Result<HordDbSnapshotManifest, HordDbError> {
+    if destination.exists() {
+        return Err(HordDbError::InvalidInput(format!(
+            "{} already exists",
+            destination.display()
+        )));
+    }
+    std::fs::create_dir_all(destination)
+        .map_err(|e| HordDbError::Io(format!("unable to create {}", destination.display()), e))?;
+
+    rocksdb::checkpoint::Checkpoint::new(blocks_db)
+        .and_then(|checkpoint| {
+            checkpoint.create_checkpoint(get_default_hord_db_file_path_rocks_db(destination))
+        })
+        .map_err(|e| HordDbError::RocksDb("unable to create blocks_db checkpoint".into(), e))?;
+    // Heights are read from the checkpoint, blocks_db being possibly written to concurrently
+    let (last_block_inserted, pruned_height) = {
+        let snapshot_blocks_db = open_readonly_hord_db_conn_rocks_db(destination, ctx)?;
+        (
+            find_last_block_inserted(&snapshot_blocks_db),
+            find_pruned_height(&snapshot_blocks_db),
+        )
+    };
+
+    let sqlite_path = get_default_hord_db_file_path(destination);
+    inscriptions_db_conn
+        .execute(
+            "VACUUM INTO ?",
+            rusqlite::params![sqlite_path.to_string_lossy()],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to copy hord.sqlite".into(), e))?;
+    let snapshot_conn = open_existing_readonly_db(&sqlite_path, ctx)?;
+    let stats = get_hord_db_stats(&snapshot_conn)?;
+    let schema_version = find_hord_db_schema_version(&snapshot_conn)?;
+    drop(snapshot_conn);
+
+    let mut files = vec![];
+    for path in list_snapshot_files(destination, "")? {
+        let (size, sha256) = hash_snapshot_file(&destination.join(&path))?;
+        files.push(HordDbSnapshotFile { path, size, sha256 });
+    }
+    let manifest = HordDbSnapshotManifest {
+        schema_version,
+        last_block_inserted,
+        pruned_height,
+        inscriptions_count: stats.inscriptions_count,
+        max_inscription_number: stats.max_inscription_number,
+        files,
+    };
+    let manifest_path = destination.join(SNAPSHOT_MANIFEST_FILE);
+    let manifest_file = std::fs::File::create(&manifest_path)
+        .map_err(|e| HordDbError::Io(format!("unable to create {}", manifest_path.display()), e))?;
+    serde_json::to_writer_pretty(manifest_file, &manifest)
+        .map_err(|e| HordDbError::Other(format!("unable to write manifest: {}", e.to_string())))?;
+
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "hord db snapshot exported to {} (last block inserted: #{last_block_inserted})",
+            destination.display()
+        )
+    });
+    Ok(manifest)
+}
+
+pub fn read_hord_db_snapshot_manifest(
+    snapshot: &PathBuf,
+) -> Result<HordDbSnapshotManifest, HordDbError> {
+    let manifest_path = snapshot.join(SNAPSHOT_MANIFEST_FILE);
+    let manifest_file = std::fs::File::open(&manifest_path)
+        .map_err(|e| HordDbError::Io(format!("unable to open {}", manifest_path.display()), e))?;
+    serde_json::from_reader(manifest_file).map_err(|e| {
+        HordDbError::InvalidInput(format!("unable to parse manifest: {}", e.to_string()))
+    })
+}
+
+/// Verifies the snapshot in `snapshot` against its manifest, then copies it in `base_dir`,
+/// which must not contain hord databases yet.
+pub fn import_hord_db_snapshot(
+    snapshot: &PathBuf,
+    base_dir: &PathBuf,
+    ctx: &Context,
+) -> Result<HordDbSnapshotManifest, HordDbError> {
+    let manifest = read_hord_db_snapshot_manifest(snapshot)?;
+    if manifest.schema_version > HORD_SCHEMA_VERSION {
+        return Err(HordDbError::InvalidInput(format!(
+            "snapshot schema version {} is more recent than the supported version {HORD_SCHEMA_VERSION}",
+            manifest.schema_version
+        )));
+    }
+    for file in manifest.files.iter() {
+        let (size, sha256) = hash_snapshot_file(&snapshot.join(&file.path))?;
+        if size != file.size || sha256 != file.sha256 {
+            return Err(HordDbError::Corrupted(format!(
+                "snapshot file {} does not match its checksum",
+                file.path
+            )));
+        }
+    }
+
+    let sqlite_path = get_default_hord_db_file_path(base_dir);
+    let rocks_db_path = get_default_hord_db_file_path_rocks_db(base_dir);
+    for path in [&sqlite_path, &rocks_db_path] {
+        if path.exists() {
+            return Err(HordDbError::InvalidInput(format!(
+                "{} already exists",
+                path.display()
+            )));
+        }
+    }
+    for file in manifest.files.iter() {
+        let destination = base_dir.join(&file.path);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                HordDbError::Io(format!("unable to create {}", parent.display()), e)
+            })?;
+        }
+        std::fs::copy(snapshot.join(&file.path), &destination)
+            .map_err(|e| HordDbError::Io(format!("unable to copy {}", destination.display()), e))?;
+    }
+
+    let blocks_db = open_readwrite_hord_db_conn_rocks_db(base_dir, ctx)?;
+    if find_last_block_inserted(&blocks_db) != manifest.last_block_inserted {
+        return Err(HordDbError::Corrupted(
+            "imported blocks_db does not match the snapshot manifest".into(),
+        ));
+    }
+    // Snapshots produced by older versions are brought up to date
+    open_readwrite_hord_db_conn(base_dir, ctx)?;
+
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "hord db snapshot imported (last block inserted: #{})",
+            manifest.last_block_inserted
+        )
+    });
+    Ok(manifest)
+}
+
+/// A satpoint, formatted as `txid:vout:offset`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SatPoint {
+    pub txid: String,
+    pub vout: u32,
+    pub offset: u64,
+}
+
+impl SatPoint {
+    pub fn parse(satpoint: &str) -> Result<SatPoint, String> {
+        let comps = satpoint.split(':').collect::<Vec<_>>();
+        match comps[..] {
+            [txid, vout, offset] if !txid.is_empty() => Ok(SatPoint {
+                txid: txid.to_string(),
+                vout: vout
+                    .parse()
+                    .map_err(|e| format!("invalid vout in satpoint {satpoint}: {e}"))?,
+                offset: offset
+                    .parse()
+                    .map_err(|e| format!("invalid offset in satpoint {satpoint}: {e}"))?,
+            }),
+            _ => Err(format!("invalid satpoint {satpoint}")),
+        }
+    }
+
+    /// Satpoint of an inscription in its reveal transaction, given its id (`txidiN`).
+    pub fn genesis_of_inscription(inscription_id: &str) -> Result<SatPoint, String> {
+        match inscription_id.rsplit_once('i') {
+            Some((txid, index)) if !txid.is_empty() && index.parse::<u32>().is_ok() => {
+                Ok(SatPoint {
+                    txid: txid.to_string(),
+                    vout: 0,
+                    offset: 0,
+                })
+            }
+            _ => Err(format!("invalid inscription id {inscription_id}")),
+        }
+    }
+
+    /// `txid:vout`, as stored in the `outpoint_to_watch` column.
+    pub fn outpoint(&self) -> String {
+        format!("{}:{}", self.txid, self.vout)
+    }
+}
+
+impl std::fmt::Display for SatPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.txid, self.vout, self.offset)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WatchedSatpoint {
+    pub inscription_id: String,
+    pub inscription_number: i64,
+    pub ordinal_number: u64,
+    pub offset: u64,
+    pub parent_inscription_id: Option<String>,
+}
+
+/// Satpoint of the inscription in its reveal transaction, as designated by its pointer.
+/// Inscriptions revealed before the `locations` table was introduced are assumed to be
+/// located on the first sat of the transaction.
+pub fn find_genesis_satpoint(
+    inscription_id: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<SatPoint, HordDbError> {
+    match find_transfer_history_for_inscription(inscription_id, inscriptions_db_conn)?
+        .into_iter()
+        .next()
+    {
+        Some(location) => SatPoint::parse(&location.satpoint).map_err(HordDbError::Corrupted),
+        None => SatPoint::genesis_of_inscription(inscription_id).map_err(HordDbError::InvalidInput),
+    }
+}
+
+pub fn find_watched_satpoint_for_inscription(
+    inscription_id: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<(u64, WatchedSatpoint), HordDbError> {
+    let args: &[&dyn ToSql] = &[&inscription_id.to_sql().unwrap()];
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT inscription_id, inscription_number, ordinal_number, offset, block_height, parent_inscription_id FROM inscriptions WHERE inscription_id = ? ORDER BY offset ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    while let Ok(Some(row)) = rows.next() {
+        let inscription_id: String = row.get(0).unwrap();
+        let inscription_number: i64 = row.get(1).unwrap();
+        let ordinal_number: u64 = row.get(2).unwrap();
+        let offset: u64 = row.get(3).unwrap();
+        let block_height: u64 = row.get(4).unwrap();
+        let parent_inscription_id: Option<String> = row.get(5).unwrap();
+        return Ok((
+            block_height,
+            WatchedSatpoint {
+                inscription_id,
+                inscription_number,
+                ordinal_number,
+                offset,
+                parent_inscription_id,
+            },
+        ));
+    }
+    return Err(HordDbError::InscriptionNotFound(inscription_id.to_string()));
+}
+
+pub fn find_inscriptions_at_watched_outpoint(
+    outpoint: &str,
+    hord_db_conn: &Connection,
+) -> Result<Vec<WatchedSatpoint>, HordDbError> {
+    let args: &[&dyn ToSql] = &[&outpoint.to_sql().unwrap()];
+    let mut stmt = hord_db_conn
+        .prepare("SELECT inscription_id, inscription_number, ordinal_number, offset, parent_inscription_id FROM inscriptions WHERE outpoint_to_watch = ? ORDER BY offset ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut results = vec![];
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    while let Ok(Some(row)) = rows.next() {
+        let inscription_id: String = row.get(0).unwrap();
+        let inscription_number: i64 = row.get(1).unwrap();
+        let ordinal_number: u64 = row.get(2).unwrap();
+        let offset: u64 = row.get(3).unwrap();
+        let parent_inscription_id: Option<String> = row.get(4).unwrap();
+        results.push(WatchedSatpoint {
+            inscription_id,
+            inscription_number,
+            ordinal_number,
+            offset,
+            parent_inscription_id,
+        });
+    }
+    return Ok(results);
+}
+
+#[deprecated(note = "use find_inscriptions_at_watched_outpoint")]
+pub fn find_inscriptions_at_wached_outpoint(
+    outpoint: &str,
+    hord_db_conn: &Connection,
+) -> Result<Vec<WatchedSatpoint>, HordDbError> {
+    find_inscriptions_at_watched_outpoint(outpoint, hord_db_conn)
+}
+
+/// Stays well below `SQLITE_MAX_VARIABLE_NUMBER` (999 before SQLite 3.32).
+const OUTPOINTS_PER_QUERY: usize = 500;
+
+/// Batched version of `find_inscriptions_at_watched_outpoint`: inscriptions are grouped by
+/// outpoint, and ordered by offset. Outpoints without inscriptions are omitted.
+pub fn find_inscriptions_at_watched_outpoints(
+    outpoints: &[String],
+    hord_db_conn: &Connection,
+) -> Result<HashMap<String, Vec<WatchedSatpoint>>, HordDbError> {
+    let mut results: HashMap<String, Vec<WatchedSatpoint>> = HashMap::new();
+    for chunk in outpoints.chunks(OUTPOINTS_PER_QUERY) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let mut stmt = hord_db_conn
+            .prepare(&format!("SELECT outpoint_to_watch, inscription_id, inscription_number, ordinal_number, offset, parent_inscription_id FROM inscriptions WHERE outpoint_to_watch IN ({placeholders}) ORDER BY offset ASC"))
+            .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+        let mut rows = stmt
+            .query(rusqlite::params_from_iter(chunk.iter()))
+            .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+        while let Ok(Some(row)) = rows.next() {
+            let outpoint: String = row.get(0).unwrap();
+            let inscription_id: String = row.get(1).unwrap();
+            let inscription_number: i64 = row.get(2).unwrap();
+            let ordinal_number: u64 = row.get(3).unwrap();
+            let offset: u64 = row.get(4).unwrap();
+            let parent_inscription_id: Option<String> = row.get(5).unwrap();
+            results.entry(outpoint).or_default().push(WatchedSatpoint {
+                inscription_id,
+                inscription_number,
+                ordinal_number,
+                offset,
+                parent_inscription_id,
+            });
+        }
+    }
+    Ok(results)
+}
+
+/// Inscriptions currently held by `address`, grouped by outpoint and ordered by offset.
+pub fn find_inscriptions_for_address(
+    address: &str,
+    hord_db_conn: &Connection,
+) -> Result<HashMap<String, Vec<WatchedSatpoint>>, HordDbError> {
+    let mut stmt = hord_db_conn
+        .prepare("SELECT outpoint_to_watch, inscription_id, inscription_number, ordinal_number, offset, parent_inscription_id FROM inscriptions WHERE address = ? ORDER BY offset ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut rows = stmt
+        .query(rusqlite::params![&address])
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut results: HashMap<String, Vec<WatchedSatpoint>> = HashMap::new();
+    while let Ok(Some(row)) = rows.next() {
+        let outpoint: String = row.get(0).unwrap();
+        let inscription_id: String = row.get(1).unwrap();
+        let inscription_number: i64 = row.get(2).unwrap();
+        let ordinal_number: u64 = row.get(3).unwrap();
+        let offset: u64 = row.get(4).unwrap();
+        let parent_inscription_id: Option<String> = row.get(5).unwrap();
+        results.entry(outpoint).or_default().push(WatchedSatpoint {
+            inscription_id,
+            inscription_number,
+            ordinal_number,
+            offset,
+            parent_inscription_id,
+        });
+    }
+    Ok(results)
+}
+
+pub fn delete_inscriptions_in_block_range(
+    start_block: u32,
+    end_block: u32,
+    inscriptions_db_conn_rw: &Connection,
+    _ctx: &Context,
+) -> Result<(), HordDbError> {
+    with_savepoint(inscriptions_db_conn_rw, || {
+        inscriptions_db_conn_rw.execute(
+            "DELETE FROM inscription_contents WHERE content_hash IN (
+                SELECT content_hash FROM inscriptions WHERE block_height >= ?1 AND block_height <= ?2
+            ) AND NOT EXISTS (
+                SELECT 1 FROM inscriptions i WHERE i.content_hash = inscription_contents.content_hash
+                AND (i.block_height < ?1 OR i.block_height > ?2)
+            )",
+            rusqlite::params![&start_block, &end_block],
+        )?;
+        inscriptions_db_conn_rw.execute(
+            "DELETE FROM inscriptions WHERE block_height >= ?1 AND block_height <= ?2",
+            rusqlite::params![&start_block, &end_block],
+        )
+    })
+    .map_err(|e| HordDbError::Sqlite("unable to delete inscriptions".into(), e))?;
+    Ok(())
+}
+
+pub fn remove_entry_from_inscriptions(
+    inscription_id: &str,
+    inscriptions_db_rw_conn: &Connection,
+    ctx: &Context,
+) {
+    if let Err(e) = try_remove_entry_from_inscriptions(inscription_id, inscriptions_db_rw_conn) {
+        ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+    }
+}
+
+fn try_remove_entry_from_inscriptions(
+    inscription_id: &str,
+    inscriptions_db_rw_conn: &Connection,
+) -> Result<(), HordDbError> {
+    // Contents are shared between inscriptions, only drop the ones no longer referenced.
+    with_savepoint(inscriptions_db_rw_conn, || {
+        inscriptions_db_rw_conn.execute(
+            "DELETE FROM inscription_contents WHERE content_hash = (
+                SELECT content_hash FROM inscriptions WHERE inscription_id = ?1
+            ) AND NOT EXISTS (
+                SELECT 1 FROM inscriptions i WHERE i.content_hash = inscription_contents.content_hash
+                AND i.inscription_id != ?1
+            )",
+            rusqlite::params![&inscription_id],
+        )?;
+        inscriptions_db_rw_conn.execute(
+            "DELETE FROM locations WHERE inscription_id = ?1",
+            rusqlite::params![&inscription_id],
+        )?;
+        inscriptions_db_rw_conn.execute(
+            "DELETE FROM inscriptions WHERE inscription_id = ?1",
+            rusqlite::params![&inscription_id],
+        )
+    })
+    .map_err(|e| HordDbError::Sqlite(format!("unable to remove inscription {inscription_id}"), e))?;
+    Ok(())
+}
+
+/// Storage of the inscriptions index, so that it can live outside of hord.sqlite
+/// (see `postgres::PostgresInscriptionsStore`, behind the `postgres` feature).
+pub trait InscriptionsStore {
+    fn store_new_inscription(
+        &self,
+        inscription_data: &OrdinalInscriptionRevealData,
+        block_identifier: &BlockIdentifier,
+    ) -> Result<InscriptionStoreOutcome, HordDbError>;
+    /// Only returns the inscription if it was revealed in the block `block_hash`.
+    fn find_inscription_with_id(
+        &self,
+        inscription_id: &str,
+        block_hash: &str,
+    ) -> Result<Option<TraversalResult>, HordDbError>;
+    fn find_inscription_with_number(
+        &self,
+        inscription_number: i64,
+    ) -> Result<Option<(String, TraversalResult)>, HordDbError>;
+    fn find_inscription_with_ordinal_number(
+        &self,
+        ordinal_number: u64,
+    ) -> Result<Option<String>, HordDbError>;
+    /// Cursed inscriptions (negative numbers) are ignored.
+    fn find_latest_inscription_number_at_block_height(
+        &self,
+        block_height: u64,
+    ) -> Result<Option<i64>, HordDbError>;
+    fn find_inscriptions_at_watched_outpoint(
+        &self,
+        outpoint: &str,
+    ) -> Result<Vec<WatchedSatpoint>, HordDbError>;
+    fn find_transfers_at_block_height(
+        &self,
+        block_height: u64,
+    ) -> Result<Vec<InscriptionTransferRecord>, HordDbError>;
+    fn update_transfered_inscription(
+        &self,
+        inscription_id: &str,
+        outpoint_pre_transfer: &str,
+        outpoint_post_transfer: &str,
+        offset: u64,
+        updated_address: Option<&str>,
+        block_identifier: &BlockIdentifier,
+    ) -> Result<(), HordDbError>;
+    fn revert_transfered_inscription(
+        &self,
+        inscription_id: &str,
+        outpoint_pre_transfer: &str,
+        offset: u64,
+    ) -> Result<(), HordDbError>;
+    fn remove_inscription(&self, inscription_id: &str) -> Result<(), HordDbError>;
+}
+
+impl InscriptionsStore for Connection {
+    fn store_new_inscription(
+        &self,
+        inscription_data: &OrdinalInscriptionRevealData,
+        block_identifier: &BlockIdentifier,
+    ) -> Result<InscriptionStoreOutcome, HordDbError> {
+        store_new_inscription(inscription_data, block_identifier, self, &Context::empty())
+    }
+
+    fn find_inscription_with_id(
+        &self,
+        inscription_id: &str,
+        block_hash: &str,
+    ) -> Result<Option<TraversalResult>, HordDbError> {
+        Ok(find_inscription_with_id(
+            inscription_id,
+            block_hash,
+            self,
+            &Context::empty(),
+        ))
+    }
+
+    fn find_inscription_with_number(
+        &self,
+        inscription_number: i64,
+    ) -> Result<Option<(String, TraversalResult)>, HordDbError> {
+        find_inscription_with_number(inscription_number, self, &Context::empty())
+    }
+
+    fn find_inscription_with_ordinal_number(
+        &self,
+        ordinal_number: u64,
+    ) -> Result<Option<String>, HordDbError> {
+        Ok(find_inscription_with_ordinal_number(
+            &ordinal_number,
+            self,
+            &Context::empty(),
+        ))
+    }
+
+    fn find_latest_inscription_number_at_block_height(
+        &self,
+        block_height: u64,
+    ) -> Result<Option<i64>, HordDbError> {
+        find_latest_inscription_number_at_block_height(&block_height, self, &Context::empty())
+    }
+
+    fn find_inscriptions_at_watched_outpoint(
+        &self,
+        outpoint: &str,
+    ) -> Result<Vec<WatchedSatpoint>, HordDbError> {
+        find_inscriptions_at_watched_outpoint(outpoint, self)
+    }
+
+    fn find_transfers_at_block_height(
+        &self,
+        block_height: u64,
+    ) -> Result<Vec<InscriptionTransferRecord>, HordDbError> {
+        find_transfers_at_block_height(block_height, self)
+    }
+
+    fn update_transfered_inscription(
+        &self,
+        inscription_id: &str,
+        outpoint_pre_transfer: &str,
+        outpoint_post_transfer: &str,
+        offset: u64,
+        updated_address: Option<&str>,
+        block_identifier: &BlockIdentifier,
+    ) -> Result<(), HordDbError> {
+        try_update_transfered_inscription(
+            inscription_id,
+            outpoint_pre_transfer,
+            outpoint_post_transfer,
+            offset,
+            updated_address,
+            block_identifier,
+            self,
+        )
+    }
+
+    fn revert_transfered_inscription(
+        &self,
+        inscription_id: &str,
+        outpoint_pre_transfer: &str,
+        offset: u64,
+    ) -> Result<(), HordDbError> {
+        try_revert_transfered_inscription(inscription_id, outpoint_pre_transfer, offset, self)
+    }
+
+    fn remove_inscription(&self, inscription_id: &str) -> Result<(), HordDbError> {
+        try_remove_entry_from_inscriptions(inscription_id, self)
+    }
+}
+
+/// Replica of the inscriptions index kept in the Postgres database at `postgres_url`, if any.
+/// Requires the `postgres` feature.
+pub fn open_inscriptions_replica(
+    postgres_url: Option<&str>,
+) -> Result<Option<Box<dyn InscriptionsStore>>, HordDbError> {
+    match postgres_url {
+        #[cfg(feature = "postgres")]
+        Some(url) => Ok(Some(Box::new(
+            self::postgres::PostgresInscriptionsStore::connect(url)?,
+        ))),
+        #[cfg(not(feature = "postgres"))]
+        Some(_) => Err(HordDbError::Other(
+            "inscriptions replicas require the `postgres` feature".into(),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Writes the inscriptions revealed and transfered by `block`, once augmented, to `store`.
+/// Used for keeping a replica of hord.sqlite (e.g. `postgres::PostgresInscriptionsStore`)
+/// in sync with the blocks indexed.
+pub fn replicate_block_in_inscriptions_store(
+    block: &BitcoinBlockData,
+    store: &dyn InscriptionsStore,
+) -> Result<(), HordDbError> {
+    for tx in block.transactions.iter() {
+        for ordinal_event in tx.metadata.ordinal_operations.iter() {
+            match ordinal_event {
+                OrdinalOperation::InscriptionRevealed(data) => {
+                    store.store_new_inscription(data, &block.block_identifier)?;
+                }
+                OrdinalOperation::InscriptionTransferred(data) => {
+                    let satpoint_pre_transfer = SatPoint::parse(&data.satpoint_pre_transfer)
+                        .map_err(HordDbError::InvalidInput)?;
+                    let satpoint_post_transfer = SatPoint::parse(&data.satpoint_post_transfer)
+                        .map_err(HordDbError::InvalidInput)?;
+                    store.update_transfered_inscription(
+                        &data.inscription_id,
+                        &satpoint_pre_transfer.outpoint(),
+                        &satpoint_post_transfer.outpoint(),
+                        satpoint_post_transfer.offset,
+                        data.updated_address.as_deref(),
+                        &block.block_identifier,
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Undoes `replicate_block_in_inscriptions_store`, the changes being reverted in reverse order.
+pub fn revert_block_in_inscriptions_store(
+    block: &BitcoinBlockData,
+    store: &dyn InscriptionsStore,
+) -> Result<(), HordDbError> {
+    for tx in block.transactions.iter().rev() {
+        for ordinal_event in tx.metadata.ordinal_operations.iter().rev() {
+            match ordinal_event {
+                OrdinalOperation::InscriptionRevealed(data) => {
+                    store.remove_inscription(&data.inscription_id)?;
+                }
+                OrdinalOperation::InscriptionTransferred(data) => {
+                    let satpoint_pre_transfer = SatPoint::parse(&data.satpoint_pre_transfer)
+                        .map_err(HordDbError::InvalidInput)?;
+                    store.revert_transfered_inscription(
+                        &data.inscription_id,
+                        &satpoint_pre_transfer.outpoint(),
+                        satpoint_pre_transfer.offset,
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HordDbDeleteError {
+    /// Inscriptions could not be deleted, the blocks store was left untouched.
+    Inscriptions(HordDbError),
+    /// Inscriptions were deleted, but the blocks could not be removed from the blocks store.
+    Blocks(HordDbError),
+}
+
+impl std::fmt::Display for HordDbDeleteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HordDbDeleteError::Inscriptions(e) => write!(f, "hord.sqlite: {e}"),
+            HordDbDeleteError::Blocks(e) => write!(f, "blocks store: {e}"),
+        }
+    }
+}
+
+/// Inscriptions are deleted first, the blocks are only removed once this deletion
+/// is committed: an interruption leaves blocks without inscriptions, which can be
+/// recovered by re-indexing the range.
+pub fn delete_data_in_hord_db(
+    start_block: u64,
+    end_block: u64,
+    blocks_db_rw: &DB,
+    inscriptions_db_conn_rw: &Connection,
+    ctx: &Context,
+) -> Result<(), HordDbDeleteError> {
+    // hord.sqlite is cleaned up in a single transaction, committed before the blocks are
+    // removed: a failure leaves the range fully indexed, and the deletion can be retried.
+    let block_write =
+        begin_block_write(inscriptions_db_conn_rw).map_err(HordDbDeleteError::Inscriptions)?;
+    delete_transfers_in_block_range(start_block as u32, end_block as u32, &block_write, &ctx)
+        .map_err(HordDbDeleteError::Inscriptions)?;
+    delete_locations_in_block_range(start_block as u32, end_block as u32, &block_write, &ctx)
+        .map_err(HordDbDeleteError::Inscriptions)?;
+    delete_brc20_entries_in_block_range(start_block as u32, end_block as u32, &block_write, &ctx)
+        .map_err(HordDbDeleteError::Inscriptions)?;
+    delete_runes_entries_in_block_range(start_block as u32, end_block as u32, &block_write, &ctx)
+        .map_err(HordDbDeleteError::Inscriptions)?;
+    delete_inscriptions_in_block_range(start_block as u32, end_block as u32, &block_write, &ctx)
+        .map_err(HordDbDeleteError::Inscriptions)?;
+    commit_block_write(block_write).map_err(HordDbDeleteError::Inscriptions)?;
+    delete_blocks_in_block_range(start_block as u32, end_block as u32, blocks_db_rw, &ctx)
+        .map_err(HordDbDeleteError::Blocks)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HordDbReport {
+    pub last_block_inserted: u32,
+    /// Block heights in pruned_below..=last_block_inserted that can't be retrieved from the blocks store
+    pub missing_blocks: Vec<u32>,
+    /// Inscriptions (inscription_id, block_height) revealed in a block missing from the blocks store
+    pub orphaned_inscriptions: Vec<(String, u64)>,
+    pub latest_inscription_block_height: Option<u64>,
+}
+
+impl HordDbReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_blocks.is_empty() && self.orphaned_inscriptions.is_empty()
+    }
+
+    /// Lowest height from which the hord db should be re-seeded, if any.
+    pub fn first_gap(&self) -> Option<u64> {
+        let first_missing_block = self.missing_blocks.first().map(|h| *h as u64);
+        let first_orphaned_inscription = self.orphaned_inscriptions.iter().map(|(_, h)| *h).min();
+        match (first_missing_block, first_orphaned_inscription) {
+            (Some(a), Some(b)) => Some(std::cmp::min(a, b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+pub fn check_hord_db_integrity(
+    blocks_db: &DB,
+    inscriptions_db_conn: &Connection,
+    ctx: &Context,
+) -> Result<HordDbReport, HordDbError> {
+    let mut report = HordDbReport::default();
+    report.last_block_inserted = find_last_block_inserted(blocks_db);
+
+    let pruned_height = find_pruned_height(blocks_db);
+    for block_height in pruned_height..=report.last_block_inserted {
+        if try_find_lazy_block_at_block_height(block_height, blocks_db).is_none() {
+            report.missing_blocks.push(block_height);
+        }
+    }
+
+    let args: &[&dyn ToSql] = &[];
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT inscription_id, block_height FROM inscriptions ORDER BY block_height ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    let mut rows = stmt
+        .query(args)
+        .map_err(|e| HordDbError::Sqlite("unable to query inscriptions table".into(), e))?;
+    while let Ok(Some(row)) = rows.next() {
+        let inscription_id: String = row.get(0).unwrap();
+        let block_height: u64 = row.get(1).unwrap();
+        report.latest_inscription_block_height = Some(block_height);
+        let is_block_stored = block_height < pruned_height as u64
+            || (block_height <= report.last_block_inserted as u64
+                && report
+                    .missing_blocks
+                    .binary_search(&(block_height as u32))
+                    .is_err());
+        if !is_block_stored {
+            report
+                .orphaned_inscriptions
+                .push((inscription_id, block_height));
+        }
+    }
+
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "hord_db integrity check: {} blocks missing, {} inscriptions without block (last block inserted: #{})",
+            report.missing_blocks.len(),
+            report.orphaned_inscriptions.len(),
+            report.last_block_inserted
+        )
+    });
+
+    Ok(report)
+}
+
+/// Downloads block #`block_height` from bitcoind, returning its hash and its lazy encoding.
+async fn download_lazy_block(
+    block_height: u32,
+    bitcoin_config: &BitcoinConfig,
+    ctx: &Context,
+) -> Result<(String, LazyBlock), HordDbError> {
+    let block_hash = retrieve_block_hash_with_retry(&(block_height as u64), bitcoin_config, ctx)
+        .await
+        .map_err(HordDbError::Other)?;
+    let block_data = download_block_with_retry(&block_hash, bitcoin_config, ctx)
+        .await
+        .map_err(HordDbError::Other)?;
+    let lazy_block = LazyBlock::from_full_block(&block_data).map_err(|e| {
+        HordDbError::Other(format!("unable to compress block #{block_height}: {e}"))
+    })?;
+    Ok((block_hash, lazy_block))
+}
+
+/// Re-downloads block #`block_height` and compares it with the block stored in `blocks_db`,
+/// e.g. to detect stale data left by a reorg. Headers are not compared, so that legacy
+/// (header-less) blocks can be verified. On mismatch, the first differing transaction
+/// is logged.
+pub async fn verify_stored_block(
+    block_height: u32,
+    blocks_db: &DB,
+    bitcoin_config: &BitcoinConfig,
+    ctx: &Context,
+) -> Result<bool, HordDbError> {
+    let stored_block = find_lazy_block_at_block_height(block_height, 0, false, blocks_db)?
+        .ok_or(HordDbError::BlockNotFound(block_height))?;
+    let (block_hash, remote_block) = download_lazy_block(block_height, bitcoin_config, ctx).await?;
+    if stored_block.body() == remote_block.body() {
+        return Ok(true);
+    }
+
+    let mismatch = if stored_block.get_coinbase_txid() != remote_block.get_coinbase_txid()
+        || stored_block.get_coinbase_sats() != remote_block.get_coinbase_sats()
+    {
+        "coinbase".to_string()
+    } else {
+        let index = stored_block
+            .iter_tx()
+            .zip(remote_block.iter_tx())
+            .position(|(stored_tx, remote_tx)| stored_tx != remote_tx)
+            .unwrap_or_else(|| {
+                stored_block
+                    .transaction_count()
+                    .min(remote_block.transaction_count()) as usize
+            });
+        format!("transaction #{index} (excluding coinbase)")
+    };
+    ctx.try_log(|logger| {
+        slog::warn!(
+            logger,
+            "Block #{block_height} stored does not match block {block_hash}: first difference at {mismatch} ({} transactions stored, {} expected)",
+            stored_block.transaction_count(),
+            remote_block.transaction_count()
+        )
+    });
+    Ok(false)
+}
+
+#[derive(Debug, Default)]
+pub struct HordDbVerificationReport {
+    /// Missing blocks and orphaned inscriptions, see `check_hord_db_integrity`
+    pub integrity: HordDbReport,
+    /// Blocks stored that can't be decoded, with the reason
+    pub corrupted_blocks: Vec<(u32, String)>,
+    /// Heights compared with bitcoind
+    pub sampled_blocks: Vec<u32>,
+    /// Sampled blocks whose transactions differ from the ones served by bitcoind
+    pub mismatching_blocks: Vec<u32>,
+}
+
+impl HordDbVerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.integrity.is_consistent()
+            && self.corrupted_blocks.is_empty()
+            && self.mismatching_blocks.is_empty()
+    }
+
+    /// Heights that should be re-downloaded from bitcoind, sorted and deduplicated.
+    pub fn blocks_to_repair(&self) -> Vec<u32> {
+        let mut block_heights = self.integrity.missing_blocks.clone();
+        block_heights.extend(self.corrupted_blocks.iter().map(|(h, _)| *h));
+        block_heights.extend(self.mismatching_blocks.iter());
+        block_heights.extend(
+            self.integrity
+                .orphaned_inscriptions
+                .iter()
+                .map(|(_, h)| *h as u32)
+                .filter(|h| *h <= self.integrity.last_block_inserted),
+        );
+        block_heights.sort();
+        block_heights.dedup();
+        block_heights
+    }
+}
+
+/// Walks every block stored in `blocks_db` and checks that it can be decoded
+/// (see `LazyBlock::validate`).
+pub fn find_corrupted_blocks(blocks_db: &DB, ctx: &Context) -> Vec<(u32, String)> {
+    let mut corrupted_blocks = vec![];
+    for block_height in iter_block_heights(blocks_db) {
+        let lazy_block = match try_find_lazy_block_at_block_height(block_height, blocks_db) {
+            Some(lazy_block) => lazy_block,
+            None => continue,
+        };
+        if let Err(e) = lazy_block.validate() {
+            ctx.try_log(|logger| slog::warn!(logger, "Block #{block_height} corrupted: {e}"));
+            corrupted_blocks.push((block_height, e));
+        }
+    }
+    corrupted_blocks
+}
+
+/// Full verification pass: integrity check of the blocks store and of the inscriptions
+/// table, decoding of every stored block, and comparison of `sample_size` random stored
+/// blocks with the ones served by bitcoind (skipped when `bitcoin_config` is `None`).
+pub async fn verify_hord_db(
+    blocks_db: &DB,
+    inscriptions_db_conn: &Connection,
+    bitcoin_config: Option<&BitcoinConfig>,
+    sample_size: usize,
+    ctx: &Context,
+) -> Result<HordDbVerificationReport, HordDbError> {
+    let mut report = HordDbVerificationReport::default();
+    report.integrity = check_hord_db_integrity(blocks_db, inscriptions_db_conn, ctx)?;
+    report.corrupted_blocks = find_corrupted_blocks(blocks_db, ctx);
+
+    let bitcoin_config = match bitcoin_config {
+        Some(bitcoin_config) if sample_size > 0 => bitcoin_config,
+        _ => return Ok(report),
+    };
+    let candidates = iter_block_heights(blocks_db)
+        .filter(|h| report.corrupted_blocks.iter().all(|(c, _)| c != h))
+        .collect::<Vec<_>>();
+    let mut sampled_blocks = {
+        use rand::seq::SliceRandom;
+        candidates
+            .choose_multiple(&mut rand::thread_rng(), sample_size)
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    sampled_blocks.sort();
+    for block_height in sampled_blocks.iter() {
+        if !verify_stored_block(*block_height, blocks_db, bitcoin_config, ctx).await? {
+            report.mismatching_blocks.push(*block_height);
+        }
+    }
+    report.sampled_blocks = sampled_blocks;
+
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "hord_db verification: {} blocks corrupted, {}/{} sampled blocks mismatching",
+            report.corrupted_blocks.len(),
+            report.mismatching_blocks.len(),
+            report.sampled_blocks.len()
+        )
+    });
+    Ok(report)
+}
+
+/// Re-downloads the blocks returned by `HordDbVerificationReport::blocks_to_repair` and
+/// overwrites them in `blocks_db_rw`. Inscriptions are left untouched: use
+/// `delete_data_in_hord_db` and a sync to re-index a range. Returns the number of blocks written.
+pub async fn repair_hord_db(
+    report: &HordDbVerificationReport,
+    blocks_db_rw: &DB,
+    bitcoin_config: &BitcoinConfig,
+    ctx: &Context,
+) -> Result<usize, HordDbError> {
+    let block_heights = report.blocks_to_repair();
+    for block_height in block_heights.iter() {
+        let (_, lazy_block) = download_lazy_block(*block_height, bitcoin_config, ctx).await?;
+        blocks_db_rw
+            .put_cf(
+                blocks_cf(blocks_db_rw),
+                block_height.to_be_bytes(),
+                &lazy_block.bytes,
+            )
+            .map_err(|e| {
+                HordDbError::RocksDb(format!("unable to repair block #{block_height}"), e)
+            })?;
+        ctx.try_log(|logger| slog::info!(logger, "Block #{block_height} repaired"));
+    }
+    flush_blocks_db(blocks_db_rw)?;
+    Ok(block_heights.len())
+}
+
+/// Resumes `fetch_and_cache_blocks_in_hord_db` from the last block known by both the blocks
+/// store and the inscriptions table, up to `target_end_block`.
+pub async fn resume_fetch_and_cache_blocks_in_hord_db(
+    bitcoin_config: &BitcoinConfig,
+    blocks_db_rw: &DB,
+    inscriptions_db_conn_rw: &Connection,
+    inscriptions_replica: Option<&dyn InscriptionsStore>,
+    target_end_block: u64,
+    ordinal_computing_height: u64,
+    fetch_config: &FetchConfig,
+    hord_db_path: &PathBuf,
+    cancellation_flag: &Arc<AtomicBool>,
+    progress: Option<&SyncProgressTracker>,
+    ctx: &Context,
+) -> Result<Option<u64>, HordDbError> {
+    let last_block_inserted = find_last_block_inserted(blocks_db_rw) as u64;
+    let latest_inscription_block_height =
+        find_latest_inscription_block_height(inscriptions_db_conn_rw, ctx)?;
+
+    let start_block = match latest_inscription_block_height {
+        Some(block_height) => last_block_inserted.min(block_height),
+        None => last_block_inserted,
+    };
+    // Blocks completed by an interrupted sync are not processed again, unless rolled back
+    let start_block = match find_sync_cursor(blocks_db_rw) {
+        Some(sync_cursor) if sync_cursor <= last_block_inserted => start_block.max(sync_cursor + 1),
+        _ => start_block,
+    };
+
+    if start_block >= target_end_block && last_block_inserted >= target_end_block {
+        ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Hord db already caught up with block #{target_end_block} (last block inserted: #{last_block_inserted})"
+            )
+        });
+        return Ok(Some(start_block));
+    }
+
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "Resuming hord db sync from block #{start_block} to #{target_end_block} (last block inserted: #{last_block_inserted}, latest inscription at block: {:?})",
+            latest_inscription_block_height
+        )
+    });
+
+    fetch_and_cache_blocks_in_hord_db(
+        bitcoin_config,
+        blocks_db_rw,
+        inscriptions_db_conn_rw,
+        inscriptions_replica,
+        start_block,
+        target_end_block,
+        ordinal_computing_height,
+        fetch_config,
+        hord_db_path,
+        cancellation_flag,
+        progress,
+        ctx,
+    )
+    .await
+}
+
+/// Height from which blocks are scanned for inscriptions: the first inscription was
+/// revealed in block #767430 on mainnet, other networks are scanned from genesis.
+pub fn get_default_ordinal_computing_height(network: &BitcoinNetwork) -> u64 {
+    match network {
+        BitcoinNetwork::Mainnet => 765000,
+        BitcoinNetwork::Testnet | BitcoinNetwork::Regtest => 0,
+    }
+}
+
+/// Sizing of the thread pools and channels of `fetch_and_cache_blocks_in_hord_db`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchConfig {
+    /// Threads of thread pool #1, retrieving block hashes from bitcoind.
+    pub block_hash_concurrency: usize,
+    /// Threads of thread pool #2, downloading full blocks (including prevouts) from bitcoind.
+    pub block_data_concurrency: usize,
+    /// Capacity of the channel between thread pool #3 and the writer: number of compressed
+    /// blocks that can be waiting to be stored.
+    pub compression_concurrency: usize,
+    /// Threads of thread pool #3, compressing full blocks into `LazyBlock`s.
+    pub processing_threads: usize,
+    pub channel_bounds: FetchChannelBounds,
+    /// Download the blocks from the REST interface of bitcoind (`rest=1`) instead of
+    /// `getblock`, their prevouts being resolved from the outputs of the blocks fetched before.
+    pub rest_interface: bool,
+    /// Memory budget of the traversals cache, in bytes.
+    pub traversals_cache_size: usize,
+}
+
+/// Capacity of the channels feeding thread pools #2 and #3.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchChannelBounds {
+    /// Block hashes retrieved by thread pool #1, waiting to be downloaded.
+    pub block_hashes: usize,
+    /// Full blocks downloaded by thread pool #2, waiting to be compressed.
+    pub block_data: usize,
+}
+
+impl FetchConfig {
+    /// Preset used when blocks are processed for inscriptions, one at a time.
+    pub fn for_inscriptions_processing(network_threads: usize) -> FetchConfig {
+        FetchConfig {
+            block_hash_concurrency: network_threads,
+            block_data_concurrency: network_threads,
+            compression_concurrency: 8,
+            processing_threads: 4,
+            channel_bounds: FetchChannelBounds {
+                block_hashes: 8,
+                block_data: 8,
+            },
+            rest_interface: false,
+            traversals_cache_size: DEFAULT_TRAVERSALS_CACHE_SIZE,
+        }
+    }
+
+    /// Preset used when blocks are only stored.
+    pub fn for_blocks_storage(network_threads: usize) -> FetchConfig {
+        FetchConfig {
+            block_hash_concurrency: network_threads,
+            block_data_concurrency: network_threads,
+            compression_concurrency: 128,
+            processing_threads: 16,
+            channel_bounds: FetchChannelBounds {
+                block_hashes: 256,
+                block_data: 128,
+            },
+            rest_interface: false,
+            traversals_cache_size: DEFAULT_TRAVERSALS_CACHE_SIZE,
+        }
+    }
+
+    /// Preset matching the first block to fetch.
+    pub fn default_for(
+        start_block: u64,
+        ordinal_computing_height: u64,
+        network_threads: usize,
+    ) -> FetchConfig {
+        if start_block >= ordinal_computing_height {
+            FetchConfig::for_inscriptions_processing(network_threads)
+        } else {
+            FetchConfig::for_blocks_storage(network_threads)
+        }
+    }
+
+    /// Caps the threads of thread pools #1 and #2 to the requests per second allowed on the
+    /// bitcoind endpoints (see `BitcoinRpcRateLimits::total_rate`): the threads beyond it
+    /// would only be waiting on the rate limiters.
+    pub fn capped_to_rate(mut self, requests_per_second: Option<u32>) -> FetchConfig {
+        if let Some(rate) = requests_per_second {
+            let max_threads = (rate as usize).max(1);
+            self.block_hash_concurrency = self.block_hash_concurrency.min(max_threads);
+            self.block_data_concurrency = self.block_data_concurrency.min(max_threads);
+        }
+        self
+    }
+}
+
+/// Block downloaded by thread pool #2.
+enum FetchedBlock {
+    /// Retrieved with `getblock`, prevouts included.
+    Full(BitcoinBlockFullBreakdown),
+    /// Retrieved from the REST interface, along with its height.
+    Raw(u64, bitcoincore_rpc::bitcoin::Block),
+    /// Left in the inbox by an interrupted sync, prevouts included.
+    Replayed(BitcoinBlockFullBreakdown),
+}
+
+/// Resolves the prevouts of a block downloaded from the REST interface, falling back on
+/// `getblock` when some of them are not cached (spending outputs created before the sync).
+fn resolve_raw_block(
+    block: &bitcoincore_rpc::bitcoin::Block,
+    block_height: u64,
+    prevout_cache: &mut PrevoutCache,
+    bitcoin_config: &BitcoinConfig,
+    ctx: &Context,
+) -> Result<BitcoinBlockFullBreakdown, String> {
+    if let Some(block_data) = prevout_cache.resolve_block(block, block_height) {
+        return Ok(block_data);
+    }
+    ctx.try_log(|logger| {
+        slog::debug!(
+            logger,
+            "Prevouts of block #{block_height} not cached, retrieving block with getblock"
+        )
+    });
+    let block_hash = block.block_hash().to_string();
+    let future = download_block_with_retry(&block_hash, bitcoin_config, ctx);
+    let block_data = hiro_system_kit::nestable_block_on(future)
+        .map_err(|e| format!("unable to fetch block #{block_height}: {e}"))?;
+    prevout_cache.record_block(&block_data);
+    Ok(block_data)
+}
+
+/// Pre-ordinal blocks waiting to be written in a single `WriteBatch`.
+#[derive(Default)]
+pub struct PendingBlocks {
+    entries: Vec<(u32, LazyBlock)>,
+    #[cfg(test)]
+    writes: usize,
+}
+
+impl PendingBlocks {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of batches written so far.
+    #[cfg(test)]
+    pub fn writes(&self) -> usize {
+        self.writes
+    }
+
+    /// Queues a block, writing the batch once it holds `BLOCKS_BATCH_SIZE` blocks.
+    pub fn push(
+        &mut self,
+        block_height: u32,
+        block: LazyBlock,
+        blocks_db_rw: &DB,
+        ctx: &Context,
+    ) -> Result<(), HordDbError> {
+        self.entries.push((block_height, block));
+        if self.entries.len() >= BLOCKS_BATCH_SIZE {
+            self.write(blocks_db_rw, ctx)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending blocks, if any, and clears the batch: blocks are written once.
+    pub fn write(&mut self, blocks_db_rw: &DB, ctx: &Context) -> Result<(), HordDbError> {
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        insert_entries_in_blocks(&self.entries, blocks_db_rw, ctx)?;
+        self.entries.clear();
+        #[cfg(test)]
+        {
+            self.writes += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the pending blocks, and rewinds `last_insert` to `last_completed_block`.
+fn flush_completed_blocks(
+    blocks_batch: &mut PendingBlocks,
+    last_completed_block: Option<u64>,
+    blocks_db_rw: &DB,
+    ctx: &Context,
+) -> Result<(), HordDbError> {
+    blocks_batch.write(blocks_db_rw, ctx)?;
+    if let Some(last_completed_block) = last_completed_block {
+        blocks_db_rw
+            .put_cf(
+                metadata_cf(blocks_db_rw),
+                LAST_INSERT_KEY,
+                (last_completed_block as u32).to_be_bytes(),
+            )
+            .map_err(|e| HordDbError::RocksDb("unable to insert metadata".into(), e))?;
+        store_sync_cursor(last_completed_block, blocks_db_rw)?;
     }
+    flush_blocks_db(blocks_db_rw)
 }
 
-pub fn remove_entry_from_inscriptions(
-    inscription_id: &str,
-    inscriptions_db_rw_conn: &Connection,
-    ctx: &Context,
-) {
-    if let Err(e) = inscriptions_db_rw_conn.execute(
-        "DELETE FROM inscriptions WHERE inscription_id = ?1",
-        rusqlite::params![&inscription_id],
-    ) {
-        ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+/// Last block of the contiguous range completed by the latest sync, if any.
+pub fn find_sync_cursor(blocks_db: &DB) -> Option<u64> {
+    match blocks_db.get_cf(metadata_cf(blocks_db), SYNC_CURSOR_KEY) {
+        Ok(Some(bytes)) if bytes.len() == 8 => {
+            Some(u64::from_be_bytes(bytes[..].try_into().unwrap()))
+        }
+        _ => None,
     }
 }
 
-pub fn delete_data_in_hord_db(
+fn store_sync_cursor(last_completed_block: u64, blocks_db_rw: &DB) -> Result<(), HordDbError> {
+    blocks_db_rw
+        .put_cf(
+            metadata_cf(blocks_db_rw),
+            SYNC_CURSOR_KEY,
+            last_completed_block.to_be_bytes(),
+        )
+        .map_err(|e| HordDbError::RocksDb("unable to store sync cursor".into(), e))
+}
+
+fn store_inboxed_block(
+    block: &BitcoinBlockFullBreakdown,
+    blocks_db_rw: &DB,
+) -> Result<(), HordDbError> {
+    let bytes = serde_json::to_vec(block).map_err(|e| {
+        HordDbError::Other(format!("unable to serialize block #{}: {e}", block.height))
+    })?;
+    blocks_db_rw
+        .put_cf(
+            sync_inbox_cf(blocks_db_rw),
+            (block.height as u32).to_be_bytes(),
+            bytes,
+        )
+        .map_err(|e| HordDbError::RocksDb("unable to store inboxed block".into(), e))
+}
+
+fn remove_inboxed_block(block_height: u64, blocks_db_rw: &DB) -> Result<(), HordDbError> {
+    blocks_db_rw
+        .delete_cf(
+            sync_inbox_cf(blocks_db_rw),
+            (block_height as u32).to_be_bytes(),
+        )
+        .map_err(|e| HordDbError::RocksDb("unable to remove inboxed block".into(), e))
+}
+
+/// Blocks left in the inbox by an interrupted sync, within `start_block..=end_block`.
+/// Entries that can't be decoded are skipped, their blocks being downloaded again.
+pub fn find_inboxed_blocks(
     start_block: u64,
     end_block: u64,
-    blocks_db_rw: &DB,
-    inscriptions_db_conn_rw: &Connection,
+    blocks_db: &DB,
     ctx: &Context,
-) -> Result<(), String> {
-    delete_blocks_in_block_range(start_block as u32, end_block as u32, blocks_db_rw, &ctx);
-    delete_inscriptions_in_block_range(
-        start_block as u32,
-        end_block as u32,
-        inscriptions_db_conn_rw,
-        &ctx,
+) -> Result<Vec<BitcoinBlockFullBreakdown>, HordDbError> {
+    let mut blocks = vec![];
+    let iterator = blocks_db.iterator_cf(
+        sync_inbox_cf(blocks_db),
+        rocksdb::IteratorMode::From(
+            &(start_block as u32).to_be_bytes(),
+            rocksdb::Direction::Forward,
+        ),
     );
-    Ok(())
+    for entry in iterator {
+        let (key, value) =
+            entry.map_err(|e| HordDbError::RocksDb("unable to read inboxed block".into(), e))?;
+        let block_height = match key[..].try_into() {
+            Ok(bytes) => u32::from_be_bytes(bytes) as u64,
+            Err(_) => continue,
+        };
+        if block_height > end_block {
+            break;
+        }
+        match serde_json::from_slice::<BitcoinBlockFullBreakdown>(&value) {
+            Ok(block) => blocks.push(block),
+            Err(e) => ctx.try_log(|logger| {
+                slog::warn!(
+                    logger,
+                    "Unable to decode inboxed block #{block_height}: {e}"
+                )
+            }),
+        }
+    }
+    Ok(blocks)
 }
 
+/// Blocks below `ordinal_computing_height` are only stored, blocks above are also
+/// processed (sequentially) for inscriptions. See `FetchConfig::default_for` for
+/// the default sizing of the pipeline.
+///
+/// Setting `cancellation_flag` stops the sync once the block being processed is written:
+/// pending blocks are flushed, and `last_insert` is rewound to the last block
+/// of the contiguous range completed so far. That block is also checkpointed as the sync
+/// cursor, and the blocks waiting in the inbox are persisted: they are replayed instead of
+/// being downloaded again by the next sync. Blocks that bitcoind keeps failing to serve
+/// (once retries are exhausted) stop the sync the same way, with a
+/// `HordDbError::SyncAborted` error. `progress` is updated after each block stored, and
+/// logged periodically. Inscriptions indexed are also replicated to `inscriptions_replica`,
+/// when set.
+/// Returns the last block height of that contiguous range, if any.
 pub async fn fetch_and_cache_blocks_in_hord_db(
     bitcoin_config: &BitcoinConfig,
     blocks_db_rw: &DB,
     inscriptions_db_conn_rw: &Connection,
+    inscriptions_replica: Option<&dyn InscriptionsStore>,
     start_block: u64,
     end_block: u64,
-    network_thread: usize,
+    ordinal_computing_height: u64,
+    fetch_config: &FetchConfig,
     hord_db_path: &PathBuf,
+    cancellation_flag: &Arc<AtomicBool>,
+    progress: Option<&SyncProgressTracker>,
     ctx: &Context,
-) -> Result<(), String> {
-    let ordinal_computing_height: u64 = 765000;
+) -> Result<Option<u64>, HordDbError> {
+    let ctx = &ctx.with_component("hord_sync");
     let number_of_blocks_to_process = end_block - start_block + 1;
-    let (block_hash_req_lim, block_req_lim, block_process_lim, processing_thread) =
-        if start_block >= ordinal_computing_height {
-            (8, 8, 8, 4)
-        } else {
-            (256, 128, 128, 16)
-        };
-    let retrieve_block_hash_pool = ThreadPool::new(network_thread);
-    let (block_hash_tx, block_hash_rx) = crossbeam_channel::bounded(block_hash_req_lim);
-    let retrieve_block_data_pool = ThreadPool::new(network_thread);
-    let (block_data_tx, block_data_rx) = crossbeam_channel::bounded(block_req_lim);
-    let compress_block_data_pool = ThreadPool::new(processing_thread);
-    let (block_compressed_tx, block_compressed_rx) = crossbeam_channel::bounded(block_process_lim);
+    // Tracked locally when the caller doesn't, for the progress to be logged
+    let local_progress = SyncProgressTracker::default();
+    let progress = progress.unwrap_or(&local_progress);
+    progress.start(start_block, end_block);
+    let retrieve_block_hash_pool = ThreadPool::new(fetch_config.block_hash_concurrency);
+    let (block_hash_tx, block_hash_rx) =
+        crossbeam_channel::bounded(fetch_config.channel_bounds.block_hashes);
+    let retrieve_block_data_pool = ThreadPool::new(fetch_config.block_data_concurrency);
+    let (block_data_tx, block_data_rx) =
+        crossbeam_channel::bounded(fetch_config.channel_bounds.block_data);
+    let compress_block_data_pool = ThreadPool::new(fetch_config.processing_threads);
+    let (block_compressed_tx, block_compressed_rx) =
+        crossbeam_channel::bounded(fetch_config.compression_concurrency);
+
+    // Set when bitcoind errors are reported to the writer: the pending jobs are skipped
+    let abort_flag = Arc::new(AtomicBool::new(false));
+
+    let replayed_blocks = find_inboxed_blocks(start_block, end_block, blocks_db_rw, ctx)?;
+    let replayed_heights = replayed_blocks
+        .iter()
+        .map(|block| block.height as u64)
+        .collect::<HashSet<_>>();
+    if !replayed_blocks.is_empty() {
+        ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Replaying {} blocks inboxed by a previous sync",
+                replayed_blocks.len()
+            )
+        });
+    }
 
     // Thread pool #1: given a block height, retrieve the block hash
     for block_cursor in start_block..=end_block {
+        if replayed_heights.contains(&block_cursor) {
+            continue;
+        }
         let block_height = block_cursor.clone();
         let block_hash_tx = block_hash_tx.clone();
         let config = bitcoin_config.clone();
         let moved_ctx = ctx.clone();
+        let moved_cancellation_flag = cancellation_flag.clone();
+        let moved_abort_flag = abort_flag.clone();
         retrieve_block_hash_pool.execute(move || {
+            if moved_cancellation_flag.load(Ordering::SeqCst)
+                || moved_abort_flag.load(Ordering::SeqCst)
+            {
+                return;
+            }
             let future = retrieve_block_hash_with_retry(&block_height, &config, &moved_ctx);
-            let block_hash = hiro_system_kit::nestable_block_on(future).unwrap();
-            block_hash_tx
-                .send(Some((block_height, block_hash)))
-                .expect("unable to channel block_hash");
+            let res = hiro_system_kit::nestable_block_on(future)
+                .map(|block_hash| (block_height, block_hash))
+                .map_err(|e| format!("unable to retrieve hash of block #{block_height}: {e}"));
+            // The receiver is gone once the sync was aborted
+            let _ = block_hash_tx.send(Some(res));
         })
     }
 
-    // Thread pool #2: given a block hash, retrieve the full block (verbosity max, including prevout)
+    // Thread pool #2: given a block hash, retrieve the full block (verbosity max, including prevout),
+    // or the raw block from the REST interface, its prevouts being resolved by thread #3
     let bitcoin_network = bitcoin_config.network.clone();
-    let bitcoin_config = bitcoin_config.clone();
+    let rest_interface = fetch_config.rest_interface;
+    let resolving_bitcoin_config = bitcoin_config.clone();
+    let retrieving_bitcoin_config = bitcoin_config.clone();
     let moved_ctx = ctx.clone();
     let block_data_tx_moved = block_data_tx.clone();
     let _ = hiro_system_kit::thread_named("Block data retrieval")
         .spawn(move || {
-            while let Ok(Some((block_height, block_hash))) = block_hash_rx.recv() {
-                let moved_bitcoin_config = bitcoin_config.clone();
+            // Replayed blocks are sent first, for their outputs to be known by the raw blocks
+            for block_data in replayed_blocks {
+                let _ = block_data_tx_moved.send(Some(Ok(FetchedBlock::Replayed(block_data))));
+            }
+            while let Ok(Some(res)) = block_hash_rx.recv() {
+                let (block_height, block_hash) = match res {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        let _ = block_data_tx_moved.send(Some(Err(e)));
+                        continue;
+                    }
+                };
+                let moved_bitcoin_config = retrieving_bitcoin_config.clone();
                 let block_data_tx = block_data_tx_moved.clone();
                 let moved_ctx = moved_ctx.clone();
                 retrieve_block_data_pool.execute(move || {
                     moved_ctx
                         .try_log(|logger| slog::debug!(logger, "Fetching block #{block_height}"));
-                    let future =
-                        download_block_with_retry(&block_hash, &moved_bitcoin_config, &moved_ctx);
-                    let res = match hiro_system_kit::nestable_block_on(future) {
-                        Ok(block_data) => Some(block_data),
+                    let mut span = StageSpan::for_block(PipelineStage::BlockDownload, block_height);
+                    let res = if rest_interface {
+                        let future = download_rest_block_with_retry(
+                            &block_hash,
+                            &moved_bitcoin_config,
+                            &moved_ctx,
+                        );
+                        hiro_system_kit::nestable_block_on(future)
+                            .map(|block| FetchedBlock::Raw(block_height, block))
+                    } else {
+                        let future = download_block_with_retry(
+                            &block_hash,
+                            &moved_bitcoin_config,
+                            &moved_ctx,
+                        );
+                        hiro_system_kit::nestable_block_on(future).map(FetchedBlock::Full)
+                    };
+                    let res = match res {
+                        Ok(block_data) => Ok(block_data),
                         Err(e) => {
                             moved_ctx.try_log(|logger| {
                                 slog::error!(logger, "unable to fetch block #{block_height}: {e}")
                             });
-                            None
+                            span.record_error(&e);
+                            Err(format!("unable to fetch block #{block_height}: {e}"))
                         }
                     };
-                    let _ = block_data_tx.send(res);
+                    drop(span);
+                    let _ = block_data_tx.send(Some(res));
                 });
                 if block_height >= ordinal_computing_height {
                     let _ = retrieve_block_data_pool.join();
@@ -799,23 +5201,90 @@ pub async fn fetch_and_cache_blocks_in_hord_db(
         })
         .expect("unable to spawn thread");
 
+    // Thread pool #3: given a full block, compress it into a LazyBlock
+    let moved_ctx = ctx.clone();
+    let skipped_raw_blocks = replayed_heights.clone();
     let _ = hiro_system_kit::thread_named("Block data compression")
         .spawn(move || {
-            while let Ok(Some(block_data)) = block_data_rx.recv() {
-                let block_compressed_tx_moved = block_compressed_tx.clone();
-                let block_height = block_data.height as u64;
-                compress_block_data_pool.execute(move || {
-                    let compressed_block =
-                        LazyBlock::from_full_block(&block_data).expect("unable to serialize block");
-                    let block_index = block_data.height as u32;
-                    let _ = block_compressed_tx_moved.send(Some((
-                        block_index,
-                        compressed_block,
-                        block_data,
-                    )));
-                });
-                if block_height >= ordinal_computing_height {
-                    let _ = compress_block_data_pool.join();
+            // Blocks downloaded from the REST interface are resolved in order
+            let mut prevout_cache = PrevoutCache::new(DEFAULT_PREVOUT_CACHE_CAPACITY);
+            let mut raw_blocks = HashMap::new();
+            let mut next_raw_block = start_block;
+            while let Ok(Some(res)) = block_data_rx.recv() {
+                let fetched_blocks = match res {
+                    Ok(FetchedBlock::Full(block_data)) => vec![Ok(block_data)],
+                    Ok(FetchedBlock::Replayed(block_data)) => {
+                        if rest_interface {
+                            prevout_cache.record_block(&block_data);
+                        }
+                        vec![Ok(block_data)]
+                    }
+                    Ok(FetchedBlock::Raw(block_height, block)) => {
+                        raw_blocks.insert(block_height, block);
+                        let mut fetched_blocks = vec![];
+                        loop {
+                            if skipped_raw_blocks.contains(&next_raw_block) {
+                                next_raw_block += 1;
+                                continue;
+                            }
+                            let block = match raw_blocks.remove(&next_raw_block) {
+                                Some(block) => block,
+                                None => break,
+                            };
+                            fetched_blocks.push(resolve_raw_block(
+                                &block,
+                                next_raw_block,
+                                &mut prevout_cache,
+                                &resolving_bitcoin_config,
+                                &moved_ctx,
+                            ));
+                            next_raw_block += 1;
+                        }
+                        fetched_blocks
+                    }
+                    Err(e) => vec![Err(e)],
+                };
+                for res in fetched_blocks {
+                    let block_data = match res {
+                        Ok(block_data) => block_data,
+                        Err(e) => {
+                            let _ = block_compressed_tx.send(Some(Err(e)));
+                            continue;
+                        }
+                    };
+                    let block_compressed_tx_moved = block_compressed_tx.clone();
+                    let block_height = block_data.height as u64;
+                    let moved_ctx = moved_ctx.clone();
+                    compress_block_data_pool.execute(move || {
+                        let mut span =
+                            StageSpan::for_block(PipelineStage::BlockCompression, block_height);
+                        let compressed_block = match LazyBlock::from_full_block(&block_data) {
+                            Ok(block) => block,
+                            Err(e) => {
+                                span.record_error(&e.to_string());
+                                moved_ctx.try_log(|logger| {
+                                    slog::error!(
+                                        logger,
+                                        "Unable to compress block #{block_height}: {e}"
+                                    )
+                                });
+                                let _ = block_compressed_tx_moved.send(Some(Err(format!(
+                                    "unable to compress block #{block_height}: {e}"
+                                ))));
+                                return;
+                            }
+                        };
+                        drop(span);
+                        let block_index = block_data.height as u32;
+                        let _ = block_compressed_tx_moved.send(Some(Ok((
+                            block_index,
+                            compressed_block,
+                            block_data,
+                        ))));
+                    });
+                    if block_height >= ordinal_computing_height {
+                        let _ = compress_block_data_pool.join();
+                    }
                 }
             }
             let res = compress_block_data_pool.join();
@@ -824,13 +5293,52 @@ pub async fn fetch_and_cache_blocks_in_hord_db(
         .expect("unable to spawn thread");
 
     let mut blocks_stored = 0;
-    let mut cursor = start_block as usize;
+    let mut cursor = std::cmp::max(start_block, ordinal_computing_height) as usize;
     let mut inbox = HashMap::new();
     let mut num_writes = 0;
-    let traversals_cache = Arc::new(new_traversals_lazy_cache());
-
-    while let Ok(Some((block_height, compacted_block, raw_block))) = block_compressed_rx.recv() {
-        insert_entry_in_blocks(block_height, &compacted_block, &blocks_db_rw, &ctx);
+    let traversals_cache = Arc::new(TraversalsLruCache::new(fetch_config.traversals_cache_size));
+    warm_traversals_cache(
+        &traversals_cache,
+        blocks_db_rw,
+        TRAVERSALS_CACHE_WARMING_DEPTH,
+        ctx,
+    );
+    let mut blocks_batch = PendingBlocks::default();
+    // Blocks completed out of order, waiting for the contiguous range to catch up
+    let mut completed_blocks = HashSet::new();
+    let mut last_completed_block: Option<u64> = None;
+    // Inboxed blocks also written to the sync inbox, to be removed once processed
+    let mut persisted_inbox = replayed_heights;
+
+    while let Ok(Some(res)) = block_compressed_rx.recv() {
+        let (block_height, compacted_block, raw_block) = match res {
+            Ok(entry) => entry,
+            Err(error) => {
+                abort_flag.store(true, Ordering::SeqCst);
+                flush_completed_blocks(&mut blocks_batch, last_completed_block, blocks_db_rw, ctx)?;
+                ctx.try_log(|logger| {
+                    slog::error!(
+                        logger,
+                        "Hord db sync aborted after storing #{blocks_stored} blocks (last contiguous block: {:?}): {error}",
+                        last_completed_block
+                    )
+                });
+                // Dropping the channels ends the threads of the pipeline
+                return Err(HordDbError::SyncAborted {
+                    last_completed_block,
+                    error,
+                });
+            }
+        };
+        let span = StageSpan::for_block(PipelineStage::DbWrite, block_height as u64);
+        if raw_block.height as u64 >= ordinal_computing_height {
+            // Traversals are reading from the blocks store: pending blocks must be written first
+            blocks_batch.write(&blocks_db_rw, &ctx)?;
+            insert_entry_in_blocks(block_height, &compacted_block, &blocks_db_rw, &ctx);
+        } else {
+            blocks_batch.push(block_height, compacted_block, &blocks_db_rw, &ctx)?;
+        }
+        drop(span);
         blocks_stored += 1;
         num_writes += 1;
 
@@ -840,10 +5348,7 @@ pub async fn fetch_and_cache_blocks_in_hord_db(
         // processing.
 
         // Should we start look for inscriptions data in blocks?
-        if raw_block.height as u64 > ordinal_computing_height {
-            if cursor == 0 {
-                cursor = raw_block.height;
-            }
+        if raw_block.height as u64 >= ordinal_computing_height {
             ctx.try_log(|logger| slog::info!(logger, "Queueing compacted block #{block_height}",));
             // Is the action of processing a block allows us
             // to process more blocks present in the inbox?
@@ -853,9 +5358,11 @@ pub async fn fetch_and_cache_blocks_in_hord_db(
                     slog::info!(
                         logger,
                         "Dequeuing block #{cursor} for processing (# blocks inboxed: {})",
-                        inbox.len()
+                        inbox.len();
+                        "block_height" => cursor
                     )
                 });
+                let processing_started_at = std::time::Instant::now();
                 let mut new_block =
                     match standardize_bitcoin_block(next_block, &bitcoin_network, &ctx) {
                         Ok(block) => block,
@@ -863,17 +5370,20 @@ pub async fn fetch_and_cache_blocks_in_hord_db(
                             ctx.try_log(|logger| {
                                 slog::error!(logger, "Unable to standardize bitcoin block: {e}",)
                             });
-                            return Err(e);
+                            return Err(HordDbError::Other(e));
                         }
                     };
 
-                let _ = blocks_db_rw.flush();
+                let _ = flush_blocks_db(blocks_db_rw);
 
                 if let Err(e) = update_hord_db_and_augment_bitcoin_block(
                     &mut new_block,
                     blocks_db_rw,
                     &inscriptions_db_conn_rw,
+                    inscriptions_replica,
                     false,
+                    &BlockPruningPolicy::KeepAll,
+                    Some(bitcoin_config),
                     &hord_db_path,
                     &traversals_cache,
                     &ctx,
@@ -885,15 +5395,50 @@ pub async fn fetch_and_cache_blocks_in_hord_db(
                             new_block.block_identifier.index
                         )
                     });
-                    return Err(e);
+                    return Err(HordDbError::Other(e));
+                }
+                ctx.try_log(|logger| {
+                    slog::info!(
+                        logger,
+                        "Block #{cursor} processed";
+                        "block_height" => cursor,
+                        "duration_ms" => processing_started_at.elapsed().as_millis() as u64
+                    )
+                });
+                if persisted_inbox.remove(&(cursor as u64)) {
+                    remove_inboxed_block(cursor as u64, blocks_db_rw)?;
                 }
+                completed_blocks.insert(cursor as u64);
                 cursor += 1;
             }
+            // Still waiting for the blocks preceding it
+            if let Some(inboxed_block) = inbox.get(&(block_height as usize)) {
+                if persisted_inbox.insert(block_height as u64) {
+                    store_inboxed_block(inboxed_block, blocks_db_rw)?;
+                }
+            }
         } else {
-            ctx.try_log(|logger| slog::info!(logger, "Storing compacted block #{block_height}",));
+            ctx.try_log(|logger| slog::debug!(logger, "Storing compacted block #{block_height}",));
+            completed_blocks.insert(block_height as u64);
+        }
+        let mut next_completed_block = last_completed_block.map_or(start_block, |h| h + 1);
+        while completed_blocks.remove(&next_completed_block) {
+            last_completed_block = Some(next_completed_block);
+            next_completed_block += 1;
+        }
+        // Blocks still batched are not written yet
+        if let (Some(last_completed_block), true) = (last_completed_block, blocks_batch.is_empty())
+        {
+            store_sync_cursor(last_completed_block, blocks_db_rw)?;
         }
+        progress.update(blocks_stored, last_completed_block);
 
         if blocks_stored == number_of_blocks_to_process {
+            blocks_batch.write(&blocks_db_rw, &ctx)?;
+            if let Some(last_completed_block) = last_completed_block {
+                store_sync_cursor(last_completed_block, blocks_db_rw)?;
+            }
+            progress.complete();
             let _ = block_data_tx.send(None);
             let _ = block_hash_tx.send(None);
             ctx.try_log(|logger| {
@@ -902,25 +5447,37 @@ pub async fn fetch_and_cache_blocks_in_hord_db(
                     "Local ordinals storage successfully seeded with #{blocks_stored} blocks"
                 )
             });
-            return Ok(());
+            return Ok(last_completed_block);
         }
 
-        if num_writes % 24 == 0 {
+        if cancellation_flag.load(Ordering::SeqCst) {
+            flush_completed_blocks(&mut blocks_batch, last_completed_block, blocks_db_rw, ctx)?;
+            let _ = block_data_tx.send(None);
+            let _ = block_hash_tx.send(None);
             ctx.try_log(|logger| {
-                slog::info!(
+                slog::warn!(
                     logger,
-                    "Flushing traversals cache (#{} entries)",
-                    traversals_cache.len()
-                );
+                    "Hord db sync cancelled after storing #{blocks_stored} blocks (last contiguous block: {:?})",
+                    last_completed_block
+                )
+            });
+            return Ok(last_completed_block);
+        }
+
+        if num_writes % 24 == 0 {
+            ctx.try_log(|logger| {
+                if let Some(progress) = progress.progress() {
+                    slog::info!(logger, "Hord db sync progress: {progress}");
+                }
+                slog::info!(logger, "Traversals cache: {}", traversals_cache.stats());
             });
-            traversals_cache.clear();
         }
 
         if num_writes % 4096 == 0 {
             ctx.try_log(|logger| {
                 slog::info!(logger, "Flushing DB to disk ({num_writes} inserts)");
             });
-            if let Err(e) = blocks_db_rw.flush() {
+            if let Err(e) = flush_blocks_db(&blocks_db_rw) {
                 ctx.try_log(|logger| {
                     slog::error!(logger, "{}", e.to_string());
                 });
@@ -929,7 +5486,9 @@ pub async fn fetch_and_cache_blocks_in_hord_db(
         }
     }
 
-    if let Err(e) = blocks_db_rw.flush() {
+    blocks_batch.write(&blocks_db_rw, &ctx)?;
+
+    if let Err(e) = flush_blocks_db(&blocks_db_rw) {
         ctx.try_log(|logger| {
             slog::error!(logger, "{}", e.to_string());
         });
@@ -937,12 +5496,12 @@ pub async fn fetch_and_cache_blocks_in_hord_db(
 
     retrieve_block_hash_pool.join();
 
-    Ok(())
+    Ok(last_completed_block)
 }
 
 #[derive(Clone, Debug)]
 pub struct TraversalResult {
-    pub inscription_number: u64,
+    pub inscription_number: i64,
     pub ordinal_number: u64,
     pub transfers: u32,
 }
@@ -957,363 +5516,461 @@ impl TraversalResult {
         let sat = Sat(self.ordinal_number);
         self.ordinal_number - sat.height().starting_sat().n()
     }
+
+    pub fn rarity(&self) -> Rarity {
+        Sat(self.ordinal_number).rarity()
+    }
+
+    /// Name of the sat, in base 26 (`a` being the last sat ever mined).
+    pub fn sat_name(&self) -> String {
+        Sat(self.ordinal_number).name()
+    }
+
+    /// Position of the sat in the total supply, between 0 and 100.
+    pub fn percentile(&self) -> f64 {
+        (self.ordinal_number as f64 / Sat::LAST.n() as f64) * 100.0
+    }
 }
 
-pub fn retrieve_satoshi_point_using_local_storage(
-    blocks_db: &DB,
-    block_identifier: &BlockIdentifier,
+/// Persists the traversal of the inscription revealed by `transaction_identifier`.
+/// Traversals only depend on the chain, so the inscription number is not stored.
+pub fn store_traversal_in_cache(
     transaction_identifier: &TransactionIdentifier,
-    inscription_number: u64,
-    traversals_cache: Arc<
-        DashMap<
-            (u32, [u8; 8]),
-            (Vec<([u8; 8], u32, u16, u64)>, Vec<u64>),
-            BuildHasherDefault<FxHasher>,
-        >,
-    >,
+    traversal: &TraversalResult,
+    blocks_db_rw: &DB,
+) -> Result<(), HordDbError> {
+    let key = decode_transaction_identifier(transaction_identifier)?;
+    let mut value = Vec::with_capacity(12);
+    value.extend_from_slice(&traversal.ordinal_number.to_be_bytes());
+    value.extend_from_slice(&traversal.transfers.to_be_bytes());
+    blocks_db_rw
+        .put_cf(traversal_cache_cf(blocks_db_rw), key, value)
+        .map_err(|e| HordDbError::RocksDb("unable to cache traversal".into(), e))
+}
+
+pub fn find_traversal_in_cache(
+    transaction_identifier: &TransactionIdentifier,
+    inscription_number: i64,
+    blocks_db: &DB,
+) -> Option<TraversalResult> {
+    let key = decode_transaction_identifier(transaction_identifier).ok()?;
+    match blocks_db.get_cf(traversal_cache_cf(blocks_db), key) {
+        Ok(Some(bytes)) if bytes.len() == 12 => Some(TraversalResult {
+            inscription_number,
+            ordinal_number: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            transfers: u32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+        }),
+        _ => None,
+    }
+}
+
+fn traversal_transaction_key(block_height: u32, txid: &[u8]) -> [u8; 12] {
+    let mut key = [0u8; 12];
+    key[..4].copy_from_slice(&block_height.to_be_bytes());
+    key[4..].copy_from_slice(&txid_cache_key(txid));
+    key
+}
+
+/// Layout: txid length (u8) and txid, number of inputs (u32) followed by, for each input,
+/// the txin length (u8) and txin, block height (u32), vout (u16) and value (u64), then
+/// the number of outputs (u32) followed by their values (u64). Integers are big-endian.
+fn serialize_traversal_transaction(transaction: &LazyBlockTransaction) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(
+        9 + transaction.txid.len() + transaction.inputs.len() * 24 + transaction.outputs.len() * 8,
+    );
+    bytes.push(transaction.txid.len() as u8);
+    bytes.extend_from_slice(&transaction.txid);
+    bytes.extend_from_slice(&(transaction.inputs.len() as u32).to_be_bytes());
+    for input in transaction.inputs.iter() {
+        bytes.push(input.txin.len() as u8);
+        bytes.extend_from_slice(&input.txin);
+        bytes.extend_from_slice(&input.block_height.to_be_bytes());
+        bytes.extend_from_slice(&input.vout.to_be_bytes());
+        bytes.extend_from_slice(&input.txin_value.to_be_bytes());
+    }
+    bytes.extend_from_slice(&(transaction.outputs.len() as u32).to_be_bytes());
+    for output in transaction.outputs.iter() {
+        bytes.extend_from_slice(&output.to_be_bytes());
+    }
+    bytes
+}
+
+/// `None` when `bytes` were not produced by `serialize_traversal_transaction`.
+fn deserialize_traversal_transaction(bytes: &[u8]) -> Option<LazyBlockTransaction> {
+    let mut cursor = 0;
+    let mut take = move |len: usize| {
+        let slice = bytes.get(cursor..cursor + len)?;
+        cursor += len;
+        Some(slice)
+    };
+    let txid_len = take(1)?[0] as usize;
+    let txid = take(txid_len)?.to_vec();
+    let inputs_len = u32::from_be_bytes(take(4)?.try_into().ok()?);
+    let mut inputs = Vec::with_capacity(inputs_len.min(4096) as usize);
+    for _ in 0..inputs_len {
+        let txin_len = take(1)?[0] as usize;
+        inputs.push(LazyBlockTransactionInput {
+            txin: take(txin_len)?.to_vec(),
+            block_height: u32::from_be_bytes(take(4)?.try_into().ok()?),
+            vout: u16::from_be_bytes(take(2)?.try_into().ok()?),
+            txin_value: u64::from_be_bytes(take(8)?.try_into().ok()?),
+        });
+    }
+    let outputs_len = u32::from_be_bytes(take(4)?.try_into().ok()?);
+    let mut outputs = Vec::with_capacity(outputs_len.min(4096) as usize);
+    for _ in 0..outputs_len {
+        outputs.push(u64::from_be_bytes(take(8)?.try_into().ok()?));
+    }
+    if take(1).is_some() {
+        return None;
+    }
+    Some(LazyBlockTransaction {
+        txid,
+        inputs,
+        outputs,
+    })
+}
+
+/// Loads the transactions persisted by the traversals of the last `depth` blocks stored into
+/// `traversals_cache`, most recent blocks last so that they are the last ones evicted.
+/// Returns the number of transactions loaded.
+pub fn warm_traversals_cache(
+    traversals_cache: &TraversalsLruCache,
+    blocks_db: &DB,
+    depth: u32,
     ctx: &Context,
-) -> Result<TraversalResult, String> {
+) -> usize {
+    let traversal_transactions_cf = match traversal_transactions_cf(blocks_db) {
+        Some(cf) => cf,
+        None => return 0,
+    };
+    let start_block = find_last_block_inserted(blocks_db).saturating_sub(depth);
+    let mut loaded = 0;
+    let iterator = blocks_db.iterator_cf(
+        traversal_transactions_cf,
+        rocksdb::IteratorMode::From(&start_block.to_be_bytes(), rocksdb::Direction::Forward),
+    );
+    for entry in iterator {
+        let (key, value) = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(logger, "Unable to warm up traversals cache: {e}")
+                });
+                break;
+            }
+        };
+        let (block_height, transaction) =
+            match (key.get(..4), deserialize_traversal_transaction(&value)) {
+                (Some(block_height), Some(transaction)) => (
+                    u32::from_be_bytes(block_height.try_into().unwrap()),
+                    transaction,
+                ),
+                _ => continue,
+            };
+        traversals_cache.insert_transaction(block_height, &transaction);
+        loaded += 1;
+    }
     ctx.try_log(|logger| {
         slog::info!(
             logger,
-            "Computing ordinal number for Satoshi point {}:0:0 (block #{})",
-            transaction_identifier.hash,
-            block_identifier.index
+            "Traversals cache warmed up with {loaded} transactions from block #{start_block}"
         )
     });
+    loaded
+}
 
-    let mut ordinal_offset = 0;
-    let mut ordinal_block_number = block_identifier.index as u32;
-    let txid = {
-        let bytes = hex::decode(&transaction_identifier.hash[2..]).unwrap();
-        [
-            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-        ]
-    };
-    let mut tx_cursor = (txid, 0);
-    let mut hops: u32 = 0;
-    loop {
-        hops += 1;
-        if hops as u64 > block_identifier.index {
-            return Err(format!(
-                "Unable to process transaction {}, manual investigation required",
-                transaction_identifier.hash
-            ));
+/// Decodes a `0x` prefixed, 32 bytes long, transaction hash.
+fn decode_transaction_identifier(
+    transaction_identifier: &TransactionIdentifier,
+) -> Result<Vec<u8>, HordDbError> {
+    let hash = &transaction_identifier.hash;
+    let bytes = match hash.strip_prefix("0x") {
+        Some(hex_hash) => hex::decode(hex_hash).map_err(|e| {
+            HordDbError::InvalidInput(format!(
+                "unable to decode transaction {hash}: {}",
+                e.to_string()
+            ))
+        })?,
+        None => {
+            return Err(HordDbError::InvalidInput(format!(
+                "transaction {hash} is missing 0x prefix"
+            )))
         }
+    };
+    if bytes.len() != 32 {
+        return Err(HordDbError::InvalidInput(format!(
+            "transaction {hash} is {} bytes long, expected 32",
+            bytes.len()
+        )));
+    }
+    Ok(bytes)
+}
 
-        if let Some(cached_tx) = traversals_cache.get(&(ordinal_block_number, tx_cursor.0)) {
-            let (inputs, outputs) = cached_tx.value();
-            let mut next_found_in_cache = false;
-
-            let mut sats_out = 0;
-            for (index, output_value) in outputs.iter().enumerate() {
-                if index == tx_cursor.1 {
-                    break;
-                }
-                // ctx.try_log(|logger| {
-                //     slog::info!(logger, "Adding {} from output #{}", output_value, index)
-                // });
-                sats_out += output_value;
-            }
-            sats_out += ordinal_offset;
-            // ctx.try_log(|logger| {
-            //     slog::info!(
-            //         logger,
-            //         "Adding offset {ordinal_offset} to sats_out {sats_out}"
-            //     )
-            // });
-
-            let mut sats_in = 0;
-            for (txin, block_height, vout, txin_value) in inputs.into_iter() {
-                sats_in += txin_value;
-                // ctx.try_log(|logger| {
-                //     slog::info!(
-                //         logger,
-                //         "Adding txin_value {txin_value} to sats_in {sats_in} (txin: {})",
-                //         hex::encode(&txin)
-                //     )
-                // });
-
-                if sats_out < sats_in {
-                    ordinal_offset = sats_out - (sats_in - txin_value);
-                    ordinal_block_number = *block_height;
-
-                    // ctx.try_log(|logger| slog::info!(logger, "Block {ordinal_block_number} / Tx {} / [in:{sats_in}, out:{sats_out}]: {block_height} -> {ordinal_block_number}:{ordinal_offset} -> {}:{vout}",
-                    // hex::encode(&txid_n),
-                    // hex::encode(&txin)));
-                    tx_cursor = (txin.clone(), *vout as usize);
-                    next_found_in_cache = true;
-                    break;
-                }
-            }
+/// Read access to the transactions of a stored block, as needed by the traversals.
+pub trait BlockView {
+    fn coinbase_txid(&self) -> &[u8];
+    /// Total value of the coinbase outputs (subsidy and fees).
+    fn coinbase_value(&self) -> u64;
+    /// Non-coinbase transactions, in block order.
+    fn iter_transactions(&self) -> Box<dyn Iterator<Item = LazyBlockTransaction> + '_>;
+    /// Transactions matching the txid prefix. Several transactions can only match a prefix
+    /// shorter than the ones of the block, when their longer prefixes collide on it.
+    fn find_transactions(&self, txid: &[u8]) -> Vec<LazyBlockTransaction>;
+}
 
-            if next_found_in_cache {
-                continue;
-            }
+fn compacted_transaction_to_lazy(
+    txid: &[u8],
+    inputs: &[([u8; 8], u32, u16, u64)],
+    outputs: &[u64],
+) -> LazyBlockTransaction {
+    LazyBlockTransaction {
+        txid: txid.to_vec(),
+        inputs: inputs
+            .iter()
+            .map(
+                |(txin, block_height, vout, txin_value)| LazyBlockTransactionInput {
+                    txin: txin.to_vec(),
+                    block_height: *block_height,
+                    vout: *vout,
+                    txin_value: *txin_value,
+                },
+            )
+            .collect(),
+        outputs: outputs.to_vec(),
+    }
+}
 
-            if sats_in == 0 {
-                ctx.try_log(|logger| {
-                    slog::error!(
-                        logger,
-                        "Transaction {} is originating from a non spending transaction",
-                        transaction_identifier.hash
-                    )
-                });
-                return Ok(TraversalResult {
-                    inscription_number: 0,
-                    ordinal_number: 0,
-                    transfers: 0,
-                });
-            }
-        }
+impl BlockView for CompactedBlock {
+    fn coinbase_txid(&self) -> &[u8] {
+        &self.0 .0 .0
+    }
 
-        let block = match find_block_at_block_height(ordinal_block_number, 3, &blocks_db) {
-            Some(block) => block,
-            None => {
-                return Err(format!("block #{ordinal_block_number} not in database"));
-            }
-        };
+    fn coinbase_value(&self) -> u64 {
+        self.0 .0 .1
+    }
 
-        let coinbase_txid = &block.0 .0 .0;
-        let txid = tx_cursor.0;
+    fn iter_transactions(&self) -> Box<dyn Iterator<Item = LazyBlockTransaction> + '_> {
+        Box::new(
+            self.0 .1.iter().map(|(txid, inputs, outputs)| {
+                compacted_transaction_to_lazy(txid, inputs, outputs)
+            }),
+        )
+    }
 
-        // ctx.try_log(|logger| {
-        //     slog::info!(
-        //         logger,
-        //         "{ordinal_block_number}:{:?}:{:?}",
-        //         hex::encode(&coinbase_txid),
-        //         hex::encode(&txid)
-        //     )
-        // });
+    fn find_transactions(&self, txid: &[u8]) -> Vec<LazyBlockTransaction> {
+        self.0
+             .1
+            .iter()
+            .filter(|(txid_n, _, _)| txids_match(txid_n, txid))
+            .map(|(txid_n, inputs, outputs)| compacted_transaction_to_lazy(txid_n, inputs, outputs))
+            .collect()
+    }
+}
 
-        // evaluate exit condition: did we reach the **final** coinbase transaction
-        if coinbase_txid.eq(&txid) {
-            let coinbase_value = &block.0 .0 .1;
-            if ordinal_offset.lt(coinbase_value) {
-                break;
-            }
+impl BlockView for LazyBlock {
+    fn coinbase_txid(&self) -> &[u8] {
+        self.get_coinbase_txid()
+    }
 
-            // loop over the transaction fees to detect the right range
-            let cut_off = ordinal_offset - coinbase_value;
-            let mut accumulated_fees = 0;
-            for (_, inputs, outputs) in block.0 .1.iter() {
-                let mut total_in = 0;
-                for (_, _, _, input_value) in inputs.iter() {
-                    total_in += input_value;
-                }
+    fn coinbase_value(&self) -> u64 {
+        self.get_coinbase_sats()
+    }
 
-                let mut total_out = 0;
-                for output_value in outputs.iter() {
-                    total_out += output_value;
-                }
+    fn iter_transactions(&self) -> Box<dyn Iterator<Item = LazyBlockTransaction> + '_> {
+        Box::new(self.iter_tx())
+    }
 
-                let fee = total_in - total_out;
-                accumulated_fees += fee;
-                if accumulated_fees > cut_off {
-                    // We are looking at the right transaction
-                    // Retraverse the inputs to select the index to be picked
-                    let mut sats_in = 0;
-                    for (txin, block_height, vout, txin_value) in inputs.into_iter() {
-                        sats_in += txin_value;
-                        if sats_in >= total_out {
-                            ordinal_offset = total_out - (sats_in - txin_value);
-                            ordinal_block_number = *block_height;
-                            tx_cursor = (txin.clone(), *vout as usize);
-                            break;
-                        }
-                    }
-                    break;
-                }
-            }
-        } else {
-            // isolate the target transaction
-            for (txid_n, inputs, outputs) in block.0 .1.into_iter() {
-                // we iterate over the transactions, looking for the transaction target
-                if !txid_n.eq(&txid) {
-                    continue;
-                }
+    fn find_transactions(&self, txid: &[u8]) -> Vec<LazyBlockTransaction> {
+        if txid.len() >= self.txid_len {
+            return self
+                .find_and_serialize_transaction_with_txid(txid)
+                .into_iter()
+                .collect();
+        }
+        self.iter_tx()
+            .filter(|tx| txids_match(&tx.txid, txid))
+            .collect()
+    }
+}
 
-                // ctx.try_log(|logger| {
-                //     slog::info!(logger, "Evaluating {}: {:?}", hex::encode(&txid_n), outputs)
-                // });
+/// Transactions decoded by previous traversals, indexed by block height and txid prefix.
+pub trait TraversalsCache {
+    fn get_transaction(&self, block_height: u32, txid: &[u8]) -> Option<LazyBlockTransaction>;
+    fn insert_transaction(&self, block_height: u32, transaction: &LazyBlockTransaction);
+}
 
-                let mut sats_out = 0;
-                for (index, output_value) in outputs.iter().enumerate() {
-                    if index == tx_cursor.1 {
-                        break;
-                    }
-                    // ctx.try_log(|logger| {
-                    //     slog::info!(logger, "Adding {} from output #{}", output_value, index)
-                    // });
-                    sats_out += output_value;
-                }
-                sats_out += ordinal_offset;
-                // ctx.try_log(|logger| {
-                //     slog::info!(
-                //         logger,
-                //         "Adding offset {ordinal_offset} to sats_out {sats_out}"
-                //     )
-                // });
-
-                let mut sats_in = 0;
-                for (txin, block_height, vout, txin_value) in inputs.iter() {
-                    sats_in += txin_value;
-                    // ctx.try_log(|logger| {
-                    //     slog::info!(
-                    //         logger,
-                    //         "Adding txin_value {txin_value} to sats_in {sats_in} (txin: {})",
-                    //         hex::encode(&txin)
-                    //     )
-                    // });
-
-                    if sats_out < sats_in {
-                        traversals_cache
-                            .insert((ordinal_block_number, txid_n), (inputs.clone(), outputs));
-                        ordinal_offset = sats_out - (sats_in - txin_value);
-                        ordinal_block_number = *block_height;
-
-                        // ctx.try_log(|logger| slog::info!(logger, "Block {ordinal_block_number} / Tx {} / [in:{sats_in}, out:{sats_out}]: {block_height} -> {ordinal_block_number}:{ordinal_offset} -> {}:{vout}",
-                        // hex::encode(&txid_n),
-                        // hex::encode(&txin)));
-                        tx_cursor = (txin.clone(), *vout as usize);
-                        break;
-                    }
-                }
+impl TraversalsCache
+    for DashMap<
+        (u32, [u8; 8]),
+        (Vec<([u8; 8], u32, u16, u64)>, Vec<u64>),
+        BuildHasherDefault<FxHasher>,
+    >
+{
+    fn get_transaction(&self, block_height: u32, txid: &[u8]) -> Option<LazyBlockTransaction> {
+        let key = txid_cache_key(txid);
+        self.get(&(block_height, key)).map(|entry| {
+            let (inputs, outputs) = entry.value();
+            compacted_transaction_to_lazy(&key, inputs, outputs)
+        })
+    }
 
-                if sats_in == 0 {
-                    ctx.try_log(|logger| {
-                        slog::error!(
-                            logger,
-                            "Transaction {} is originating from a non spending transaction",
-                            transaction_identifier.hash
-                        )
-                    });
-                    return Ok(TraversalResult {
-                        inscription_number: 0,
-                        ordinal_number: 0,
-                        transfers: 0,
-                    });
-                }
-            }
+    fn insert_transaction(&self, block_height: u32, transaction: &LazyBlockTransaction) {
+        // Transactions with longer txids can not be stored without truncating them, which
+        // also keeps the transactions of the blocks with colliding prefixes out of the cache.
+        if transaction.txid.len() > TXID_LEN
+            || transaction
+                .inputs
+                .iter()
+                .any(|input| input.txin.len() > TXID_LEN)
+        {
+            return;
         }
+        let inputs = transaction
+            .inputs
+            .iter()
+            .map(|input| {
+                (
+                    txid_cache_key(&input.txin),
+                    input.block_height,
+                    input.vout,
+                    input.txin_value,
+                )
+            })
+            .collect();
+        self.insert(
+            (block_height, txid_cache_key(&transaction.txid)),
+            (inputs, transaction.outputs.clone()),
+        );
     }
-
-    let height = Height(ordinal_block_number.into());
-    let ordinal_number = height.starting_sat().0 + ordinal_offset;
-
-    Ok(TraversalResult {
-        inscription_number,
-        ordinal_number,
-        transfers: hops,
-    })
 }
 
-pub fn retrieve_satoshi_point_using_lazy_storage(
+pub fn retrieve_satoshi_point_using_local_storage(
     blocks_db: &DB,
     block_identifier: &BlockIdentifier,
     transaction_identifier: &TransactionIdentifier,
-    inscription_number: u64,
+    inscription_pointer: u64,
+    inscription_number: i64,
     traversals_cache: Arc<
-        DashMap<(u32, [u8; 8]), LazyBlockTransaction, BuildHasherDefault<FxHasher>>,
+        DashMap<
+            (u32, [u8; 8]),
+            (Vec<([u8; 8], u32, u16, u64)>, Vec<u64>),
+            BuildHasherDefault<FxHasher>,
+        >,
     >,
     ctx: &Context,
-) -> Result<TraversalResult, String> {
+) -> Result<TraversalResult, HordDbError> {
+    retrieve_satoshi_point(
+        |block_height| try_find_block_at_block_height(block_height, blocks_db),
+        block_identifier,
+        transaction_identifier,
+        inscription_pointer,
+        inscription_number,
+        traversals_cache.as_ref(),
+        None,
+        ctx,
+    )
+}
+
+/// Walks back the ancestry of the sat located `inscription_pointer` sats into the outputs of
+/// `transaction_identifier` (its first sat by default), until reaching the coinbase
+/// transaction that minted it. Blocks are only accessed through `find_block`
+/// and the `BlockView` trait, so that the same logic is shared by every storage format.
+fn retrieve_satoshi_point<B: BlockView>(
+    find_block: impl Fn(u32) -> Option<B>,
+    block_identifier: &BlockIdentifier,
+    transaction_identifier: &TransactionIdentifier,
+    inscription_pointer: u64,
+    inscription_number: i64,
+    traversals_cache: &impl TraversalsCache,
+    metrics: Option<&TraversalMetrics>,
+    ctx: &Context,
+) -> Result<TraversalResult, HordDbError> {
     ctx.try_log(|logger| {
         slog::info!(
             logger,
-            "Computing ordinal number for Satoshi point {}:0:0 (block #{})",
+            "Computing ordinal number for Satoshi point {}:0:{} (block #{})",
             transaction_identifier.hash,
+            inscription_pointer,
             block_identifier.index
         )
     });
 
-    let mut ordinal_offset = 0;
+    // Offsets within an output can exceed its value, the sat is then looked up in the
+    // following outputs.
+    let mut ordinal_offset = inscription_pointer;
     let mut ordinal_block_number = block_identifier.index as u32;
-    let txid = {
-        let bytes = hex::decode(&transaction_identifier.hash[2..]).unwrap();
-        [
-            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-        ]
-    };
+    let txid = decode_transaction_identifier(transaction_identifier)?;
     let mut tx_cursor = (txid, 0);
     let mut hops: u32 = 0;
     loop {
         hops += 1;
+        if let Some(metrics) = metrics {
+            metrics.record_hop();
+        }
         if hops as u64 > block_identifier.index {
-            return Err(format!(
+            return Err(HordDbError::Other(format!(
                 "Unable to process transaction {} detected after {hops} iterations. Manual investigation required",
                 transaction_identifier.hash
-            ));
+            )));
         }
 
-        if let Some(cached_tx) = traversals_cache.get(&(ordinal_block_number, tx_cursor.0)) {
-            let tx = cached_tx.value();
-            let mut next_found_in_cache = false;
-            let mut sats_out = 0;
-            for (index, output_value) in tx.outputs.iter().enumerate() {
-                if index == tx_cursor.1 {
-                    break;
-                }
-                sats_out += output_value;
-            }
-            sats_out += ordinal_offset;
-
-            let mut sats_in = 0;
-            for input in tx.inputs.iter() {
-                sats_in += input.txin_value;
-
-                if sats_out < sats_in {
-                    ordinal_offset = sats_out - (sats_in - input.txin_value);
+        let cached_tx = traversals_cache.get_transaction(ordinal_block_number, &tx_cursor.0);
+        if let Some(metrics) = metrics {
+            metrics.record_cache_lookup(cached_tx.is_some());
+        }
+        if let Some(tx) = cached_tx {
+            match trace_sat_to_input(&tx, tx_cursor.1, ordinal_offset) {
+                Some((input, offset)) => {
+                    ordinal_offset = offset;
                     ordinal_block_number = input.block_height;
                     tx_cursor = (input.txin.clone(), input.vout as usize);
-                    next_found_in_cache = true;
-                    break;
+                    continue;
                 }
-            }
-
-            if next_found_in_cache {
-                continue;
-            }
-
-            if sats_in == 0 {
-                ctx.try_log(|logger| {
-                    slog::error!(
-                        logger,
-                        "Transaction {} is originating from a non spending transaction",
-                        transaction_identifier.hash
-                    )
-                });
-                return Ok(TraversalResult {
-                    inscription_number: 0,
-                    ordinal_number: 0,
-                    transfers: 0,
-                });
+                None if is_non_spending_transaction(&tx) => {
+                    ctx.try_log(|logger| {
+                        slog::error!(
+                            logger,
+                            "Transaction {} is originating from a non spending transaction",
+                            transaction_identifier.hash
+                        )
+                    });
+                    if let Some(metrics) = metrics {
+                        metrics.record_traversal(hops);
+                    }
+                    return Ok(TraversalResult {
+                        inscription_number: 0,
+                        ordinal_number: 0,
+                        transfers: 0,
+                    });
+                }
+                None => {}
             }
         }
 
-        let lazy_block = match find_lazy_block_at_block_height(ordinal_block_number, 3, &blocks_db)
-        {
+        if let Some(metrics) = metrics {
+            metrics.record_block_fetched();
+        }
+        let block = match find_block(ordinal_block_number) {
             Some(block) => block,
             None => {
-                return Err(format!("block #{ordinal_block_number} not in database"));
+                return Err(HordDbError::BlockNotFound(ordinal_block_number));
             }
         };
 
-        let coinbase_txid = lazy_block.get_coinbase_txid();
-        let txid = tx_cursor.0;
-
         // evaluate exit condition: did we reach the **final** coinbase transaction
-        if coinbase_txid.eq(&txid) {
+        if txids_match(block.coinbase_txid(), &tx_cursor.0) {
             let subsidy = Height(ordinal_block_number.into()).subsidy();
             if ordinal_offset.lt(&subsidy) {
                 // Great!
                 break;
             }
 
-            // loop over the transaction fees to detect the right range
+            // The coinbase outputs are made of the newly minted sats (subsidy),
+            // followed by the fees of each transaction, in order.
             let mut accumulated_fees = subsidy;
 
-            for tx in lazy_block.iter_tx() {
+            for tx in block.iter_transactions() {
                 let mut total_in = 0;
                 for input in tx.inputs.iter() {
                     total_in += input.txin_value;
@@ -1335,7 +5992,7 @@ pub fn retrieve_satoshi_point_using_lazy_storage(
                     for input in tx.inputs.into_iter() {
                         sats_in += input.txin_value;
 
-                        if sats_in >= total_out {
+                        if sats_in > total_out {
                             ordinal_offset = total_out - (sats_in - input.txin_value);
                             ordinal_block_number = input.block_height;
                             tx_cursor = (input.txin.clone(), input.vout as usize);
@@ -1349,34 +6006,30 @@ pub fn retrieve_satoshi_point_using_lazy_storage(
             }
         } else {
             // isolate the target transaction
-            let lazy_tx = match lazy_block.find_and_serialize_transaction_with_txid(&txid) {
-                Some(entry) => entry,
-                None => unreachable!(),
-            };
-
-            let mut sats_out = 0;
-            for (index, output_value) in lazy_tx.outputs.iter().enumerate() {
-                if index == tx_cursor.1 {
-                    break;
-                }
-                sats_out += output_value;
+            let mut matches = block.find_transactions(&tx_cursor.0);
+            if matches.len() > 1 {
+                return Err(HordDbError::AmbiguousTxid {
+                    block_height: ordinal_block_number,
+                    txid: hex::encode(&tx_cursor.0),
+                    candidates: matches.iter().map(|tx| hex::encode(&tx.txid)).collect(),
+                });
             }
-            sats_out += ordinal_offset;
-
-            let mut sats_in = 0;
-            for input in lazy_tx.inputs.iter() {
-                sats_in += input.txin_value;
-
-                if sats_out < sats_in {
-                    traversals_cache.insert((ordinal_block_number, tx_cursor.0), lazy_tx.clone());
-                    ordinal_offset = sats_out - (sats_in - input.txin_value);
-                    ordinal_block_number = input.block_height;
-                    tx_cursor = (input.txin.clone(), input.vout as usize);
-                    break;
+            let tx = match matches.pop() {
+                Some(entry) => entry,
+                None => {
+                    return Err(HordDbError::Corrupted(format!(
+                        "transaction {} not found in block #{ordinal_block_number}",
+                        hex::encode(&tx_cursor.0)
+                    )));
                 }
-            }
+            };
 
-            if sats_in == 0 {
+            if let Some((input, offset)) = trace_sat_to_input(&tx, tx_cursor.1, ordinal_offset) {
+                traversals_cache.insert_transaction(ordinal_block_number, &tx);
+                ordinal_offset = offset;
+                ordinal_block_number = input.block_height;
+                tx_cursor = (input.txin.clone(), input.vout as usize);
+            } else if is_non_spending_transaction(&tx) {
                 ctx.try_log(|logger| {
                     slog::error!(
                         logger,
@@ -1384,6 +6037,9 @@ pub fn retrieve_satoshi_point_using_lazy_storage(
                         transaction_identifier.hash
                     )
                 });
+                if let Some(metrics) = metrics {
+                    metrics.record_traversal(hops);
+                }
                 return Ok(TraversalResult {
                     inscription_number: 0,
                     ordinal_number: 0,
@@ -1396,6 +6052,9 @@ pub fn retrieve_satoshi_point_using_lazy_storage(
     let height = Height(ordinal_block_number.into());
     let ordinal_number = height.starting_sat().0 + ordinal_offset;
 
+    if let Some(metrics) = metrics {
+        metrics.record_traversal(hops);
+    }
     Ok(TraversalResult {
         inscription_number,
         ordinal_number,
@@ -1403,40 +6062,490 @@ pub fn retrieve_satoshi_point_using_lazy_storage(
     })
 }
 
+/// Input of `tx` spending the sat located at `ordinal_offset` within output `vout`,
+/// along with the offset of that sat within the input.
+fn trace_sat_to_input(
+    tx: &LazyBlockTransaction,
+    vout: usize,
+    ordinal_offset: u64,
+) -> Option<(&LazyBlockTransactionInput, u64)> {
+    let mut sats_out = 0;
+    for (index, output_value) in tx.outputs.iter().enumerate() {
+        if index == vout {
+            break;
+        }
+        sats_out += output_value;
+    }
+    sats_out += ordinal_offset;
+
+    let mut sats_in = 0;
+    for input in tx.inputs.iter() {
+        sats_in += input.txin_value;
+        if sats_out < sats_in {
+            return Some((input, sats_out - (sats_in - input.txin_value)));
+        }
+    }
+    None
+}
+
+fn is_non_spending_transaction(tx: &LazyBlockTransaction) -> bool {
+    tx.inputs.iter().all(|input| input.txin_value == 0)
+}
+
+/// Number of buckets of the hops histogram: bucket `i` counts the traversals that
+/// took `2^i..2^(i+1)` hops, the last bucket gathering the longest traversals.
+pub const TRAVERSAL_HOPS_HISTOGRAM_BUCKETS: usize = 16;
+
+/// Aggregated statistics on lazy traversals, shareable across threads.
+#[derive(Debug, Default)]
+pub struct TraversalMetrics {
+    traversals: AtomicU64,
+    total_hops: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    blocks_fetched: AtomicU64,
+    hops_histogram: [AtomicU64; TRAVERSAL_HOPS_HISTOGRAM_BUCKETS],
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraversalMetricsSnapshot {
+    pub traversals: u64,
+    pub total_hops: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub blocks_fetched: u64,
+    pub hops_histogram: [u64; TRAVERSAL_HOPS_HISTOGRAM_BUCKETS],
+}
+
+impl TraversalMetricsSnapshot {
+    pub fn cache_hit_rate(&self) -> f64 {
+        let lookups = self.cache_hits + self.cache_misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.cache_hits as f64 / lookups as f64
+    }
+}
+
+impl TraversalMetrics {
+    pub fn new() -> TraversalMetrics {
+        TraversalMetrics::default()
+    }
+
+    fn record_hop(&self) {
+        self.total_hops.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_cache_lookup(&self, hit: bool) {
+        if hit {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn record_block_fetched(&self) {
+        self.blocks_fetched.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_traversal(&self, hops: u32) {
+        self.traversals.fetch_add(1, Ordering::Relaxed);
+        let bucket = (u32::BITS - hops.max(1).leading_zeros() - 1) as usize;
+        self.hops_histogram[bucket.min(TRAVERSAL_HOPS_HISTOGRAM_BUCKETS - 1)]
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TraversalMetricsSnapshot {
+        let mut hops_histogram = [0; TRAVERSAL_HOPS_HISTOGRAM_BUCKETS];
+        for (bucket, count) in self.hops_histogram.iter().enumerate() {
+            hops_histogram[bucket] = count.load(Ordering::Relaxed);
+        }
+        TraversalMetricsSnapshot {
+            traversals: self.traversals.load(Ordering::Relaxed),
+            total_hops: self.total_hops.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            blocks_fetched: self.blocks_fetched.load(Ordering::Relaxed),
+            hops_histogram,
+        }
+    }
+
+    /// Returns the metrics collected so far, and starts over from zero.
+    pub fn reset(&self) -> TraversalMetricsSnapshot {
+        let mut hops_histogram = [0; TRAVERSAL_HOPS_HISTOGRAM_BUCKETS];
+        for (bucket, count) in self.hops_histogram.iter().enumerate() {
+            hops_histogram[bucket] = count.swap(0, Ordering::Relaxed);
+        }
+        TraversalMetricsSnapshot {
+            traversals: self.traversals.swap(0, Ordering::Relaxed),
+            total_hops: self.total_hops.swap(0, Ordering::Relaxed),
+            cache_hits: self.cache_hits.swap(0, Ordering::Relaxed),
+            cache_misses: self.cache_misses.swap(0, Ordering::Relaxed),
+            blocks_fetched: self.blocks_fetched.swap(0, Ordering::Relaxed),
+            hops_histogram,
+        }
+    }
+}
+
+/// `metrics`, when provided, is updated as the traversal progresses.
+pub fn retrieve_satoshi_point_using_lazy_storage(
+    blocks_db: &(impl BlockStore + ?Sized),
+    block_identifier: &BlockIdentifier,
+    transaction_identifier: &TransactionIdentifier,
+    inscription_pointer: u64,
+    inscription_number: i64,
+    traversals_cache: Arc<TraversalsLruCache>,
+    block_cache: Option<&LruBlockCache>,
+    metrics: Option<&TraversalMetrics>,
+    ctx: &Context,
+) -> Result<TraversalResult, HordDbError> {
+    retrieve_satoshi_point(
+        |block_height| match block_cache {
+            Some(block_cache) => block_cache.get_or_fetch(block_height, 0),
+            None => try_find_lazy_block_at_block_height(block_height, blocks_db),
+        },
+        block_identifier,
+        transaction_identifier,
+        inscription_pointer,
+        inscription_number,
+        &PersistedTraversalsCache::new(traversals_cache.as_ref(), blocks_db),
+        metrics,
+        ctx,
+    )
+}
+
+/// Resolves several satoshi points concurrently. Traversals are sharing `traversals_cache`,
+/// so that common ancestors are only decoded once. Results are returned in the order of `requests`,
+/// made of the block, the transaction, the inscription pointer and the inscription number.
+pub fn retrieve_satoshi_points_batch(
+    blocks_db: &Arc<DB>,
+    requests: &[(BlockIdentifier, TransactionIdentifier, u64, i64)],
+    traversals_cache: &Arc<TraversalsLruCache>,
+    ctx: &Context,
+) -> Vec<Result<TraversalResult, HordDbError>> {
+    let parallelism = std::thread::available_parallelism()
+        .map(|p| p.get())
+        .unwrap_or(4);
+    execute_in_parallel(
+        requests,
+        parallelism,
+        |(block_identifier, transaction_identifier, inscription_pointer, inscription_number)| {
+            retrieve_satoshi_point_using_lazy_storage(
+                blocks_db.as_ref(),
+                block_identifier,
+                transaction_identifier,
+                *inscription_pointer,
+                *inscription_number,
+                traversals_cache.clone(),
+                None,
+                None,
+                ctx,
+            )
+        },
+    )
+}
+
 #[derive(Debug)]
 pub struct LazyBlock {
     pub bytes: Vec<u8>,
     pub tx_len: u16,
+    pub txid_len: usize,
+    pub header_len: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LazyBlockTransaction {
-    pub txid: [u8; 8],
+    pub txid: Vec<u8>,
     pub inputs: Vec<LazyBlockTransactionInput>,
     pub outputs: Vec<u64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LazyBlockTransactionInput {
-    pub txin: [u8; 8],
+    pub txin: Vec<u8>,
     pub block_height: u32,
     pub vout: u16,
     pub txin_value: u64,
 }
 
-const TXID_LEN: usize = 8;
+/// Length of the txid prefixes used by legacy (header-less) lazy blocks.
+pub const TXID_LEN: usize = 8;
+/// Length of the txid prefixes used when 8 bytes prefixes are colliding within a block.
+pub const LONG_TXID_LEN: usize = 16;
 const SATS_LEN: usize = 8;
-const INPUT_SIZE: usize = TXID_LEN + 4 + 2 + SATS_LEN;
 const OUTPUT_SIZE: usize = 8;
+/// Versioned lazy blocks start with this marker. Legacy blocks start with
+/// their number of transactions (u16), which can not reach 0xff00.
+const LAZY_BLOCK_VERSION_MARKER: u8 = 0xff;
+/// Follows the version marker in checksummed headers. Headers only carrying
+/// the txid prefix length have a non-zero byte at this position.
+const LAZY_BLOCK_CHECKSUM_VERSION: u8 = 0x00;
+/// Marker, version, txid prefix length (u8), body length (u32) and body CRC32 (u32).
+const LAZY_BLOCK_CHECKSUM_HEADER_LEN: usize = 11;
+
+/// CRC32 (IEEE 802.3), as used by zlib.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[derive(Debug)]
+pub enum LazyBlockError {
+    /// The input `input_index` of transaction `txid` is missing its previous output data,
+    /// which happens when bitcoind is not indexing transactions (`txindex`).
+    MissingPrevoutData {
+        txid: String,
+        input_index: usize,
+    },
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for LazyBlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LazyBlockError::MissingPrevoutData { txid, input_index } => write!(
+                f,
+                "transaction {txid} is missing prevout data for input #{input_index}"
+            ),
+            LazyBlockError::Io(e) => write!(f, "unable to serialize block: {}", e.to_string()),
+        }
+    }
+}
+
+/// Reason why `LazyBlock::from_full_block` would not be able to serialize a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockValidationIssue {
+    /// The block does not include any transaction.
+    MissingCoinbase,
+    /// The number of transactions exceeds what the lazy format can encode (u16).
+    TooManyTransactions(usize),
+    TooManyInputs {
+        txid: String,
+        inputs: usize,
+    },
+    TooManyOutputs {
+        txid: String,
+        outputs: usize,
+    },
+    MissingTxin {
+        txid: String,
+        input_index: usize,
+    },
+    MissingVout {
+        txid: String,
+        input_index: usize,
+    },
+    /// See `LazyBlockError::MissingPrevoutData`.
+    MissingPrevout {
+        txid: String,
+        input_index: usize,
+    },
+    /// The vout exceeds what the lazy format can encode (u16).
+    VoutOutOfRange {
+        txid: String,
+        input_index: usize,
+        vout: u32,
+    },
+    /// The prevout height exceeds what the lazy format can encode (u32).
+    PrevoutHeightOutOfRange {
+        txid: String,
+        input_index: usize,
+        height: u64,
+    },
+}
+
+impl std::fmt::Display for BlockValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockValidationIssue::MissingCoinbase => write!(f, "block is missing its coinbase"),
+            BlockValidationIssue::TooManyTransactions(transactions) => {
+                write!(f, "block has too many transactions ({transactions})")
+            }
+            BlockValidationIssue::TooManyInputs { txid, inputs } => {
+                write!(f, "transaction {txid} has too many inputs ({inputs})")
+            }
+            BlockValidationIssue::TooManyOutputs { txid, outputs } => {
+                write!(f, "transaction {txid} has too many outputs ({outputs})")
+            }
+            BlockValidationIssue::MissingTxin { txid, input_index } => {
+                write!(f, "transaction {txid} is missing txin for input #{input_index}")
+            }
+            BlockValidationIssue::MissingVout { txid, input_index } => {
+                write!(f, "transaction {txid} is missing vout for input #{input_index}")
+            }
+            BlockValidationIssue::MissingPrevout { txid, input_index } => write!(
+                f,
+                "transaction {txid} is missing prevout data for input #{input_index}"
+            ),
+            BlockValidationIssue::VoutOutOfRange {
+                txid,
+                input_index,
+                vout,
+            } => write!(
+                f,
+                "transaction {txid} is spending out of range vout {vout} in input #{input_index}"
+            ),
+            BlockValidationIssue::PrevoutHeightOutOfRange {
+                txid,
+                input_index,
+                height,
+            } => write!(
+                f,
+                "transaction {txid} is spending an output at out of range height {height} in input #{input_index}"
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for LazyBlockError {
+    fn from(e: std::io::Error) -> Self {
+        LazyBlockError::Io(e)
+    }
+}
+
+/// Compare 2 txids on their common prefix, since blocks can be using different
+/// prefix lengths.
+pub fn txids_match(a: &[u8], b: &[u8]) -> bool {
+    let len = std::cmp::min(a.len(), b.len());
+    a[..len].eq(&b[..len])
+}
+
+/// Whether the transaction stored with `txid` is the one searched with `searched_txid`.
+/// Shorter prefixes can't tell apart the transactions whose longer prefixes collide on them.
+pub fn txid_identifies(txid: &[u8], searched_txid: &[u8]) -> bool {
+    searched_txid.len() >= txid.len() && txids_match(txid, searched_txid)
+}
+
+/// Key used for indexing transactions in the traversals cache.
+pub fn txid_cache_key(txid: &[u8]) -> [u8; 8] {
+    let mut key = [0u8; 8];
+    let len = std::cmp::min(txid.len(), TXID_LEN);
+    key[..len].copy_from_slice(&txid[..len]);
+    key
+}
 
+/// Lazy blocks are serialized with the following layout:
+///
+/// - optional header: version marker (0xff), then either the txid prefix length (u8),
+///   or the checksum version (0x00), the txid prefix length (u8), the length (u32) and
+///   the CRC32 (u32) of the rest of the block. Without header, txid prefixes are 8 bytes long.
+/// - tx_len (u16): number of transactions in the block (not including coinbase)
+/// - for each transaction: inputs_len (u16), outputs_len (u16)
+/// - coinbase txid prefix and coinbase value (u64)
+/// - for each transaction: txid prefix, then for each input: txin prefix,
+///   block height (u32), vout (u16), value (u64), then for each output: value (u64)
 impl LazyBlock {
+    /// Does not check the integrity of `bytes`: truncated blocks are only
+    /// detected by `validate`.
     pub fn new(bytes: Vec<u8>) -> LazyBlock {
-        let tx_len = u16::from_be_bytes([bytes[0], bytes[1]]);
-        LazyBlock { bytes, tx_len }
+        let byte_at = |pos: usize| bytes.get(pos).cloned().unwrap_or(0);
+        let (txid_len, header_len) = match (byte_at(0), byte_at(1)) {
+            (LAZY_BLOCK_VERSION_MARKER, LAZY_BLOCK_CHECKSUM_VERSION) => {
+                (byte_at(2) as usize, LAZY_BLOCK_CHECKSUM_HEADER_LEN)
+            }
+            (LAZY_BLOCK_VERSION_MARKER, txid_len) => (txid_len as usize, 2),
+            _ => (TXID_LEN, 0),
+        };
+        let tx_len = u16::from_be_bytes([byte_at(header_len), byte_at(header_len + 1)]);
+        LazyBlock {
+            bytes,
+            tx_len,
+            txid_len,
+            header_len,
+        }
+    }
+
+    /// Prepends a checksummed header to `body` (a lazy block without header).
+    pub fn from_body(txid_len: usize, body: Vec<u8>) -> LazyBlock {
+        let mut bytes = Vec::with_capacity(LAZY_BLOCK_CHECKSUM_HEADER_LEN + body.len());
+        bytes.extend_from_slice(&[
+            LAZY_BLOCK_VERSION_MARKER,
+            LAZY_BLOCK_CHECKSUM_VERSION,
+            txid_len as u8,
+        ]);
+        bytes.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&crc32(&body).to_be_bytes());
+        bytes.extend_from_slice(&body);
+        Self::new(bytes)
+    }
+
+    /// Checksummed equivalent of a `CompactedBlock`, using `TXID_LEN` bytes txid prefixes.
+    pub fn from_compacted(block: &CompactedBlock) -> LazyBlock {
+        let mut body = vec![];
+        block
+            .serialize_to_lazy_format(&mut body)
+            .expect("unable to serialize block in memory");
+        LazyBlock::from_body(TXID_LEN, body)
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.bytes[self.header_len.min(self.bytes.len())..]
+    }
+
+    pub fn has_checksum(&self) -> bool {
+        self.header_len == LAZY_BLOCK_CHECKSUM_HEADER_LEN
+    }
+
+    /// Checks the length and checksum recorded in the header, when present, and that
+    /// the transactions formats are consistent with the size of the block.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.bytes.len() < self.header_len + 2 {
+            return Err(format!("block truncated ({} bytes)", self.bytes.len()));
+        }
+        if self.txid_len == 0 || self.txid_len > 32 {
+            return Err(format!("invalid txid prefix length {}", self.txid_len));
+        }
+        if self.has_checksum() {
+            let body = self.body();
+            let expected_len =
+                u32::from_be_bytes([self.bytes[3], self.bytes[4], self.bytes[5], self.bytes[6]])
+                    as usize;
+            if body.len() != expected_len {
+                return Err(format!(
+                    "block length mismatch (expected {expected_len} bytes, found {})",
+                    body.len()
+                ));
+            }
+            let expected_crc =
+                u32::from_be_bytes([self.bytes[7], self.bytes[8], self.bytes[9], self.bytes[10]]);
+            let crc = crc32(body);
+            if crc != expected_crc {
+                return Err(format!(
+                    "block checksum mismatch (expected {expected_crc:08x}, found {crc:08x})"
+                ));
+            }
+        }
+        let transactions_data_pos = self.get_transactions_data_pos();
+        if self.bytes.len() < transactions_data_pos {
+            return Err(format!("block truncated ({} bytes)", self.bytes.len()));
+        }
+        let mut expected_len = transactions_data_pos;
+        for i in 0..self.tx_len {
+            let (_, _, size) = self.get_transaction_format(i);
+            expected_len += size;
+        }
+        if expected_len != self.bytes.len() {
+            return Err(format!(
+                "block length mismatch (expected {expected_len} bytes, found {})",
+                self.bytes.len()
+            ));
+        }
+        Ok(())
+    }
+
+    fn get_input_size(&self) -> usize {
+        self.txid_len + 4 + 2 + SATS_LEN
     }
 
     pub fn get_coinbase_data_pos(&self) -> usize {
-        (2 + self.tx_len * 2 * 2) as usize
+        self.header_len + 2 + self.tx_len as usize * 2 * 2
     }
 
     pub fn get_u64_at_pos(&self, pos: usize) -> u64 {
@@ -1454,40 +6563,42 @@ impl LazyBlock {
 
     pub fn get_coinbase_txid(&self) -> &[u8] {
         let pos = self.get_coinbase_data_pos();
-        &self.bytes[pos..pos + TXID_LEN]
+        &self.bytes[pos..pos + self.txid_len]
     }
 
     pub fn get_coinbase_sats(&self) -> u64 {
-        let pos = self.get_coinbase_data_pos() + TXID_LEN;
+        let pos = self.get_coinbase_data_pos() + self.txid_len;
         self.get_u64_at_pos(pos)
     }
 
     pub fn get_transactions_data_pos(&self) -> usize {
-        self.get_coinbase_data_pos() + TXID_LEN + SATS_LEN
+        self.get_coinbase_data_pos() + self.txid_len + SATS_LEN
     }
 
     pub fn get_transaction_format(&self, index: u16) -> (u16, u16, usize) {
-        let inputs_len_pos = (2 + index * 2 * 2) as usize;
+        let inputs_len_pos = self.header_len + 2 + index as usize * 2 * 2;
         let inputs =
             u16::from_be_bytes([self.bytes[inputs_len_pos], self.bytes[inputs_len_pos + 1]]);
         let outputs = u16::from_be_bytes([
             self.bytes[inputs_len_pos + 2],
             self.bytes[inputs_len_pos + 3],
         ]);
-        let size = TXID_LEN + (inputs as usize * INPUT_SIZE) + (outputs as usize * OUTPUT_SIZE);
+        let size = self.txid_len
+            + (inputs as usize * self.get_input_size())
+            + (outputs as usize * OUTPUT_SIZE);
         (inputs, outputs, size)
     }
 
     pub fn get_lazy_transaction_at_pos(
         &self,
         cursor: &mut Cursor<&Vec<u8>>,
-        txid: [u8; 8],
+        txid: Vec<u8>,
         inputs_len: u16,
         outputs_len: u16,
     ) -> LazyBlockTransaction {
         let mut inputs = Vec::with_capacity(inputs_len as usize);
         for _ in 0..inputs_len {
-            let mut txin = [0u8; 8];
+            let mut txin = vec![0u8; self.txid_len];
             cursor.read_exact(&mut txin).expect("data corrupted");
             let mut block_height = [0u8; 4];
             cursor
@@ -1527,14 +6638,17 @@ impl LazyBlock {
         let mut cumulated_offset = 0;
         let mut i = 0;
         while entry.is_none() {
+            if i >= self.tx_len {
+                break;
+            }
             let pos = self.get_transactions_data_pos() + cumulated_offset;
             let (inputs_len, outputs_len, size) = self.get_transaction_format(i);
             // println!("{inputs_len} / {outputs_len} / {size}");
             cursor.set_position(pos as u64);
-            let mut txid = [0u8; 8];
+            let mut txid = vec![0u8; self.txid_len];
             let _ = cursor.read_exact(&mut txid);
             // println!("-> {}", hex::encode(txid));
-            if searched_txid.eq(&txid) {
+            if txids_match(searched_txid, &txid) {
                 entry = Some(self.get_lazy_transaction_at_pos(
                     &mut cursor,
                     txid,
@@ -1544,23 +6658,70 @@ impl LazyBlock {
             } else {
                 cumulated_offset += size;
                 i += 1;
-                if i >= self.tx_len {
-                    break;
-                }
             }
         }
         entry
     }
 
+    /// Number of transactions in the block (not including coinbase).
+    pub fn transaction_count(&self) -> u16 {
+        self.tx_len
+    }
+
+    /// Deserialize the non-coinbase transaction at `index`, only reading the
+    /// transactions formats preceding it.
+    pub fn get_transaction_at_index(&self, index: u16) -> Option<LazyBlockTransaction> {
+        if index >= self.tx_len {
+            return None;
+        }
+        let mut cumulated_offset = 0;
+        for i in 0..index {
+            let (_, _, size) = self.get_transaction_format(i);
+            cumulated_offset += size;
+        }
+        let pos = self.get_transactions_data_pos() + cumulated_offset;
+        let (inputs_len, outputs_len, _) = self.get_transaction_format(index);
+        let mut cursor = Cursor::new(&self.bytes);
+        cursor.set_position(pos as u64);
+        let mut txid = vec![0u8; self.txid_len];
+        cursor.read_exact(&mut txid).ok()?;
+        Some(self.get_lazy_transaction_at_pos(&mut cursor, txid, inputs_len, outputs_len))
+    }
+
     pub fn iter_tx(&self) -> LazyBlockTransactionIterator {
         LazyBlockTransactionIterator::new(&self)
     }
 
-    pub fn from_full_block(block: &BitcoinBlockFullBreakdown) -> std::io::Result<LazyBlock> {
+    /// Detects if some of the non-coinbase transactions of the block are sharing
+    /// the same txid prefix, given the prefix length currently used by the block.
+    pub fn has_txid_collisions(&self) -> bool {
+        let mut txids = HashSet::new();
+        for tx in self.iter_tx() {
+            if !txids.insert(tx.txid) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Serialize a block using 8 bytes txid prefixes, unless these prefixes are colliding,
+    /// in which case `LONG_TXID_LEN` bytes prefixes are used.
+    pub fn from_full_block(block: &BitcoinBlockFullBreakdown) -> Result<LazyBlock, LazyBlockError> {
+        let lazy_block = Self::from_full_block_with_txid_len(block, TXID_LEN)?;
+        if lazy_block.has_txid_collisions() {
+            return Self::from_full_block_with_txid_len(block, LONG_TXID_LEN);
+        }
+        Ok(lazy_block)
+    }
+
+    pub fn from_full_block_with_txid_len(
+        block: &BitcoinBlockFullBreakdown,
+        txid_len: usize,
+    ) -> Result<LazyBlock, LazyBlockError> {
         let mut buffer = vec![];
         // Number of transactions in the block (not including coinbase)
         let tx_len = block.tx.len() as u16 - 1;
-        buffer.write(&tx_len.to_be_bytes())?;
+        buffer.write_all(&tx_len.to_be_bytes())?;
         // For each transaction:
         for tx in block.tx.iter().skip(1) {
             let inputs_len = tx.vin.len() as u16;
@@ -1570,14 +6731,9 @@ impl LazyBlock {
             // Number of outputs
             buffer.write(&outputs_len.to_be_bytes())?;
         }
-        // Coinbase transaction txid -  8 first bytes
-        let coinbase_txid = {
-            let txid = hex::decode(block.tx[0].txid.to_string()).unwrap();
-            [
-                txid[0], txid[1], txid[2], txid[3], txid[4], txid[5], txid[6], txid[7],
-            ]
-        };
-        buffer.write_all(&coinbase_txid)?;
+        // Coinbase transaction txid - txid_len first bytes
+        let coinbase_txid = hex::decode(block.tx[0].txid.to_string()).unwrap();
+        buffer.write_all(&coinbase_txid[..txid_len])?;
         // Coinbase transaction value
         let mut coinbase_value = 0;
         for coinbase_output in block.tx[0].vout.iter() {
@@ -1586,32 +6742,31 @@ impl LazyBlock {
         buffer.write(&coinbase_value.to_be_bytes())?;
         // For each transaction:
         for tx in block.tx.iter().skip(1) {
-            // txid - 8 first bytes
-            let txid = {
-                let txid = hex::decode(tx.txid.to_string()).unwrap();
-                [
-                    txid[0], txid[1], txid[2], txid[3], txid[4], txid[5], txid[6], txid[7],
-                ]
-            };
-            buffer.write_all(&txid)?;
+            // txid - txid_len first bytes
+            let txid = hex::decode(tx.txid.to_string()).unwrap();
+            buffer.write_all(&txid[..txid_len])?;
             // For each transaction input:
-            for input in tx.vin.iter() {
-                // txin - 8 first bytes
-                let txin = {
-                    let txid = hex::decode(input.txid.unwrap().to_string()).unwrap();
-                    [
-                        txid[0], txid[1], txid[2], txid[3], txid[4], txid[5], txid[6], txid[7],
-                    ]
+            for (input_index, input) in tx.vin.iter().enumerate() {
+                let (txin, vout, prevout) = match (&input.txid, input.vout, &input.prevout) {
+                    (Some(txin), Some(vout), Some(prevout)) => (txin, vout, prevout),
+                    _ => {
+                        return Err(LazyBlockError::MissingPrevoutData {
+                            txid: tx.txid.to_string(),
+                            input_index,
+                        })
+                    }
                 };
-                buffer.write_all(&txin)?;
+                // txin - txid_len first bytes
+                let txin = hex::decode(txin.to_string()).unwrap();
+                buffer.write_all(&txin[..txid_len])?;
                 // txin's block height
-                let block_height = input.prevout.as_ref().unwrap().height as u32;
+                let block_height = prevout.height as u32;
                 buffer.write(&block_height.to_be_bytes())?;
                 // txin's vout index
-                let vout = input.vout.unwrap() as u16;
+                let vout = vout as u16;
                 buffer.write(&vout.to_be_bytes())?;
                 // txin's sats value
-                let sats = input.prevout.as_ref().unwrap().value.to_sat();
+                let sats = prevout.value.to_sat();
                 buffer.write(&sats.to_be_bytes())?;
             }
             // For each transaction output:
@@ -1620,14 +6775,97 @@ impl LazyBlock {
                 buffer.write(&sats.to_be_bytes())?;
             }
         }
-        Ok(Self::new(buffer))
+        Ok(Self::from_body(txid_len, buffer))
+    }
+
+    /// Dry run of `from_full_block`: walks the same fields, without serializing them,
+    /// and reports every issue that would prevent the block from being stored.
+    pub fn validate_full_block(
+        block: &BitcoinBlockFullBreakdown,
+    ) -> Result<(), Vec<BlockValidationIssue>> {
+        let mut issues = vec![];
+        if block.tx.is_empty() {
+            issues.push(BlockValidationIssue::MissingCoinbase);
+        } else if block.tx.len() - 1 > u16::MAX as usize {
+            issues.push(BlockValidationIssue::TooManyTransactions(
+                block.tx.len() - 1,
+            ));
+        }
+        for tx in block.tx.iter().skip(1) {
+            if tx.vin.len() > u16::MAX as usize {
+                issues.push(BlockValidationIssue::TooManyInputs {
+                    txid: tx.txid.to_string(),
+                    inputs: tx.vin.len(),
+                });
+            }
+            if tx.vout.len() > u16::MAX as usize {
+                issues.push(BlockValidationIssue::TooManyOutputs {
+                    txid: tx.txid.to_string(),
+                    outputs: tx.vout.len(),
+                });
+            }
+            for (input_index, input) in tx.vin.iter().enumerate() {
+                if input.txid.is_none() {
+                    issues.push(BlockValidationIssue::MissingTxin {
+                        txid: tx.txid.to_string(),
+                        input_index,
+                    });
+                }
+                match input.vout {
+                    None => issues.push(BlockValidationIssue::MissingVout {
+                        txid: tx.txid.to_string(),
+                        input_index,
+                    }),
+                    Some(vout) if vout > u16::MAX as u32 => {
+                        issues.push(BlockValidationIssue::VoutOutOfRange {
+                            txid: tx.txid.to_string(),
+                            input_index,
+                            vout,
+                        })
+                    }
+                    Some(_) => {}
+                }
+                match input.prevout {
+                    None => issues.push(BlockValidationIssue::MissingPrevout {
+                        txid: tx.txid.to_string(),
+                        input_index,
+                    }),
+                    Some(ref prevout) if prevout.height > u32::MAX as u64 => {
+                        issues.push(BlockValidationIssue::PrevoutHeightOutOfRange {
+                            txid: tx.txid.to_string(),
+                            input_index,
+                            height: prevout.height,
+                        })
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
     }
 
+    /// Serialize a block using 8 bytes txid prefixes, unless these prefixes are colliding,
+    /// in which case `LONG_TXID_LEN` bytes prefixes are used.
     pub fn from_standardized_block(block: &BitcoinBlockData) -> std::io::Result<LazyBlock> {
+        let lazy_block = Self::from_standardized_block_with_txid_len(block, TXID_LEN)?;
+        if lazy_block.has_txid_collisions() {
+            return Self::from_standardized_block_with_txid_len(block, LONG_TXID_LEN);
+        }
+        Ok(lazy_block)
+    }
+
+    pub fn from_standardized_block_with_txid_len(
+        block: &BitcoinBlockData,
+        txid_len: usize,
+    ) -> std::io::Result<LazyBlock> {
         let mut buffer = vec![];
         // Number of transactions in the block (not including coinbase)
         let tx_len = block.transactions.len() as u16 - 1;
-        buffer.write(&tx_len.to_be_bytes())?;
+        buffer.write_all(&tx_len.to_be_bytes())?;
         // For each transaction:
         for tx in block.transactions.iter().skip(1) {
             let inputs_len = tx.metadata.inputs.len() as u16;
@@ -1637,15 +6875,10 @@ impl LazyBlock {
             // Number of outputs
             buffer.write(&outputs_len.to_be_bytes())?;
         }
-        // Coinbase transaction txid -  8 first bytes
-        let coinbase_txid = {
-            let txid =
-                hex::decode(&block.transactions[0].transaction_identifier.hash[2..]).unwrap();
-            [
-                txid[0], txid[1], txid[2], txid[3], txid[4], txid[5], txid[6], txid[7],
-            ]
-        };
-        buffer.write_all(&coinbase_txid)?;
+        // Coinbase transaction txid - txid_len first bytes
+        let coinbase_txid =
+            hex::decode(&block.transactions[0].transaction_identifier.hash[2..]).unwrap();
+        buffer.write_all(&coinbase_txid[..txid_len])?;
         // Coinbase transaction value
         let mut coinbase_value = 0;
         for coinbase_output in block.transactions[0].metadata.outputs.iter() {
@@ -1654,24 +6887,14 @@ impl LazyBlock {
         buffer.write(&coinbase_value.to_be_bytes())?;
         // For each transaction:
         for tx in block.transactions.iter().skip(1) {
-            // txid - 8 first bytes
-            let txid = {
-                let txid = hex::decode(&tx.transaction_identifier.hash[2..]).unwrap();
-                [
-                    txid[0], txid[1], txid[2], txid[3], txid[4], txid[5], txid[6], txid[7],
-                ]
-            };
-            buffer.write_all(&txid)?;
+            // txid - txid_len first bytes
+            let txid = hex::decode(&tx.transaction_identifier.hash[2..]).unwrap();
+            buffer.write_all(&txid[..txid_len])?;
             // For each transaction input:
             for input in tx.metadata.inputs.iter() {
-                // txin - 8 first bytes
-                let txin = {
-                    let txid = hex::decode(&input.previous_output.txid[2..]).unwrap();
-                    [
-                        txid[0], txid[1], txid[2], txid[3], txid[4], txid[5], txid[6], txid[7],
-                    ]
-                };
-                buffer.write_all(&txin)?;
+                // txin - txid_len first bytes
+                let txin = hex::decode(&input.previous_output.txid[2..]).unwrap();
+                buffer.write_all(&txin[..txid_len])?;
                 // txin's block height
                 let block_height = input.previous_output.block_height as u32;
                 buffer.write(&block_height.to_be_bytes())?;
@@ -1688,7 +6911,7 @@ impl LazyBlock {
                 buffer.write(&sats.to_be_bytes())?;
             }
         }
-        Ok(Self::new(buffer))
+        Ok(Self::from_body(txid_len, buffer))
     }
 }
 
@@ -1720,7 +6943,7 @@ impl<'a> Iterator for LazyBlockTransactionIterator<'a> {
         // println!("{inputs_len} / {outputs_len} / {size}");
         let mut cursor = Cursor::new(&self.lazy_block.bytes);
         cursor.set_position(pos as u64);
-        let mut txid = [0u8; 8];
+        let mut txid = vec![0u8; self.lazy_block.txid_len];
         let _ = cursor.read_exact(&mut txid);
         self.cumulated_offset += size;
         self.tx_index += 1;
@@ -1732,3 +6955,9 @@ impl<'a> Iterator for LazyBlockTransactionIterator<'a> {
         ))
     }
 }
+
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+
+/// Runs `task` on each of the `items`, using up to `parallelism` threads, and returns the results
+/// in the order of `items`, however long each task takes.
+///
+/// Items are split upfront into contiguous ranges, one per worker. Workers are processing their
+/// own range from the front and, once done, are stealing from the back of the range of another
+/// worker: a few long tasks (e.g. traversals going back to genesis) don't hold back the others.
+pub fn execute_in_parallel<T, R, F>(items: &[T], parallelism: usize, task: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = parallelism.min(items.len()).max(1);
+    if workers == 1 {
+        return items.iter().map(task).collect();
+    }
+
+    let chunk_size = (items.len() + workers - 1) / workers;
+    let queues = (0..workers)
+        .map(|worker| {
+            let start = (worker * chunk_size).min(items.len());
+            let end = ((worker + 1) * chunk_size).min(items.len());
+            Mutex::new((start..end).collect::<VecDeque<_>>())
+        })
+        .collect::<Vec<_>>();
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<Option<R>>>());
+
+    std::thread::scope(|scope| {
+        for worker in 0..workers {
+            let (queues, results, task) = (&queues, &results, &task);
+            scope.spawn(move || {
+                while let Some(index) = next_index(queues, worker) {
+                    let result = task(&items[index]);
+                    results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|result| result.expect("every item processed"))
+        .collect()
+}
+
+fn lock(queue: &Mutex<VecDeque<usize>>) -> MutexGuard<VecDeque<usize>> {
+    queue.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Next item of the worker, stolen from the longest queue of the other workers once its own
+/// queue is empty.
+fn next_index(queues: &[Mutex<VecDeque<usize>>], worker: usize) -> Option<usize> {
+    if let Some(index) = lock(&queues[worker]).pop_front() {
+        return Some(index);
+    }
+    loop {
+        let (pending, victim) = (0..queues.len())
+            .filter(|victim| *victim != worker)
+            .map(|victim| (lock(&queues[victim]).len(), victim))
+            .max()?;
+        if pending == 0 {
+            return None;
+        }
+        // The queue might have been emptied in the meantime
+        if let Some(index) = lock(&queues[victim]).pop_back() {
+            return Some(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn return_results_in_order() {
+        let items = (0..100u64).collect::<Vec<_>>();
+        let processed = AtomicUsize::new(0);
+        let results = execute_in_parallel(&items, 4, |item| {
+            // Tasks of the first range are the longest ones
+            if *item < 5 {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            processed.fetch_add(1, Ordering::SeqCst);
+            item * 2
+        });
+        assert_eq!(processed.load(Ordering::SeqCst), 100);
+        assert_eq!(
+            results,
+            items.iter().map(|item| item * 2).collect::<Vec<_>>()
+        );
+
+        assert_eq!(execute_in_parallel(&[3u64], 4, |item| item + 1), vec![4]);
+        assert!(execute_in_parallel(&Vec::<u64>::new(), 4, |item| *item).is_empty());
+    }
+}
@@ -1,7 +1,8 @@
 use std::collections::BTreeMap;
 use std::str::FromStr;
 
-use bitcoincore_rpc::bitcoin::Transaction;
+use bitcoincore_rpc::bitcoin::hashes::Hash;
+use bitcoincore_rpc::bitcoin::{Transaction, Txid};
 use {
     bitcoincore_rpc::bitcoin::{
         blockdata::{
@@ -14,15 +15,24 @@ use {
     std::{iter::Peekable, str},
 };
 
+use super::ord::inscription_id::InscriptionId;
+
 const PROTOCOL_ID: &[u8] = b"ord";
 
 const BODY_TAG: &[u8] = &[];
 const CONTENT_TYPE_TAG: &[u8] = &[1];
+const POINTER_TAG: &[u8] = &[2];
+const PARENT_TAG: &[u8] = &[3];
+const METADATA_TAG: &[u8] = &[5];
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Inscription {
     body: Option<Vec<u8>>,
     content_type: Option<Vec<u8>>,
+    metadata: Option<Vec<u8>>,
+    pointer: Option<Vec<u8>>,
+    parent: Option<Vec<u8>>,
+    unrecognized_even_field: bool,
 }
 
 impl Inscription {
@@ -37,6 +47,49 @@ impl Inscription {
     pub(crate) fn content_type(&self) -> Option<&str> {
         str::from_utf8(self.content_type.as_ref()?).ok()
     }
+
+    /// CBOR metadata of the inscription, decoded to JSON. None if absent or not valid CBOR.
+    pub(crate) fn metadata(&self) -> Option<serde_json::Value> {
+        let value: serde_cbor::Value = serde_cbor::from_slice(self.metadata.as_ref()?).ok()?;
+        Some(cbor_to_json(value))
+    }
+
+    /// Offset of the inscribed sat within the outputs of the reveal transaction, encoded
+    /// in little endian. None if absent or overflowing a u64.
+    pub(crate) fn pointer(&self) -> Option<u64> {
+        let pointer = self.pointer.as_ref()?;
+        if pointer.iter().skip(8).any(|byte| *byte != 0) {
+            return None;
+        }
+        let mut bytes = [0u8; 8];
+        let len = pointer.len().min(8);
+        bytes[..len].copy_from_slice(&pointer[..len]);
+        Some(u64::from_le_bytes(bytes))
+    }
+
+    /// Parent of the inscription, encoded as the txid in consensus order followed by the index
+    /// in little endian, trailing zeros trimmed. None if absent or malformed.
+    pub(crate) fn parent(&self) -> Option<InscriptionId> {
+        let parent = self.parent.as_ref()?;
+        if parent.len() < 32 || parent.len() > 36 {
+            return None;
+        }
+        let (txid, index) = parent.split_at(32);
+        if index.last() == Some(&0) {
+            return None;
+        }
+        let mut bytes = [0u8; 4];
+        bytes[..index.len()].copy_from_slice(index);
+        Some(InscriptionId {
+            txid: Txid::from_slice(txid).ok()?,
+            index: u32::from_le_bytes(bytes),
+        })
+    }
+
+    /// Cursed inscriptions can use even tags unknown to the protocol.
+    pub(crate) fn has_unrecognized_even_field(&self) -> bool {
+        self.unrecognized_even_field
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -95,12 +148,34 @@ impl<'a> InscriptionParser<'a> {
 
             if next == Instruction::PushBytes(&[]) {
                 if let Some(inscription) = self.parse_inscription()? {
+                    if inscription.unrecognized_even_field {
+                        return Err(InscriptionError::UnrecognizedEvenField);
+                    }
                     return Ok(inscription);
                 }
             }
         }
     }
 
+    /// Every envelope of the script, in order, including the ones that would be cursed.
+    /// Invalid envelopes are skipped.
+    pub fn parse_inscriptions(mut self) -> Vec<Inscription> {
+        let mut inscriptions = vec![];
+        loop {
+            match self.advance() {
+                Ok(Instruction::PushBytes(&[])) => match self.parse_inscription() {
+                    Ok(Some(inscription)) => inscriptions.push(inscription),
+                    Err(InscriptionError::Script(_)) => break,
+                    // Exhausted instructions are caught by the next `advance`
+                    Ok(None) | Err(_) => {}
+                },
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        inscriptions
+    }
+
     fn advance(&mut self) -> Result<Instruction<'a>> {
         self.instructions
             .next()
@@ -115,6 +190,7 @@ impl<'a> InscriptionParser<'a> {
             }
 
             let mut fields = BTreeMap::new();
+            let mut metadata: Option<Vec<u8>> = None;
 
             loop {
                 match self.advance()? {
@@ -126,6 +202,12 @@ impl<'a> InscriptionParser<'a> {
                         fields.insert(BODY_TAG, body);
                         break;
                     }
+                    // Metadata exceeding the push limit is split over several fields
+                    Instruction::PushBytes(METADATA_TAG) => {
+                        metadata
+                            .get_or_insert_with(Vec::new)
+                            .extend_from_slice(self.expect_push()?);
+                    }
                     Instruction::PushBytes(tag) => {
                         if fields.contains_key(tag) {
                             return Err(InscriptionError::InvalidInscription);
@@ -139,16 +221,21 @@ impl<'a> InscriptionParser<'a> {
 
             let body = fields.remove(BODY_TAG);
             let content_type = fields.remove(CONTENT_TYPE_TAG);
-
-            for tag in fields.keys() {
-                if let Some(lsb) = tag.first() {
-                    if lsb % 2 == 0 {
-                        return Err(InscriptionError::UnrecognizedEvenField);
-                    }
-                }
-            }
-
-            return Ok(Some(Inscription { body, content_type }));
+            let pointer = fields.remove(POINTER_TAG);
+            let parent = fields.remove(PARENT_TAG);
+
+            let unrecognized_even_field = fields
+                .keys()
+                .any(|tag| matches!(tag.first(), Some(lsb) if lsb % 2 == 0));
+
+            return Ok(Some(Inscription {
+                body,
+                content_type,
+                metadata,
+                pointer,
+                parent,
+                unrecognized_even_field,
+            }));
         }
 
         Ok(None)
@@ -177,6 +264,44 @@ impl<'a> InscriptionParser<'a> {
     }
 }
 
+/// CBOR maps can be keyed by any value: keys that are not text are serialized to JSON.
+/// Byte strings are hex encoded.
+fn cbor_to_json(value: serde_cbor::Value) -> serde_json::Value {
+    use serde_cbor::Value as Cbor;
+    use serde_json::Value as Json;
+    match value {
+        Cbor::Null => Json::Null,
+        Cbor::Bool(value) => Json::Bool(value),
+        Cbor::Integer(value) => {
+            if let Ok(value) = i64::try_from(value) {
+                Json::from(value)
+            } else if let Ok(value) = u64::try_from(value) {
+                Json::from(value)
+            } else {
+                Json::String(value.to_string())
+            }
+        }
+        Cbor::Float(value) => serde_json::Number::from_f64(value).map_or(Json::Null, Json::Number),
+        Cbor::Bytes(bytes) => Json::String(format!("0x{}", hex::encode(bytes))),
+        Cbor::Text(text) => Json::String(text),
+        Cbor::Array(values) => Json::Array(values.into_iter().map(cbor_to_json).collect()),
+        Cbor::Map(entries) => Json::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        Cbor::Text(text) => text,
+                        key => cbor_to_json(key).to_string(),
+                    };
+                    (key, cbor_to_json(value))
+                })
+                .collect(),
+        ),
+        Cbor::Tag(_, value) => cbor_to_json(*value),
+        _ => Json::Null,
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub(crate) enum Media {
     Audio,
@@ -225,3 +350,142 @@ impl FromStr for Media {
         Err("unknown content type: {s}".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bitcoincore_rpc::bitcoin::blockdata::script::Builder;
+
+    use super::*;
+
+    fn envelope(builder: Builder, fields: &[(&[u8], &[u8])], body: &[u8]) -> Builder {
+        let mut builder = builder
+            .push_slice(&[])
+            .push_opcode(opcodes::all::OP_IF)
+            .push_slice(PROTOCOL_ID);
+        for (tag, value) in fields {
+            builder = builder.push_slice(tag).push_slice(value);
+        }
+        builder
+            .push_slice(BODY_TAG)
+            .push_slice(body)
+            .push_opcode(opcodes::all::OP_ENDIF)
+    }
+
+    fn parser(script: &Script) -> InscriptionParser {
+        InscriptionParser {
+            instructions: script.instructions().peekable(),
+        }
+    }
+
+    #[test]
+    fn parse_inscriptions() {
+        let builder = envelope(Builder::new(), &[(CONTENT_TYPE_TAG, b"text/plain")], b"a");
+        let script = envelope(builder, &[(&[4], b"x")], b"b").into_script();
+
+        let inscriptions = parser(&script).parse_inscriptions();
+        assert_eq!(inscriptions.len(), 2);
+        assert_eq!(inscriptions[0].body(), Some(&b"a"[..]));
+        assert_eq!(inscriptions[0].content_type(), Some("text/plain"));
+        assert!(!inscriptions[0].has_unrecognized_even_field());
+        assert_eq!(inscriptions[1].body(), Some(&b"b"[..]));
+        assert!(inscriptions[1].has_unrecognized_even_field());
+
+        // Only the first envelope is returned
+        assert_eq!(parser(&script).parse_script(), Ok(inscriptions[0].clone()));
+    }
+
+    #[test]
+    fn pointer() {
+        let script = envelope(Builder::new(), &[(POINTER_TAG, &[0x10, 0x27])], b"a").into_script();
+        let inscription = parser(&script).parse_script().unwrap();
+        assert_eq!(inscription.pointer(), Some(10_000));
+        assert!(!inscription.has_unrecognized_even_field());
+
+        let script = envelope(
+            Builder::new(),
+            &[(POINTER_TAG, &[1, 0, 0, 0, 0, 0, 0, 0, 0, 0])],
+            b"a",
+        )
+        .into_script();
+        assert_eq!(parser(&script).parse_script().unwrap().pointer(), Some(1));
+
+        let script = envelope(
+            Builder::new(),
+            &[(POINTER_TAG, &[1, 0, 0, 0, 0, 0, 0, 0, 1])],
+            b"a",
+        )
+        .into_script();
+        assert_eq!(parser(&script).parse_script().unwrap().pointer(), None);
+    }
+
+    #[test]
+    fn parent() {
+        let parent: InscriptionId =
+            "1111111111111111111111111111111111111111111111111111111111111111i257"
+                .parse()
+                .unwrap();
+        let mut value = parent.txid.into_inner().to_vec();
+        let script = envelope(Builder::new(), &[(PARENT_TAG, &value)], b"a").into_script();
+        assert_eq!(
+            parser(&script).parse_script().unwrap().parent(),
+            Some(InscriptionId {
+                txid: parent.txid,
+                index: 0
+            })
+        );
+
+        value.extend_from_slice(&[1, 1]);
+        let script = envelope(Builder::new(), &[(PARENT_TAG, &value)], b"a").into_script();
+        let inscription = parser(&script).parse_script().unwrap();
+        assert_eq!(inscription.parent(), Some(parent));
+        assert!(!inscription.has_unrecognized_even_field());
+
+        // Trailing zeros must be trimmed
+        value.push(0);
+        let script = envelope(Builder::new(), &[(PARENT_TAG, &value)], b"a").into_script();
+        assert_eq!(parser(&script).parse_script().unwrap().parent(), None);
+    }
+
+    #[test]
+    fn metadata() {
+        let mut metadata = BTreeMap::new();
+        metadata.insert(
+            serde_cbor::Value::Text("name".into()),
+            serde_cbor::Value::Text("x".into()),
+        );
+        metadata.insert(
+            serde_cbor::Value::Integer(1),
+            serde_cbor::Value::Bytes(vec![0xab]),
+        );
+        let bytes = serde_cbor::to_vec(&serde_cbor::Value::Map(metadata)).unwrap();
+        let (head, tail) = bytes.split_at(4);
+
+        let script = envelope(
+            Builder::new(),
+            &[(METADATA_TAG, head), (METADATA_TAG, tail)],
+            b"a",
+        )
+        .into_script();
+        let inscription = parser(&script).parse_script().unwrap();
+        assert_eq!(
+            inscription.metadata(),
+            Some(serde_json::json!({ "name": "x", "1": "0xab" }))
+        );
+
+        let script = envelope(Builder::new(), &[(METADATA_TAG, &[0xff])], b"a").into_script();
+        assert_eq!(parser(&script).parse_script().unwrap().metadata(), None);
+    }
+
+    #[test]
+    fn parse_script_rejects_unrecognized_even_field() {
+        let script = envelope(Builder::new(), &[(&[4], b"x")], b"a").into_script();
+        assert_eq!(
+            parser(&script).parse_script(),
+            Err(InscriptionError::UnrecognizedEvenField)
+        );
+
+        // Odd fields are ignored
+        let script = envelope(Builder::new(), &[(&[3], b"x")], b"a").into_script();
+        assert!(!parser(&script).parse_inscriptions()[0].has_unrecognized_even_field());
+    }
+}
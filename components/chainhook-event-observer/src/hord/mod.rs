@@ -1,83 +1,122 @@
+#[cfg(feature = "brc20")]
+pub mod brc20;
 pub mod db;
+mod executor;
 pub mod inscription;
 pub mod ord;
+pub mod runes;
+pub mod sync_progress;
+pub mod traversals_cache;
 
 use bitcoincore_rpc::bitcoin::hashes::hex::FromHex;
 use bitcoincore_rpc::bitcoin::{Address, Network, Script};
 use chainhook_types::{
-    BitcoinBlockData, OrdinalInscriptionRevealData, OrdinalInscriptionTransferData,
-    OrdinalOperation, TransactionIdentifier,
+    BitcoinBlockData, BitcoinNetwork, OrdinalInscriptionCurseType, OrdinalInscriptionRevealData,
+    OrdinalInscriptionTransferData, OrdinalOperation, SatMovementData, TransactionIdentifier,
 };
 use dashmap::DashMap;
 use fxhash::{FxBuildHasher, FxHasher};
 use hiro_system_kit::slog;
-use rand::seq::SliceRandom;
-use rand::thread_rng;
 use rocksdb::DB;
 use rusqlite::Connection;
 use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::hash::BuildHasherDefault;
 use std::path::PathBuf;
-use std::sync::mpsc::channel;
 use std::sync::Arc;
-use threadpool::ThreadPool;
 
 use crate::indexer::bitcoin::BitcoinTransactionFullBreakdown;
 use crate::{
     hord::{
         db::{
-            find_inscription_with_ordinal_number, find_inscriptions_at_wached_outpoint,
-            insert_entry_in_blocks, retrieve_satoshi_point_using_lazy_storage,
-            store_new_inscription, update_transfered_inscription,
+            find_inscription_with_ordinal_number, find_inscriptions_at_watched_outpoint,
+            flush_blocks_db, insert_entry_in_blocks, retrieve_satoshi_point_using_lazy_storage,
+            retrieve_satoshi_points_batch, store_inscription_location, store_new_inscription,
+            update_transfered_inscription, InscriptionLocation,
         },
         ord::height::Height,
     },
-    utils::Context,
+    observer::BitcoinConfig,
+    utils::{
+        telemetry::{PipelineStage, StageSpan},
+        Context,
+    },
 };
 
 use self::db::{
-    find_inscription_with_id, find_latest_inscription_number_at_block_height,
-    open_readonly_hord_db_conn_rocks_db, remove_entry_from_blocks, remove_entry_from_inscriptions,
-    LazyBlock, LazyBlockTransaction, TraversalResult, WatchedSatpoint,
+    apply_block_pruning_policy, begin_block_write, commit_block_write,
+    delete_brc20_entries_in_block_range, delete_locations_in_block_range,
+    delete_runes_entries_in_block_range, delete_transfers_in_block_range, find_inscription_with_id,
+    find_latest_cursed_inscription_number_at_block_height,
+    find_latest_inscription_number_at_block_height, open_readonly_hord_db_conn_rocks_db,
+    remove_entry_from_blocks, remove_entry_from_inscriptions,
+    replicate_block_in_inscriptions_store, revert_block_in_inscriptions_store,
+    revert_transfered_inscription, BlockPruningPolicy, BlockStore, InscriptionsStore, LazyBlock,
+    RefetchingBlockStore, SatPoint, TraversalResult, WatchedSatpoint,
 };
+use self::executor::execute_in_parallel;
 use self::inscription::InscriptionParser;
 use self::ord::inscription_id::InscriptionId;
+use self::traversals_cache::TraversalsLruCache;
 
-pub fn try_parse_ordinal_operation(
+/// Inscriptions revealed by the transaction, numbered `txidi0`, `txidi1`... in the order of
+/// the inputs and of their envelopes. Only the first envelope of the first input can be blessed,
+/// the other ones are cursed (see `OrdinalInscriptionCurseType`).
+pub fn try_parse_ordinal_operations(
     tx: &BitcoinTransactionFullBreakdown,
     _block_height: u64,
     _ctx: &Context,
-) -> Option<OrdinalOperation> {
-    // This should eventually become a loop once/if there is settlement on https://github.com/casey/ord/issues/2000.
-    if let Some(first_input) = tx.vin.get(0) {
-        if let Some(ref witnesses) = first_input.txinwitness {
-            for bytes in witnesses.iter() {
-                let script = Script::from(bytes.to_vec());
-                let parser = InscriptionParser {
-                    instructions: script.instructions().peekable(),
-                };
+) -> Vec<OrdinalOperation> {
+    let mut operations = vec![];
+    for (input_index, input) in tx.vin.iter().enumerate() {
+        let witnesses = match input.txinwitness {
+            Some(ref witnesses) => witnesses,
+            None => continue,
+        };
+        let mut envelope_index = 0;
+        for bytes in witnesses.iter() {
+            let script = Script::from(bytes.to_vec());
+            let parser = InscriptionParser {
+                instructions: script.instructions().peekable(),
+            };
 
-                let inscription = match parser.parse_script() {
-                    Ok(inscription) => inscription,
-                    Err(_) => continue,
+            for inscription in parser.parse_inscriptions() {
+                let curse_type = if input_index > 0 {
+                    Some(OrdinalInscriptionCurseType::NotInFirstInput)
+                } else if envelope_index > 0 {
+                    Some(OrdinalInscriptionCurseType::NotAtOffsetZero)
+                } else if inscription.has_unrecognized_even_field() {
+                    Some(OrdinalInscriptionCurseType::UnrecognizedEvenField)
+                } else {
+                    None
                 };
+                envelope_index += 1;
 
                 let inscription_id = InscriptionId {
                     txid: tx.txid.clone(),
-                    index: 0,
+                    index: operations.len() as u32,
                 };
 
-                let inscription_output_value = tx
-                    .vout
-                    .get(0)
-                    .and_then(|o| Some(o.value.to_sat()))
+                // Pointers past the outputs are ignored, the inscription then lands on the
+                // first sat of the first output.
+                let output_values = tx.vout.iter().map(|o| o.value.to_sat()).collect::<Vec<_>>();
+                let (inscription_output_index, inscription_offset) = inscription
+                    .pointer()
+                    .and_then(|pointer| locate_sat_in_outputs(&output_values, pointer))
+                    .unwrap_or((0, 0));
+
+                let inscription_output_value = output_values
+                    .get(inscription_output_index)
+                    .copied()
                     .unwrap_or(0);
 
                 let no_content_bytes = vec![];
                 let inscription_content_bytes = inscription.body().unwrap_or(&no_content_bytes);
 
                 let inscriber_address = if let Ok(authors) = Address::from_script(
-                    &tx.vout[0].script_pub_key.script().unwrap(),
+                    &tx.vout[inscription_output_index]
+                        .script_pub_key
+                        .script()
+                        .unwrap(),
                     bitcoincore_rpc::bitcoin::Network::Bitcoin,
                 ) {
                     Some(authors.to_string())
@@ -85,7 +124,7 @@ pub fn try_parse_ordinal_operation(
                     None
                 };
 
-                return Some(OrdinalOperation::InscriptionRevealed(
+                operations.push(OrdinalOperation::InscriptionRevealed(
                     OrdinalInscriptionRevealData {
                         content_type: inscription.content_type().unwrap_or("unknown").to_string(),
                         content_bytes: format!("0x{}", hex::encode(&inscription_content_bytes)),
@@ -98,16 +137,48 @@ pub fn try_parse_ordinal_operation(
                         ordinal_number: 0,
                         ordinal_block_height: 0,
                         ordinal_offset: 0,
+                        ordinal_rarity: String::new(),
                         transfers_pre_inscription: 0,
-                        satpoint_post_inscription: format!("{}:0:0", tx.txid.clone()),
+                        satpoint_post_inscription: format!(
+                            "{}:{}:{}",
+                            tx.txid.clone(),
+                            inscription_output_index,
+                            inscription_offset
+                        ),
+                        metadata: inscription.metadata(),
+                        parent_inscription_id: inscription.parent().map(|p| p.to_string()),
+                        curse_type,
                     },
                 ));
             }
         }
     }
+    operations
+}
+
+/// Output holding the sat located `pointer` sats into the outputs, along with the offset of
+/// that sat within the output.
+fn locate_sat_in_outputs(output_values: &[u64], pointer: u64) -> Option<(usize, u64)> {
+    let mut sats_out = 0;
+    for (index, value) in output_values.iter().enumerate() {
+        if pointer < sats_out + value {
+            return Some((index, pointer - sats_out));
+        }
+        sats_out += value;
+    }
     None
 }
 
+/// Offset of the sat at `satpoint` within the outputs of its transaction, the reverse
+/// of `locate_sat_in_outputs`.
+fn pointer_of_satpoint(output_values: &[u64], satpoint: &SatPoint) -> u64 {
+    output_values
+        .iter()
+        .take(satpoint.vout as usize)
+        .sum::<u64>()
+        + satpoint.offset
+}
+
 pub fn get_inscriptions_revealed_in_block(
     block: &BitcoinBlockData,
 ) -> Vec<&OrdinalInscriptionRevealData> {
@@ -122,14 +193,16 @@ pub fn get_inscriptions_revealed_in_block(
     ops
 }
 
+/// The changes are also reverted in `inscriptions_replica`, when set. hord.sqlite is reverted
+/// in a single transaction, committed before the block is removed from the blocks store.
 pub fn revert_hord_db_with_augmented_bitcoin_block(
     block: &BitcoinBlockData,
     blocks_db_rw: &DB,
     inscriptions_db_conn_rw: &Connection,
+    inscriptions_replica: Option<&dyn InscriptionsStore>,
     ctx: &Context,
 ) -> Result<(), String> {
-    // Remove block from
-    remove_entry_from_blocks(block.block_identifier.index as u32, &blocks_db_rw, ctx);
+    let block_write = begin_block_write(inscriptions_db_conn_rw).map_err(|e| e.to_string())?;
     for tx_index in 1..=block.transactions.len() {
         // Undo the changes in reverse order
         let tx = &block.transactions[block.transactions.len() - tx_index];
@@ -137,30 +210,61 @@ pub fn revert_hord_db_with_augmented_bitcoin_block(
             match ordinal_event {
                 OrdinalOperation::InscriptionRevealed(data) => {
                     // We remove any new inscription created
-                    remove_entry_from_inscriptions(
-                        &data.inscription_id,
-                        &inscriptions_db_conn_rw,
-                        ctx,
-                    );
+                    remove_entry_from_inscriptions(&data.inscription_id, &block_write, ctx);
                 }
                 OrdinalOperation::InscriptionTransferred(data) => {
                     // We revert the outpoint to the pre-transfer value
-                    let comps = data.satpoint_pre_transfer.split(":").collect::<Vec<_>>();
-                    let outpoint_pre_transfer = format!("{}:{}", comps[0], comps[1]);
-                    let offset_pre_transfer = comps[2]
-                        .parse::<u64>()
-                        .map_err(|e| format!("hord_db corrupted {}", e.to_string()))?;
-                    update_transfered_inscription(
+                    let satpoint_pre_transfer = SatPoint::parse(&data.satpoint_pre_transfer)
+                        .map_err(|e| format!("hord_db corrupted {e}"))?;
+                    revert_transfered_inscription(
                         &&data.inscription_id,
-                        &outpoint_pre_transfer,
-                        offset_pre_transfer,
-                        &inscriptions_db_conn_rw,
+                        &satpoint_pre_transfer.outpoint(),
+                        satpoint_pre_transfer.offset,
+                        &block_write,
                         &ctx,
                     );
                 }
             }
         }
     }
+    delete_transfers_in_block_range(
+        block.block_identifier.index as u32,
+        block.block_identifier.index as u32,
+        &block_write,
+        ctx,
+    )
+    .map_err(|e| e.to_string())?;
+    delete_locations_in_block_range(
+        block.block_identifier.index as u32,
+        block.block_identifier.index as u32,
+        &block_write,
+        ctx,
+    )
+    .map_err(|e| e.to_string())?;
+    delete_brc20_entries_in_block_range(
+        block.block_identifier.index as u32,
+        block.block_identifier.index as u32,
+        &block_write,
+        ctx,
+    )
+    .map_err(|e| e.to_string())?;
+    delete_runes_entries_in_block_range(
+        block.block_identifier.index as u32,
+        block.block_identifier.index as u32,
+        &block_write,
+        ctx,
+    )
+    .map_err(|e| e.to_string())?;
+    commit_block_write(block_write).map_err(|e| e.to_string())?;
+    remove_entry_from_blocks(block.block_identifier.index as u32, &blocks_db_rw, ctx);
+    if let Some(store) = inscriptions_replica {
+        revert_block_in_inscriptions_store(block, store).map_err(|e| {
+            format!(
+                "unable to revert block {} in inscriptions replica: {e}",
+                block.block_identifier.index
+            )
+        })?;
+    }
     Ok(())
 }
 
@@ -171,27 +275,29 @@ pub fn new_traversals_cache(
     DashMap::with_hasher(hasher)
 }
 
-pub fn new_traversals_lazy_cache(
-) -> DashMap<(u32, [u8; 8]), LazyBlockTransaction, BuildHasherDefault<FxHasher>> {
-    let hasher = FxBuildHasher::default();
-    DashMap::with_hasher(hasher)
+/// Traversals cache bounded by `DEFAULT_TRAVERSALS_CACHE_SIZE`.
+pub fn new_traversals_lazy_cache() -> TraversalsLruCache {
+    TraversalsLruCache::default()
 }
 
+/// When `bitcoin_config` is provided, blocks missing from the blocks store (e.g. pruned)
+/// are re-downloaded from bitcoind during traversals. Traversals run concurrently, their
+/// results being returned in the order of the transactions of the block.
 pub fn retrieve_inscribed_satoshi_points_from_block(
     block: &BitcoinBlockData,
     inscriptions_db_conn: Option<&Connection>,
     hord_db_path: &PathBuf,
-    traversals_cache: &Arc<
-        DashMap<(u32, [u8; 8]), LazyBlockTransaction, BuildHasherDefault<FxHasher>>,
-    >,
+    bitcoin_config: Option<&BitcoinConfig>,
+    traversals_cache: &Arc<TraversalsLruCache>,
     ctx: &Context,
 ) -> HashMap<TransactionIdentifier, TraversalResult> {
     let mut transactions_ids = vec![];
     let mut traversals = HashMap::new();
 
     for tx in block.transactions.iter().skip(1) {
-        // Have a new inscription been revealed, if so, are looking at a re-inscription
-        for ordinal_event in tx.metadata.ordinal_operations.iter() {
+        // Have a new inscription been revealed, if so, are looking at a re-inscription.
+        // Traversals are performed per transaction: its first inscription is enough.
+        for ordinal_event in tx.metadata.ordinal_operations.iter().take(1) {
             if let OrdinalOperation::InscriptionRevealed(inscription_data) = ordinal_event {
                 if let Some(inscriptions_db_conn) = inscriptions_db_conn {
                     if let Some(traversal) = find_inscription_with_id(
@@ -201,60 +307,72 @@ pub fn retrieve_inscribed_satoshi_points_from_block(
                         ctx,
                     ) {
                         traversals.insert(tx.transaction_identifier.clone(), traversal);
-                    } else {
-                        // Enqueue for traversals
-                        transactions_ids.push(tx.transaction_identifier.clone());
+                        continue;
                     }
-                } else {
-                    // Enqueue for traversals
-                    transactions_ids.push(tx.transaction_identifier.clone());
                 }
+                // Enqueue for traversals, starting from the sat designated by the pointer
+                let inscription_pointer =
+                    match SatPoint::parse(&inscription_data.satpoint_post_inscription) {
+                        Ok(satpoint) => {
+                            let output_values = tx
+                                .metadata
+                                .outputs
+                                .iter()
+                                .map(|output| output.value)
+                                .collect::<Vec<_>>();
+                            pointer_of_satpoint(&output_values, &satpoint)
+                        }
+                        Err(_) => 0,
+                    };
+                transactions_ids.push((tx.transaction_identifier.clone(), inscription_pointer));
             }
         }
     }
 
     if !transactions_ids.is_empty() {
         let expected_traversals = transactions_ids.len();
-        let (traversal_tx, traversal_rx) = channel::<(TransactionIdentifier, _)>();
-        let traversal_data_pool = ThreadPool::new(10);
-
-        let mut rng = thread_rng();
-        transactions_ids.shuffle(&mut rng);
-        for transaction_id in transactions_ids.into_iter() {
-            let moved_traversal_tx = traversal_tx.clone();
-            let moved_ctx = ctx.clone();
-            let block_identifier = block.block_identifier.clone();
-            let moved_hord_db_path = hord_db_path.clone();
-            let local_cache = traversals_cache.clone();
-            traversal_data_pool.execute(move || loop {
-                match open_readonly_hord_db_conn_rocks_db(&moved_hord_db_path, &moved_ctx) {
-                    Ok(blocks_db) => {
-                        let traversal = retrieve_satoshi_point_using_lazy_storage(
-                            &blocks_db,
-                            &block_identifier,
-                            &transaction_id,
-                            0,
-                            local_cache,
-                            &moved_ctx,
-                        );
-                        let _ = moved_traversal_tx.send((transaction_id, traversal));
-                        break;
-                    }
-                    Err(e) => {
-                        moved_ctx.try_log(|logger| {
-                            slog::warn!(
-                                logger,
-                                "Unable to open db: {e}",
-                            );
-                        });
-                    }
+        let blocks_db = loop {
+            match open_readonly_hord_db_conn_rocks_db(hord_db_path, ctx) {
+                Ok(blocks_db) => break blocks_db,
+                Err(e) => {
+                    ctx.try_log(|logger| slog::warn!(logger, "Unable to open db: {e}"));
                 }
-            });
-        }
+            }
+        };
+        let parallelism = std::thread::available_parallelism()
+            .map(|p| p.get())
+            .unwrap_or(4);
+        // Traversals are independent, their results are collected in the order of the
+        // transactions and inscription numbers are assigned afterwards, in that same order.
+        let results = execute_in_parallel(
+            &transactions_ids,
+            parallelism,
+            |(transaction_id, inscription_pointer)| {
+                let refetching_blocks_db = bitcoin_config.map(|bitcoin_config| {
+                    RefetchingBlockStore::with_bitcoind(&blocks_db, bitcoin_config, ctx)
+                });
+                let blocks_store: &dyn BlockStore = match refetching_blocks_db {
+                    Some(ref refetching_blocks_db) => refetching_blocks_db,
+                    None => &blocks_db,
+                };
+                retrieve_satoshi_point_using_lazy_storage(
+                    blocks_store,
+                    &block.block_identifier,
+                    transaction_id,
+                    *inscription_pointer,
+                    0,
+                    traversals_cache.clone(),
+                    None,
+                    None,
+                    ctx,
+                )
+            },
+        );
 
-        let mut traversals_received = 0;
-        while let Ok((transaction_identifier, traversal_result)) = traversal_rx.recv() {
-            traversals_received += 1;
+        for (traversals_received, ((transaction_identifier, _), traversal_result)) in
+            transactions_ids.into_iter().zip(results).enumerate()
+        {
+            let traversals_received = traversals_received + 1;
             match traversal_result {
                 Ok(traversal) => {
                     ctx.try_log(|logger| {
@@ -276,28 +394,97 @@ pub fn retrieve_inscribed_satoshi_points_from_block(
                     });
                 }
             }
-            if traversals_received == expected_traversals {
-                break;
-            }
         }
-        let _ = traversal_data_pool.join();
     }
 
     traversals
 }
 
+/// Augments the transactions with the first sat of each outpoint they are spending, traversed
+/// using the blocks store. Rare sats being usually isolated in outpoints of their own, the other
+/// sats of the outpoints are not traversed.
+pub fn augment_bitcoin_block_with_sat_movements(
+    block: &mut BitcoinBlockData,
+    hord_db_path: &PathBuf,
+    traversals_cache: &Arc<TraversalsLruCache>,
+    ctx: &Context,
+) -> Result<(), String> {
+    let mut requests = vec![];
+    let mut spent_inputs = vec![];
+    for (tx_index, tx) in block.transactions.iter().enumerate().skip(1) {
+        // The sats of an input are following the sats of the previous inputs
+        let mut pointer = 0;
+        for (input_index, input) in tx.metadata.inputs.iter().enumerate() {
+            requests.push((
+                block.block_identifier.clone(),
+                tx.transaction_identifier.clone(),
+                pointer,
+                0,
+            ));
+            spent_inputs.push((tx_index, input_index));
+            pointer += input.previous_output.value;
+        }
+    }
+    if requests.is_empty() {
+        return Ok(());
+    }
+
+    let blocks_db = Arc::new(
+        open_readonly_hord_db_conn_rocks_db(hord_db_path, ctx).map_err(|e| e.to_string())?,
+    );
+    let traversals = retrieve_satoshi_points_batch(&blocks_db, &requests, traversals_cache, ctx);
+    for ((tx_index, input_index), traversal) in spent_inputs.into_iter().zip(traversals) {
+        let tx = &mut block.transactions[tx_index];
+        match traversal {
+            Ok(traversal) => {
+                let input = &tx.metadata.inputs[input_index];
+                let outpoint_pre_transfer = format!(
+                    "{}:{}",
+                    input
+                        .previous_output
+                        .txid
+                        .strip_prefix("0x")
+                        .unwrap_or(&input.previous_output.txid),
+                    input.previous_output.vout
+                );
+                tx.metadata.sat_movements.push(SatMovementData {
+                    ordinal_number: traversal.ordinal_number,
+                    rarity: traversal.rarity().to_string(),
+                    input_index,
+                    outpoint_pre_transfer,
+                });
+            }
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "Unable to compute the sat spent by input #{input_index} of transaction {}: {e}",
+                        tx.transaction_identifier.hash
+                    )
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// When `write_block` is set, `new_block` is stored and, once its inscriptions are indexed,
+/// `pruning_policy` is applied to the blocks store. Once committed, the inscriptions revealed
+/// and transfered are replicated to `inscriptions_replica`, when set.
 pub fn update_hord_db_and_augment_bitcoin_block(
     new_block: &mut BitcoinBlockData,
     blocks_db_rw: &DB,
     inscriptions_db_conn_rw: &Connection,
+    inscriptions_replica: Option<&dyn InscriptionsStore>,
     write_block: bool,
+    pruning_policy: &BlockPruningPolicy,
+    bitcoin_config: Option<&BitcoinConfig>,
     hord_db_path: &PathBuf,
-    traversals_cache: &Arc<
-        DashMap<(u32, [u8; 8]), LazyBlockTransaction, BuildHasherDefault<FxHasher>>,
-    >,
+    traversals_cache: &Arc<TraversalsLruCache>,
     ctx: &Context,
 ) -> Result<(), String> {
     if write_block {
+        let _span = StageSpan::for_block(PipelineStage::DbWrite, new_block.block_identifier.index);
         ctx.try_log(|logger| {
             slog::info!(
                 logger,
@@ -319,32 +506,76 @@ pub fn update_hord_db_and_augment_bitcoin_block(
             &blocks_db_rw,
             &ctx,
         );
-        let _ = blocks_db_rw.flush();
+        let _ = flush_blocks_db(&blocks_db_rw);
     }
 
+    let block_height = new_block.block_identifier.index;
+    let mut span = StageSpan::for_block(PipelineStage::Traversal, block_height);
     let traversals = retrieve_inscribed_satoshi_points_from_block(
         &new_block,
         Some(inscriptions_db_conn_rw),
         hord_db_path,
+        bitcoin_config,
         traversals_cache,
         ctx,
     );
+    span.set_attribute("traversals", traversals.len() as i64);
+    if let Err(e) = traversals_cache.persist(blocks_db_rw) {
+        ctx.try_log(|logger| slog::warn!(logger, "{e}"));
+    }
+    drop(span);
+
+    // Inscriptions revealed and transfered by the block are committed at once
+    let _span = StageSpan::for_block(PipelineStage::DbWrite, block_height);
+    let block_write = begin_block_write(inscriptions_db_conn_rw).map_err(|e| e.to_string())?;
+    {
+        let mut storage = Storage::Sqlite(&block_write);
+        update_storage_and_augment_bitcoin_block_with_inscription_reveal_data(
+            new_block,
+            &mut storage,
+            &traversals,
+            &block_write,
+            &ctx,
+        );
 
-    let mut storage = Storage::Sqlite(inscriptions_db_conn_rw);
-    update_storage_and_augment_bitcoin_block_with_inscription_reveal_data(
-        new_block,
-        &mut storage,
-        &traversals,
-        &inscriptions_db_conn_rw,
-        &ctx,
-    );
+        // Have inscriptions been transfered?
+        update_storage_and_augment_bitcoin_block_with_inscription_transfer_data(
+            new_block,
+            &mut storage,
+            &ctx,
+        )?;
+
+        #[cfg(feature = "brc20")]
+        brc20::update_brc20_ledger_and_augment_bitcoin_block(new_block, &block_write, &ctx)
+            .map_err(|e| e.to_string())?;
+
+        let network = match bitcoin_config {
+            Some(config) => config.network.clone(),
+            None => BitcoinNetwork::Mainnet,
+        };
+        runes::update_runes_ledger_and_augment_bitcoin_block(
+            new_block,
+            &network,
+            &block_write,
+            &ctx,
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    commit_block_write(block_write).map_err(|e| e.to_string())?;
 
-    // Have inscriptions been transfered?
-    update_storage_and_augment_bitcoin_block_with_inscription_transfer_data(
-        new_block,
-        &mut storage,
-        &ctx,
-    )?;
+    if let Some(store) = inscriptions_replica {
+        replicate_block_in_inscriptions_store(new_block, store).map_err(|e| {
+            format!(
+                "unable to replicate block {} to inscriptions replica: {e}",
+                new_block.block_identifier.index
+            )
+        })?;
+    }
+
+    if write_block {
+        apply_block_pruning_policy(pruning_policy, &blocks_db_rw, &ctx)
+            .map_err(|e| e.to_string())?;
+    }
     Ok(())
 }
 
@@ -379,6 +610,25 @@ pub fn update_storage_and_augment_bitcoin_block_with_inscription_reveal_data(
             return;
         }
     };
+    let mut latest_cursed_inscription_number =
+        match find_latest_cursed_inscription_number_at_block_height(
+            &block.block_identifier.index,
+            &inscription_db_conn,
+            &ctx,
+        ) {
+            Ok(None) => -1,
+            Ok(Some(inscription_number)) => inscription_number - 1,
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::error!(
+                        logger,
+                        "unable to retrieve cursed inscription number: {}",
+                        e.to_string()
+                    );
+                });
+                return;
+            }
+        };
     for new_tx in block.transactions.iter_mut().skip(1) {
         let mut ordinals_events_indexes_to_discard = VecDeque::new();
         // Have a new inscription been revealed, if so, are looking at a re-inscription
@@ -386,7 +636,12 @@ pub fn update_storage_and_augment_bitcoin_block_with_inscription_reveal_data(
             new_tx.metadata.ordinal_operations.iter_mut().enumerate()
         {
             if let OrdinalOperation::InscriptionRevealed(inscription) = ordinal_event {
-                let inscription_number = latest_inscription_number;
+                let is_cursed = inscription.curse_type.is_some();
+                let inscription_number = if is_cursed {
+                    latest_cursed_inscription_number
+                } else {
+                    latest_inscription_number
+                };
                 let traversal = match traversals.get(&new_tx.transaction_identifier) {
                     Some(traversal) => traversal,
                     None => {
@@ -404,6 +659,7 @@ pub fn update_storage_and_augment_bitcoin_block_with_inscription_reveal_data(
                 inscription.ordinal_offset = traversal.get_ordinal_coinbase_offset();
                 inscription.ordinal_block_height = traversal.get_ordinal_coinbase_height();
                 inscription.ordinal_number = traversal.ordinal_number;
+                inscription.ordinal_rarity = traversal.rarity().to_string();
                 inscription.inscription_number = traversal.inscription_number;
                 inscription.transfers_pre_inscription = traversal.transfers;
                 inscription.inscription_fee = new_tx.metadata.fee;
@@ -411,11 +667,16 @@ pub fn update_storage_and_augment_bitcoin_block_with_inscription_reveal_data(
                 match storage {
                     Storage::Sqlite(rw_hord_db_conn) => {
                         if traversal.ordinal_number > 0 {
-                            if let Some(_entry) = find_inscription_with_ordinal_number(
-                                &traversal.ordinal_number,
-                                &inscription_db_conn,
-                                &ctx,
-                            ) {
+                            // Cursed inscriptions can be inscribed on an already inscribed sat
+                            let existing_inscription = match is_cursed {
+                                true => None,
+                                false => find_inscription_with_ordinal_number(
+                                    &traversal.ordinal_number,
+                                    &inscription_db_conn,
+                                    &ctx,
+                                ),
+                            };
+                            if let Some(_entry) = existing_inscription {
                                 ctx.try_log(|logger| {
                                         slog::warn!(
                                             logger,
@@ -433,7 +694,11 @@ pub fn update_storage_and_augment_bitcoin_block_with_inscription_reveal_data(
                             // but exclude it from the block data
                             ordinals_events_indexes_to_discard.push_front(ordinal_event_index);
                         }
-                        latest_inscription_number += 1;
+                        if is_cursed {
+                            latest_cursed_inscription_number -= 1;
+                        } else {
+                            latest_inscription_number += 1;
+                        }
                         inscription.inscription_number = inscription_number;
                         ctx.try_log(|logger| {
                                     slog::info!(
@@ -446,26 +711,31 @@ pub fn update_storage_and_augment_bitcoin_block_with_inscription_reveal_data(
                                 inscription.transfers_pre_inscription,
                             );
                                 });
-                        store_new_inscription(
+                        if let Err(e) = store_new_inscription(
                             &inscription,
                             &block.block_identifier,
                             &rw_hord_db_conn,
                             &ctx,
-                        );
+                        ) {
+                            ctx.try_log(|logger| slog::error!(logger, "{}", e));
+                        }
                     }
                     Storage::Memory(map) => {
-                        let outpoint = inscription.satpoint_post_inscription
-                            [0..inscription.satpoint_post_inscription.len() - 2]
-                            .to_string();
-                        map.insert(
-                            outpoint,
-                            vec![WatchedSatpoint {
-                                inscription_id: inscription.inscription_id.clone(),
-                                inscription_number: inscription.inscription_number,
-                                ordinal_number: inscription.ordinal_number,
-                                offset: 0,
-                            }],
-                        );
+                        let satpoint = SatPoint::parse(&inscription.satpoint_post_inscription);
+                        let (outpoint, offset) = match satpoint {
+                            Ok(satpoint) => (satpoint.outpoint(), satpoint.offset),
+                            Err(e) => {
+                                ctx.try_log(|logger| slog::error!(logger, "{}", e));
+                                continue;
+                            }
+                        };
+                        map.entry(outpoint).or_default().push(WatchedSatpoint {
+                            inscription_id: inscription.inscription_id.clone(),
+                            inscription_number: inscription.inscription_number,
+                            ordinal_number: inscription.ordinal_number,
+                            offset,
+                            parent_inscription_id: inscription.parent_inscription_id.clone(),
+                        });
                     }
                 }
             }
@@ -509,7 +779,8 @@ pub fn update_storage_and_augment_bitcoin_block_with_inscription_transfer_data(
 
             let entries = match storage {
                 Storage::Sqlite(rw_hord_db_conn) => {
-                    find_inscriptions_at_wached_outpoint(&outpoint_pre_transfer, &rw_hord_db_conn)?
+                    find_inscriptions_at_watched_outpoint(&outpoint_pre_transfer, &rw_hord_db_conn)
+                        .map_err(|e| e.to_string())?
                 }
                 Storage::Memory(ref mut map) => match map.remove(&outpoint_pre_transfer) {
                     Some(entries) => entries,
@@ -609,16 +880,33 @@ pub fn update_storage_and_augment_bitcoin_block_with_inscription_transfer_data(
                     )
                 });
 
+                let satpoint_post_transfer =
+                    format!("{}:{}", outpoint_post_transfer, offset_post_transfer);
+
                 // Update watched outpoint
                 match storage {
                     Storage::Sqlite(rw_hord_db_conn) => {
                         update_transfered_inscription(
                             &watched_satpoint.inscription_id,
+                            &outpoint_pre_transfer,
                             &outpoint_post_transfer,
                             offset_post_transfer,
+                            updated_address.as_deref(),
+                            &block.block_identifier,
                             &rw_hord_db_conn,
                             &ctx,
                         );
+                        let location = InscriptionLocation {
+                            inscription_id: watched_satpoint.inscription_id.clone(),
+                            block_height: block.block_identifier.index,
+                            block_hash: block.block_identifier.hash.clone(),
+                            tx_id: new_tx.transaction_identifier.hash[2..].to_string(),
+                            satpoint: satpoint_post_transfer.clone(),
+                            address: updated_address.clone(),
+                        };
+                        if let Err(e) = store_inscription_location(&location, &rw_hord_db_conn) {
+                            ctx.try_log(|logger| slog::error!(logger, "{}", e.to_string()));
+                        }
                     }
                     Storage::Memory(ref mut map) => {
                         watched_satpoint.offset = offset_post_transfer;
@@ -628,9 +916,6 @@ pub fn update_storage_and_augment_bitcoin_block_with_inscription_transfer_data(
                     }
                 };
 
-                let satpoint_post_transfer =
-                    format!("{}:{}", outpoint_post_transfer, offset_post_transfer);
-
                 let event_data = OrdinalInscriptionTransferData {
                     inscription_id: watched_satpoint.inscription_id.clone(),
                     inscription_number: watched_satpoint.inscription_number,
@@ -639,6 +924,7 @@ pub fn update_storage_and_augment_bitcoin_block_with_inscription_transfer_data(
                     satpoint_pre_transfer,
                     satpoint_post_transfer,
                     post_transfer_output_value,
+                    parent_inscription_id: watched_satpoint.parent_inscription_id.clone(),
                 };
 
                 // Attach transfer event
@@ -10,6 +10,9 @@ pub mod deserialize_from_str;
 pub mod epoch;
 pub mod height;
 pub mod inscription_id;
+pub mod rarity;
+pub mod rune;
+pub mod runestone;
 pub mod sat;
 pub mod sat_point;
 
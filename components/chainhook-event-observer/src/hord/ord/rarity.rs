@@ -0,0 +1,115 @@
+use std::fmt::{self, Display, Formatter};
+
+use schemars::JsonSchema;
+
+use super::{sat::Sat, *};
+
+#[derive(
+    Copy, Clone, Eq, PartialEq, Debug, Ord, PartialOrd, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+    Mythic,
+}
+
+impl Display for Rarity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Common => "common",
+                Self::Uncommon => "uncommon",
+                Self::Rare => "rare",
+                Self::Epic => "epic",
+                Self::Legendary => "legendary",
+                Self::Mythic => "mythic",
+            }
+        )
+    }
+}
+
+impl From<Sat> for Rarity {
+    fn from(sat: Sat) -> Self {
+        let height = sat.height().n();
+        let hour = height / (CYCLE_EPOCHS * SUBSIDY_HALVING_INTERVAL);
+        let minute = height % SUBSIDY_HALVING_INTERVAL;
+        let second = height % DIFFCHANGE_INTERVAL;
+        let third = sat.third();
+
+        if hour == 0 && minute == 0 && second == 0 && third == 0 {
+            Self::Mythic
+        } else if minute == 0 && second == 0 && third == 0 {
+            Self::Legendary
+        } else if minute == 0 && third == 0 {
+            Self::Epic
+        } else if second == 0 && third == 0 {
+            Self::Rare
+        } else if third == 0 {
+            Self::Uncommon
+        } else {
+            Self::Common
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoincore_rpc::bitcoin::blockdata::constants::COIN_VALUE;
+
+    use super::*;
+    use crate::hord::ord::height::Height;
+
+    #[test]
+    fn rarity() {
+        assert_eq!(Sat(0).rarity(), Rarity::Mythic);
+        assert_eq!(Sat(1).rarity(), Rarity::Common);
+
+        assert_eq!(Sat(50 * COIN_VALUE - 1).rarity(), Rarity::Common);
+        assert_eq!(Sat(50 * COIN_VALUE).rarity(), Rarity::Uncommon);
+        assert_eq!(Sat(50 * COIN_VALUE + 1).rarity(), Rarity::Common);
+
+        assert_eq!(
+            Height(DIFFCHANGE_INTERVAL).starting_sat().rarity(),
+            Rarity::Rare
+        );
+        assert_eq!(
+            Height(SUBSIDY_HALVING_INTERVAL).starting_sat().rarity(),
+            Rarity::Epic
+        );
+        assert_eq!(
+            Height(SUBSIDY_HALVING_INTERVAL * CYCLE_EPOCHS)
+                .starting_sat()
+                .rarity(),
+            Rarity::Legendary
+        );
+    }
+
+    #[test]
+    fn is_common_matches_rarity() {
+        for n in [0, 1, 50 * COIN_VALUE, 50 * COIN_VALUE + 1, Sat::LAST.n()] {
+            let sat = Sat(n);
+            assert_eq!(sat.is_common(), sat.rarity() == Rarity::Common);
+        }
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Rarity::Common.to_string(), "common");
+        assert_eq!(Rarity::Mythic.to_string(), "mythic");
+    }
+
+    #[test]
+    fn serde_matches_display() {
+        for rarity in [Rarity::Common, Rarity::Rare, Rarity::Legendary] {
+            let json = serde_json::to_string(&rarity).unwrap();
+            assert_eq!(json, format!("\"{rarity}\""));
+            assert_eq!(serde_json::from_str::<Rarity>(&json).unwrap(), rarity);
+        }
+    }
+}
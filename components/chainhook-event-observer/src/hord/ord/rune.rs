@@ -0,0 +1,193 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+/// Name of a rune, encoded in modified base-26: A is 0, Z is 25, AA is 26...
+#[derive(Debug, PartialEq, Copy, Clone, Hash, Eq, Ord, PartialOrd, Default)]
+pub struct Rune(pub u128);
+
+impl Rune {
+    /// Names above this value are assigned to the etchings that do not specify a name.
+    const RESERVED: u128 = 6402364363415443603228541259936211926;
+
+    pub fn reserved(block: u64, tx: u32) -> Self {
+        Self(Self::RESERVED + ((u128::from(block) << 32) | u128::from(tx)))
+    }
+
+    pub fn is_reserved(self) -> bool {
+        self.0 >= Self::RESERVED
+    }
+
+    /// Name including a `•` after each letter flagged in `spacers`.
+    pub fn spaced(self, spacers: u32) -> String {
+        let mut spaced = String::new();
+        let name = self.to_string();
+        for (i, c) in name.chars().enumerate() {
+            spaced.push(c);
+            if i < name.len() - 1 && spacers & (1 << i) != 0 {
+                spaced.push('•');
+            }
+        }
+        spaced
+    }
+}
+
+impl Display for Rune {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut n = self.0;
+        if n == u128::MAX {
+            return write!(f, "BCGDENLQRQWDSLRUGSNLBTMFIJAV");
+        }
+
+        n += 1;
+        let mut symbol = String::new();
+        while n > 0 {
+            symbol.push(
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+                    .chars()
+                    .nth(((n - 1) % 26) as usize)
+                    .unwrap(),
+            );
+            n = (n - 1) / 26;
+        }
+
+        for c in symbol.chars().rev() {
+            write!(f, "{c}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Rune {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut x = 0u128;
+        for (i, c) in s.chars().enumerate() {
+            if i > 0 {
+                x = x.checked_add(1).ok_or("name out of range")?;
+            }
+            x = x.checked_mul(26).ok_or("name out of range")?;
+            match c {
+                'A'..='Z' => {
+                    x = x
+                        .checked_add(c as u128 - 'A' as u128)
+                        .ok_or("name out of range")?;
+                }
+                _ => return Err(format!("invalid character in rune name: {c}")),
+            }
+        }
+        Ok(Rune(x))
+    }
+}
+
+/// Block height and index in the block of the transaction etching a rune.
+#[derive(Debug, PartialEq, Copy, Clone, Hash, Eq, Ord, PartialOrd, Default)]
+pub struct RuneId {
+    pub block: u64,
+    pub tx: u32,
+}
+
+impl RuneId {
+    pub fn new(block: u64, tx: u32) -> Option<RuneId> {
+        let id = RuneId { block, tx };
+        if id.block == 0 && id.tx > 0 {
+            return None;
+        }
+        Some(id)
+    }
+
+    /// Runestone edicts encode their ids as deltas from the previous one.
+    pub fn next(self, block: u128, tx: u128) -> Option<RuneId> {
+        let block = u64::try_from(block).ok()?;
+        let tx = u32::try_from(tx).ok()?;
+        RuneId::new(
+            self.block.checked_add(block)?,
+            if block == 0 {
+                self.tx.checked_add(tx)?
+            } else {
+                tx
+            },
+        )
+    }
+}
+
+impl Display for RuneId {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.block, self.tx)
+    }
+}
+
+impl FromStr for RuneId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (block, tx) = s.split_once(':').ok_or(format!("invalid rune id: {s}"))?;
+        Ok(RuneId {
+            block: block.parse().map_err(|_| format!("invalid rune id: {s}"))?,
+            tx: tx.parse().map_err(|_| format!("invalid rune id: {s}"))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(Rune(0).to_string(), "A");
+        assert_eq!(Rune(25).to_string(), "Z");
+        assert_eq!(Rune(26).to_string(), "AA");
+        assert_eq!(Rune(27).to_string(), "AB");
+        assert_eq!(Rune(u128::MAX).to_string(), "BCGDENLQRQWDSLRUGSNLBTMFIJAV");
+    }
+
+    #[test]
+    fn from_str() {
+        for n in [0, 1, 25, 26, 27, 2055, 99246114928149462] {
+            assert_eq!(Rune(n).to_string().parse::<Rune>(), Ok(Rune(n)));
+        }
+        assert!("a".parse::<Rune>().is_err());
+        assert!("BCGDENLQRQWDSLRUGSNLBTMFIJAW".parse::<Rune>().is_err());
+    }
+
+    #[test]
+    fn spaced() {
+        assert_eq!(Rune(0).spaced(0b1), "A");
+        assert_eq!(Rune(27).spaced(0b1), "A•B");
+        assert_eq!("ABCD".parse::<Rune>().unwrap().spaced(0b101), "A•BC•D");
+    }
+
+    #[test]
+    fn reserved() {
+        assert!(!Rune(Rune::RESERVED - 1).is_reserved());
+        assert!(Rune::reserved(0, 0).is_reserved());
+        assert_eq!(Rune::reserved(1, 2), Rune(Rune::RESERVED + (1 << 32) + 2));
+    }
+
+    #[test]
+    fn rune_id() {
+        assert_eq!(RuneId::new(0, 1), None);
+        let id = RuneId::new(840000, 1).unwrap();
+        assert_eq!(id.to_string(), "840000:1");
+        assert_eq!("840000:1".parse::<RuneId>(), Ok(id));
+        assert_eq!(
+            id.next(0, 2),
+            Some(RuneId {
+                block: 840000,
+                tx: 3
+            })
+        );
+        assert_eq!(
+            id.next(1, 2),
+            Some(RuneId {
+                block: 840001,
+                tx: 2
+            })
+        );
+        assert_eq!(id.next(u128::from(u64::MAX), 0), None);
+    }
+}
@@ -0,0 +1,618 @@
+use std::collections::{HashMap, VecDeque};
+
+use bitcoincore_rpc::bitcoin::blockdata::{
+    opcodes,
+    script::{Instruction, Script},
+};
+use bitcoincore_rpc::bitcoin::hashes::hex::FromHex;
+use chainhook_types::TxOut;
+
+use super::rune::{Rune, RuneId};
+
+/// Runestones are OP_RETURN outputs starting with `OP_RETURN OP_13`.
+const MAGIC_NUMBER: opcodes::All = opcodes::all::OP_PUSHNUM_13;
+const MAX_DIVISIBILITY: u8 = 38;
+const MAX_SPACERS: u32 = 0b00000111_11111111_11111111_11111111;
+
+mod tag {
+    pub const BODY: u128 = 0;
+    pub const DIVISIBILITY: u128 = 1;
+    pub const FLAGS: u128 = 2;
+    pub const SPACERS: u128 = 3;
+    pub const RUNE: u128 = 4;
+    pub const SYMBOL: u128 = 5;
+    pub const PREMINE: u128 = 6;
+    pub const CAP: u128 = 8;
+    pub const AMOUNT: u128 = 10;
+    pub const HEIGHT_START: u128 = 12;
+    pub const HEIGHT_END: u128 = 14;
+    pub const OFFSET_START: u128 = 16;
+    pub const OFFSET_END: u128 = 18;
+    pub const MINT: u128 = 20;
+    pub const POINTER: u128 = 22;
+}
+
+mod flag {
+    pub const ETCHING: u128 = 1 << 0;
+    pub const TERMS: u128 = 1 << 1;
+    pub const TURBO: u128 = 1 << 2;
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Artifact {
+    Runestone(Runestone),
+    /// Malformed runestone: the runes of the inputs are burned, an etching creates a rune
+    /// without supply and a mint is counted but burned.
+    Cenotaph(Cenotaph),
+}
+
+impl Artifact {
+    pub fn mint(&self) -> Option<RuneId> {
+        match self {
+            Artifact::Runestone(runestone) => runestone.mint,
+            Artifact::Cenotaph(cenotaph) => cenotaph.mint,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Runestone {
+    pub edicts: Vec<Edict>,
+    pub etching: Option<Etching>,
+    pub mint: Option<RuneId>,
+    pub pointer: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Cenotaph {
+    pub etching: Option<Rune>,
+    pub mint: Option<RuneId>,
+    pub flaw: Flaw,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum Flaw {
+    EdictOutput,
+    EdictRuneId,
+    #[default]
+    InvalidScript,
+    Opcode,
+    SupplyOverflow,
+    TrailingIntegers,
+    TruncatedField,
+    UnrecognizedEvenTag,
+    UnrecognizedFlag,
+    Varint,
+}
+
+/// Moves `amount` of the rune `id` to `output`. An `output` equal to the number of outputs
+/// of the transaction targets every output that is not an OP_RETURN.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Edict {
+    pub id: RuneId,
+    pub amount: u128,
+    pub output: u32,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct Etching {
+    pub divisibility: Option<u8>,
+    pub premine: Option<u128>,
+    pub rune: Option<Rune>,
+    pub spacers: Option<u32>,
+    pub symbol: Option<char>,
+    pub terms: Option<Terms>,
+    pub turbo: bool,
+}
+
+impl Etching {
+    /// None if the premine and the open mints overflow.
+    pub fn supply(&self) -> Option<u128> {
+        let premine = self.premine.unwrap_or_default();
+        let cap = self.terms.and_then(|terms| terms.cap).unwrap_or_default();
+        let amount = self
+            .terms
+            .and_then(|terms| terms.amount)
+            .unwrap_or_default();
+        premine.checked_add(cap.checked_mul(amount)?)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct Terms {
+    pub amount: Option<u128>,
+    pub cap: Option<u128>,
+    /// Absolute block heights window of the mints, end excluded.
+    pub height: (Option<u64>, Option<u64>),
+    /// Window of the mints relative to the etching block height, end excluded.
+    pub offset: (Option<u64>, Option<u64>),
+}
+
+impl Runestone {
+    /// Runestone of the first `OP_RETURN OP_13` output of the transaction, if any.
+    pub fn decipher(outputs: &[TxOut]) -> Option<Artifact> {
+        let payload = match payload(outputs)? {
+            Ok(payload) => payload,
+            Err(flaw) => {
+                return Some(Artifact::Cenotaph(Cenotaph {
+                    flaw,
+                    ..Default::default()
+                }))
+            }
+        };
+
+        let integers = match integers(&payload) {
+            Some(integers) => integers,
+            None => {
+                return Some(Artifact::Cenotaph(Cenotaph {
+                    flaw: Flaw::Varint,
+                    ..Default::default()
+                }))
+            }
+        };
+
+        let mut flaw = None;
+        let mut edicts = Vec::new();
+        let mut fields: HashMap<u128, VecDeque<u128>> = HashMap::new();
+        for i in (0..integers.len()).step_by(2) {
+            let tag = integers[i];
+            if tag == tag::BODY {
+                let mut id = RuneId::default();
+                for chunk in integers[i + 1..].chunks(4) {
+                    if chunk.len() != 4 {
+                        flaw.get_or_insert(Flaw::TrailingIntegers);
+                        break;
+                    }
+                    let next = match id.next(chunk[0], chunk[1]) {
+                        Some(next) => next,
+                        None => {
+                            flaw.get_or_insert(Flaw::EdictRuneId);
+                            break;
+                        }
+                    };
+                    let output = match u32::try_from(chunk[3]) {
+                        Ok(output) if output as usize <= outputs.len() => output,
+                        _ => {
+                            flaw.get_or_insert(Flaw::EdictOutput);
+                            break;
+                        }
+                    };
+                    id = next;
+                    edicts.push(Edict {
+                        id,
+                        amount: chunk[2],
+                        output,
+                    });
+                }
+                break;
+            }
+            match integers.get(i + 1) {
+                Some(value) => fields.entry(tag).or_default().push_back(*value),
+                None => {
+                    flaw.get_or_insert(Flaw::TruncatedField);
+                    break;
+                }
+            }
+        }
+
+        let mut flags = take(&mut fields, tag::FLAGS, 1, |values| Some(values[0])).unwrap_or(0);
+        let etching = if take_flag(&mut flags, flag::ETCHING) {
+            let mut etching = Etching {
+                divisibility: take(&mut fields, tag::DIVISIBILITY, 1, |values| {
+                    u8::try_from(values[0])
+                        .ok()
+                        .filter(|divisibility| *divisibility <= MAX_DIVISIBILITY)
+                }),
+                premine: take(&mut fields, tag::PREMINE, 1, |values| Some(values[0])),
+                rune: take(&mut fields, tag::RUNE, 1, |values| Some(Rune(values[0]))),
+                spacers: take(&mut fields, tag::SPACERS, 1, |values| {
+                    u32::try_from(values[0])
+                        .ok()
+                        .filter(|spacers| *spacers <= MAX_SPACERS)
+                }),
+                symbol: take(&mut fields, tag::SYMBOL, 1, |values| {
+                    char::from_u32(u32::try_from(values[0]).ok()?)
+                }),
+                terms: None,
+                turbo: false,
+            };
+            if take_flag(&mut flags, flag::TERMS) {
+                etching.terms = Some(Terms {
+                    cap: take(&mut fields, tag::CAP, 1, |values| Some(values[0])),
+                    height: (
+                        take(&mut fields, tag::HEIGHT_START, 1, |values| {
+                            u64::try_from(values[0]).ok()
+                        }),
+                        take(&mut fields, tag::HEIGHT_END, 1, |values| {
+                            u64::try_from(values[0]).ok()
+                        }),
+                    ),
+                    amount: take(&mut fields, tag::AMOUNT, 1, |values| Some(values[0])),
+                    offset: (
+                        take(&mut fields, tag::OFFSET_START, 1, |values| {
+                            u64::try_from(values[0]).ok()
+                        }),
+                        take(&mut fields, tag::OFFSET_END, 1, |values| {
+                            u64::try_from(values[0]).ok()
+                        }),
+                    ),
+                });
+            }
+            etching.turbo = take_flag(&mut flags, flag::TURBO);
+            Some(etching)
+        } else {
+            None
+        };
+
+        let mint = take(&mut fields, tag::MINT, 2, |values| {
+            RuneId::new(
+                u64::try_from(values[0]).ok()?,
+                u32::try_from(values[1]).ok()?,
+            )
+        });
+        let pointer = take(&mut fields, tag::POINTER, 1, |values| {
+            u32::try_from(values[0])
+                .ok()
+                .filter(|pointer| (*pointer as usize) < outputs.len())
+        });
+
+        if etching
+            .map(|etching| etching.supply().is_none())
+            .unwrap_or(false)
+        {
+            flaw.get_or_insert(Flaw::SupplyOverflow);
+        }
+        if flags != 0 {
+            flaw.get_or_insert(Flaw::UnrecognizedFlag);
+        }
+        // Odd tags are optional, unknown even tags can not be ignored
+        if fields.keys().any(|tag| tag % 2 == 0) {
+            flaw.get_or_insert(Flaw::UnrecognizedEvenTag);
+        }
+
+        if let Some(flaw) = flaw {
+            return Some(Artifact::Cenotaph(Cenotaph {
+                flaw,
+                mint,
+                etching: etching.and_then(|etching| etching.rune),
+            }));
+        }
+
+        Some(Artifact::Runestone(Runestone {
+            edicts,
+            etching,
+            mint,
+            pointer,
+        }))
+    }
+}
+
+pub fn is_op_return(output: &TxOut) -> bool {
+    output.get_script_pubkey_hex().starts_with("6a")
+}
+
+/// Concatenated data pushes of the runestone output, or the flaw making it a cenotaph.
+fn payload(outputs: &[TxOut]) -> Option<Result<Vec<u8>, Flaw>> {
+    for output in outputs.iter() {
+        let script = match Script::from_hex(output.get_script_pubkey_hex()) {
+            Ok(script) => script,
+            Err(_) => continue,
+        };
+        let mut instructions = script.instructions();
+        if !matches!(instructions.next(), Some(Ok(Instruction::Op(op))) if op == opcodes::all::OP_RETURN)
+        {
+            continue;
+        }
+        if !matches!(instructions.next(), Some(Ok(Instruction::Op(op))) if op == MAGIC_NUMBER) {
+            continue;
+        }
+        let mut payload = Vec::new();
+        for instruction in instructions {
+            match instruction {
+                Ok(Instruction::PushBytes(push)) => payload.extend_from_slice(push),
+                Ok(Instruction::Op(_)) => return Some(Err(Flaw::Opcode)),
+                Err(_) => return Some(Err(Flaw::InvalidScript)),
+            }
+        }
+        return Some(Ok(payload));
+    }
+    None
+}
+
+/// LEB128 encoded integers of the payload, None if one of them is malformed.
+fn integers(payload: &[u8]) -> Option<Vec<u128>> {
+    let mut integers = Vec::new();
+    let mut i = 0;
+    while i < payload.len() {
+        let (integer, length) = decode_varint(&payload[i..])?;
+        integers.push(integer);
+        i += length;
+    }
+    Some(integers)
+}
+
+fn decode_varint(buffer: &[u8]) -> Option<(u128, usize)> {
+    let mut n = 0u128;
+    for (i, byte) in buffer.iter().enumerate() {
+        if i > 18 {
+            return None;
+        }
+        let value = u128::from(*byte) & 0b0111_1111;
+        if i == 18 && value & 0b0111_1100 != 0 {
+            return None;
+        }
+        n |= value << (7 * i);
+        if byte & 0b1000_0000 == 0 {
+            return Some((n, i + 1));
+        }
+    }
+    None
+}
+
+/// Consumes the `count` first values of `tag`, unless missing or rejected by `with`.
+fn take<T>(
+    fields: &mut HashMap<u128, VecDeque<u128>>,
+    tag: u128,
+    count: usize,
+    with: impl Fn(&[u128]) -> Option<T>,
+) -> Option<T> {
+    let field = fields.get_mut(&tag)?;
+    if field.len() < count {
+        return None;
+    }
+    let values = field.iter().take(count).copied().collect::<Vec<u128>>();
+    let value = with(&values)?;
+    field.drain(0..count);
+    if field.is_empty() {
+        fields.remove(&tag);
+    }
+    Some(value)
+}
+
+fn take_flag(flags: &mut u128, flag: u128) -> bool {
+    let set = *flags & flag != 0;
+    *flags &= !flag;
+    set
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoincore_rpc::bitcoin::blockdata::script::Builder;
+
+    use super::*;
+
+    fn encode_varint(mut n: u128, buffer: &mut Vec<u8>) {
+        while n >> 7 > 0 {
+            buffer.push(n.to_le_bytes()[0] | 0b1000_0000);
+            n >>= 7;
+        }
+        buffer.push(n.to_le_bytes()[0]);
+    }
+
+    fn output(script: Script) -> TxOut {
+        TxOut {
+            value: 0,
+            script_pubkey: format!("0x{}", hex::encode(script.as_bytes())),
+        }
+    }
+
+    fn runestone_output(integers: &[u128]) -> TxOut {
+        let mut payload = vec![];
+        for integer in integers {
+            encode_varint(*integer, &mut payload);
+        }
+        output(
+            Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .push_opcode(MAGIC_NUMBER)
+                .push_slice(&payload)
+                .into_script(),
+        )
+    }
+
+    fn spendable_output() -> TxOut {
+        TxOut {
+            value: 10_000,
+            script_pubkey: "0x76a914000000000000000000000000000000000000000088ac".into(),
+        }
+    }
+
+    fn decipher(integers: &[u128]) -> Option<Artifact> {
+        Runestone::decipher(&[runestone_output(integers), spendable_output()])
+    }
+
+    #[test]
+    fn varint_round_trip() {
+        for n in [0, 1, 127, 128, 255, 16384, u64::MAX as u128, u128::MAX] {
+            let mut buffer = vec![];
+            encode_varint(n, &mut buffer);
+            assert_eq!(decode_varint(&buffer), Some((n, buffer.len())));
+        }
+        assert_eq!(decode_varint(&[0b1000_0000]), None);
+        assert_eq!(decode_varint(&[0xff; 19]), None);
+    }
+
+    #[test]
+    fn outputs_without_magic_number_are_ignored() {
+        assert_eq!(Runestone::decipher(&[spendable_output()]), None);
+        let op_return = output(
+            Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .push_slice(b"data")
+                .into_script(),
+        );
+        assert_eq!(Runestone::decipher(&[op_return]), None);
+    }
+
+    #[test]
+    fn decipher_etching() {
+        assert_eq!(
+            decipher(&[
+                tag::FLAGS,
+                flag::ETCHING | flag::TERMS,
+                tag::RUNE,
+                4,
+                tag::DIVISIBILITY,
+                2,
+                tag::SYMBOL,
+                'R' as u128,
+                tag::PREMINE,
+                1000,
+                tag::AMOUNT,
+                100,
+                tag::CAP,
+                10,
+                tag::OFFSET_END,
+                5,
+            ]),
+            Some(Artifact::Runestone(Runestone {
+                etching: Some(Etching {
+                    divisibility: Some(2),
+                    premine: Some(1000),
+                    rune: Some(Rune(4)),
+                    symbol: Some('R'),
+                    terms: Some(Terms {
+                        amount: Some(100),
+                        cap: Some(10),
+                        height: (None, None),
+                        offset: (None, Some(5)),
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn decipher_mint_edicts_and_pointer() {
+        assert_eq!(
+            decipher(&[
+                tag::MINT,
+                840000,
+                tag::MINT,
+                3,
+                tag::POINTER,
+                1,
+                tag::BODY,
+                840000,
+                3,
+                50,
+                1,
+                0,
+                2,
+                25,
+                2,
+            ]),
+            Some(Artifact::Runestone(Runestone {
+                edicts: vec![
+                    Edict {
+                        id: RuneId {
+                            block: 840000,
+                            tx: 3
+                        },
+                        amount: 50,
+                        output: 1,
+                    },
+                    Edict {
+                        id: RuneId {
+                            block: 840000,
+                            tx: 5
+                        },
+                        amount: 25,
+                        output: 2,
+                    },
+                ],
+                mint: Some(RuneId {
+                    block: 840000,
+                    tx: 3
+                }),
+                pointer: Some(1),
+                ..Default::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn decipher_cenotaphs() {
+        let flaw = |artifact: Option<Artifact>| match artifact {
+            Some(Artifact::Cenotaph(cenotaph)) => Some(cenotaph.flaw),
+            _ => None,
+        };
+        assert_eq!(flaw(decipher(&[tag::FLAGS])), Some(Flaw::TruncatedField));
+        assert_eq!(flaw(decipher(&[126, 0])), Some(Flaw::UnrecognizedEvenTag));
+        assert_eq!(
+            flaw(decipher(&[tag::FLAGS, 1 << 5])),
+            Some(Flaw::UnrecognizedFlag)
+        );
+        assert_eq!(
+            flaw(decipher(&[tag::BODY, 1, 1, 1])),
+            Some(Flaw::TrailingIntegers)
+        );
+        assert_eq!(
+            flaw(decipher(&[tag::BODY, 1, 1, 1, 3])),
+            Some(Flaw::EdictOutput)
+        );
+        assert_eq!(
+            flaw(decipher(&[tag::BODY, 0, 1, 1, 0])),
+            Some(Flaw::EdictRuneId)
+        );
+        assert_eq!(
+            flaw(decipher(&[
+                tag::FLAGS,
+                flag::ETCHING | flag::TERMS,
+                tag::PREMINE,
+                u128::MAX,
+                tag::CAP,
+                1,
+                tag::AMOUNT,
+                1,
+            ])),
+            Some(Flaw::SupplyOverflow)
+        );
+
+        // Cenotaphs keep the etched rune and the mint
+        assert_eq!(
+            decipher(&[
+                tag::FLAGS,
+                flag::ETCHING,
+                tag::RUNE,
+                4,
+                tag::MINT,
+                1,
+                tag::MINT,
+                0,
+                126,
+                0
+            ]),
+            Some(Artifact::Cenotaph(Cenotaph {
+                etching: Some(Rune(4)),
+                mint: Some(RuneId { block: 1, tx: 0 }),
+                flaw: Flaw::UnrecognizedEvenTag,
+            }))
+        );
+
+        let invalid_opcode = output(
+            Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .push_opcode(MAGIC_NUMBER)
+                .push_opcode(opcodes::all::OP_VERIFY)
+                .into_script(),
+        );
+        assert_eq!(
+            flaw(Runestone::decipher(&[invalid_opcode])),
+            Some(Flaw::Opcode)
+        );
+    }
+
+    #[test]
+    fn odd_tags_are_ignored() {
+        assert_eq!(
+            decipher(&[127, 1, tag::POINTER, 0]),
+            Some(Artifact::Runestone(Runestone {
+                pointer: Some(0),
+                ..Default::default()
+            }))
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::ops::{Add, AddAssign};
 
-use super::{epoch::Epoch, height::Height, *};
+use super::{epoch::Epoch, height::Height, rarity::Rarity, *};
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Ord, PartialOrd, Deserialize, Serialize)]
 #[serde(transparent)]
@@ -38,6 +38,10 @@ impl Sat {
         self.0 - self.epoch().starting_sat().0
     }
 
+    pub(crate) fn rarity(self) -> Rarity {
+        self.into()
+    }
+
     /// `Sat::rarity` is expensive and is called frequently when indexing.
     /// Sat::is_common only checks if self is `Rarity::Common` but is
     /// much faster.
@@ -0,0 +1,952 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bitcoincore_rpc::bitcoin::hashes::hex::FromHex;
+use bitcoincore_rpc::bitcoin::{Address, Network, Script};
+use chainhook_types::{
+    BitcoinBlockData, BitcoinNetwork, BitcoinTransactionData, RuneEtchingData, RuneMintData,
+    RuneOperation, RuneTransferData, TxIn, TxOut,
+};
+use hiro_system_kit::slog;
+use rusqlite::{Connection, OptionalExtension, Row};
+
+use crate::utils::Context;
+
+use super::db::HordDbError;
+use super::ord::rune::{Rune, RuneId};
+use super::ord::runestone::{is_op_return, Artifact, Runestone};
+
+/// Runestones are indexed from the activation height of the protocol.
+pub fn first_rune_height(network: &BitcoinNetwork) -> u64 {
+    match network {
+        BitcoinNetwork::Mainnet => 840_000,
+        BitcoinNetwork::Testnet => 2_583_205,
+        BitcoinNetwork::Regtest => 0,
+    }
+}
+
+/// Mint terms of an etched rune.
+struct RuneEntry {
+    block_height: u64,
+    cap: Option<u128>,
+    amount: Option<u128>,
+    height: (Option<u64>, Option<u64>),
+    offset: (Option<u64>, Option<u64>),
+}
+
+impl RuneEntry {
+    /// Amount minted at `block_height`, None if the rune can't be minted anymore.
+    fn mintable(&self, mints: u128, block_height: u64) -> Option<u128> {
+        let relative_start = self
+            .offset
+            .0
+            .map(|offset| self.block_height.saturating_add(offset));
+        let start = match (relative_start, self.height.0) {
+            (Some(relative), Some(absolute)) => Some(relative.max(absolute)),
+            (relative, absolute) => relative.or(absolute),
+        };
+        let relative_end = self
+            .offset
+            .1
+            .map(|offset| self.block_height.saturating_add(offset));
+        let end = match (relative_end, self.height.1) {
+            (Some(relative), Some(absolute)) => Some(relative.min(absolute)),
+            (relative, absolute) => relative.or(absolute),
+        };
+        if start.map(|start| block_height < start).unwrap_or(false)
+            || end.map(|end| block_height >= end).unwrap_or(false)
+            || mints >= self.cap.unwrap_or_default()
+        {
+            return None;
+        }
+        Some(self.amount.unwrap_or_default())
+    }
+}
+
+/// Applies the runestones of the block to the rune balances of the outpoints, and attaches the
+/// etchings, mints, transfers and burns to their transaction.
+/// Etched names are not checked against the unlocking schedule nor their commitment.
+pub fn update_runes_ledger_and_augment_bitcoin_block(
+    block: &mut BitcoinBlockData,
+    network: &BitcoinNetwork,
+    inscriptions_db_conn_rw: &Connection,
+    ctx: &Context,
+) -> Result<(), HordDbError> {
+    let block_height = block.block_identifier.index;
+    if block_height < first_rune_height(network) {
+        return Ok(());
+    }
+    let network = match network {
+        BitcoinNetwork::Mainnet => Network::Bitcoin,
+        BitcoinNetwork::Testnet => Network::Testnet,
+        BitcoinNetwork::Regtest => Network::Regtest,
+    };
+    for (tx_index, tx) in block.transactions.iter_mut().enumerate() {
+        let rune_operations = index_runes_in_transaction(
+            tx,
+            tx_index as u32,
+            block_height,
+            network,
+            inscriptions_db_conn_rw,
+        )?;
+        if !rune_operations.is_empty() {
+            ctx.try_log(|logger| {
+                slog::info!(
+                    logger,
+                    "{} rune operations detected in transaction {} (block {})",
+                    rune_operations.len(),
+                    tx.transaction_identifier.hash,
+                    block_height,
+                )
+            });
+        }
+        tx.metadata.rune_operations = rune_operations;
+    }
+    Ok(())
+}
+
+fn index_runes_in_transaction(
+    tx: &BitcoinTransactionData,
+    tx_index: u32,
+    block_height: u64,
+    network: Network,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<Vec<RuneOperation>, HordDbError> {
+    let tx_id = &tx.transaction_identifier.hash;
+    let outputs = &tx.metadata.outputs;
+    let artifact = Runestone::decipher(outputs);
+    let mut operations = vec![];
+
+    let mut unallocated =
+        spend_rune_balances(&tx.metadata.inputs, block_height, inscriptions_db_conn_rw)?;
+    let mut allocated: Vec<BTreeMap<RuneId, u128>> = vec![BTreeMap::new(); outputs.len()];
+
+    if let Some(artifact) = &artifact {
+        if let Some(id) = artifact.mint() {
+            if let Some(amount) = mint_rune(&id, block_height, tx_id, inscriptions_db_conn_rw)? {
+                let balance = unallocated.entry(id).or_default();
+                *balance = balance.saturating_add(amount);
+                operations.push(RuneOperation::RuneMinted(RuneMintData {
+                    rune_id: id.to_string(),
+                    amount,
+                }));
+            }
+        }
+
+        let etched = etched_rune(artifact, tx_index, block_height, inscriptions_db_conn_rw)?;
+
+        if let Artifact::Runestone(runestone) = artifact {
+            if let Some((id, _)) = etched {
+                let premine = runestone
+                    .etching
+                    .and_then(|etching| etching.premine)
+                    .unwrap_or_default();
+                let balance = unallocated.entry(id).or_default();
+                *balance = balance.saturating_add(premine);
+            }
+
+            for edict in runestone.edicts.iter() {
+                // The id 0:0 designates the rune etched by the transaction
+                let id = match (edict.id == RuneId::default(), etched) {
+                    (true, Some((id, _))) => id,
+                    (true, None) => continue,
+                    (false, _) => edict.id,
+                };
+                let balance = match unallocated.get_mut(&id) {
+                    Some(balance) => balance,
+                    None => continue,
+                };
+                let output = edict.output as usize;
+                if output == outputs.len() {
+                    let destinations = outputs
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, output)| !is_op_return(output))
+                        .map(|(vout, _)| vout)
+                        .collect::<Vec<usize>>();
+                    if destinations.is_empty() {
+                        continue;
+                    }
+                    if edict.amount == 0 {
+                        // The balance is split evenly between the outputs
+                        let amount = *balance / destinations.len() as u128;
+                        let remainder = (*balance % destinations.len() as u128) as usize;
+                        for (i, vout) in destinations.iter().enumerate() {
+                            let amount = if i < remainder { amount + 1 } else { amount };
+                            allocate(&mut allocated[*vout], balance, id, amount);
+                        }
+                    } else {
+                        for vout in destinations.iter() {
+                            let amount = edict.amount.min(*balance);
+                            allocate(&mut allocated[*vout], balance, id, amount);
+                        }
+                    }
+                } else {
+                    let amount = if edict.amount == 0 {
+                        *balance
+                    } else {
+                        edict.amount.min(*balance)
+                    };
+                    allocate(&mut allocated[output], balance, id, amount);
+                }
+            }
+        }
+
+        if let Some((id, rune)) = etched {
+            let etching = store_rune_etching(
+                &id,
+                rune,
+                artifact,
+                block_height,
+                tx_id,
+                inscriptions_db_conn_rw,
+            )?;
+            operations.push(RuneOperation::RuneEtched(etching));
+        }
+    }
+
+    let mut burned: BTreeMap<RuneId, u128> = BTreeMap::new();
+    if let Some(Artifact::Cenotaph(_)) = artifact {
+        for (id, balance) in unallocated.into_iter() {
+            if balance > 0 {
+                *burned.entry(id).or_default() += balance;
+            }
+        }
+    } else {
+        // Runes left unallocated go to the pointed output, or to the first output that is
+        // not an OP_RETURN
+        let pointer = match &artifact {
+            Some(Artifact::Runestone(runestone)) => runestone.pointer.map(|vout| vout as usize),
+            _ => None,
+        };
+        let vout = pointer.or_else(|| outputs.iter().position(|output| !is_op_return(output)));
+        for (id, balance) in unallocated.into_iter() {
+            if balance == 0 {
+                continue;
+            }
+            match vout {
+                Some(vout) => {
+                    let allocation = allocated[vout].entry(id).or_default();
+                    *allocation = allocation.saturating_add(balance);
+                }
+                None => *burned.entry(id).or_default() += balance,
+            }
+        }
+    }
+
+    for (vout, balances) in allocated.into_iter().enumerate() {
+        if balances.is_empty() {
+            continue;
+        }
+        if is_op_return(&outputs[vout]) {
+            for (id, amount) in balances.into_iter() {
+                *burned.entry(id).or_default() += amount;
+            }
+            continue;
+        }
+        let outpoint = format!("{}:{}", &tx_id[2..], vout);
+        let address = output_address(&outputs[vout], network);
+        for (id, amount) in balances.into_iter() {
+            let transfer = RuneTransferData {
+                rune_id: id.to_string(),
+                amount,
+                outpoint: outpoint.clone(),
+                address: address.clone(),
+            };
+            insert_rune_balance(&transfer, block_height, tx_id, inscriptions_db_conn_rw)?;
+            operations.push(RuneOperation::RuneTransferred(transfer));
+        }
+    }
+
+    for (id, amount) in burned.into_iter() {
+        let burn = RuneMintData {
+            rune_id: id.to_string(),
+            amount,
+        };
+        inscriptions_db_conn_rw
+            .execute(
+                "INSERT INTO rune_burns (rune_id, amount, block_height, tx_id) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![&burn.rune_id, &amount.to_string(), &block_height, &tx_id],
+            )
+            .map_err(|e| HordDbError::Sqlite("unable to insert rune burn".into(), e))?;
+        operations.push(RuneOperation::RuneBurned(burn));
+    }
+
+    Ok(operations)
+}
+
+fn allocate(allocation: &mut BTreeMap<RuneId, u128>, balance: &mut u128, id: RuneId, amount: u128) {
+    if amount > 0 {
+        *balance -= amount;
+        *allocation.entry(id).or_default() += amount;
+    }
+}
+
+fn output_address(output: &TxOut, network: Network) -> Option<String> {
+    let script = Script::from_hex(output.get_script_pubkey_hex()).ok()?;
+    Address::from_script(&script, network)
+        .ok()
+        .map(|address| address.to_string())
+}
+
+/// Marks the rune balances of the outpoints spent by `inputs` as spent, and returns their sum.
+fn spend_rune_balances(
+    inputs: &[TxIn],
+    block_height: u64,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<BTreeMap<RuneId, u128>, HordDbError> {
+    let mut unallocated: BTreeMap<RuneId, u128> = BTreeMap::new();
+    for input in inputs.iter() {
+        let txid = &input.previous_output.txid;
+        let outpoint = format!(
+            "{}:{}",
+            txid.strip_prefix("0x").unwrap_or(txid),
+            input.previous_output.vout
+        );
+        for (rune_id, amount) in find_rune_balances_at_outpoint(&outpoint, inscriptions_db_conn_rw)?
+        {
+            let id = rune_id.parse::<RuneId>().map_err(HordDbError::Corrupted)?;
+            let balance = unallocated.entry(id).or_default();
+            *balance = balance.saturating_add(amount);
+        }
+        inscriptions_db_conn_rw
+            .execute(
+                "UPDATE rune_balances SET spent_block_height = ?1 WHERE outpoint = ?2 AND spent_block_height IS NULL",
+                rusqlite::params![&block_height, &outpoint],
+            )
+            .map_err(|e| HordDbError::Sqlite("unable to spend rune balances".into(), e))?;
+    }
+    Ok(unallocated)
+}
+
+fn mint_rune(
+    id: &RuneId,
+    block_height: u64,
+    tx_id: &str,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<Option<u128>, HordDbError> {
+    let rune_id = id.to_string();
+    let entry = inscriptions_db_conn_rw
+        .query_row(
+            "SELECT block_height, cap, amount, height_start, height_end, offset_start, offset_end FROM runes WHERE rune_id = ?",
+            rusqlite::params![&rune_id],
+            |row| {
+                Ok(RuneEntry {
+                    block_height: row.get(0)?,
+                    cap: get_optional_amount(row, 1)?,
+                    amount: get_optional_amount(row, 2)?,
+                    height: (row.get(3)?, row.get(4)?),
+                    offset: (row.get(5)?, row.get(6)?),
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| HordDbError::Sqlite("unable to query runes table".into(), e))?;
+    let entry = match entry {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    let mints: u64 = inscriptions_db_conn_rw
+        .query_row(
+            "SELECT COUNT(*) FROM rune_mints WHERE rune_id = ?",
+            rusqlite::params![&rune_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to query rune_mints table".into(), e))?;
+    let amount = match entry.mintable(u128::from(mints), block_height) {
+        Some(amount) => amount,
+        None => return Ok(None),
+    };
+    inscriptions_db_conn_rw
+        .execute(
+            "INSERT INTO rune_mints (rune_id, amount, block_height, tx_id) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![&rune_id, &amount.to_string(), &block_height, &tx_id],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to insert rune mint".into(), e))?;
+    Ok(Some(amount))
+}
+
+/// Id and name of the rune etched by the transaction, if valid. Etchings without a name get
+/// a reserved one, derived from their id.
+fn etched_rune(
+    artifact: &Artifact,
+    tx_index: u32,
+    block_height: u64,
+    inscriptions_db_conn: &Connection,
+) -> Result<Option<(RuneId, Rune)>, HordDbError> {
+    let rune = match artifact {
+        Artifact::Runestone(runestone) => match runestone.etching {
+            Some(etching) => etching.rune,
+            None => return Ok(None),
+        },
+        Artifact::Cenotaph(cenotaph) => match cenotaph.etching {
+            Some(rune) => Some(rune),
+            None => return Ok(None),
+        },
+    };
+    let rune = match rune {
+        Some(rune) => {
+            let name_taken = inscriptions_db_conn
+                .query_row(
+                    "SELECT rune_id FROM runes WHERE name = ?",
+                    rusqlite::params![&rune.to_string()],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()
+                .map_err(|e| HordDbError::Sqlite("unable to query runes table".into(), e))?
+                .is_some();
+            if rune.is_reserved() || name_taken {
+                return Ok(None);
+            }
+            rune
+        }
+        None => Rune::reserved(block_height, tx_index),
+    };
+    Ok(Some((
+        RuneId {
+            block: block_height,
+            tx: tx_index,
+        },
+        rune,
+    )))
+}
+
+fn store_rune_etching(
+    id: &RuneId,
+    rune: Rune,
+    artifact: &Artifact,
+    block_height: u64,
+    tx_id: &str,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<RuneEtchingData, HordDbError> {
+    // Runes etched by cenotaphs have no supply
+    let (etching, cenotaph) = match artifact {
+        Artifact::Runestone(runestone) => (runestone.etching.unwrap_or_default(), false),
+        Artifact::Cenotaph(_) => (Default::default(), true),
+    };
+    let terms = etching.terms.unwrap_or_default();
+    let spacers = etching.spacers.unwrap_or_default();
+    let data = RuneEtchingData {
+        rune_id: id.to_string(),
+        name: rune.to_string(),
+        spaced_name: rune.spaced(spacers),
+        divisibility: etching.divisibility.unwrap_or_default(),
+        symbol: etching.symbol.map(|symbol| symbol.to_string()),
+        premine: etching.premine.unwrap_or_default(),
+        cap: terms.cap,
+        amount: terms.amount,
+        height_start: terms.height.0,
+        height_end: terms.height.1,
+        offset_start: terms.offset.0,
+        offset_end: terms.offset.1,
+        turbo: etching.turbo,
+        cenotaph,
+    };
+    inscriptions_db_conn_rw
+        .execute(
+            "INSERT INTO runes (rune_id, name, spacers, divisibility, symbol, premine, cap, amount, height_start, height_end, offset_start, offset_end, turbo, cenotaph, block_height, tx_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            rusqlite::params![
+                &data.rune_id,
+                &data.name,
+                &spacers,
+                &data.divisibility,
+                &data.symbol,
+                &data.premine.to_string(),
+                &data.cap.map(|cap| cap.to_string()),
+                &data.amount.map(|amount| amount.to_string()),
+                &data.height_start,
+                &data.height_end,
+                &data.offset_start,
+                &data.offset_end,
+                &data.turbo,
+                &data.cenotaph,
+                &block_height,
+                &tx_id
+            ],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to insert rune".into(), e))?;
+    Ok(data)
+}
+
+fn insert_rune_balance(
+    transfer: &RuneTransferData,
+    block_height: u64,
+    tx_id: &str,
+    inscriptions_db_conn_rw: &Connection,
+) -> Result<(), HordDbError> {
+    inscriptions_db_conn_rw
+        .execute(
+            "INSERT INTO rune_balances (outpoint, rune_id, amount, address, block_height, tx_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                &transfer.outpoint,
+                &transfer.rune_id,
+                &transfer.amount.to_string(),
+                &transfer.address,
+                &block_height,
+                &tx_id
+            ],
+        )
+        .map_err(|e| HordDbError::Sqlite("unable to insert rune balance".into(), e))?;
+    Ok(())
+}
+
+fn get_amount(row: &Row, index: usize) -> rusqlite::Result<u128> {
+    let amount: String = row.get(index)?;
+    amount.parse::<u128>().map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+fn get_optional_amount(row: &Row, index: usize) -> rusqlite::Result<Option<u128>> {
+    match row.get::<_, Option<String>>(index)? {
+        Some(_) => get_amount(row, index).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Reads the columns of the runes table, starting at column `first`.
+fn rune_etching_from_row(row: &Row, first: usize) -> rusqlite::Result<RuneEtchingData> {
+    let name: String = row.get(first + 1)?;
+    let spacers: u32 = row.get(first + 2)?;
+    let spaced_name = match name.parse::<Rune>() {
+        Ok(rune) => rune.spaced(spacers),
+        Err(_) => name.clone(),
+    };
+    Ok(RuneEtchingData {
+        rune_id: row.get(first)?,
+        name,
+        spaced_name,
+        divisibility: row.get(first + 3)?,
+        symbol: row.get(first + 4)?,
+        premine: get_amount(row, first + 5)?,
+        cap: get_optional_amount(row, first + 6)?,
+        amount: get_optional_amount(row, first + 7)?,
+        height_start: row.get(first + 8)?,
+        height_end: row.get(first + 9)?,
+        offset_start: row.get(first + 10)?,
+        offset_end: row.get(first + 11)?,
+        turbo: row.get(first + 12)?,
+        cenotaph: row.get(first + 13)?,
+    })
+}
+
+pub fn find_rune_with_id(
+    rune_id: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<Option<RuneEtchingData>, HordDbError> {
+    inscriptions_db_conn
+        .query_row(
+            "SELECT rune_id, name, spacers, divisibility, symbol, premine, cap, amount, height_start, height_end, offset_start, offset_end, turbo, cenotaph FROM runes WHERE rune_id = ?",
+            rusqlite::params![&rune_id],
+            |row| rune_etching_from_row(row, 0),
+        )
+        .optional()
+        .map_err(|e| HordDbError::Sqlite("unable to query runes table".into(), e))
+}
+
+/// Unspent rune balances (rune_id, amount) of the outpoint.
+pub fn find_rune_balances_at_outpoint(
+    outpoint: &str,
+    inscriptions_db_conn: &Connection,
+) -> Result<Vec<(String, u128)>, HordDbError> {
+    let mut stmt = inscriptions_db_conn
+        .prepare("SELECT rune_id, amount FROM rune_balances WHERE outpoint = ? AND spent_block_height IS NULL ORDER BY rowid ASC")
+        .map_err(|e| HordDbError::Sqlite("unable to query rune_balances table".into(), e))?;
+    let rows = stmt
+        .query_map(rusqlite::params![&outpoint], |row| {
+            Ok((row.get(0)?, get_amount(row, 1)?))
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| HordDbError::Sqlite("unable to query rune_balances table".into(), e))?;
+    Ok(rows)
+}
+
+/// Rune operations applied by the block, keyed by transaction hash, e.g. for evaluating
+/// predicates against blocks indexed earlier.
+pub fn find_rune_operations_at_block_height(
+    block_height: u64,
+    inscriptions_db_conn: &Connection,
+) -> Result<HashMap<String, Vec<RuneOperation>>, HordDbError> {
+    let mut operations: HashMap<String, Vec<RuneOperation>> = HashMap::new();
+    let queries: [(&str, fn(&Row) -> rusqlite::Result<RuneOperation>); 4] = [
+        (
+            "SELECT tx_id, rune_id, amount FROM rune_mints WHERE block_height = ? ORDER BY rowid ASC",
+            |row| {
+                Ok(RuneOperation::RuneMinted(RuneMintData {
+                    rune_id: row.get(1)?,
+                    amount: get_amount(row, 2)?,
+                }))
+            },
+        ),
+        (
+            "SELECT tx_id, rune_id, name, spacers, divisibility, symbol, premine, cap, amount, height_start, height_end, offset_start, offset_end, turbo, cenotaph FROM runes WHERE block_height = ? ORDER BY rowid ASC",
+            |row| Ok(RuneOperation::RuneEtched(rune_etching_from_row(row, 1)?)),
+        ),
+        (
+            "SELECT tx_id, rune_id, amount, outpoint, address FROM rune_balances WHERE block_height = ? ORDER BY rowid ASC",
+            |row| {
+                Ok(RuneOperation::RuneTransferred(RuneTransferData {
+                    rune_id: row.get(1)?,
+                    amount: get_amount(row, 2)?,
+                    outpoint: row.get(3)?,
+                    address: row.get(4)?,
+                }))
+            },
+        ),
+        (
+            "SELECT tx_id, rune_id, amount FROM rune_burns WHERE block_height = ? ORDER BY rowid ASC",
+            |row| {
+                Ok(RuneOperation::RuneBurned(RuneMintData {
+                    rune_id: row.get(1)?,
+                    amount: get_amount(row, 2)?,
+                }))
+            },
+        ),
+    ];
+    for (query, operation_from_row) in queries.into_iter() {
+        let mut stmt = inscriptions_db_conn
+            .prepare(query)
+            .map_err(|e| HordDbError::Sqlite("unable to query runes tables".into(), e))?;
+        let rows = stmt
+            .query_map(rusqlite::params![&block_height], |row| {
+                Ok((row.get::<_, String>(0)?, operation_from_row(row)?))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| HordDbError::Sqlite("unable to query runes tables".into(), e))?;
+        for (tx_id, operation) in rows.into_iter() {
+            operations.entry(tx_id).or_default().push(operation);
+        }
+    }
+    Ok(operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoincore_rpc::bitcoin::blockdata::{opcodes, script::Builder};
+    use chainhook_types::{
+        BitcoinBlockMetadata, BitcoinTransactionMetadata, BlockIdentifier, OutPoint,
+        TransactionIdentifier,
+    };
+
+    use super::*;
+    use crate::hord::db::{delete_runes_entries_in_block_range, migrate_hord_db};
+
+    // Runestone tags and flags
+    const BODY: u128 = 0;
+    const FLAGS: u128 = 2;
+    const RUNE: u128 = 4;
+    const PREMINE: u128 = 6;
+    const CAP: u128 = 8;
+    const AMOUNT: u128 = 10;
+    const MINT: u128 = 20;
+    const ETCHING: u128 = 1 << 0;
+    const TERMS: u128 = 1 << 1;
+
+    fn runestone_output(integers: &[u128]) -> TxOut {
+        let mut payload = vec![];
+        for integer in integers {
+            let mut n = *integer;
+            while n >> 7 > 0 {
+                payload.push(n.to_le_bytes()[0] | 0b1000_0000);
+                n >>= 7;
+            }
+            payload.push(n.to_le_bytes()[0]);
+        }
+        let script = Builder::new()
+            .push_opcode(opcodes::all::OP_RETURN)
+            .push_opcode(opcodes::all::OP_PUSHNUM_13)
+            .push_slice(&payload)
+            .into_script();
+        TxOut {
+            value: 0,
+            script_pubkey: format!("0x{}", hex::encode(script.as_bytes())),
+        }
+    }
+
+    fn spendable_output() -> TxOut {
+        TxOut {
+            value: 10_000,
+            script_pubkey: "0x76a914000000000000000000000000000000000000000088ac".into(),
+        }
+    }
+
+    fn tx_id(height: u64, index: u64) -> String {
+        format!("0x{:064x}", height * 1000 + index)
+    }
+
+    fn input(height: u64, index: u64, vout: u32) -> TxIn {
+        TxIn {
+            previous_output: OutPoint {
+                txid: tx_id(height, index),
+                vout,
+                value: 10_000,
+                block_height: height,
+            },
+            script_sig: String::new(),
+            sequence: 0,
+            witness: vec![],
+        }
+    }
+
+    fn outpoint(height: u64, index: u64, vout: u32) -> String {
+        format!("{}:{}", &tx_id(height, index)[2..], vout)
+    }
+
+    fn block(height: u64, transactions: Vec<(Vec<TxIn>, Vec<TxOut>)>) -> BitcoinBlockData {
+        // The first transaction is the coinbase
+        let transactions = [(vec![], vec![spendable_output()])]
+            .into_iter()
+            .chain(transactions.into_iter())
+            .enumerate()
+            .map(|(i, (inputs, outputs))| BitcoinTransactionData {
+                transaction_identifier: TransactionIdentifier {
+                    hash: tx_id(height, i as u64),
+                },
+                operations: vec![],
+                metadata: BitcoinTransactionMetadata {
+                    inputs,
+                    outputs,
+                    stacks_operations: vec![],
+                    ordinal_operations: vec![],
+                    brc20_operations: vec![],
+                    rune_operations: vec![],
+                    stamp_operations: vec![],
+                    sat_movements: vec![],
+                    taproot_spends: vec![],
+                    proof: None,
+                    fee: 0,
+                    vsize: 0,
+                    fee_rate: 0.0,
+                },
+            })
+            .collect();
+        BitcoinBlockData {
+            block_identifier: BlockIdentifier {
+                index: height,
+                hash: format!("0x{:064x}", height),
+            },
+            parent_block_identifier: BlockIdentifier {
+                index: height - 1,
+                hash: format!("0x{:064x}", height - 1),
+            },
+            timestamp: 0,
+            transactions,
+            metadata: BitcoinBlockMetadata {},
+        }
+    }
+
+    fn index(block: &mut BitcoinBlockData, conn: &Connection) -> Vec<Vec<RuneOperation>> {
+        update_runes_ledger_and_augment_bitcoin_block(
+            block,
+            &BitcoinNetwork::Regtest,
+            conn,
+            &Context::empty(),
+        )
+        .unwrap();
+        block
+            .transactions
+            .iter()
+            .map(|tx| tx.metadata.rune_operations.clone())
+            .collect()
+    }
+
+    fn transferred(rune_id: &str, amount: u128, outpoint: String) -> RuneOperation {
+        RuneOperation::RuneTransferred(RuneTransferData {
+            rune_id: rune_id.into(),
+            amount,
+            outpoint,
+            address: Some("mfWxJ45yp2SFn7UciZyNpvDKrzbhyfKrY8".into()),
+        })
+    }
+
+    #[test]
+    fn mintable() {
+        let entry = RuneEntry {
+            block_height: 10,
+            cap: Some(2),
+            amount: Some(100),
+            height: (Some(12), Some(20)),
+            offset: (None, Some(5)),
+        };
+        assert_eq!(entry.mintable(0, 11), None);
+        assert_eq!(entry.mintable(0, 12), Some(100));
+        assert_eq!(entry.mintable(1, 14), Some(100));
+        assert_eq!(entry.mintable(1, 15), None);
+        assert_eq!(entry.mintable(2, 12), None);
+    }
+
+    #[test]
+    fn ledger_tracks_etchings_mints_transfers_and_burns() {
+        let ctx = Context::empty();
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_hord_db(&conn, &ctx).unwrap();
+
+        let mut first_block = block(
+            1,
+            vec![(
+                vec![],
+                vec![
+                    runestone_output(&[
+                        FLAGS,
+                        ETCHING | TERMS,
+                        RUNE,
+                        4,
+                        PREMINE,
+                        1000,
+                        AMOUNT,
+                        100,
+                        CAP,
+                        2,
+                    ]),
+                    spendable_output(),
+                ],
+            )],
+        );
+        let operations = index(&mut first_block, &conn);
+        assert_eq!(operations[0], vec![]);
+        assert_eq!(
+            operations[1],
+            vec![
+                RuneOperation::RuneEtched(RuneEtchingData {
+                    rune_id: "1:1".into(),
+                    name: "E".into(),
+                    spaced_name: "E".into(),
+                    divisibility: 0,
+                    symbol: None,
+                    premine: 1000,
+                    cap: Some(2),
+                    amount: Some(100),
+                    height_start: None,
+                    height_end: None,
+                    offset_start: None,
+                    offset_end: None,
+                    turbo: false,
+                    cenotaph: false,
+                }),
+                transferred("1:1", 1000, outpoint(1, 1, 1)),
+            ]
+        );
+
+        // Mints are added to the balances of the inputs, and distributed by the edicts
+        let mut second_block = block(
+            2,
+            vec![
+                (
+                    vec![input(1, 1, 1)],
+                    vec![
+                        runestone_output(&[MINT, 1, MINT, 1, BODY, 1, 1, 300, 2]),
+                        spendable_output(),
+                        spendable_output(),
+                    ],
+                ),
+                (
+                    vec![],
+                    vec![runestone_output(&[MINT, 1, MINT, 1]), spendable_output()],
+                ),
+            ],
+        );
+        let operations = index(&mut second_block, &conn);
+        assert_eq!(
+            operations[1],
+            vec![
+                RuneOperation::RuneMinted(RuneMintData {
+                    rune_id: "1:1".into(),
+                    amount: 100,
+                }),
+                transferred("1:1", 800, outpoint(2, 1, 1)),
+                transferred("1:1", 300, outpoint(2, 1, 2)),
+            ]
+        );
+        assert_eq!(operations[2].len(), 2);
+        assert_eq!(
+            find_rune_balances_at_outpoint(&outpoint(1, 1, 1), &conn).unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            find_rune_balances_at_outpoint(&outpoint(2, 1, 2), &conn).unwrap(),
+            vec![("1:1".to_string(), 300)]
+        );
+
+        // The cap is reached, runes sent to an OP_RETURN or spent by a cenotaph are burned
+        let mut third_block = block(
+            3,
+            vec![
+                (
+                    vec![input(2, 1, 2)],
+                    vec![
+                        runestone_output(&[MINT, 1, MINT, 1, BODY, 1, 1, 0, 0]),
+                        spendable_output(),
+                    ],
+                ),
+                (
+                    vec![input(2, 1, 1)],
+                    vec![runestone_output(&[126, 0]), spendable_output()],
+                ),
+            ],
+        );
+        let operations = index(&mut third_block, &conn);
+        let burned = |amount| {
+            vec![RuneOperation::RuneBurned(RuneMintData {
+                rune_id: "1:1".into(),
+                amount,
+            })]
+        };
+        assert_eq!(operations[1], burned(300));
+        assert_eq!(operations[2], burned(800));
+        assert_eq!(
+            find_rune_operations_at_block_height(3, &conn).unwrap(),
+            HashMap::from([(tx_id(3, 1), burned(300)), (tx_id(3, 2), burned(800))])
+        );
+
+        // Reverting the block restores the balances it spent
+        delete_runes_entries_in_block_range(3, 3, &conn, &ctx).unwrap();
+        assert!(find_rune_operations_at_block_height(3, &conn)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            find_rune_balances_at_outpoint(&outpoint(2, 1, 1), &conn).unwrap(),
+            vec![("1:1".to_string(), 800)]
+        );
+        assert_eq!(
+            find_rune_with_id("1:1", &conn)
+                .unwrap()
+                .map(|rune| rune.premine),
+            Some(1000)
+        );
+    }
+
+    #[test]
+    fn edicts_split_balances_between_outputs() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_hord_db(&conn, &Context::empty()).unwrap();
+
+        // The id 0:0 targets the etched rune, and output 3 splits it between the outputs
+        let mut block = block(
+            1,
+            vec![(
+                vec![],
+                vec![
+                    spendable_output(),
+                    runestone_output(&[FLAGS, ETCHING, PREMINE, 1000, BODY, 0, 0, 0, 3]),
+                    spendable_output(),
+                ],
+            )],
+        );
+        let operations = index(&mut block, &conn);
+        assert!(matches!(
+            &operations[1][0],
+            RuneOperation::RuneEtched(etching) if etching.name == Rune::reserved(1, 1).to_string()
+        ));
+        assert_eq!(
+            operations[1][1..].to_vec(),
+            vec![
+                transferred("1:1", 500, outpoint(1, 1, 0)),
+                transferred("1:1", 500, outpoint(1, 1, 2)),
+            ]
+        );
+        assert_eq!(
+            find_rune_operations_at_block_height(1, &conn).unwrap(),
+            HashMap::from([(tx_id(1, 1), operations[1].clone())])
+        );
+    }
+}
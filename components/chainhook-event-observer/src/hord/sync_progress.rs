@@ -0,0 +1,209 @@
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+
+use crossbeam_channel::{Receiver, Sender};
+
+/// Snapshot of the progress of a hord db sync.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SyncProgress {
+    pub start_block: u64,
+    /// Height the sync is catching up with.
+    pub end_block: u64,
+    /// Blocks downloaded and stored so far, in any order.
+    pub blocks_stored: u64,
+    /// Blocks of the contiguous range completed from `start_block`.
+    pub blocks_processed: u64,
+    pub blocks_to_process: u64,
+    /// Last block of the contiguous range completed, if any.
+    pub current_height: Option<u64>,
+    /// Blocks processed per second since the start of the sync.
+    pub blocks_per_second: f64,
+    /// Estimated time left, in seconds, unknown until a first block is processed.
+    pub eta_seconds: Option<u64>,
+    pub completed: bool,
+}
+
+impl SyncProgress {
+    fn new(start_block: u64, end_block: u64) -> SyncProgress {
+        SyncProgress {
+            start_block,
+            end_block,
+            blocks_stored: 0,
+            blocks_processed: 0,
+            blocks_to_process: end_block - start_block + 1,
+            current_height: None,
+            blocks_per_second: 0.0,
+            eta_seconds: None,
+            completed: false,
+        }
+    }
+
+    fn update_rate(&mut self, elapsed_seconds: f64) {
+        if self.blocks_processed == 0 || elapsed_seconds <= 0.0 {
+            return;
+        }
+        self.blocks_per_second = self.blocks_processed as f64 / elapsed_seconds;
+        let blocks_left = self.blocks_to_process - self.blocks_processed;
+        self.eta_seconds = Some((blocks_left as f64 / self.blocks_per_second).ceil() as u64);
+    }
+}
+
+impl fmt::Display for SyncProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} blocks processed ({} stored), {:.1} blocks/s",
+            self.blocks_processed,
+            self.blocks_to_process,
+            self.blocks_stored,
+            self.blocks_per_second
+        )?;
+        if let Some(current_height) = self.current_height {
+            write!(f, ", at block #{current_height} of #{}", self.end_block)?;
+        }
+        if let Some(eta_seconds) = self.eta_seconds {
+            write!(
+                f,
+                ", ETA {}h{:02}m{:02}s",
+                eta_seconds / 3600,
+                eta_seconds % 3600 / 60,
+                eta_seconds % 60
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    progress: Option<SyncProgress>,
+    started_at: Option<Instant>,
+}
+
+/// Progress of the latest hord db sync, shared between the sync and its observers: it is
+/// either polled with `progress`, or pushed to the channels returned by `subscribe` on each
+/// update.
+#[derive(Debug, Default)]
+pub struct SyncProgressTracker {
+    state: Mutex<TrackerState>,
+    subscribers: Mutex<Vec<Sender<SyncProgress>>>,
+}
+
+impl SyncProgressTracker {
+    fn state(&self) -> MutexGuard<TrackerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Progress of the latest sync, `None` until a sync is started.
+    pub fn progress(&self) -> Option<SyncProgress> {
+        self.state().progress.clone()
+    }
+
+    /// Receives the progress of the syncs on each update. Subscribers are dropped once their
+    /// receiver is.
+    pub fn subscribe(&self) -> Receiver<SyncProgress> {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(tx);
+        rx
+    }
+
+    pub fn start(&self, start_block: u64, end_block: u64) {
+        let progress = SyncProgress::new(start_block, end_block);
+        *self.state() = TrackerState {
+            progress: Some(progress.clone()),
+            started_at: Some(Instant::now()),
+        };
+        self.publish(progress);
+    }
+
+    /// Records `blocks_stored` and the last block of the contiguous range completed.
+    pub fn update(&self, blocks_stored: u64, last_completed_block: Option<u64>) {
+        let progress = {
+            let mut state = self.state();
+            let elapsed_seconds = state
+                .started_at
+                .map_or(0.0, |started_at| started_at.elapsed().as_secs_f64());
+            let progress = match state.progress.as_mut() {
+                Some(progress) => progress,
+                None => return,
+            };
+            progress.blocks_stored = blocks_stored;
+            progress.current_height = last_completed_block;
+            progress.blocks_processed =
+                last_completed_block.map_or(0, |height| height + 1 - progress.start_block);
+            progress.update_rate(elapsed_seconds);
+            progress.clone()
+        };
+        self.publish(progress);
+    }
+
+    pub fn complete(&self) {
+        let progress = {
+            let mut state = self.state();
+            let progress = match state.progress.as_mut() {
+                Some(progress) => progress,
+                None => return,
+            };
+            progress.completed = true;
+            progress.eta_seconds = Some(0);
+            progress.clone()
+        };
+        self.publish(progress);
+    }
+
+    fn publish(&self, progress: SyncProgress) {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|tx| tx.send(progress.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_time_left() {
+        let mut progress = SyncProgress::new(100, 199);
+        progress.update_rate(5.0);
+        assert_eq!(progress.eta_seconds, None);
+
+        progress.blocks_processed = 10;
+        progress.update_rate(5.0);
+        assert_eq!(progress.blocks_per_second, 2.0);
+        assert_eq!(progress.eta_seconds, Some(45));
+        progress.current_height = Some(109);
+        assert_eq!(
+            progress.to_string(),
+            "10/100 blocks processed (0 stored), 2.0 blocks/s, at block #109 of #199, ETA 0h00m45s"
+        );
+    }
+
+    #[test]
+    fn publish_updates_to_subscribers() {
+        let tracker = SyncProgressTracker::default();
+        assert_eq!(tracker.progress(), None);
+        let rx = tracker.subscribe();
+        let dropped_rx = tracker.subscribe();
+        drop(dropped_rx);
+
+        tracker.start(100, 199);
+        tracker.update(3, None);
+        tracker.update(5, Some(101));
+        tracker.complete();
+
+        let updates = rx.try_iter().collect::<Vec<_>>();
+        assert_eq!(updates.len(), 4);
+        assert_eq!(updates[1].blocks_stored, 3);
+        assert_eq!(updates[2].blocks_processed, 2);
+        assert_eq!(updates[2].current_height, Some(101));
+        assert!(updates[3].completed);
+        assert_eq!(tracker.progress(), Some(updates[3].clone()));
+        assert_eq!(tracker.subscribers.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::BuildHasherDefault;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use fxhash::FxHasher;
+
+use super::db::{
+    txid_cache_key, txid_identifies, BlockStore, HordDbError, LazyBlockTransaction,
+    LazyBlockTransactionInput, TraversalsCache,
+};
+
+/// Memory budget of the traversals cache, unless configured otherwise.
+pub const DEFAULT_TRAVERSALS_CACHE_SIZE: usize = 512 * 1024 * 1024;
+/// Entries are spread over several shards, each with its own lock and its own share of the
+/// budget, for concurrent traversals not to contend on a single lock.
+const SHARDS: usize = 16;
+/// Estimated bookkeeping of an entry (key, hashmap and usage index slots).
+const ENTRY_OVERHEAD: usize = 64;
+/// Transactions waiting to be persisted, beyond which new ones are only kept in memory.
+const MAX_UNPERSISTED_TRANSACTIONS: usize = 500_000;
+/// Blocks whose persisted transactions are loaded when warming up the cache (about a week).
+pub const TRAVERSALS_CACHE_WARMING_DEPTH: u32 = 1008;
+
+type CacheKey = (u32, [u8; 8]);
+
+struct CachedTransaction {
+    transaction: LazyBlockTransaction,
+    size: usize,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Shard {
+    transactions: HashMap<CacheKey, CachedTransaction, BuildHasherDefault<FxHasher>>,
+    // Keys by last use, least recently used first
+    usage: BTreeMap<u64, CacheKey>,
+    clock: u64,
+    size: usize,
+}
+
+impl Shard {
+    fn touch(&mut self, key: &CacheKey) -> Option<&CachedTransaction> {
+        self.clock += 1;
+        let clock = self.clock;
+        let cached = self.transactions.get_mut(key)?;
+        self.usage.remove(&cached.last_used);
+        self.usage.insert(clock, *key);
+        cached.last_used = clock;
+        Some(cached)
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(cached) = self.transactions.remove(key) {
+            self.usage.remove(&cached.last_used);
+            self.size -= cached.size;
+        }
+    }
+}
+
+/// Transactions decoded by previous traversals, the least recently used ones being evicted
+/// once their estimated size exceeds the memory budget of the cache.
+pub struct TraversalsLruCache {
+    shards: Vec<Mutex<Shard>>,
+    max_shard_size: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    // Inserted through a `PersistedTraversalsCache` since the last `persist`
+    unpersisted: Mutex<Vec<(u32, LazyBlockTransaction)>>,
+}
+
+/// Snapshot of the occupancy and of the efficiency of a `TraversalsLruCache`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraversalsCacheStats {
+    pub entries: usize,
+    /// Estimated size of the cached transactions, in bytes.
+    pub size: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl fmt::Display for TraversalsCacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lookups = self.hits + self.misses;
+        let hit_rate = match lookups {
+            0 => 0.0,
+            _ => self.hits as f64 * 100.0 / lookups as f64,
+        };
+        write!(
+            f,
+            "{} entries, {} MB, {} hits, {} misses ({:.1}% hit rate)",
+            self.entries,
+            self.size / (1024 * 1024),
+            self.hits,
+            self.misses,
+            hit_rate
+        )
+    }
+}
+
+impl TraversalsLruCache {
+    /// `max_size` is the memory budget of the cache, in bytes.
+    pub fn new(max_size: usize) -> TraversalsLruCache {
+        TraversalsLruCache {
+            shards: (0..SHARDS).map(|_| Mutex::new(Shard::default())).collect(),
+            max_shard_size: max_size / SHARDS,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            unpersisted: Mutex::new(vec![]),
+        }
+    }
+
+    fn shard(&self, key: &CacheKey) -> MutexGuard<Shard> {
+        let index = (u64::from_le_bytes(key.1) ^ key.0 as u64) as usize % SHARDS;
+        self.shards[index].lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                shard
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .transactions
+                    .len()
+            })
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the cached transactions, e.g. when the blocks they belong to are rolled back.
+    /// Hits and misses are kept.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            *shard.lock().unwrap_or_else(|e| e.into_inner()) = Shard::default();
+        }
+    }
+
+    /// Writes the transactions decoded since the last call to `blocks_db`, for restarts and
+    /// later traversals not to decode them again. Returns the number of transactions written.
+    pub fn persist(&self, blocks_db: &(impl BlockStore + ?Sized)) -> Result<usize, HordDbError> {
+        let transactions =
+            std::mem::take(&mut *self.unpersisted.lock().unwrap_or_else(|e| e.into_inner()));
+        blocks_db.put_traversal_transactions(&transactions)?;
+        Ok(transactions.len())
+    }
+
+    pub fn stats(&self) -> TraversalsCacheStats {
+        let (entries, size) = self.shards.iter().fold((0, 0), |(entries, size), shard| {
+            let shard = shard.lock().unwrap_or_else(|e| e.into_inner());
+            (entries + shard.transactions.len(), size + shard.size)
+        });
+        TraversalsCacheStats {
+            entries,
+            size,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for TraversalsLruCache {
+    fn default() -> Self {
+        TraversalsLruCache::new(DEFAULT_TRAVERSALS_CACHE_SIZE)
+    }
+}
+
+fn estimated_size(transaction: &LazyBlockTransaction) -> usize {
+    let inputs_size: usize = transaction
+        .inputs
+        .iter()
+        .map(|input| size_of::<LazyBlockTransactionInput>() + input.txin.len())
+        .sum();
+    ENTRY_OVERHEAD
+        + size_of::<LazyBlockTransaction>()
+        + transaction.txid.len()
+        + inputs_size
+        + transaction.outputs.len() * size_of::<u64>()
+}
+
+impl TraversalsCache for TraversalsLruCache {
+    fn get_transaction(&self, block_height: u32, txid: &[u8]) -> Option<LazyBlockTransaction> {
+        let key = (block_height, txid_cache_key(txid));
+        let transaction = self
+            .shard(&key)
+            .touch(&key)
+            .filter(|cached| txid_identifies(&cached.transaction.txid, txid))
+            .map(|cached| cached.transaction.clone());
+        match transaction {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        transaction
+    }
+
+    fn insert_transaction(&self, block_height: u32, transaction: &LazyBlockTransaction) {
+        let size = estimated_size(transaction);
+        if size > self.max_shard_size {
+            return;
+        }
+        let key = (block_height, txid_cache_key(&transaction.txid));
+        let mut shard = self.shard(&key);
+        shard.remove(&key);
+        shard.clock += 1;
+        let last_used = shard.clock;
+        shard.transactions.insert(
+            key,
+            CachedTransaction {
+                transaction: transaction.clone(),
+                size,
+                last_used,
+            },
+        );
+        shard.usage.insert(last_used, key);
+        shard.size += size;
+        while shard.size > self.max_shard_size {
+            let evicted = match shard.usage.values().next() {
+                Some(evicted) => *evicted,
+                None => break,
+            };
+            shard.remove(&evicted);
+        }
+    }
+}
+
+/// `TraversalsLruCache` backed by the transactions persisted in a `BlockStore`: misses are
+/// looked up in the store, new transactions are queued until `TraversalsLruCache::persist`.
+pub struct PersistedTraversalsCache<'a, S: ?Sized> {
+    cache: &'a TraversalsLruCache,
+    blocks_db: &'a S,
+}
+
+impl<'a, S: BlockStore + ?Sized> PersistedTraversalsCache<'a, S> {
+    pub fn new(cache: &'a TraversalsLruCache, blocks_db: &'a S) -> PersistedTraversalsCache<'a, S> {
+        PersistedTraversalsCache { cache, blocks_db }
+    }
+}
+
+impl<'a, S: BlockStore + ?Sized> TraversalsCache for PersistedTraversalsCache<'a, S> {
+    fn get_transaction(&self, block_height: u32, txid: &[u8]) -> Option<LazyBlockTransaction> {
+        if let Some(transaction) = self.cache.get_transaction(block_height, txid) {
+            return Some(transaction);
+        }
+        let transaction = self
+            .blocks_db
+            .get_traversal_transaction(block_height, txid)?;
+        self.cache.insert_transaction(block_height, &transaction);
+        Some(transaction)
+    }
+
+    fn insert_transaction(&self, block_height: u32, transaction: &LazyBlockTransaction) {
+        self.cache.insert_transaction(block_height, transaction);
+        let mut unpersisted = self
+            .cache
+            .unpersisted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if unpersisted.len() < MAX_UNPERSISTED_TRANSACTIONS {
+            unpersisted.push((block_height, transaction.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_transaction(txid_byte: u8, inputs: usize) -> LazyBlockTransaction {
+        LazyBlockTransaction {
+            txid: vec![txid_byte; 8],
+            inputs: (0..inputs)
+                .map(|vout| LazyBlockTransactionInput {
+                    txin: vec![0xaa; 8],
+                    block_height: 10,
+                    vout: vout as u16,
+                    txin_value: 1_000,
+                })
+                .collect(),
+            outputs: vec![1_000],
+        }
+    }
+
+    #[test]
+    fn count_hits_and_misses() {
+        let cache = TraversalsLruCache::default();
+        let transaction = build_transaction(0x01, 1);
+        assert!(cache.get_transaction(100, &transaction.txid).is_none());
+        cache.insert_transaction(100, &transaction);
+        assert_eq!(
+            cache.get_transaction(100, &transaction.txid),
+            Some(transaction.clone())
+        );
+        // Same txid prefix, other block
+        assert!(cache.get_transaction(101, &transaction.txid).is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 1, 2));
+        assert_eq!(stats.size, estimated_size(&transaction));
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats().size, 0);
+    }
+
+    #[test]
+    fn evict_least_recently_used_transactions() {
+        let transaction = build_transaction(0x01, 2);
+        // Room for 2 transactions per shard
+        let cache = TraversalsLruCache::new(SHARDS * (estimated_size(&transaction) * 5 / 2));
+        // Keys of a shard only differing by block height, multiple of the number of shards
+        cache.insert_transaction(0, &transaction);
+        cache.insert_transaction(SHARDS as u32, &transaction);
+        assert!(cache.get_transaction(0, &transaction.txid).is_some());
+        cache.insert_transaction(2 * SHARDS as u32, &transaction);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get_transaction(0, &transaction.txid).is_some());
+        assert!(cache
+            .get_transaction(SHARDS as u32, &transaction.txid)
+            .is_none());
+        assert!(cache
+            .get_transaction(2 * SHARDS as u32, &transaction.txid)
+            .is_some());
+
+        // Transactions larger than the budget of a shard are not cached
+        let cache = TraversalsLruCache::new(SHARDS * estimated_size(&transaction) / 2);
+        cache.insert_transaction(0, &transaction);
+        assert!(cache.is_empty());
+    }
+}
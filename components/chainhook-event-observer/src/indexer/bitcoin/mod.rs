@@ -1,4 +1,8 @@
 mod blocks_pool;
+mod prevouts;
+mod rpc_endpoints;
+mod stamps;
+mod taproot;
 
 use std::time::Duration;
 
@@ -23,6 +27,8 @@ use chainhook_types::{
     StacksBlockCommitmentData, TransactionIdentifier, TransferSTXData,
 };
 use hiro_system_kit::slog;
+pub use prevouts::{PrevoutCache, DEFAULT_PREVOUT_CACHE_CAPACITY};
+pub use rpc_endpoints::{BitcoinRpcEndpoints, BitcoinRpcRateLimits};
 
 use serde::Deserialize;
 
@@ -63,6 +69,9 @@ impl BitcoinBlockFullBreakdown {
 #[serde(rename_all = "camelCase")]
 pub struct BitcoinTransactionFullBreakdown {
     pub txid: bitcoin::Txid,
+    /// Virtual size, in vbytes.
+    #[serde(default)]
+    pub vsize: u64,
     pub vin: Vec<BitcoinTransactionInputFullBreakdown>,
     pub vout: Vec<BitcoinTransactionOutputFullBreakdown>,
 }
@@ -81,7 +90,12 @@ pub struct BitcoinTransactionInputFullBreakdown {
     /// The scriptSig in case of a non-coinbase tx.
     pub script_sig: Option<GetRawTransactionResultVinScriptSig>,
     /// Not provided for coinbase txs.
-    #[serde(default, deserialize_with = "deserialize_hex_array_opt")]
+    #[serde(
+        default,
+        deserialize_with = "deserialize_hex_array_opt",
+        serialize_with = "serialize_hex_array_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub txinwitness: Option<Vec<Vec<u8>>>,
     pub prevout: Option<BitcoinTransactionInputPrevoutFullBreakdown>,
 }
@@ -111,6 +125,21 @@ where
     Ok(Some(res))
 }
 
+/// Counterpart of `deserialize_hex_array_opt`, for blocks to be serialized back as
+/// returned by bitcoind.
+fn serialize_hex_array_opt<S>(
+    value: &Option<Vec<Vec<u8>>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match value {
+        Some(items) => serializer.collect_seq(items.iter().map(hex::encode)),
+        None => serializer.serialize_none(),
+    }
+}
+
 #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BitcoinTransactionInputPrevoutFullBreakdown {
@@ -213,6 +242,31 @@ pub async fn download_block_with_retry(
     Ok(block)
 }
 
+pub async fn download_rest_block_with_retry(
+    block_hash: &str,
+    bitcoin_config: &BitcoinConfig,
+    ctx: &Context,
+) -> Result<bitcoin::Block, String> {
+    let mut errors_count = 0;
+    let block = loop {
+        match download_rest_block(block_hash, bitcoin_config, ctx).await {
+            Ok(result) => break result,
+            Err(e) => {
+                errors_count += 1;
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "unable to retrieve block #{block_hash} (attempt #{errors_count}): {}",
+                        e.to_string()
+                    )
+                });
+                std::thread::sleep(std::time::Duration::from_millis(500));
+            }
+        }
+    };
+    Ok(block)
+}
+
 pub async fn retrieve_block_hash_with_retry(
     block_height: &u64,
     bitcoin_config: &BitcoinConfig,
@@ -238,36 +292,116 @@ pub async fn retrieve_block_hash_with_retry(
     Ok(block_hash)
 }
 
+/// Sends a request to the next bitcoind endpoint, once allowed by its rate limit. Endpoints
+/// not answering are set aside, endpoints answering `503` (work queue depth exceeded) are
+/// throttled. Returns the url of the endpoint along with its response.
+async fn send_to_bitcoind_endpoint(
+    bitcoin_config: &BitcoinConfig,
+    build_request: impl Fn(&reqwest::Client, &str) -> reqwest::RequestBuilder,
+) -> Result<(String, reqwest::Response), String> {
+    use reqwest::Client as HttpClient;
+    let http_client = HttpClient::builder()
+        .timeout(Duration::from_secs(20))
+        .build()
+        .expect("Unable to build http client");
+    let rpc_url = bitcoin_config.rpc_endpoints.next_url();
+    let delay = bitcoin_config.rpc_endpoints.acquire(&rpc_url);
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    let response = match build_request(&http_client, &rpc_url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            bitcoin_config.rpc_endpoints.report_failure(&rpc_url);
+            return Err(format!("unable to send request to {} ({})", rpc_url, e));
+        }
+    };
+    if response.status() == reqwest::StatusCode::SERVICE_UNAVAILABLE {
+        bitcoin_config.rpc_endpoints.report_overload(&rpc_url);
+        return Err(format!(
+            "{} is overloaded (work queue depth exceeded)",
+            rpc_url
+        ));
+    }
+    Ok((rpc_url, response))
+}
+
+fn build_jsonrpc_request(
+    http_client: &reqwest::Client,
+    rpc_url: &str,
+    body: &serde_json::Value,
+    bitcoin_config: &BitcoinConfig,
+) -> reqwest::RequestBuilder {
+    http_client
+        .post(rpc_url)
+        .basic_auth(&bitcoin_config.username, Some(&bitcoin_config.password))
+        .header("Content-Type", "application/json")
+        .header("Host", &rpc_url[7..])
+        .json(body)
+}
+
 pub async fn download_block(
     block_hash: &str,
     bitcoin_config: &BitcoinConfig,
     _ctx: &Context,
 ) -> Result<Vec<u8>, String> {
-    use reqwest::Client as HttpClient;
     let body = json!({
         "jsonrpc": "1.0",
         "id": "chainhook-cli",
         "method": "getblock",
         "params": [block_hash, 3]
     });
-    let http_client = HttpClient::builder()
-        .timeout(Duration::from_secs(20))
-        .build()
-        .expect("Unable to build http client");
-    let block = http_client
-        .post(&bitcoin_config.rpc_url)
-        .basic_auth(&bitcoin_config.username, Some(&bitcoin_config.password))
-        .header("Content-Type", "application/json")
-        .header("Host", &bitcoin_config.rpc_url[7..])
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| format!("unable to send request ({})", e))?
-        .bytes()
-        .await
-        .map_err(|e| format!("unable to get bytes ({})", e))?
-        .to_vec();
-    Ok(block)
+    let (rpc_url, response) = send_to_bitcoind_endpoint(bitcoin_config, |http_client, rpc_url| {
+        build_jsonrpc_request(http_client, rpc_url, &body, bitcoin_config)
+    })
+    .await?;
+    match response.bytes().await {
+        Ok(block) => {
+            bitcoin_config.rpc_endpoints.report_success(&rpc_url);
+            Ok(block.to_vec())
+        }
+        Err(e) => {
+            bitcoin_config.rpc_endpoints.report_failure(&rpc_url);
+            Err(format!("unable to get block from {} ({})", rpc_url, e))
+        }
+    }
+}
+
+/// Downloads a block from the REST interface of bitcoind (`rest=1`), in its consensus
+/// encoding. Cheaper to serve and to decode than `getblock` with verbosity 3, but the
+/// prevouts are not included (see `PrevoutCache`).
+pub async fn download_rest_block(
+    block_hash: &str,
+    bitcoin_config: &BitcoinConfig,
+    _ctx: &Context,
+) -> Result<bitcoin::Block, String> {
+    let (rpc_url, response) = send_to_bitcoind_endpoint(bitcoin_config, |http_client, rpc_url| {
+        http_client.get(format!(
+            "{}/rest/block/{}.bin",
+            rpc_url.trim_end_matches('/'),
+            block_hash
+        ))
+    })
+    .await?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "unable to get block from {} ({}), is the REST interface enabled?",
+            rpc_url,
+            response.status()
+        ));
+    }
+    let bytes = match response.bytes().await {
+        Ok(bytes) => {
+            bitcoin_config.rpc_endpoints.report_success(&rpc_url);
+            bytes
+        }
+        Err(e) => {
+            bitcoin_config.rpc_endpoints.report_failure(&rpc_url);
+            return Err(format!("unable to get block from {} ({})", rpc_url, e));
+        }
+    };
+    bitcoin::consensus::deserialize::<bitcoin::Block>(&bytes)
+        .map_err(|e| format!("unable to decode block ({})", e))
 }
 
 pub fn parse_downloaded_block(
@@ -294,33 +428,82 @@ pub async fn retrieve_block_hash(
     bitcoin_config: &BitcoinConfig,
     _ctx: &Context,
 ) -> Result<String, String> {
-    use reqwest::Client as HttpClient;
+    send_bitcoind_rpc_request("getblockhash", json!([block_height]), bitcoin_config).await
+}
+
+#[derive(Deserialize)]
+struct BlockTxids {
+    tx: Vec<bitcoin::Txid>,
+}
+
+async fn send_bitcoind_rpc_request<T: serde::de::DeserializeOwned>(
+    method: &str,
+    params: serde_json::Value,
+    bitcoin_config: &BitcoinConfig,
+) -> Result<T, String> {
     let body = json!({
         "jsonrpc": "1.0",
         "id": "chainhook-cli",
-        "method": "getblockhash",
-        "params": [block_height]
+        "method": method,
+        "params": params
     });
-    let http_client = HttpClient::builder()
-        .timeout(Duration::from_secs(20))
-        .build()
-        .expect("Unable to build http client");
-    let block_hash = http_client
-        .post(&bitcoin_config.rpc_url)
-        .basic_auth(&bitcoin_config.username, Some(&bitcoin_config.password))
-        .header("Content-Type", "application/json")
-        .header("Host", &bitcoin_config.rpc_url[7..])
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| format!("unable to send request ({})", e))?
-        .json::<bitcoincore_rpc::jsonrpc::Response>()
-        .await
-        .map_err(|e| format!("unable to parse response ({})", e))?
-        .result::<String>()
-        .map_err(|e| format!("unable to parse response ({})", e))?;
+    let (rpc_url, response) = send_to_bitcoind_endpoint(bitcoin_config, |http_client, rpc_url| {
+        build_jsonrpc_request(http_client, rpc_url, &body, bitcoin_config)
+    })
+    .await?;
+    match response.json::<bitcoincore_rpc::jsonrpc::Response>().await {
+        Ok(response) => {
+            bitcoin_config.rpc_endpoints.report_success(&rpc_url);
+            response
+                .result::<T>()
+                .map_err(|e| format!("unable to parse {} result ({})", method, e))
+        }
+        Err(e) => {
+            bitcoin_config.rpc_endpoints.report_failure(&rpc_url);
+            Err(format!(
+                "unable to send {} request to {} ({})",
+                method, rpc_url, e
+            ))
+        }
+    }
+}
 
-    Ok(block_hash)
+pub async fn retrieve_block_count(bitcoin_config: &BitcoinConfig) -> Result<u64, String> {
+    send_bitcoind_rpc_request("getblockcount", json!([]), bitcoin_config).await
+}
+
+pub async fn retrieve_block_txids(
+    block_height: &u64,
+    bitcoin_config: &BitcoinConfig,
+    ctx: &Context,
+) -> Result<Vec<bitcoin::Txid>, String> {
+    let block_hash = retrieve_block_hash(block_height, bitcoin_config, ctx).await?;
+    let block: BlockTxids =
+        send_bitcoind_rpc_request("getblock", json!([block_hash, 1]), bitcoin_config).await?;
+    Ok(block.tx)
+}
+
+pub async fn retrieve_mempool_txids(
+    bitcoin_config: &BitcoinConfig,
+) -> Result<Vec<bitcoin::Txid>, String> {
+    send_bitcoind_rpc_request("getrawmempool", json!([]), bitcoin_config).await
+}
+
+/// Downloads a transaction pending in the mempool, `None` if it was confirmed in the meantime.
+pub async fn download_and_parse_mempool_transaction(
+    txid: &bitcoin::Txid,
+    bitcoin_config: &BitcoinConfig,
+) -> Result<Option<BitcoinTransactionFullBreakdown>, String> {
+    let transaction: MempoolTransactionFullBreakdown = send_bitcoind_rpc_request(
+        "getrawtransaction",
+        json!([txid.to_string(), 2]),
+        bitcoin_config,
+    )
+    .await?;
+    match transaction.blockhash {
+        Some(_) => Ok(None),
+        None => Ok(Some(transaction.tx)),
+    }
 }
 
 pub fn standardize_bitcoin_block(
@@ -335,101 +518,14 @@ pub fn standardize_bitcoin_block(
 
     ctx.try_log(|logger| slog::debug!(logger, "Standardizing Bitcoin block {}", block.hash,));
 
-    for mut tx in block.tx.into_iter() {
-        let txid = tx.txid.to_string();
-
-        ctx.try_log(|logger| slog::debug!(logger, "Standardizing Bitcoin transaction {txid}"));
-
-        let mut stacks_operations = vec![];
-        if let Some(op) = try_parse_stacks_operation(
+    for tx in block.tx.into_iter() {
+        let tx = standardize_bitcoin_transaction(
+            tx,
             block_height,
-            &tx.vin,
-            &tx.vout,
             &pox_config,
             &expected_magic_bytes,
             ctx,
-        ) {
-            stacks_operations.push(op);
-        }
-
-        let mut ordinal_operations = vec![];
-
-        #[cfg(feature = "ordinals")]
-        if let Some(op) = crate::hord::try_parse_ordinal_operation(&tx, block_height, ctx) {
-            ordinal_operations.push(op);
-        }
-
-        let mut inputs = vec![];
-        let mut sats_in = 0;
-        for (index, input) in tx.vin.drain(..).enumerate() {
-            if input.is_coinbase() {
-                continue;
-            }
-            let prevout = input.prevout.as_ref().ok_or(format!(
-                "error retrieving prevout for transaction {}, input #{} (block #{})",
-                tx.txid, index, block.height
-            ))?;
-
-            let txid = input.txid.as_ref().ok_or(format!(
-                "error retrieving txid for transaction {}, input #{} (block #{})",
-                tx.txid, index, block.height
-            ))?;
-
-            let vout = input.vout.ok_or(format!(
-                "error retrieving vout for transaction {}, input #{} (block #{})",
-                tx.txid, index, block.height
-            ))?;
-
-            let script_sig = input.script_sig.ok_or(format!(
-                "error retrieving script_sig for transaction {}, input #{} (block #{})",
-                tx.txid, index, block.height
-            ))?;
-
-            sats_in += prevout.value.to_sat();
-            inputs.push(TxIn {
-                previous_output: OutPoint {
-                    txid: format!("0x{}", txid.to_string()),
-                    vout,
-                    block_height: prevout.height,
-                    value: prevout.value.to_sat(),
-                },
-                script_sig: format!("0x{}", hex::encode(&script_sig.hex)),
-                sequence: input.sequence,
-                witness: input
-                    .txinwitness
-                    .unwrap_or(vec![])
-                    .to_vec()
-                    .iter()
-                    .map(|w| format!("0x{}", hex::encode(w)))
-                    .collect::<Vec<_>>(),
-            })
-        }
-
-        let mut outputs = vec![];
-        let mut sats_out = 0;
-        for output in tx.vout.drain(..) {
-            let value = output.value.to_sat();
-            sats_out += value;
-            outputs.push(TxOut {
-                value,
-                script_pubkey: format!("0x{}", hex::encode(&output.script_pub_key.hex)),
-            });
-        }
-
-        let tx = BitcoinTransactionData {
-            transaction_identifier: TransactionIdentifier {
-                hash: format!("0x{}", txid),
-            },
-            operations: vec![],
-            metadata: BitcoinTransactionMetadata {
-                inputs,
-                outputs,
-                stacks_operations,
-                ordinal_operations,
-                proof: None,
-                fee: sats_in - sats_out,
-            },
-        };
+        )?;
         transactions.push(tx);
     }
 
@@ -451,6 +547,151 @@ pub fn standardize_bitcoin_block(
     })
 }
 
+/// Standardizes a transaction pending in the mempool, parsed as if it was included in the
+/// block `expected_block_height`.
+pub fn standardize_bitcoin_mempool_transaction(
+    tx: BitcoinTransactionFullBreakdown,
+    expected_block_height: u64,
+    network: &BitcoinNetwork,
+    ctx: &Context,
+) -> Result<BitcoinTransactionData, String> {
+    let expected_magic_bytes = get_stacks_canonical_magic_bytes(network);
+    let pox_config = get_canonical_pox_config(network);
+    standardize_bitcoin_transaction(
+        tx,
+        expected_block_height,
+        &pox_config,
+        &expected_magic_bytes,
+        ctx,
+    )
+}
+
+fn standardize_bitcoin_transaction(
+    mut tx: BitcoinTransactionFullBreakdown,
+    block_height: u64,
+    pox_config: &PoxConfig,
+    expected_magic_bytes: &[u8; 2],
+    ctx: &Context,
+) -> Result<BitcoinTransactionData, String> {
+    let txid = tx.txid.to_string();
+
+    ctx.try_log(|logger| slog::debug!(logger, "Standardizing Bitcoin transaction {txid}"));
+
+    let mut stacks_operations = vec![];
+    if let Some(op) = try_parse_stacks_operation(
+        block_height,
+        &tx.vin,
+        &tx.vout,
+        pox_config,
+        expected_magic_bytes,
+        ctx,
+    ) {
+        stacks_operations.push(op);
+    }
+
+    let mut ordinal_operations = vec![];
+
+    #[cfg(feature = "ordinals")]
+    ordinal_operations.extend(crate::hord::try_parse_ordinal_operations(
+        &tx,
+        block_height,
+        ctx,
+    ));
+
+    let mut inputs = vec![];
+    let mut taproot_spends = vec![];
+    let mut sats_in = 0;
+    for (index, input) in tx.vin.drain(..).enumerate() {
+        if input.is_coinbase() {
+            continue;
+        }
+        let prevout = input.prevout.as_ref().ok_or(format!(
+            "error retrieving prevout for transaction {}, input #{} (block #{})",
+            tx.txid, index, block_height
+        ))?;
+
+        let txid = input.txid.as_ref().ok_or(format!(
+            "error retrieving txid for transaction {}, input #{} (block #{})",
+            tx.txid, index, block_height
+        ))?;
+
+        let vout = input.vout.ok_or(format!(
+            "error retrieving vout for transaction {}, input #{} (block #{})",
+            tx.txid, index, block_height
+        ))?;
+
+        let script_sig = input.script_sig.ok_or(format!(
+            "error retrieving script_sig for transaction {}, input #{} (block #{})",
+            tx.txid, index, block_height
+        ))?;
+
+        let witness = input.txinwitness.unwrap_or(vec![]);
+        if let Some(taproot_spend) = taproot::try_parse_taproot_spend(inputs.len(), &witness) {
+            taproot_spends.push(taproot_spend);
+        }
+
+        sats_in += prevout.value.to_sat();
+        inputs.push(TxIn {
+            previous_output: OutPoint {
+                txid: format!("0x{}", txid.to_string()),
+                vout,
+                block_height: prevout.height,
+                value: prevout.value.to_sat(),
+            },
+            script_sig: format!("0x{}", hex::encode(&script_sig.hex)),
+            sequence: input.sequence,
+            witness: witness
+                .iter()
+                .map(|w| format!("0x{}", hex::encode(w)))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    let mut outputs = vec![];
+    let mut sats_out = 0;
+    for output in tx.vout.drain(..) {
+        let value = output.value.to_sat();
+        sats_out += value;
+        outputs.push(TxOut {
+            value,
+            script_pubkey: format!("0x{}", hex::encode(&output.script_pub_key.hex)),
+        });
+    }
+
+    // Coinbase transactions are not paying fees
+    let fee = sats_in.saturating_sub(sats_out);
+    let fee_rate = match tx.vsize {
+        0 => 0.0,
+        vsize => fee as f64 / vsize as f64,
+    };
+
+    let stamp_operations = stamps::try_parse_stamp_operation(&inputs, &outputs)
+        .into_iter()
+        .collect();
+
+    Ok(BitcoinTransactionData {
+        transaction_identifier: TransactionIdentifier {
+            hash: format!("0x{}", txid),
+        },
+        operations: vec![],
+        metadata: BitcoinTransactionMetadata {
+            inputs,
+            outputs,
+            stacks_operations,
+            ordinal_operations,
+            brc20_operations: vec![],
+            rune_operations: vec![],
+            stamp_operations,
+            sat_movements: vec![],
+            taproot_spends,
+            proof: None,
+            fee,
+            vsize: tx.vsize,
+            fee_rate,
+        },
+    })
+}
+
 fn try_parse_stacks_operation(
     block_height: u64,
     _inputs: &Vec<BitcoinTransactionInputFullBreakdown>,
@@ -615,6 +856,52 @@ fn try_parse_stacks_operation(
     Some(op)
 }
 
+/// Hash of the block announced by a `hashblock` or `rawblock` ZMQ notification of bitcoind.
+pub fn parse_zmq_block_notification(topic: &[u8], payload: &[u8]) -> Option<String> {
+    match topic {
+        b"hashblock" if payload.len() == 32 => Some(hex::encode(payload)),
+        b"rawblock" => {
+            let header = payload.get(..80)?;
+            bitcoin::consensus::deserialize::<bitcoin::BlockHeader>(header)
+                .ok()
+                .map(|header| header.block_hash().to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Data pushed by an `OP_RETURN` output, the pushes being concatenated.
+/// Returns `None` for the other outputs, or when the script is not made of pushes only.
+pub fn get_op_return_data(script_pubkey: &str) -> Option<Vec<u8>> {
+    let script = hex::decode(script_pubkey.strip_prefix("0x").unwrap_or(script_pubkey)).ok()?;
+    if script.first() != Some(&0x6a) {
+        return None;
+    }
+    let mut data = vec![];
+    let mut cursor = 1;
+    while cursor < script.len() {
+        let opcode = script[cursor];
+        cursor += 1;
+        let (length, length_size) = match opcode {
+            0x00..=0x4b => (opcode as usize, 0),
+            0x4c => (*script.get(cursor)? as usize, 1),
+            0x4d => (
+                u16::from_le_bytes(script.get(cursor..cursor + 2)?.try_into().ok()?) as usize,
+                2,
+            ),
+            0x4e => (
+                u32::from_le_bytes(script.get(cursor..cursor + 4)?.try_into().ok()?) as usize,
+                4,
+            ),
+            _ => return None,
+        };
+        cursor += length_size;
+        data.extend_from_slice(script.get(cursor..cursor + length)?);
+        cursor += length;
+    }
+    Some(data)
+}
+
 fn try_parse_block_commit_op(bytes: &[u8]) -> Option<BlockCommitmentData> {
     if bytes.len() < 32 {
         return None;
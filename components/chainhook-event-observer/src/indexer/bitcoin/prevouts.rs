@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use bitcoincore_rpc::bitcoin::{self, Amount, Block, OutPoint};
+use bitcoincore_rpc_json::{
+    GetRawTransactionResultVinScriptSig, GetRawTransactionResultVoutScriptPubKey,
+};
+
+use super::{
+    BitcoinBlockFullBreakdown, BitcoinTransactionFullBreakdown,
+    BitcoinTransactionInputFullBreakdown, BitcoinTransactionInputPrevoutFullBreakdown,
+    BitcoinTransactionOutputFullBreakdown,
+};
+
+/// Outputs kept by default, roughly 3GB of memory.
+pub const DEFAULT_PREVOUT_CACHE_CAPACITY: usize = 40_000_000;
+
+/// Outputs created by the blocks already resolved, with their block height and their value,
+/// for resolving the prevouts of the blocks downloaded from the REST interface of bitcoind.
+/// Blocks have to be resolved in order. Once full, the oldest half of the outputs is evicted.
+pub struct PrevoutCache {
+    outputs: HashMap<OutPoint, (u32, u64)>,
+    capacity: usize,
+}
+
+impl PrevoutCache {
+    pub fn new(capacity: usize) -> PrevoutCache {
+        PrevoutCache {
+            outputs: HashMap::new(),
+            capacity,
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Builds the full breakdown of a block, the prevouts of its inputs being looked up in the
+    /// cache (or among the outputs of the previous transactions of the block). Returns `None`
+    /// when some prevouts are unknown, the cache being left untouched: the block should then be
+    /// retrieved with `getblock` and passed to `record_block`.
+    pub fn resolve_block(
+        &mut self,
+        block: &Block,
+        block_height: u64,
+    ) -> Option<BitcoinBlockFullBreakdown> {
+        let mut block_outputs = HashMap::new();
+        let mut transactions = Vec::with_capacity(block.txdata.len());
+        for tx in block.txdata.iter() {
+            let txid = tx.txid();
+            let mut vin = Vec::with_capacity(tx.input.len());
+            for input in tx.input.iter() {
+                if tx.is_coin_base() {
+                    vin.push(BitcoinTransactionInputFullBreakdown {
+                        sequence: input.sequence.0,
+                        coinbase: Some(input.script_sig.to_bytes()),
+                        txid: None,
+                        vout: None,
+                        script_sig: None,
+                        txinwitness: None,
+                        prevout: None,
+                    });
+                    continue;
+                }
+                let (height, value) = match block_outputs.get(&input.previous_output) {
+                    Some(prevout) => *prevout,
+                    None => *self.outputs.get(&input.previous_output)?,
+                };
+                vin.push(BitcoinTransactionInputFullBreakdown {
+                    sequence: input.sequence.0,
+                    coinbase: None,
+                    txid: Some(input.previous_output.txid),
+                    vout: Some(input.previous_output.vout),
+                    // Only the raw scripts are read downstream
+                    script_sig: Some(GetRawTransactionResultVinScriptSig {
+                        asm: String::new(),
+                        hex: input.script_sig.to_bytes(),
+                    }),
+                    txinwitness: Some(input.witness.to_vec()),
+                    prevout: Some(BitcoinTransactionInputPrevoutFullBreakdown {
+                        height: height as u64,
+                        value: Amount::from_sat(value),
+                    }),
+                });
+            }
+            let mut vout = Vec::with_capacity(tx.output.len());
+            for (n, output) in tx.output.iter().enumerate() {
+                block_outputs.insert(
+                    OutPoint::new(txid, n as u32),
+                    (block_height as u32, output.value),
+                );
+                vout.push(BitcoinTransactionOutputFullBreakdown {
+                    value: Amount::from_sat(output.value),
+                    n: n as u32,
+                    script_pub_key: GetRawTransactionResultVoutScriptPubKey {
+                        asm: String::new(),
+                        hex: output.script_pubkey.to_bytes(),
+                        req_sigs: None,
+                        type_: None,
+                        addresses: vec![],
+                        address: None,
+                    },
+                });
+            }
+            transactions.push(BitcoinTransactionFullBreakdown {
+                txid,
+                vsize: tx.vsize() as u64,
+                vin,
+                vout,
+            });
+        }
+
+        for tx in block.txdata.iter() {
+            let txid = tx.txid();
+            for (n, output) in tx.output.iter().enumerate() {
+                if !output.script_pubkey.is_provably_unspendable() {
+                    self.insert(
+                        OutPoint::new(txid, n as u32),
+                        block_height as u32,
+                        output.value,
+                    );
+                }
+            }
+        }
+        // Including the outputs created and spent within the block
+        for tx in block.txdata.iter().filter(|tx| !tx.is_coin_base()) {
+            for input in tx.input.iter() {
+                self.outputs.remove(&input.previous_output);
+            }
+        }
+
+        Some(BitcoinBlockFullBreakdown {
+            hash: block.block_hash(),
+            height: block_height as usize,
+            merkleroot: block.header.merkle_root,
+            tx: transactions,
+            time: block.header.time as usize,
+            nonce: block.header.nonce,
+            previousblockhash: match block_height {
+                0 => None,
+                _ => Some(block.header.prev_blockhash),
+            },
+        })
+    }
+
+    /// Records the outputs created and spent by a block retrieved with `getblock`.
+    pub fn record_block(&mut self, block: &BitcoinBlockFullBreakdown) {
+        for tx in block.tx.iter() {
+            for input in tx.vin.iter() {
+                if let (Some(txid), Some(vout)) = (input.txid, input.vout) {
+                    self.outputs.remove(&OutPoint::new(txid, vout));
+                }
+            }
+            for output in tx.vout.iter() {
+                let script = bitcoin::Script::from(output.script_pub_key.hex.clone());
+                if !script.is_provably_unspendable() {
+                    self.insert(
+                        OutPoint::new(tx.txid, output.n),
+                        block.height as u32,
+                        output.value.to_sat(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, outpoint: OutPoint, block_height: u32, value: u64) {
+        if self.outputs.len() >= self.capacity {
+            self.evict_oldest_half(block_height);
+        }
+        self.outputs.insert(outpoint, (block_height, value));
+    }
+
+    fn evict_oldest_half(&mut self, block_height: u32) {
+        let oldest_height = self
+            .outputs
+            .values()
+            .map(|(height, _)| *height)
+            .min()
+            .unwrap_or(block_height);
+        let cutoff = oldest_height + block_height.saturating_sub(oldest_height) / 2;
+        self.outputs.retain(|_, (height, _)| *height > cutoff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoincore_rpc::bitcoin::hashes::Hash;
+    use bitcoincore_rpc::bitcoin::{
+        BlockHash, BlockHeader, PackedLockTime, Script, Sequence, Transaction, TxIn, TxMerkleNode,
+        TxOut, Txid, Witness,
+    };
+
+    fn build_tx(previous_outputs: &[OutPoint], values: &[u64]) -> Transaction {
+        let coinbase = previous_outputs.is_empty();
+        Transaction {
+            version: 2,
+            lock_time: PackedLockTime(0),
+            input: match coinbase {
+                true => vec![TxIn {
+                    previous_output: OutPoint::null(),
+                    script_sig: Script::from(vec![0x51, values.len() as u8]),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                }],
+                false => previous_outputs
+                    .iter()
+                    .map(|previous_output| TxIn {
+                        previous_output: *previous_output,
+                        script_sig: Script::new(),
+                        sequence: Sequence::MAX,
+                        witness: Witness::from_vec(vec![vec![0x01; 64]]),
+                    })
+                    .collect(),
+            },
+            output: values
+                .iter()
+                .map(|value| TxOut {
+                    value: *value,
+                    script_pubkey: Script::from(vec![0x51]),
+                })
+                .collect(),
+        }
+    }
+
+    fn build_block(txdata: Vec<Transaction>) -> Block {
+        Block {
+            header: BlockHeader {
+                version: 2,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: 0,
+                bits: 0,
+                nonce: 0,
+            },
+            txdata,
+        }
+    }
+
+    #[test]
+    fn resolve_prevouts_of_previous_blocks() {
+        let mut cache = PrevoutCache::new(DEFAULT_PREVOUT_CACHE_CAPACITY);
+        let coinbase = build_tx(&[], &[5_000]);
+        let coinbase_txid = coinbase.txid();
+        assert!(cache
+            .resolve_block(&build_block(vec![coinbase]), 10)
+            .is_some());
+
+        let spend = build_tx(&[OutPoint::new(coinbase_txid, 0)], &[3_000, 1_000]);
+        let chained_spend = build_tx(&[OutPoint::new(spend.txid(), 1)], &[900]);
+        let block = build_block(vec![build_tx(&[], &[6_000]), spend, chained_spend]);
+        let resolved = cache.resolve_block(&block, 11).unwrap();
+
+        let prevout = resolved.tx[1].vin[0].prevout.as_ref().unwrap();
+        assert_eq!((prevout.height, prevout.value.to_sat()), (10, 5_000));
+        let prevout = resolved.tx[2].vin[0].prevout.as_ref().unwrap();
+        assert_eq!((prevout.height, prevout.value.to_sat()), (11, 1_000));
+        assert!(resolved.tx[0].vin[0].is_coinbase());
+        // Coinbase of block #11, first output of the spend, and the chained spend
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn leave_cache_untouched_when_prevouts_are_unknown() {
+        let mut cache = PrevoutCache::new(DEFAULT_PREVOUT_CACHE_CAPACITY);
+        let unknown = OutPoint::new(Txid::all_zeros(), 1);
+        let block = build_block(vec![build_tx(&[], &[6_000]), build_tx(&[unknown], &[10])]);
+        assert!(cache.resolve_block(&block, 12).is_none());
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn evict_oldest_outputs() {
+        let mut cache = PrevoutCache::new(4);
+        for height in 0..4 {
+            let block = build_block(vec![build_tx(&[], &[height + 1])]);
+            cache.resolve_block(&block, height).unwrap();
+        }
+        let block = build_block(vec![build_tx(&[], &[100])]);
+        cache.resolve_block(&block, 4).unwrap();
+        // Outputs of blocks #0 to #2 evicted
+        assert_eq!(cache.len(), 2);
+    }
+}
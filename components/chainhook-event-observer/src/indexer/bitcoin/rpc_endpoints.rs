@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Endpoints failing are set aside for this long, times their number of consecutive failures.
+const UNHEALTHY_ENDPOINT_COOLDOWN: Duration = Duration::from_secs(5);
+const UNHEALTHY_ENDPOINT_MAX_COOLDOWN: Duration = Duration::from_secs(120);
+/// Rate applied to an endpoint without limit once overloaded, before being halved.
+const OVERLOADED_ENDPOINT_INITIAL_RATE: f64 = 64.0;
+const OVERLOADED_ENDPOINT_MIN_RATE: f64 = 1.0;
+/// Requests per second given back to an endpoint throttled, after each request served.
+const THROTTLED_ENDPOINT_RATE_RECOVERY: f64 = 0.5;
+
+/// Requests per second allowed on the bitcoind endpoints.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BitcoinRpcRateLimits {
+    /// Applied to the endpoints without limit of their own, unlimited when unset.
+    pub default: Option<u32>,
+    /// Limits of the endpoints, by url.
+    pub endpoints: HashMap<String, u32>,
+}
+
+impl BitcoinRpcRateLimits {
+    /// Requests per second allowed across the endpoints, unlimited when one of them is.
+    pub fn total_rate<'a>(&self, urls: impl IntoIterator<Item = &'a str>) -> Option<u32> {
+        urls.into_iter()
+            .map(|url| self.endpoints.get(url).copied().or(self.default))
+            .sum()
+    }
+}
+
+/// Token bucket of an endpoint. Its rate is halved when bitcoind reports being overloaded,
+/// then gradually given back as requests are served, up to the configured limit.
+#[derive(Debug)]
+struct RateLimiter {
+    max_rate: Option<f64>,
+    /// Requests per second currently allowed, unlimited when unset.
+    rate: Option<f64>,
+    /// Requests that can be sent right away, negative when requests are waiting.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(max_rate: Option<u32>) -> RateLimiter {
+        let max_rate = max_rate.map(|rate| (rate as f64).max(OVERLOADED_ENDPOINT_MIN_RATE));
+        RateLimiter {
+            max_rate,
+            rate: max_rate,
+            tokens: max_rate.unwrap_or(0.0),
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token, returning how long the request has to wait for it.
+    fn acquire(&mut self, now: Instant) -> Duration {
+        let rate = match self.rate {
+            Some(rate) => rate,
+            None => return Duration::ZERO,
+        };
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.last_refill = now;
+        self.tokens -= 1.0;
+        match self.tokens < 0.0 {
+            true => Duration::from_secs_f64(-self.tokens / rate),
+            false => Duration::ZERO,
+        }
+    }
+
+    fn throttle(&mut self) {
+        let rate = self
+            .rate
+            .or(self.max_rate)
+            .unwrap_or(OVERLOADED_ENDPOINT_INITIAL_RATE);
+        let rate = (rate / 2.0).max(OVERLOADED_ENDPOINT_MIN_RATE);
+        self.rate = Some(rate);
+        self.tokens = self.tokens.min(0.0);
+    }
+
+    fn recover(&mut self) {
+        if let Some(rate) = self.rate {
+            let rate = rate + THROTTLED_ENDPOINT_RATE_RECOVERY;
+            self.rate = match self.max_rate {
+                Some(max_rate) => Some(rate.min(max_rate)),
+                None if rate >= OVERLOADED_ENDPOINT_INITIAL_RATE => None,
+                None => Some(rate),
+            };
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RpcEndpoint {
+    url: String,
+    consecutive_failures: u32,
+    unhealthy_until: Option<Instant>,
+    rate_limiter: RateLimiter,
+}
+
+#[derive(Debug)]
+struct RpcEndpointsState {
+    endpoints: Vec<RpcEndpoint>,
+    next: usize,
+}
+
+/// RPC endpoints of the bitcoind nodes, used in turn. Endpoints failing to answer are set aside
+/// for a while, requests failing over to the other ones. Shared by the clones of the config.
+#[derive(Clone, Debug)]
+pub struct BitcoinRpcEndpoints(Arc<Mutex<RpcEndpointsState>>);
+
+impl BitcoinRpcEndpoints {
+    pub fn new(
+        rpc_url: &str,
+        fallback_rpc_urls: &[String],
+        rate_limits: &BitcoinRpcRateLimits,
+    ) -> BitcoinRpcEndpoints {
+        let endpoints = std::iter::once(rpc_url)
+            .chain(fallback_rpc_urls.iter().map(|url| url.as_str()))
+            .map(|url| RpcEndpoint {
+                url: url.to_string(),
+                consecutive_failures: 0,
+                unhealthy_until: None,
+                rate_limiter: RateLimiter::new(
+                    rate_limits
+                        .endpoints
+                        .get(url)
+                        .cloned()
+                        .or(rate_limits.default),
+                ),
+            })
+            .collect();
+        BitcoinRpcEndpoints(Arc::new(Mutex::new(RpcEndpointsState {
+            endpoints,
+            next: 0,
+        })))
+    }
+
+    /// Endpoints restricted to the main one, with its own rate limit.
+    pub fn main_endpoint_only(&self) -> BitcoinRpcEndpoints {
+        let state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let endpoint = &state.endpoints[0];
+        let rate_limits = BitcoinRpcRateLimits {
+            default: endpoint.rate_limiter.max_rate.map(|rate| rate as u32),
+            endpoints: HashMap::new(),
+        };
+        BitcoinRpcEndpoints::new(&endpoint.url, &[], &rate_limits)
+    }
+
+    /// Next healthy endpoint in turn, or the one getting back in service the earliest when
+    /// all of them are set aside. Configs without endpoint are rejected when loaded.
+    pub fn next_url(&self) -> String {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let count = state.endpoints.len();
+        for offset in 0..count {
+            let index = (state.next + offset) % count;
+            let endpoint = &state.endpoints[index];
+            if endpoint.unhealthy_until.map_or(true, |until| until <= now) {
+                let url = endpoint.url.clone();
+                state.next = (index + 1) % count;
+                return url;
+            }
+        }
+        state
+            .endpoints
+            .iter()
+            .min_by_key(|endpoint| endpoint.unhealthy_until)
+            .map(|endpoint| endpoint.url.clone())
+            .expect("at least one bitcoind endpoint")
+    }
+
+    /// Takes a request slot on the endpoint, returning how long the request has to wait before
+    /// being sent for its rate limit to be respected.
+    pub fn acquire(&self, url: &str) -> Duration {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        match state.endpoints.iter_mut().find(|e| e.url == url) {
+            Some(endpoint) => endpoint.rate_limiter.acquire(Instant::now()),
+            None => Duration::ZERO,
+        }
+    }
+
+    pub fn report_success(&self, url: &str) {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(endpoint) = state.endpoints.iter_mut().find(|e| e.url == url) {
+            endpoint.consecutive_failures = 0;
+            endpoint.unhealthy_until = None;
+            endpoint.rate_limiter.recover();
+        }
+    }
+
+    pub fn report_failure(&self, url: &str) {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(endpoint) = state.endpoints.iter_mut().find(|e| e.url == url) {
+            endpoint.consecutive_failures += 1;
+            let cooldown = UNHEALTHY_ENDPOINT_COOLDOWN
+                .saturating_mul(endpoint.consecutive_failures)
+                .min(UNHEALTHY_ENDPOINT_MAX_COOLDOWN);
+            endpoint.unhealthy_until = Some(Instant::now() + cooldown);
+        }
+    }
+
+    /// The endpoint is up but can not keep up with the requests (work queue depth exceeded).
+    pub fn report_overload(&self, url: &str) {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(endpoint) = state.endpoints.iter_mut().find(|e| e.url == url) {
+            endpoint.rate_limiter.throttle();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin_over_healthy_endpoints() {
+        let endpoints = BitcoinRpcEndpoints::new(
+            "http://node-1:8332",
+            &["http://node-2:8332".into(), "http://node-3:8332".into()],
+            &BitcoinRpcRateLimits::default(),
+        );
+        assert_eq!(endpoints.next_url(), "http://node-1:8332");
+        assert_eq!(endpoints.next_url(), "http://node-2:8332");
+        assert_eq!(endpoints.next_url(), "http://node-3:8332");
+        assert_eq!(endpoints.next_url(), "http://node-1:8332");
+
+        endpoints.report_failure("http://node-2:8332");
+        assert_eq!(endpoints.next_url(), "http://node-3:8332");
+        assert_eq!(endpoints.next_url(), "http://node-1:8332");
+        assert_eq!(endpoints.next_url(), "http://node-3:8332");
+
+        endpoints.report_success("http://node-2:8332");
+        assert_eq!(endpoints.next_url(), "http://node-1:8332");
+        assert_eq!(endpoints.next_url(), "http://node-2:8332");
+    }
+
+    #[test]
+    fn fall_back_on_endpoint_recovering_first() {
+        let endpoints = BitcoinRpcEndpoints::new(
+            "http://node-1:8332",
+            &["http://node-2:8332".into()],
+            &BitcoinRpcRateLimits::default(),
+        );
+        endpoints.report_failure("http://node-1:8332");
+        endpoints.report_failure("http://node-1:8332");
+        endpoints.report_failure("http://node-2:8332");
+        assert_eq!(endpoints.next_url(), "http://node-2:8332");
+        assert_eq!(endpoints.next_url(), "http://node-2:8332");
+    }
+
+    #[test]
+    fn rate_limit_requests() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(Some(2));
+        assert_eq!(limiter.acquire(now), Duration::ZERO);
+        assert_eq!(limiter.acquire(now), Duration::ZERO);
+        assert_eq!(limiter.acquire(now), Duration::from_millis(500));
+        assert_eq!(limiter.acquire(now), Duration::from_secs(1));
+        assert_eq!(
+            limiter.acquire(now + Duration::from_secs(2)),
+            Duration::ZERO
+        );
+
+        let mut limiter = RateLimiter::new(None);
+        assert_eq!(limiter.acquire(now), Duration::ZERO);
+    }
+
+    #[test]
+    fn delay_requests_over_endpoint_rate_limit() {
+        let endpoints = BitcoinRpcEndpoints::new(
+            "http://node-1:8332",
+            &[],
+            &BitcoinRpcRateLimits {
+                default: Some(1),
+                endpoints: HashMap::new(),
+            },
+        );
+        assert_eq!(endpoints.acquire("http://node-1:8332"), Duration::ZERO);
+        assert!(endpoints.acquire("http://node-1:8332") > Duration::from_millis(900));
+        assert_eq!(endpoints.acquire("http://node-2:8332"), Duration::ZERO);
+    }
+
+    #[test]
+    fn sum_rate_limits_of_endpoints() {
+        let mut rate_limits = BitcoinRpcRateLimits {
+            default: None,
+            endpoints: HashMap::from([("http://node-2:8332".to_string(), 20)]),
+        };
+        assert_eq!(rate_limits.total_rate(["http://node-2:8332"]), Some(20));
+        assert_eq!(
+            rate_limits.total_rate(["http://node-1:8332", "http://node-2:8332"]),
+            None
+        );
+        rate_limits.default = Some(100);
+        assert_eq!(
+            rate_limits.total_rate(["http://node-1:8332", "http://node-2:8332"]),
+            Some(120)
+        );
+    }
+
+    #[test]
+    fn throttle_overloaded_endpoints() {
+        let mut limiter = RateLimiter::new(Some(10));
+        limiter.throttle();
+        assert_eq!(limiter.rate, Some(5.0));
+        for _ in 0..20 {
+            limiter.recover();
+        }
+        assert_eq!(limiter.rate, Some(10.0));
+
+        let mut limiter = RateLimiter::new(None);
+        limiter.throttle();
+        assert_eq!(limiter.rate, Some(OVERLOADED_ENDPOINT_INITIAL_RATE / 2.0));
+        for _ in 0..64 {
+            limiter.recover();
+        }
+        assert_eq!(limiter.rate, None);
+    }
+}
@@ -0,0 +1,298 @@
+use chainhook_types::{Src20OperationData, StampOperation, StampRevealData, TxIn, TxOut};
+use serde_json::Value as JsonValue;
+
+/// Stamp payloads start with `stamp:` (case insensitive).
+const STAMP_PREFIX: &[u8] = b"stamp:";
+/// Counterparty messages are chunked, each chunk starting with `CNTRPRTY`.
+const COUNTERPARTY_PREFIX: &[u8] = b"CNTRPRTY";
+
+/// Stamps are stored in the 2 first keys of 1-of-3 bare multisig outputs, ARC4 encrypted with
+/// the txid of the first input. SRC-20 payloads are encrypted at once and prefixed with their
+/// length, classic stamps are embedded in Counterparty messages, encrypted output by output.
+pub fn try_parse_stamp_operation(inputs: &[TxIn], outputs: &[TxOut]) -> Option<StampOperation> {
+    let chunks = outputs
+        .iter()
+        .filter_map(multisig_data_chunk)
+        .collect::<Vec<Vec<u8>>>();
+    if chunks.is_empty() {
+        return None;
+    }
+    let txid = &inputs.first()?.previous_output.txid;
+    let key = hex::decode(txid.strip_prefix("0x").unwrap_or(txid)).ok()?;
+    if key.is_empty() {
+        return None;
+    }
+
+    let decrypted = arc4(&key, &chunks.concat());
+    if decrypted.len() >= 2 {
+        let length = u16::from_be_bytes([decrypted[0], decrypted[1]]) as usize;
+        if let Some(payload) = decrypted.get(2..2 + length) {
+            if let Some(operation) = parse_stamp(payload) {
+                return Some(operation);
+            }
+        }
+    }
+
+    let mut message = vec![];
+    for chunk in chunks.iter() {
+        let decrypted = arc4(&key, chunk);
+        let length = *decrypted.first()? as usize;
+        let data = decrypted.get(1..1 + length)?;
+        message.extend_from_slice(data.strip_prefix(COUNTERPARTY_PREFIX)?);
+    }
+    let position = message
+        .windows(STAMP_PREFIX.len())
+        .position(|window| window.eq_ignore_ascii_case(STAMP_PREFIX))?;
+    parse_stamp(&message[position..])
+}
+
+/// Data held by a `OP_1 <key> <key> <key> OP_3 OP_CHECKMULTISIG` output, minus the sign and
+/// nonce bytes of the keys. The third key, spendable, is ignored.
+fn multisig_data_chunk(output: &TxOut) -> Option<Vec<u8>> {
+    let script = hex::decode(output.script_pubkey.strip_prefix("0x")?).ok()?;
+    if script.len() != 105
+        || script[0] != 0x51
+        || script[1] != 0x21
+        || script[35] != 0x21
+        || script[69] != 0x21
+        || script[103] != 0x53
+        || script[104] != 0xae
+    {
+        return None;
+    }
+    let mut chunk = script[3..34].to_vec();
+    chunk.extend_from_slice(&script[37..68]);
+    Some(chunk)
+}
+
+fn parse_stamp(payload: &[u8]) -> Option<StampOperation> {
+    if payload.len() <= STAMP_PREFIX.len()
+        || !payload[..STAMP_PREFIX.len()].eq_ignore_ascii_case(STAMP_PREFIX)
+    {
+        return None;
+    }
+    let content = &payload[STAMP_PREFIX.len()..];
+
+    if content[0] == b'{' {
+        let json: JsonValue = serde_json::from_slice(content).ok()?;
+        if !json.get("p")?.as_str()?.eq_ignore_ascii_case("src-20") {
+            return None;
+        }
+        let field = |name: &str| match json.get(name) {
+            Some(JsonValue::String(value)) => Some(value.to_string()),
+            Some(JsonValue::Number(value)) => Some(value.to_string()),
+            _ => None,
+        };
+        return Some(StampOperation::Src20(Src20OperationData {
+            op: field("op")?.to_lowercase(),
+            tick: field("tick")?.to_lowercase(),
+            amt: field("amt"),
+            max: field("max"),
+            lim: field("lim"),
+        }));
+    }
+
+    // The base64 content can be followed by the other fields of the Counterparty message
+    let end = content
+        .iter()
+        .position(|byte| !(byte.is_ascii_alphanumeric() || b"+/=".contains(byte)))
+        .unwrap_or(content.len());
+    let bytes = base64::decode(&content[..end]).ok()?;
+    if bytes.is_empty() {
+        return None;
+    }
+    Some(StampOperation::StampRevealed(StampRevealData {
+        content_type: guess_content_type(&bytes).into(),
+        content_bytes: format!("0x{}", hex::encode(&bytes)),
+        content_length: bytes.len(),
+    }))
+}
+
+fn guess_content_type(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if bytes.starts_with(b"GIF8") {
+        "image/gif"
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        "image/jpeg"
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(&b"WEBP"[..]) {
+        "image/webp"
+    } else if bytes.starts_with(b"<svg") || bytes.starts_with(b"<?xml") {
+        "image/svg+xml"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+fn arc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state = (0..=255).collect::<Vec<u8>>();
+    let mut j = 0u8;
+    for i in 0..=255u8 {
+        j = j
+            .wrapping_add(state[i as usize])
+            .wrapping_add(key[i as usize % key.len()]);
+        state.swap(i as usize, j as usize);
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chainhook_types::OutPoint;
+
+    use super::*;
+
+    const TXID: &str = "0x9eebe848baaf8dd4810e4e4a91168e2e471c949439faf5d768750ca21d067689";
+
+    fn inputs() -> Vec<TxIn> {
+        vec![TxIn {
+            previous_output: OutPoint {
+                txid: TXID.into(),
+                vout: 0,
+                value: 10_000,
+                block_height: 0,
+            },
+            script_sig: String::new(),
+            sequence: 0,
+            witness: vec![],
+        }]
+    }
+
+    fn key() -> Vec<u8> {
+        hex::decode(&TXID[2..]).unwrap()
+    }
+
+    fn multisig_output(chunk: &[u8]) -> TxOut {
+        let mut chunk = chunk.to_vec();
+        chunk.resize(62, 0);
+        let mut script = vec![0x51, 0x21, 0x02];
+        script.extend_from_slice(&chunk[..31]);
+        script.extend_from_slice(&[0x00, 0x21, 0x03]);
+        script.extend_from_slice(&chunk[31..]);
+        script.extend_from_slice(&[0x00, 0x21, 0x02]);
+        script.extend_from_slice(&[0x01; 32]);
+        script.extend_from_slice(&[0x53, 0xae]);
+        TxOut {
+            value: 800,
+            script_pubkey: format!("0x{}", hex::encode(script)),
+        }
+    }
+
+    fn p2pkh_output() -> TxOut {
+        TxOut {
+            value: 800,
+            script_pubkey: "0x76a914000000000000000000000000000000000000000088ac".into(),
+        }
+    }
+
+    fn src20_outputs(content: &str) -> Vec<TxOut> {
+        let mut payload = (content.len() as u16).to_be_bytes().to_vec();
+        payload.extend_from_slice(content.as_bytes());
+        payload.resize((payload.len() + 61) / 62 * 62, 0);
+        let encrypted = arc4(&key(), &payload);
+        [p2pkh_output()]
+            .into_iter()
+            .chain(encrypted.chunks(62).map(multisig_output))
+            .collect()
+    }
+
+    fn counterparty_outputs(message: &[u8]) -> Vec<TxOut> {
+        message
+            .chunks(62 - 1 - COUNTERPARTY_PREFIX.len())
+            .map(|data| {
+                let mut chunk = vec![(COUNTERPARTY_PREFIX.len() + data.len()) as u8];
+                chunk.extend_from_slice(COUNTERPARTY_PREFIX);
+                chunk.extend_from_slice(data);
+                chunk.resize(62, 0);
+                multisig_output(&arc4(&key(), &chunk))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn arc4_test_vector() {
+        assert_eq!(
+            hex::encode(arc4(b"Key", b"Plaintext")),
+            "bbf316e8d940af0ad3"
+        );
+    }
+
+    #[test]
+    fn parse_src20_operations() {
+        assert_eq!(
+            try_parse_stamp_operation(
+                &inputs(),
+                &src20_outputs(
+                    r#"stamp:{"p":"src-20","op":"deploy","tick":"KEVIN","max":"2300","lim":100}"#
+                )
+            ),
+            Some(StampOperation::Src20(Src20OperationData {
+                op: "deploy".into(),
+                tick: "kevin".into(),
+                amt: None,
+                max: Some("2300".into()),
+                lim: Some("100".into()),
+            }))
+        );
+        assert_eq!(
+            try_parse_stamp_operation(
+                &inputs(),
+                &src20_outputs(r#"STAMP:{"p":"SRC-20","op":"transfer","tick":"kevin","amt":"5"}"#)
+            ),
+            Some(StampOperation::Src20(Src20OperationData {
+                op: "transfer".into(),
+                tick: "kevin".into(),
+                amt: Some("5".into()),
+                max: None,
+                lim: None,
+            }))
+        );
+        assert_eq!(
+            try_parse_stamp_operation(
+                &inputs(),
+                &src20_outputs(r#"stamp:{"p":"brc-20","op":"mint","tick":"ordi","amt":"1"}"#)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_classic_stamps() {
+        let image = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR\x00\x00\x00\x18\x00\x00\x00\x18";
+        let mut message = b"\x00\x00\x00\x16\x00\x00\x00\x00\x00\x01\x00\x00".to_vec();
+        message.extend_from_slice(b"STAMP:");
+        message.extend_from_slice(base64::encode(image).as_bytes());
+        assert_eq!(
+            try_parse_stamp_operation(&inputs(), &counterparty_outputs(&message)),
+            Some(StampOperation::StampRevealed(StampRevealData {
+                content_type: "image/png".into(),
+                content_bytes: format!("0x{}", hex::encode(image)),
+                content_length: image.len(),
+            }))
+        );
+    }
+
+    #[test]
+    fn ignore_transactions_without_stamps() {
+        assert_eq!(
+            try_parse_stamp_operation(&inputs(), &[p2pkh_output()]),
+            None
+        );
+        assert_eq!(
+            try_parse_stamp_operation(&inputs(), &[multisig_output(&[0xff; 62])]),
+            None
+        );
+        assert_eq!(
+            try_parse_stamp_operation(&[], &src20_outputs(r#"stamp:{"p":"src-20"}"#)),
+            None
+        );
+    }
+}
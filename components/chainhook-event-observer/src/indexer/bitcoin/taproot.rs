@@ -0,0 +1,124 @@
+use bitcoincore_rpc::bitcoin::hashes::Hash;
+use bitcoincore_rpc::bitcoin::util::taproot::{LeafVersion, TapLeafHash};
+use bitcoincore_rpc::bitcoin::Script;
+use chainhook_types::{TaprootSpendData, TaprootSpendType};
+
+/// Control blocks are made of the leaf version and the internal key (33 bytes), followed by
+/// up to 128 nodes of the merkle path (32 bytes each).
+const CONTROL_BLOCK_BASE_SIZE: usize = 33;
+const CONTROL_BLOCK_NODE_SIZE: usize = 32;
+const CONTROL_BLOCK_MAX_NODES: usize = 128;
+const TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+/// First byte of the annex, optionally ending the witness stack.
+const ANNEX_TAG: u8 = 0x50;
+
+/// Classifies a taproot spend from the witness of the input, the script of the spent output
+/// not being available. Key path spends are made of a single schnorr signature, script path
+/// spends are ending with the tapleaf script followed by its control block.
+pub fn try_parse_taproot_spend(
+    input_index: usize,
+    witness: &[Vec<u8>],
+) -> Option<TaprootSpendData> {
+    let witness = match witness.split_last() {
+        Some((last, stack)) if !stack.is_empty() && last.first() == Some(&ANNEX_TAG) => stack,
+        _ => witness,
+    };
+
+    if let [signature] = witness {
+        if signature.len() != 64 && signature.len() != 65 {
+            return None;
+        }
+        return Some(TaprootSpendData {
+            input_index,
+            spend_type: TaprootSpendType::KeyPath,
+            tapleaf_script: None,
+            tapleaf_hash: None,
+        });
+    }
+
+    let (control_block, stack) = witness.split_last()?;
+    let script = stack.last()?;
+    if control_block.len() < CONTROL_BLOCK_BASE_SIZE
+        || (control_block.len() - CONTROL_BLOCK_BASE_SIZE) % CONTROL_BLOCK_NODE_SIZE != 0
+        || (control_block.len() - CONTROL_BLOCK_BASE_SIZE) / CONTROL_BLOCK_NODE_SIZE
+            > CONTROL_BLOCK_MAX_NODES
+    {
+        return None;
+    }
+    // Future leaf versions are not considered, they would be mistaken for P2WPKH public keys
+    if control_block[0] & 0xfe != TAPSCRIPT_LEAF_VERSION {
+        return None;
+    }
+    let tapleaf_hash =
+        TapLeafHash::from_script(&Script::from(script.clone()), LeafVersion::TapScript);
+    Some(TaprootSpendData {
+        input_index,
+        spend_type: TaprootSpendType::ScriptPath,
+        tapleaf_script: Some(format!("0x{}", hex::encode(script))),
+        tapleaf_hash: Some(format!("0x{}", hex::encode(tapleaf_hash.into_inner()))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP-341 wallet test vector
+    const SCRIPT: &str = "20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac";
+    const TAPLEAF_HASH: &str = "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21";
+
+    fn control_block(nodes: usize) -> Vec<u8> {
+        let mut control_block = vec![0xc1];
+        control_block.extend_from_slice(&[0x02; 32]);
+        control_block.resize(33 + 32 * nodes, 0x03);
+        control_block
+    }
+
+    #[test]
+    fn parse_key_path_spends() {
+        let expected = Some(TaprootSpendData {
+            input_index: 1,
+            spend_type: TaprootSpendType::KeyPath,
+            tapleaf_script: None,
+            tapleaf_hash: None,
+        });
+        assert_eq!(try_parse_taproot_spend(1, &[vec![0x01; 64]]), expected);
+        assert_eq!(
+            try_parse_taproot_spend(1, &[vec![0x01; 65], vec![ANNEX_TAG, 0x00]]),
+            expected
+        );
+    }
+
+    #[test]
+    fn parse_script_path_spends() {
+        let script = hex::decode(SCRIPT).unwrap();
+        let expected = Some(TaprootSpendData {
+            input_index: 0,
+            spend_type: TaprootSpendType::ScriptPath,
+            tapleaf_script: Some(format!("0x{SCRIPT}")),
+            tapleaf_hash: Some(format!("0x{TAPLEAF_HASH}")),
+        });
+        assert_eq!(
+            try_parse_taproot_spend(0, &[vec![0x01; 64], script.clone(), control_block(0)]),
+            expected
+        );
+        assert_eq!(
+            try_parse_taproot_spend(
+                0,
+                &[vec![0x01; 64], script, control_block(2), vec![ANNEX_TAG]]
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn ignore_other_spends() {
+        // P2WPKH: signature and public key
+        assert_eq!(
+            try_parse_taproot_spend(0, &[vec![0x30; 71], vec![0x02; 33]]),
+            None
+        );
+        assert_eq!(try_parse_taproot_spend(0, &[vec![0x30; 71]]), None);
+        assert_eq!(try_parse_taproot_spend(0, &[]), None);
+    }
+}
@@ -3,6 +3,7 @@ use bitcoincore_rpc::bitcoin::Script;
 use crate::hord::inscription::InscriptionParser;
 
 use super::super::tests::{helpers, process_bitcoin_blocks_and_check_expectations};
+use super::{get_op_return_data, parse_zmq_block_notification};
 
 #[test]
 fn test_bitcoin_vector_001() {
@@ -227,3 +228,43 @@ fn test_ordinal_inscription_parsing() {
 
     println!("{:?}", inscription);
 }
+
+#[test]
+fn test_op_return_data_parsing() {
+    assert_eq!(
+        get_op_return_data("0x6a0a6f6d6e690000000000"),
+        Some(b"omni\0\0\0\0\0\0".to_vec())
+    );
+    assert_eq!(get_op_return_data("6a4c03585832"), Some(b"XX2".to_vec()));
+    assert_eq!(get_op_return_data("0x6a0258320158"), Some(b"X2X".to_vec()));
+    assert_eq!(get_op_return_data("0x6a"), Some(vec![]));
+    // Truncated push, or non push opcode
+    assert_eq!(get_op_return_data("0x6a0558"), None);
+    assert_eq!(get_op_return_data("0x6a0158ac"), None);
+    assert_eq!(
+        get_op_return_data("0x76a914000000000000000000000000000000000000000088ac"),
+        None
+    );
+}
+
+#[test]
+fn test_parse_zmq_block_notification() {
+    let genesis_hash = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+    let genesis_header = hex::decode("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c").unwrap();
+    // The header is followed by the transactions
+    let mut genesis_block = genesis_header.clone();
+    genesis_block.extend_from_slice(&[0x01, 0x01]);
+    assert_eq!(
+        parse_zmq_block_notification(b"rawblock", &genesis_block),
+        Some(genesis_hash.to_string())
+    );
+    assert_eq!(
+        parse_zmq_block_notification(b"hashblock", &hex::decode(genesis_hash).unwrap()),
+        Some(genesis_hash.to_string())
+    );
+    assert_eq!(
+        parse_zmq_block_notification(b"rawblock", &genesis_header[..79]),
+        None
+    );
+    assert_eq!(parse_zmq_block_notification(b"rawtx", &genesis_block), None);
+}
@@ -2,6 +2,7 @@ pub mod bitcoin;
 pub mod fork_scratch_pad;
 pub mod stacks;
 
+use crate::indexer::bitcoin::BitcoinRpcRateLimits;
 use crate::utils::{AbstractBlock, Context};
 
 use chainhook_types::{
@@ -55,8 +56,15 @@ pub struct IndexerConfig {
     pub stacks_network: StacksNetwork,
     pub stacks_node_rpc_url: String,
     pub bitcoind_rpc_url: String,
+    /// RPC endpoints of other bitcoind nodes, failed over to when the main one is unavailable.
+    pub bitcoind_fallback_rpc_urls: Vec<String>,
+    /// Requests per second allowed on each bitcoind endpoint.
+    pub bitcoind_rpc_rate_limits: BitcoinRpcRateLimits,
     pub bitcoind_rpc_username: String,
     pub bitcoind_rpc_password: String,
+    /// bitcoind serving its REST interface (`rest=1`), blocks are then fetched from it when
+    /// syncing the hord db.
+    pub bitcoind_rest_enabled: bool,
     pub bitcoin_block_signaling: BitcoinBlockSignaling,
 }
 
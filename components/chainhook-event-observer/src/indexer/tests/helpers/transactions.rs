@@ -105,9 +105,16 @@ pub fn generate_test_tx_bitcoin_p2pkh_transfer(
             inputs: vec![],
             outputs,
             ordinal_operations: vec![],
+            brc20_operations: vec![],
+            rune_operations: vec![],
+            stamp_operations: vec![],
+            sat_movements: vec![],
+            taproot_spends: vec![],
             stacks_operations: vec![],
             proof: None,
             fee: 0,
+            vsize: 0,
+            fee_rate: 0.0,
         },
     }
 }
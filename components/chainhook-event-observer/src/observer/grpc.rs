@@ -0,0 +1,343 @@
+use super::HordDbReader;
+use crate::hord::db::{
+    find_inscription_row_with_id, find_inscription_rows_at_outpoint,
+    find_inscription_with_ordinal_number, HordDbError, InscriptionRow,
+};
+use crate::utils::Context;
+use chainhook_types::{BitcoinBlockData, BitcoinChainEvent, OrdinalOperation};
+use hiro_system_kit::slog;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("chainhook.ordinals.v1");
+}
+
+use proto::ordinal_event::Operation;
+use proto::ordinals_server::{Ordinals, OrdinalsServer};
+
+/// Events emitted while subscribers are too slow to consume them are dropped, and reported to
+/// the lagging subscribers by ending their stream with a `DATA_LOSS` status.
+const ORDINAL_EVENTS_CHANNEL_CAPACITY: usize = 16_384;
+
+pub struct OrdinalsService {
+    hord_db_reader: HordDbReader,
+    events_tx: broadcast::Sender<proto::OrdinalEvent>,
+    ctx: Context,
+}
+
+impl OrdinalsService {
+    fn query<F, R>(&self, query: F) -> Result<R, Status>
+    where
+        F: FnOnce(&rusqlite::Connection) -> Result<R, HordDbError>,
+    {
+        self.hord_db_reader.query(&self.ctx, query).map_err(|e| {
+            self.ctx
+                .try_log(|logger| slog::error!(logger, "Unable to query hord db: {}", e));
+            Status::internal(e.to_string())
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Ordinals for OrdinalsService {
+    type StreamOrdinalEventsStream =
+        Pin<Box<dyn Stream<Item = Result<proto::OrdinalEvent, Status>> + Send>>;
+
+    async fn stream_ordinal_events(
+        &self,
+        request: Request<proto::StreamOrdinalEventsRequest>,
+    ) -> Result<Response<Self::StreamOrdinalEventsStream>, Status> {
+        let transfers_only = request.into_inner().transfers_only;
+        let stream =
+            BroadcastStream::new(self.events_tx.subscribe()).filter_map(move |event| match event {
+                Ok(event) => match event.operation {
+                    Some(Operation::InscriptionRevealed(_)) if transfers_only => None,
+                    _ => Some(Ok(event)),
+                },
+                Err(BroadcastStreamRecvError::Lagged(skipped)) => Some(Err(Status::data_loss(
+                    format!("{skipped} events dropped, subscriber too slow"),
+                ))),
+            });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_inscription(
+        &self,
+        request: Request<proto::GetInscriptionRequest>,
+    ) -> Result<Response<proto::Inscription>, Status> {
+        let inscription_id = request.into_inner().inscription_id;
+        match self.query(|conn| find_inscription_row_with_id(&inscription_id, conn))? {
+            Some(inscription) => Ok(Response::new(inscription.into())),
+            None => Err(Status::not_found(format!(
+                "inscription {inscription_id} not found"
+            ))),
+        }
+    }
+
+    async fn get_inscription_by_ordinal_number(
+        &self,
+        request: Request<proto::GetInscriptionByOrdinalNumberRequest>,
+    ) -> Result<Response<proto::Inscription>, Status> {
+        let ordinal_number = request.into_inner().ordinal_number;
+        let inscription = self.query(|conn| {
+            match find_inscription_with_ordinal_number(&ordinal_number, conn, &self.ctx) {
+                Some(inscription_id) => find_inscription_row_with_id(&inscription_id, conn),
+                None => Ok(None),
+            }
+        })?;
+        match inscription {
+            Some(inscription) => Ok(Response::new(inscription.into())),
+            None => Err(Status::not_found(format!(
+                "no inscription on ordinal {ordinal_number}"
+            ))),
+        }
+    }
+
+    async fn get_inscriptions_at_outpoint(
+        &self,
+        request: Request<proto::GetInscriptionsAtOutpointRequest>,
+    ) -> Result<Response<proto::Inscriptions>, Status> {
+        let outpoint = request.into_inner().outpoint;
+        let outpoint = outpoint.strip_prefix("0x").unwrap_or(&outpoint);
+        let inscriptions = self.query(|conn| find_inscription_rows_at_outpoint(outpoint, conn))?;
+        Ok(Response::new(proto::Inscriptions {
+            inscriptions: inscriptions.into_iter().map(|i| i.into()).collect(),
+        }))
+    }
+}
+
+impl From<InscriptionRow> for proto::Inscription {
+    fn from(row: InscriptionRow) -> Self {
+        proto::Inscription {
+            inscription_id: row.inscription_id,
+            block_height: row.block_height,
+            block_hash: row.block_hash,
+            outpoint_to_watch: row.outpoint_to_watch,
+            ordinal_number: row.ordinal_number,
+            inscription_number: row.inscription_number,
+            offset: row.offset,
+            transfers: row.transfers,
+            content_type: row.content_type,
+            content_length: row.content_length,
+        }
+    }
+}
+
+/// Ordinal operations of the blocks applied and rolled back by `chain_event`. The operations of
+/// a rolled back block are listed in reverse order, the way they are undone.
+pub fn ordinal_events_from_chain_event(
+    chain_event: &BitcoinChainEvent,
+) -> Vec<proto::OrdinalEvent> {
+    let mut events = vec![];
+    match chain_event {
+        BitcoinChainEvent::ChainUpdatedWithBlocks(data) => {
+            for block in data.new_blocks.iter() {
+                append_ordinal_events(block, false, &mut events);
+            }
+        }
+        BitcoinChainEvent::ChainUpdatedWithReorg(data) => {
+            for block in data.blocks_to_rollback.iter() {
+                append_ordinal_events(block, true, &mut events);
+            }
+            for block in data.blocks_to_apply.iter() {
+                append_ordinal_events(block, false, &mut events);
+            }
+        }
+    }
+    events
+}
+
+fn append_ordinal_events(
+    block: &BitcoinBlockData,
+    rollback: bool,
+    events: &mut Vec<proto::OrdinalEvent>,
+) {
+    let block_identifier = proto::BlockIdentifier {
+        index: block.block_identifier.index,
+        hash: block.block_identifier.hash.clone(),
+    };
+    let mut block_events = vec![];
+    for tx in block.transactions.iter() {
+        for operation in tx.metadata.ordinal_operations.iter() {
+            let operation = match operation {
+                OrdinalOperation::InscriptionRevealed(data) => {
+                    Operation::InscriptionRevealed(proto::InscriptionRevealed {
+                        inscription_id: data.inscription_id.clone(),
+                        inscription_number: data.inscription_number,
+                        ordinal_number: data.ordinal_number,
+                        ordinal_rarity: data.ordinal_rarity.clone(),
+                        content_type: data.content_type.clone(),
+                        content_length: data.content_length as u64,
+                        inscriber_address: data.inscriber_address.clone(),
+                        satpoint_post_inscription: data.satpoint_post_inscription.clone(),
+                        inscription_output_value: data.inscription_output_value,
+                        parent_inscription_id: data.parent_inscription_id.clone(),
+                    })
+                }
+                OrdinalOperation::InscriptionTransferred(data) => {
+                    Operation::InscriptionTransferred(proto::InscriptionTransferred {
+                        inscription_id: data.inscription_id.clone(),
+                        inscription_number: data.inscription_number,
+                        ordinal_number: data.ordinal_number,
+                        updated_address: data.updated_address.clone(),
+                        satpoint_pre_transfer: data.satpoint_pre_transfer.clone(),
+                        satpoint_post_transfer: data.satpoint_post_transfer.clone(),
+                        post_transfer_output_value: data.post_transfer_output_value,
+                        parent_inscription_id: data.parent_inscription_id.clone(),
+                    })
+                }
+            };
+            block_events.push(proto::OrdinalEvent {
+                block_identifier: Some(block_identifier.clone()),
+                transaction_hash: tx.transaction_identifier.hash.clone(),
+                rollback,
+                operation: Some(operation),
+            });
+        }
+    }
+    if rollback {
+        block_events.reverse();
+    }
+    events.append(&mut block_events);
+}
+
+/// Serves the `Ordinals` service on `port`, in a dedicated thread. Events sent to the returned
+/// channel are streamed to the subscribers.
+pub fn start_grpc_server(
+    port: u16,
+    hord_db_reader: HordDbReader,
+    ctx: &Context,
+) -> broadcast::Sender<proto::OrdinalEvent> {
+    let (events_tx, _) = broadcast::channel(ORDINAL_EVENTS_CHANNEL_CAPACITY);
+    let service = OrdinalsService {
+        hord_db_reader,
+        events_tx: events_tx.clone(),
+        ctx: ctx.clone(),
+    };
+    let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
+    let ctx = ctx.clone();
+    let _ = std::thread::spawn(move || {
+        ctx.try_log(|logger| slog::info!(logger, "Serving gRPC ordinals service on {}", address));
+        let res = hiro_system_kit::nestable_block_on(
+            tonic::transport::Server::builder()
+                .add_service(OrdinalsServer::new(service))
+                .serve(address),
+        );
+        if let Err(e) = res {
+            ctx.try_log(|logger| slog::error!(logger, "gRPC server stopped: {}", e));
+        }
+    });
+    events_tx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chainhook_types::{
+        BitcoinBlockMetadata, BitcoinChainUpdatedWithBlocksData, BitcoinChainUpdatedWithReorgData,
+        BitcoinTransactionData, BitcoinTransactionMetadata, BlockIdentifier,
+        OrdinalInscriptionTransferData, TransactionIdentifier,
+    };
+
+    fn build_block(index: u64, transfers: &[&str]) -> BitcoinBlockData {
+        BitcoinBlockData {
+            block_identifier: BlockIdentifier {
+                index,
+                hash: format!("0x{:064x}", index),
+            },
+            parent_block_identifier: BlockIdentifier {
+                index: index - 1,
+                hash: format!("0x{:064x}", index - 1),
+            },
+            timestamp: 0,
+            transactions: vec![BitcoinTransactionData {
+                transaction_identifier: TransactionIdentifier {
+                    hash: format!("0x{:064x}", index),
+                },
+                operations: vec![],
+                metadata: BitcoinTransactionMetadata {
+                    inputs: vec![],
+                    outputs: vec![],
+                    stacks_operations: vec![],
+                    ordinal_operations: transfers
+                        .iter()
+                        .map(|inscription_id| {
+                            OrdinalOperation::InscriptionTransferred(
+                                OrdinalInscriptionTransferData {
+                                    inscription_number: 0,
+                                    inscription_id: inscription_id.to_string(),
+                                    ordinal_number: 0,
+                                    updated_address: None,
+                                    satpoint_pre_transfer: "ab:0:0".into(),
+                                    satpoint_post_transfer: "cd:0:0".into(),
+                                    post_transfer_output_value: None,
+                                    parent_inscription_id: None,
+                                },
+                            )
+                        })
+                        .collect(),
+                    brc20_operations: vec![],
+                    rune_operations: vec![],
+                    stamp_operations: vec![],
+                    sat_movements: vec![],
+                    taproot_spends: vec![],
+                    proof: None,
+                    fee: 0,
+                    vsize: 0,
+                    fee_rate: 0.0,
+                },
+            }],
+            metadata: BitcoinBlockMetadata {},
+        }
+    }
+
+    fn summarize(events: &[proto::OrdinalEvent]) -> Vec<(u64, bool, String)> {
+        events
+            .iter()
+            .map(|event| match &event.operation {
+                Some(Operation::InscriptionTransferred(data)) => (
+                    event.block_identifier.as_ref().unwrap().index,
+                    event.rollback,
+                    data.inscription_id.clone(),
+                ),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ordinal_events_follow_the_chain_event() {
+        let events = ordinal_events_from_chain_event(&BitcoinChainEvent::ChainUpdatedWithBlocks(
+            BitcoinChainUpdatedWithBlocksData {
+                new_blocks: vec![build_block(10, &["a", "b"]), build_block(11, &[])],
+                confirmed_blocks: vec![],
+            },
+        ));
+        assert_eq!(
+            summarize(&events),
+            vec![(10, false, "a".into()), (10, false, "b".into())]
+        );
+        assert_eq!(events[0].transaction_hash, format!("0x{:064x}", 10));
+
+        let events = ordinal_events_from_chain_event(&BitcoinChainEvent::ChainUpdatedWithReorg(
+            BitcoinChainUpdatedWithReorgData {
+                blocks_to_rollback: vec![build_block(10, &["a", "b"])],
+                blocks_to_apply: vec![build_block(10, &["c"])],
+                confirmed_blocks: vec![],
+            },
+        ));
+        assert_eq!(
+            summarize(&events),
+            vec![
+                (10, true, "b".into()),
+                (10, true, "a".into()),
+                (10, false, "c".into())
+            ]
+        );
+    }
+}
@@ -0,0 +1,536 @@
+use super::websocket::send_websocket_message;
+use super::{BitcoinChainMempoolEvent, BitcoinConfig, ChainhookStore, ObserverCommand};
+use crate::chainhooks::bitcoin::{
+    evaluate_bitcoin_chainhooks_on_mempool_transactions, handle_bitcoin_mempool_hook_action,
+    BitcoinChainhookOccurrence, BitcoinMempoolDropReason, BitcoinMempoolTriggerChainhook,
+};
+use crate::indexer::bitcoin::{
+    download_and_parse_mempool_transaction, retrieve_block_count, retrieve_block_txids,
+    retrieve_mempool_txids, standardize_bitcoin_mempool_transaction,
+};
+use crate::utils::amqp::publish_amqp_message;
+use crate::utils::kafka::produce_kafka_message;
+use crate::utils::nats::publish_nats_message;
+use crate::utils::pubsub::publish_pubsub_message;
+use crate::utils::redis_streams::append_to_redis_stream;
+use crate::utils::{send_webhook_request, Context};
+use bitcoincore_rpc::bitcoin::Txid;
+use chainhook_types::bitcoin::TxIn;
+use chainhook_types::{BitcoinBlockData, BitcoinTransactionData, TransactionIdentifier};
+use hiro_system_kit::slog;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::Instant;
+
+const BITCOIN_MEMPOOL_POLLING_INTERVAL: Duration = Duration::from_secs(5);
+/// New transactions being announced over ZMQ, polling is only catching up with the
+/// notifications missed and the transactions dropped.
+#[cfg(feature = "zeromq")]
+const BITCOIN_MEMPOOL_ZMQ_POLLING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Watches the mempool of bitcoind. New transactions are admitted as soon as they are announced
+/// over ZMQ (`rawtx`) when available, and `getrawmempool` is diffed periodically to catch the
+/// transactions missed and to detect the ones dropped without being confirmed.
+pub fn start_bitcoin_mempool_watcher(
+    bitcoin_config: BitcoinConfig,
+    observer_commands_tx: Sender<ObserverCommand>,
+    ctx: &Context,
+) {
+    let (txids_tx, txids_rx) = unbounded_channel();
+    #[allow(unused_mut)]
+    let mut polling_interval = BITCOIN_MEMPOOL_POLLING_INTERVAL;
+
+    #[cfg(feature = "zeromq")]
+    if let chainhook_types::BitcoinBlockSignaling::ZeroMQ(ref bitcoind_zmq_url) =
+        bitcoin_config.bitcoin_block_signaling
+    {
+        start_rawtx_listener(bitcoind_zmq_url.clone(), txids_tx.clone(), ctx.clone());
+        polling_interval = BITCOIN_MEMPOOL_ZMQ_POLLING_INTERVAL;
+    }
+
+    // Mempools differ from a node to another, snapshots are only diffed against the main one
+    let bitcoin_config = BitcoinConfig {
+        rpc_endpoints: bitcoin_config.rpc_endpoints.main_endpoint_only(),
+        ..bitcoin_config
+    };
+    let ctx = ctx.clone();
+    hiro_system_kit::thread_named("Bitcoind mempool watcher")
+        .spawn(move || {
+            // Keeps the channel open when no ZMQ listener is feeding it
+            let _txids_tx = txids_tx;
+            hiro_system_kit::nestable_block_on(watch_bitcoin_mempool(
+                bitcoin_config,
+                polling_interval,
+                txids_rx,
+                observer_commands_tx,
+                ctx,
+            ));
+        })
+        .expect("unable to spawn thread");
+}
+
+#[cfg(feature = "zeromq")]
+fn start_rawtx_listener(bitcoind_zmq_url: String, txids_tx: UnboundedSender<Txid>, ctx: Context) {
+    use bitcoincore_rpc::bitcoin::{consensus, Transaction};
+    use std::error::Error;
+    use zeromq::{Socket, SocketRecv};
+
+    hiro_system_kit::thread_named("Bitcoind zmq rawtx listener")
+        .spawn(move || {
+            let _: Result<(), Box<dyn Error>> = hiro_system_kit::nestable_block_on(async move {
+                let mut socket = zeromq::SubSocket::new();
+                socket.connect(&bitcoind_zmq_url).await?;
+                socket.subscribe("rawtx").await?;
+
+                loop {
+                    let message = match socket.recv().await {
+                        Ok(message) => message,
+                        Err(e) => {
+                            ctx.try_log(|logger| {
+                                slog::error!(
+                                    logger,
+                                    "Unable to receive ZMQ message: {}",
+                                    e.to_string()
+                                )
+                            });
+                            continue;
+                        }
+                    };
+                    let raw_tx = match message.get(1) {
+                        Some(raw_tx) => raw_tx.to_vec(),
+                        None => continue,
+                    };
+                    if let Ok(tx) = consensus::deserialize::<Transaction>(&raw_tx) {
+                        if txids_tx.send(tx.txid()).is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+            });
+        })
+        .expect("unable to spawn thread");
+}
+
+async fn watch_bitcoin_mempool(
+    bitcoin_config: BitcoinConfig,
+    polling_interval: Duration,
+    mut txids_rx: UnboundedReceiver<Txid>,
+    observer_commands_tx: Sender<ObserverCommand>,
+    ctx: Context,
+) {
+    // Transactions already pending when the watcher starts are not evaluated
+    let (mut known_txids, mut chain_tip) = loop {
+        match retrieve_mempool_snapshot(&bitcoin_config).await {
+            Ok(snapshot) => break snapshot,
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(logger, "Unable to retrieve bitcoind mempool: {e}")
+                });
+                tokio::time::sleep(polling_interval).await;
+            }
+        }
+    };
+    let mut next_poll = Instant::now() + polling_interval;
+
+    loop {
+        // Announced transactions are handled until the next poll is due
+        tokio::select! {
+            txid = txids_rx.recv() => {
+                let txid = match txid {
+                    Some(txid) => txid,
+                    None => break,
+                };
+                if known_txids.insert(txid) {
+                    admit_transactions(
+                        vec![txid],
+                        chain_tip + 1,
+                        &bitcoin_config,
+                        &observer_commands_tx,
+                        &ctx,
+                    )
+                    .await;
+                }
+                continue;
+            }
+            _ = tokio::time::sleep_until(next_poll) => {}
+        }
+        next_poll = Instant::now() + polling_interval;
+
+        let (txids, new_chain_tip) = match retrieve_mempool_snapshot(&bitcoin_config).await {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(logger, "Unable to retrieve bitcoind mempool: {e}")
+                });
+                continue;
+            }
+        };
+
+        // Transactions leaving the mempool are either confirmed, or evicted / replaced
+        let mut dropped_txids = known_txids
+            .difference(&txids)
+            .cloned()
+            .collect::<HashSet<_>>();
+        if !dropped_txids.is_empty() {
+            match retrieve_confirmed_txids(chain_tip, new_chain_tip, &bitcoin_config, &ctx).await {
+                Ok(confirmed_txids) => {
+                    dropped_txids.retain(|txid| !confirmed_txids.contains(txid));
+                }
+                Err(e) => {
+                    ctx.try_log(|logger| {
+                        slog::warn!(logger, "Unable to retrieve confirmed transactions: {e}")
+                    });
+                    continue;
+                }
+            }
+        }
+        let new_txids = txids.difference(&known_txids).cloned().collect::<Vec<_>>();
+        known_txids = txids;
+        chain_tip = new_chain_tip;
+
+        admit_transactions(
+            new_txids,
+            chain_tip + 1,
+            &bitcoin_config,
+            &observer_commands_tx,
+            &ctx,
+        )
+        .await;
+
+        if !dropped_txids.is_empty() {
+            let _ = observer_commands_tx.send(ObserverCommand::PropagateBitcoinMempoolEvent(
+                BitcoinChainMempoolEvent::TransactionsDropped(
+                    dropped_txids
+                        .into_iter()
+                        .map(|txid| TransactionIdentifier {
+                            hash: format!("0x{}", txid),
+                        })
+                        .collect(),
+                ),
+            ));
+        }
+    }
+}
+
+/// Retrieves the mempool before the chain tip, so that the transactions confirmed in between
+/// are found in the blocks up to the tip.
+async fn retrieve_mempool_snapshot(
+    bitcoin_config: &BitcoinConfig,
+) -> Result<(HashSet<Txid>, u64), String> {
+    let txids = retrieve_mempool_txids(bitcoin_config).await?;
+    let chain_tip = retrieve_block_count(bitcoin_config).await?;
+    Ok((txids.into_iter().collect(), chain_tip))
+}
+
+async fn retrieve_confirmed_txids(
+    previous_chain_tip: u64,
+    chain_tip: u64,
+    bitcoin_config: &BitcoinConfig,
+    ctx: &Context,
+) -> Result<HashSet<Txid>, String> {
+    let mut confirmed_txids = HashSet::new();
+    for block_height in (previous_chain_tip + 1)..=chain_tip {
+        confirmed_txids.extend(retrieve_block_txids(&block_height, bitcoin_config, ctx).await?);
+    }
+    Ok(confirmed_txids)
+}
+
+async fn admit_transactions(
+    txids: Vec<Txid>,
+    expected_block_height: u64,
+    bitcoin_config: &BitcoinConfig,
+    observer_commands_tx: &Sender<ObserverCommand>,
+    ctx: &Context,
+) {
+    let mut transactions = vec![];
+    for txid in txids.iter() {
+        // Transactions confirmed or evicted in the meantime are skipped
+        let tx = match download_and_parse_mempool_transaction(txid, bitcoin_config).await {
+            Ok(Some(tx)) => tx,
+            Ok(None) => continue,
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::debug!(logger, "Unable to retrieve mempool transaction {txid}: {e}")
+                });
+                continue;
+            }
+        };
+        match standardize_bitcoin_mempool_transaction(
+            tx,
+            expected_block_height,
+            &bitcoin_config.network,
+            ctx,
+        ) {
+            Ok(tx) => transactions.push(tx),
+            Err(e) => ctx.try_log(|logger| {
+                slog::warn!(
+                    logger,
+                    "Unable to standardize mempool transaction {txid}: {e}"
+                )
+            }),
+        }
+    }
+    if !transactions.is_empty() {
+        let _ = observer_commands_tx.send(ObserverCommand::PropagateBitcoinMempoolEvent(
+            BitcoinChainMempoolEvent::TransactionsAdmitted(transactions),
+        ));
+    }
+}
+
+/// Delivers the transactions admitted in the mempool to the chainhooks they are matching, and
+/// notifies the chainhooks previously triggered by the transactions dropped.
+pub async fn handle_unconfirmed_bitcoin_transactions(
+    admitted_transactions: Vec<BitcoinTransactionData>,
+    dropped_transactions: Vec<DroppedTransaction>,
+    bitcoin_mempool_tracker: &mut BitcoinMempoolTracker,
+    chainhook_store: &Arc<RwLock<ChainhookStore>>,
+    ctx: &Context,
+) {
+    let mut requests = vec![];
+    let mut kafka_messages = vec![];
+    let mut nats_messages = vec![];
+    let mut amqp_messages = vec![];
+    let mut redis_stream_messages = vec![];
+    let mut pubsub_messages = vec![];
+    let mut websocket_messages = vec![];
+    match chainhook_store.read() {
+        Err(e) => {
+            ctx.try_log(|logger| slog::error!(logger, "unable to obtain lock {:?}", e));
+            return;
+        }
+        Ok(chainhook_store_reader) => {
+            let bitcoin_chainhooks = chainhook_store_reader
+                .entries
+                .values()
+                .map(|v| &v.bitcoin_chainhooks)
+                .flatten()
+                .filter(|p| p.enabled)
+                .collect::<Vec<_>>();
+
+            let mut chainhooks_to_trigger = evaluate_bitcoin_chainhooks_on_mempool_transactions(
+                &admitted_transactions,
+                bitcoin_chainhooks.clone(),
+                ctx,
+            );
+            for trigger in chainhooks_to_trigger.iter() {
+                for tx in trigger.apply.iter() {
+                    bitcoin_mempool_tracker.track(tx, &trigger.chainhook.uuid);
+                }
+            }
+            for chainhook in bitcoin_chainhooks.into_iter() {
+                let rollback = dropped_transactions
+                    .iter()
+                    .filter(|dropped| dropped.chainhooks.contains(&chainhook.uuid))
+                    .map(|dropped| (&dropped.tx, &dropped.reason))
+                    .collect::<Vec<_>>();
+                if !rollback.is_empty() {
+                    chainhooks_to_trigger.push(BitcoinMempoolTriggerChainhook {
+                        chainhook,
+                        apply: vec![],
+                        rollback,
+                    });
+                }
+            }
+
+            ctx.try_log(|logger| {
+                slog::info!(
+                    logger,
+                    "{} bitcoin chainhooks will be triggered by unconfirmed transactions",
+                    chainhooks_to_trigger.len()
+                )
+            });
+            for chainhook_to_trigger in chainhooks_to_trigger.into_iter() {
+                match handle_bitcoin_mempool_hook_action(chainhook_to_trigger) {
+                    Err(e) => {
+                        ctx.try_log(|logger| slog::error!(logger, "unable to handle action {}", e));
+                    }
+                    Ok(Some(BitcoinChainhookOccurrence::Http(request))) => {
+                        requests.push(request);
+                    }
+                    Ok(Some(BitcoinChainhookOccurrence::Kafka(message))) => {
+                        kafka_messages.push(message);
+                    }
+                    Ok(Some(BitcoinChainhookOccurrence::Nats(messages))) => {
+                        nats_messages.extend(messages);
+                    }
+                    Ok(Some(BitcoinChainhookOccurrence::Amqp(message))) => {
+                        amqp_messages.push(message);
+                    }
+                    Ok(Some(BitcoinChainhookOccurrence::RedisStream(message))) => {
+                        redis_stream_messages.push(message);
+                    }
+                    Ok(Some(BitcoinChainhookOccurrence::PubSub(message))) => {
+                        pubsub_messages.push(message);
+                    }
+                    Ok(Some(BitcoinChainhookOccurrence::WebSocket(uuid, payload))) => {
+                        websocket_messages.push((uuid, payload));
+                    }
+                    Ok(Some(BitcoinChainhookOccurrence::File(_path, _bytes))) => {
+                        ctx.try_log(|logger| {
+                            slog::info!(logger, "Writing to disk not supported in server mode")
+                        })
+                    }
+                    Ok(_) => {}
+                }
+            }
+        }
+    };
+
+    for request in requests.into_iter() {
+        let _ = send_webhook_request(request, 3, 1, ctx).await;
+    }
+    for message in kafka_messages.into_iter() {
+        let _ = produce_kafka_message(message, ctx).await;
+    }
+    for message in nats_messages.into_iter() {
+        let _ = publish_nats_message(message, ctx).await;
+    }
+    for message in amqp_messages.into_iter() {
+        let _ = publish_amqp_message(message, ctx).await;
+    }
+    for message in redis_stream_messages.into_iter() {
+        let _ = append_to_redis_stream(message, ctx).await;
+    }
+    for message in pubsub_messages.into_iter() {
+        let _ = publish_pubsub_message(message, ctx).await;
+    }
+    for (uuid, payload) in websocket_messages.into_iter() {
+        let _ = send_websocket_message(&uuid, payload, ctx);
+    }
+}
+
+/// Unconfirmed transaction delivered to some chainhooks.
+struct TrackedTransaction {
+    tx: BitcoinTransactionData,
+    chainhooks: HashSet<String>,
+    /// Transaction spending some of the same outputs, either bumping the fees (RBF) or
+    /// double-spending them.
+    replaced_by: Option<TransactionIdentifier>,
+    /// Left the mempool while no conflicting transaction was seen.
+    dropped: bool,
+}
+
+/// Unconfirmed transaction which will not be confirmed, along with the chainhooks to notify.
+pub struct DroppedTransaction {
+    pub tx: BitcoinTransactionData,
+    pub chainhooks: HashSet<String>,
+    pub reason: BitcoinMempoolDropReason,
+}
+
+/// Tracks the unconfirmed transactions delivered until they get confirmed, replaced or evicted.
+#[derive(Default)]
+pub struct BitcoinMempoolTracker {
+    transactions: HashMap<TransactionIdentifier, TrackedTransaction>,
+    /// Outputs spent by the transactions tracked, formatted as `txid:vout`.
+    spent_outpoints: HashMap<String, TransactionIdentifier>,
+}
+
+fn format_outpoint(input: &TxIn) -> String {
+    format!(
+        "{}:{}",
+        input.previous_output.txid, input.previous_output.vout
+    )
+}
+
+impl BitcoinMempoolTracker {
+    pub fn track(&mut self, tx: &BitcoinTransactionData, chainhook_uuid: &str) {
+        let tracked = self
+            .transactions
+            .entry(tx.transaction_identifier.clone())
+            .or_insert_with(|| TrackedTransaction {
+                tx: tx.clone(),
+                chainhooks: HashSet::new(),
+                replaced_by: None,
+                dropped: false,
+            });
+        tracked.chainhooks.insert(chainhook_uuid.to_string());
+        for input in tx.metadata.inputs.iter() {
+            self.spent_outpoints
+                .insert(format_outpoint(input), tx.transaction_identifier.clone());
+        }
+    }
+
+    /// Records the transactions tracked conflicting with the transactions given, returned.
+    pub fn detect_conflicts(
+        &mut self,
+        transactions: &[BitcoinTransactionData],
+    ) -> Vec<TransactionIdentifier> {
+        let mut conflicting_txids = vec![];
+        for tx in transactions.iter() {
+            for input in tx.metadata.inputs.iter() {
+                let conflicting_txid = match self.spent_outpoints.get(&format_outpoint(input)) {
+                    Some(txid) if txid != &tx.transaction_identifier => txid,
+                    _ => continue,
+                };
+                if let Some(tracked) = self.transactions.get_mut(conflicting_txid) {
+                    tracked.replaced_by = Some(tx.transaction_identifier.clone());
+                    conflicting_txids.push(conflicting_txid.clone());
+                }
+            }
+        }
+        conflicting_txids
+    }
+
+    /// Transactions replaced are reported right away, the other ones are reported as evicted
+    /// with the next blocks, unless one of them turns out to be double-spending them.
+    pub fn drop_transactions(
+        &mut self,
+        transaction_identifiers: &[TransactionIdentifier],
+    ) -> Vec<DroppedTransaction> {
+        let mut replaced_txids = vec![];
+        for txid in transaction_identifiers.iter() {
+            match self.transactions.get_mut(txid) {
+                Some(tracked) if tracked.replaced_by.is_some() => replaced_txids.push(txid.clone()),
+                Some(tracked) => tracked.dropped = true,
+                None => {}
+            }
+        }
+        replaced_txids
+            .iter()
+            .filter_map(|txid| self.untrack(txid))
+            .collect()
+    }
+
+    /// Forgets the transactions confirmed, and reports the transactions double-spent by the
+    /// blocks along with the transactions which left the mempool in the meantime.
+    pub fn confirm_blocks(&mut self, blocks: &[BitcoinBlockData]) -> Vec<DroppedTransaction> {
+        let mut resolved_txids = vec![];
+        for block in blocks.iter() {
+            for tx in block.transactions.iter() {
+                self.untrack(&tx.transaction_identifier);
+            }
+            resolved_txids.extend(self.detect_conflicts(&block.transactions));
+        }
+        resolved_txids.extend(
+            self.transactions
+                .iter()
+                .filter(|(_, tracked)| tracked.dropped)
+                .map(|(txid, _)| txid.clone()),
+        );
+        resolved_txids
+            .iter()
+            .filter_map(|txid| self.untrack(txid))
+            .collect()
+    }
+
+    fn untrack(&mut self, txid: &TransactionIdentifier) -> Option<DroppedTransaction> {
+        let tracked = self.transactions.remove(txid)?;
+        for input in tracked.tx.metadata.inputs.iter() {
+            let outpoint = format_outpoint(input);
+            if self.spent_outpoints.get(&outpoint) == Some(txid) {
+                self.spent_outpoints.remove(&outpoint);
+            }
+        }
+        Some(DroppedTransaction {
+            tx: tracked.tx,
+            chainhooks: tracked.chainhooks,
+            reason: match tracked.replaced_by {
+                Some(replaced_by) => BitcoinMempoolDropReason::Replaced(replaced_by),
+                None => BitcoinMempoolDropReason::Evicted,
+            },
+        })
+    }
+}
@@ -7,29 +7,49 @@ use crate::chainhooks::stacks::{
     StacksChainhookOccurrence, StacksChainhookOccurrencePayload,
 };
 use crate::chainhooks::types::{
-    ChainhookConfig, ChainhookFullSpecification, ChainhookSpecification,
+    BitcoinPredicateType, ChainhookConfig, ChainhookFullSpecification, ChainhookSpecification,
+    HookAction,
 };
 
-use crate::hord::new_traversals_lazy_cache;
+use crate::hord::traversals_cache::{TraversalsLruCache, TRAVERSALS_CACHE_WARMING_DEPTH};
 #[cfg(feature = "ordinals")]
 use crate::hord::{
-    db::{open_readwrite_hord_db_conn, open_readwrite_hord_db_conn_rocks_db},
-    revert_hord_db_with_augmented_bitcoin_block, update_hord_db_and_augment_bitcoin_block,
+    augment_bitcoin_block_with_sat_movements,
+    db::{
+        find_inscription_row_with_id, find_inscription_rows, find_inscription_rows_at_outpoint,
+        open_inscriptions_replica, open_readonly_hord_db_conn_rocks_db, open_readonly_hord_db_pool,
+        open_readwrite_hord_db_conn, open_readwrite_hord_db_conn_rocks_db_with_options,
+        warm_traversals_cache, BlockPruningPolicy, HordDbError, HordDbOptions,
+        InscriptionRowsQuery, ReadonlyHordDbPool,
+    },
+    revert_hord_db_with_augmented_bitcoin_block,
+    sync_progress::SyncProgressTracker,
+    update_hord_db_and_augment_bitcoin_block,
 };
+#[cfg(feature = "zeromq")]
+use crate::indexer::bitcoin::parse_zmq_block_notification;
 use crate::indexer::bitcoin::{
     download_and_parse_block_with_retry, standardize_bitcoin_block, BitcoinBlockFullBreakdown,
-    NewBitcoinBlock,
+    BitcoinRpcEndpoints, BitcoinRpcRateLimits, NewBitcoinBlock,
 };
 use crate::indexer::fork_scratch_pad::ForkScratchPad;
 use crate::indexer::{self, Indexer, IndexerConfig};
-use crate::utils::{send_request, Context};
+use crate::observer::mempool::BitcoinMempoolTracker;
+use crate::utils::amqp::publish_amqp_message;
+use crate::utils::kafka::produce_kafka_message;
+use crate::utils::nats::publish_nats_message;
+use crate::utils::pubsub::publish_pubsub_message;
+use crate::utils::redis_streams::append_to_redis_stream;
+use crate::utils::telemetry::{PipelineStage, StageSpan};
+use crate::utils::{send_webhook_request, Context};
+use websocket::send_websocket_message;
 
 use bitcoincore_rpc::bitcoin::{BlockHash, Txid};
 use bitcoincore_rpc::{Auth, Client, RpcApi};
 use chainhook_types::{
     BitcoinBlockData, BitcoinBlockSignaling, BitcoinChainEvent, BitcoinChainUpdatedWithBlocksData,
-    BitcoinChainUpdatedWithReorgData, BitcoinNetwork, BlockIdentifier, BlockchainEvent,
-    StacksChainEvent, StacksNetwork, TransactionIdentifier,
+    BitcoinChainUpdatedWithReorgData, BitcoinNetwork, BitcoinTransactionData, BlockIdentifier,
+    BlockchainEvent, StacksChainEvent, StacksNetwork, TransactionIdentifier,
 };
 use clarity_repl::clarity::util::hash::bytes_to_hex;
 use hiro_system_kit;
@@ -44,6 +64,8 @@ use rocket::serde::Deserialize;
 use rocket::Shutdown;
 use rocket::State;
 use rocket_okapi::{openapi, openapi_get_routes, request::OpenApiFromRequest};
+#[cfg(feature = "zeromq")]
+use std::collections::VecDeque;
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::net::{IpAddr, Ipv4Addr};
@@ -58,6 +80,8 @@ use zeromq::{Socket, SocketRecv};
 
 pub const DEFAULT_INGESTION_PORT: u16 = 20445;
 pub const DEFAULT_CONTROL_PORT: u16 = 20446;
+#[cfg(feature = "zeromq")]
+const ANNOUNCED_BLOCK_HASHES_CAPACITY: usize = 16;
 
 #[derive(Deserialize)]
 pub struct NewTransaction {
@@ -73,10 +97,12 @@ pub enum Event {
     StacksChainEvent(StacksChainEvent),
 }
 
-// TODO(lgalabru): Support for GRPC?
 #[derive(Clone, Debug)]
 pub enum EventHandler {
     WebHook(String),
+    /// Streams the ordinal operations of the bitcoin events to the gRPC subscribers.
+    #[cfg(feature = "grpc")]
+    Grpc(tokio::sync::broadcast::Sender<grpc::proto::OrdinalEvent>),
 }
 
 impl EventHandler {
@@ -98,6 +124,8 @@ impl EventHandler {
                     .await;
                 // TODO(lgalabru): handle response errors
             }
+            #[cfg(feature = "grpc")]
+            EventHandler::Grpc(_) => {}
         }
     }
 
@@ -120,6 +148,13 @@ impl EventHandler {
                     .await;
                 // TODO(lgalabru): handle response errors
             }
+            #[cfg(feature = "grpc")]
+            EventHandler::Grpc(events_tx) => {
+                for event in grpc::ordinal_events_from_chain_event(bitcoin_event) {
+                    // Fails when nobody is subscribed
+                    let _ = events_tx.send(event);
+                }
+            }
         }
     }
 
@@ -138,6 +173,9 @@ pub struct EventObserverConfig {
     pub bitcoind_rpc_username: String,
     pub bitcoind_rpc_password: String,
     pub bitcoind_rpc_url: String,
+    /// RPC endpoints of other bitcoind nodes, failed over to when the main one is unavailable.
+    pub bitcoind_fallback_rpc_urls: Vec<String>,
+    pub bitcoind_rpc_rate_limits: BitcoinRpcRateLimits,
     pub bitcoin_block_signaling: BitcoinBlockSignaling,
     pub stacks_node_rpc_url: String,
     pub operators: HashSet<String>,
@@ -145,6 +183,28 @@ pub struct EventObserverConfig {
     pub cache_path: String,
     pub bitcoin_network: BitcoinNetwork,
     pub stacks_network: StacksNetwork,
+    #[cfg(feature = "ordinals")]
+    pub hord_block_pruning: BlockPruningPolicy,
+    #[cfg(feature = "ordinals")]
+    pub hord_db_options: HordDbOptions,
+    /// Memory budget of the traversals cache, in bytes.
+    #[cfg(feature = "ordinals")]
+    pub hord_traversals_cache_size: usize,
+    /// Progress of the hord db syncs, served on `/progress`.
+    #[cfg(feature = "ordinals")]
+    pub hord_sync_progress: Arc<SyncProgressTracker>,
+    /// Postgres database the inscriptions index is replicated to, requires the `postgres`
+    /// feature.
+    #[cfg(feature = "ordinals")]
+    pub inscriptions_postgres_url: Option<String>,
+    /// Port of the gRPC ordinals service, not served when unset.
+    #[cfg(feature = "grpc")]
+    pub grpc_port: Option<u16>,
+    /// Port of the WebSocket subscriptions server, not served when unset.
+    #[cfg(feature = "websocket")]
+    pub websocket_port: Option<u16>,
+    /// Evaluate the bitcoin predicates against the transactions pending in the mempool.
+    pub watch_bitcoin_mempool: bool,
 }
 
 impl EventObserverConfig {
@@ -159,6 +219,11 @@ impl EventObserverConfig {
             username: self.bitcoind_rpc_username.clone(),
             password: self.bitcoind_rpc_password.clone(),
             rpc_url: self.bitcoind_rpc_url.clone(),
+            rpc_endpoints: BitcoinRpcEndpoints::new(
+                &self.bitcoind_rpc_url,
+                &self.bitcoind_fallback_rpc_urls,
+                &self.bitcoind_rpc_rate_limits,
+            ),
             network: self.bitcoin_network.clone(),
             bitcoin_block_signaling: self.bitcoin_block_signaling.clone(),
         };
@@ -179,6 +244,7 @@ pub enum ObserverCommand {
     PropagateBitcoinChainEvent(BlockchainEvent),
     PropagateStacksChainEvent(StacksChainEvent),
     PropagateStacksMempoolEvent(StacksChainMempoolEvent),
+    PropagateBitcoinMempoolEvent(BitcoinChainMempoolEvent),
     RegisterPredicate(ChainhookFullSpecification, ApiKey),
     EnablePredicate(ChainhookSpecification, ApiKey),
     DeregisterBitcoinPredicate(String, ApiKey),
@@ -199,6 +265,13 @@ pub struct MempoolAdmissionData {
     pub tx_description: String,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum BitcoinChainMempoolEvent {
+    TransactionsAdmitted(Vec<BitcoinTransactionData>),
+    /// Transactions evicted or replaced, confirmations are not reported.
+    TransactionsDropped(Vec<TransactionIdentifier>),
+}
+
 #[derive(Clone, Debug)]
 pub enum ObserverEvent {
     Error(String),
@@ -234,6 +307,8 @@ pub struct BitcoinConfig {
     pub username: String,
     pub password: String,
     pub rpc_url: String,
+    /// Endpoints used by the indexer, starting with `rpc_url`.
+    pub rpc_endpoints: BitcoinRpcEndpoints,
     pub network: BitcoinNetwork,
     pub bitcoin_block_signaling: BitcoinBlockSignaling,
 }
@@ -253,6 +328,31 @@ impl ChainhookStore {
     pub fn is_authorized(&self, token: Option<String>) -> bool {
         self.entries.contains_key(&ApiKey(token))
     }
+
+    /// Is a predicate registered with this uuid, by any client?
+    pub fn is_registered(&self, predicate_uuid: &str) -> bool {
+        self.entries.values().any(|config| {
+            config
+                .stacks_chainhooks
+                .iter()
+                .any(|predicate| predicate.uuid == predicate_uuid)
+                || config
+                    .bitcoin_chainhooks
+                    .iter()
+                    .any(|predicate| predicate.uuid == predicate_uuid)
+        })
+    }
+
+    /// Are some enabled predicates requiring the sats spent by the transactions?
+    pub fn is_tracking_sat_movements(&self) -> bool {
+        self.entries
+            .values()
+            .flat_map(|config| config.bitcoin_chainhooks.iter())
+            .any(|predicate| {
+                predicate.enabled
+                    && matches!(predicate.predicate, BitcoinPredicateType::RareSats(_))
+            })
+    }
 }
 
 pub async fn start_event_observer(
@@ -262,11 +362,15 @@ pub async fn start_event_observer(
     observer_events_tx: Option<crossbeam_channel::Sender<ObserverEvent>>,
     ctx: Context,
 ) -> Result<(), Box<dyn Error>> {
+    let ctx = ctx.with_component("observer");
     let indexer_config = IndexerConfig {
         stacks_node_rpc_url: config.stacks_node_rpc_url.clone(),
         bitcoind_rpc_url: config.bitcoind_rpc_url.clone(),
+        bitcoind_fallback_rpc_urls: config.bitcoind_fallback_rpc_urls.clone(),
+        bitcoind_rpc_rate_limits: config.bitcoind_rpc_rate_limits.clone(),
         bitcoind_rpc_username: config.bitcoind_rpc_username.clone(),
         bitcoind_rpc_password: config.bitcoind_rpc_password.clone(),
+        bitcoind_rest_enabled: false,
         stacks_network: StacksNetwork::Devnet,
         bitcoin_network: BitcoinNetwork::Regtest,
         bitcoin_block_signaling: config.bitcoin_block_signaling.clone(),
@@ -393,6 +497,17 @@ pub async fn start_event_observer(
         handle_delete_bitcoin_hook,
         handle_delete_stacks_hook
     ];
+    #[cfg(feature = "ordinals")]
+    let routes = [
+        routes,
+        routes![
+            handle_get_inscriptions,
+            handle_get_inscription,
+            handle_get_inscriptions_at_outpoint,
+            handle_get_progress
+        ],
+    ]
+    .concat();
 
     let background_job_tx_mutex = Arc::new(Mutex::new(observer_commands_tx.clone()));
     let managed_chainhook_store = chainhook_store.clone();
@@ -401,16 +516,46 @@ pub async fn start_event_observer(
     let ignite = rocket::custom(control_config)
         .manage(background_job_tx_mutex)
         .manage(managed_chainhook_store)
-        .manage(ctx_cloned)
-        .mount("/", routes)
-        .ignite()
-        .await?;
+        .manage(ctx_cloned);
+    #[cfg(feature = "ordinals")]
+    let ignite = ignite
+        .manage(HordDbReader::new(config.get_cache_path_buf()))
+        .manage(config.hord_sync_progress.clone());
+    let ignite = ignite.mount("/", routes).ignite().await?;
     let control_shutdown = Some(ignite.shutdown());
 
     let _ = std::thread::spawn(move || {
         let _ = hiro_system_kit::nestable_block_on(ignite.launch());
     });
 
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_port) = config.grpc_port {
+        let events_tx = grpc::start_grpc_server(
+            grpc_port,
+            HordDbReader::new(config.get_cache_path_buf()),
+            &ctx,
+        );
+        config.event_handlers.push(EventHandler::Grpc(events_tx));
+    }
+
+    #[cfg(feature = "websocket")]
+    if let Some(websocket_port) = config.websocket_port {
+        websocket::start_websocket_server(
+            websocket_port,
+            chainhook_store.clone(),
+            observer_commands_tx.clone(),
+            &ctx,
+        );
+    }
+
+    if config.watch_bitcoin_mempool {
+        mempool::start_bitcoin_mempool_watcher(
+            config.get_bitcoin_config(),
+            observer_commands_tx.clone(),
+            &ctx,
+        );
+    }
+
     #[cfg(feature = "zeromq")]
     if let BitcoinBlockSignaling::ZeroMQ(ref bitcoind_zmq_url) = config.bitcoin_block_signaling {
         let bitcoind_zmq_url = bitcoind_zmq_url.clone();
@@ -435,12 +580,15 @@ pub async fn start_event_observer(
                             .await
                             .expect("Failed to connect");
 
-                        socket.subscribe("").await?;
+                        // Blocks are announced twice when both topics are published
+                        socket.subscribe("hashblock").await?;
+                        socket.subscribe("rawblock").await?;
                         ctx_moved.try_log(|logger| {
                             slog::info!(logger, "Waiting for ZMQ messages from bitcoind")
                         });
 
                         let mut bitcoin_blocks_pool = ForkScratchPad::new();
+                        let mut announced_block_hashes = VecDeque::new();
 
                         loop {
                             let message = match socket.recv().await {
@@ -456,8 +604,24 @@ pub async fn start_event_observer(
                                     continue;
                                 }
                             };
-                            let block_hash = hex::encode(message.get(1).unwrap().to_vec());
+                            let block_hash = match (message.get(0), message.get(1)) {
+                                (Some(topic), Some(payload)) => {
+                                    match parse_zmq_block_notification(topic, payload) {
+                                        Some(block_hash) => block_hash,
+                                        None => continue,
+                                    }
+                                }
+                                _ => continue,
+                            };
+                            if announced_block_hashes.contains(&block_hash) {
+                                continue;
+                            }
+                            if announced_block_hashes.len() == ANNOUNCED_BLOCK_HASHES_CAPACITY {
+                                announced_block_hashes.pop_front();
+                            }
+                            announced_block_hashes.push_back(block_hash.clone());
 
+                            let mut span = StageSpan::start(PipelineStage::BlockDownload);
                             let block = match download_and_parse_block_with_retry(
                                 &block_hash,
                                 &bitcoin_config,
@@ -474,15 +638,19 @@ pub async fn start_event_observer(
                                             e.to_string()
                                         )
                                     });
+                                    span.record_error(&e);
                                     continue;
                                 }
                             };
+                            span.set_block_height(block.height as u64);
+                            drop(span);
 
                             ctx_moved.try_log(|logger| {
                                 slog::info!(
                                     logger,
                                     "Bitcoin block #{} dispatched for processing",
-                                    block.height
+                                    block.height;
+                                    "block_height" => block.height
                                 )
                             });
 
@@ -597,7 +765,21 @@ pub async fn start_observer_commands_handler(
     let mut chainhooks_lookup: HashMap<String, ApiKey> = HashMap::new();
     let networks = (&config.bitcoin_network, &config.stacks_network);
     let mut bitcoin_block_store: HashMap<BlockIdentifier, BitcoinBlockData> = HashMap::new();
-    let traversals_cache = Arc::new(new_traversals_lazy_cache());
+    let traversals_cache = Arc::new(TraversalsLruCache::new(config.hord_traversals_cache_size));
+    #[cfg(feature = "ordinals")]
+    if let Ok(blocks_db) = open_readonly_hord_db_conn_rocks_db(&config.get_cache_path_buf(), &ctx) {
+        warm_traversals_cache(
+            &traversals_cache,
+            &blocks_db,
+            TRAVERSALS_CACHE_WARMING_DEPTH,
+            &ctx,
+        );
+    }
+    // Connected once, blocks being replicated as they are applied or rolled back
+    #[cfg(feature = "ordinals")]
+    let inscriptions_replica =
+        open_inscriptions_replica(config.inscriptions_postgres_url.as_deref())?;
+    let mut bitcoin_mempool_tracker = BitcoinMempoolTracker::default();
 
     loop {
         let command = match observer_commands_rx.recv() {
@@ -645,6 +827,11 @@ pub async fn start_observer_commands_handler(
                     slog::info!(logger, "Handling PropagateBitcoinChainEvent command")
                 });
                 let mut confirmed_blocks = vec![];
+                #[cfg(feature = "ordinals")]
+                let track_sat_movements = chainhook_store
+                    .read()
+                    .map(|store| store.is_tracking_sat_movements())
+                    .unwrap_or(false);
 
                 // Update Chain event before propagation
                 let chain_event = match blockchain_event {
@@ -652,8 +839,9 @@ pub async fn start_observer_commands_handler(
                         let mut new_blocks = vec![];
 
                         #[cfg(feature = "ordinals")]
-                        let blocks_db = match open_readwrite_hord_db_conn_rocks_db(
+                        let blocks_db = match open_readwrite_hord_db_conn_rocks_db_with_options(
                             &config.get_cache_path_buf(),
+                            &config.hord_db_options,
                             &ctx,
                         ) {
                             Ok(conn) => conn,
@@ -661,7 +849,7 @@ pub async fn start_observer_commands_handler(
                                 if let Some(ref tx) = observer_events_tx {
                                     let _ = tx.send(ObserverEvent::Error(format!(
                                         "Channel error: {:?}",
-                                        e
+                                        e.to_string()
                                     )));
                                 } else {
                                     ctx.try_log(|logger| {
@@ -680,7 +868,7 @@ pub async fn start_observer_commands_handler(
                                     if let Some(ref tx) = observer_events_tx {
                                         let _ = tx.send(ObserverEvent::Error(format!(
                                             "Channel error: {:?}",
-                                            e
+                                            e.to_string()
                                         )));
                                     } else {
                                         ctx.try_log(|logger| {
@@ -703,7 +891,10 @@ pub async fn start_observer_commands_handler(
                                             block,
                                             &blocks_db,
                                             &inscriptions_db_conn_rw,
+                                            inscriptions_replica.as_deref(),
                                             true,
+                                            &config.hord_block_pruning,
+                                            Some(&config.get_bitcoin_config()),
                                             &config.get_cache_path_buf(),
                                             &traversals_cache,
                                             &ctx,
@@ -716,6 +907,22 @@ pub async fn start_observer_commands_handler(
                                             )
                                             });
                                         }
+                                        if track_sat_movements {
+                                            if let Err(e) = augment_bitcoin_block_with_sat_movements(
+                                                block,
+                                                &config.get_cache_path_buf(),
+                                                &traversals_cache,
+                                                &ctx,
+                                            ) {
+                                                ctx.try_log(|logger| {
+                                                    slog::error!(
+                                                        logger,
+                                                        "Unable to retrieve the sats spent in bitcoin block {}: {e}",
+                                                        block.block_identifier.index
+                                                    )
+                                                });
+                                            }
+                                        }
                                     }
                                     new_blocks.push(block.clone());
                                 }
@@ -784,8 +991,9 @@ pub async fn start_observer_commands_handler(
                         traversals_cache.clear();
 
                         #[cfg(feature = "ordinals")]
-                        let blocks_db = match open_readwrite_hord_db_conn_rocks_db(
+                        let blocks_db = match open_readwrite_hord_db_conn_rocks_db_with_options(
                             &config.get_cache_path_buf(),
+                            &config.hord_db_options,
                             &ctx,
                         ) {
                             Ok(conn) => conn,
@@ -793,7 +1001,7 @@ pub async fn start_observer_commands_handler(
                                 if let Some(ref tx) = observer_events_tx {
                                     let _ = tx.send(ObserverEvent::Error(format!(
                                         "Channel error: {:?}",
-                                        e
+                                        e.to_string()
                                     )));
                                 } else {
                                     ctx.try_log(|logger| {
@@ -812,7 +1020,7 @@ pub async fn start_observer_commands_handler(
                                     if let Some(ref tx) = observer_events_tx {
                                         let _ = tx.send(ObserverEvent::Error(format!(
                                             "Channel error: {:?}",
-                                            e
+                                            e.to_string()
                                         )));
                                     } else {
                                         ctx.try_log(|logger| {
@@ -834,6 +1042,7 @@ pub async fn start_observer_commands_handler(
                                         block,
                                         &blocks_db,
                                         &inscriptions_db_conn_rw,
+                                        inscriptions_replica.as_deref(),
                                         &ctx,
                                     ) {
                                         ctx.try_log(|logger| {
@@ -863,21 +1072,50 @@ pub async fn start_observer_commands_handler(
                                 Some(block) => {
                                     #[cfg(feature = "ordinals")]
                                     {
-                                        if let Err(e) = update_hord_db_and_augment_bitcoin_block(
+                                        let started_at = std::time::Instant::now();
+                                        match update_hord_db_and_augment_bitcoin_block(
                                             block,
                                             &blocks_db,
                                             &inscriptions_db_conn_rw,
+                                            inscriptions_replica.as_deref(),
                                             true,
+                                            &config.hord_block_pruning,
+                                            Some(&config.get_bitcoin_config()),
                                             &config.get_cache_path_buf(),
                                             &traversals_cache,
                                             &ctx,
                                         ) {
-                                            ctx.try_log(|logger| {
+                                            Ok(()) => ctx.try_log(|logger| {
+                                                slog::info!(
+                                                    logger,
+                                                    "Bitcoin block #{} applied to hord_db", block.block_identifier.index;
+                                                    "block_height" => block.block_identifier.index,
+                                                    "duration_ms" => started_at.elapsed().as_millis() as u64
+                                                )
+                                            }),
+                                            Err(e) => ctx.try_log(|logger| {
                                                 slog::error!(
                                                     logger,
-                                                    "Unable to apply bitcoin block {} with hord_db: {e}", block.block_identifier.index
+                                                    "Unable to apply bitcoin block {} with hord_db: {e}", block.block_identifier.index;
+                                                    "block_height" => block.block_identifier.index
                                                 )
-                                            });
+                                            }),
+                                        }
+                                        if track_sat_movements {
+                                            if let Err(e) = augment_bitcoin_block_with_sat_movements(
+                                                block,
+                                                &config.get_cache_path_buf(),
+                                                &traversals_cache,
+                                                &ctx,
+                                            ) {
+                                                ctx.try_log(|logger| {
+                                                    slog::error!(
+                                                        logger,
+                                                        "Unable to retrieve the sats spent in bitcoin block {}: {e}",
+                                                        block.block_identifier.index
+                                                    )
+                                                });
+                                            }
                                         }
                                     }
                                     blocks_to_apply.push(block.clone());
@@ -919,12 +1157,35 @@ pub async fn start_observer_commands_handler(
                     }
                 };
 
+                // Unconfirmed transactions getting confirmed are delivered with their block
+                let applied_blocks = match &chain_event {
+                    BitcoinChainEvent::ChainUpdatedWithBlocks(data) => &data.new_blocks,
+                    BitcoinChainEvent::ChainUpdatedWithReorg(data) => &data.blocks_to_apply,
+                };
+                let dropped_transactions = bitcoin_mempool_tracker.confirm_blocks(applied_blocks);
+                if config.hooks_enabled && !dropped_transactions.is_empty() {
+                    mempool::handle_unconfirmed_bitcoin_transactions(
+                        vec![],
+                        dropped_transactions,
+                        &mut bitcoin_mempool_tracker,
+                        &chainhook_store,
+                        &ctx,
+                    )
+                    .await;
+                }
+
                 for event_handler in event_handlers.iter() {
                     event_handler.propagate_bitcoin_event(&chain_event).await;
                 }
                 // process hooks
                 let mut hooks_ids_to_deregister = vec![];
                 let mut requests = vec![];
+                let mut kafka_messages = vec![];
+                let mut nats_messages = vec![];
+                let mut amqp_messages = vec![];
+                let mut redis_stream_messages = vec![];
+                let mut pubsub_messages = vec![];
+                let mut websocket_messages = vec![];
 
                 if config.hooks_enabled {
                     match chainhook_store.read() {
@@ -1012,6 +1273,24 @@ pub async fn start_observer_commands_handler(
                                     Ok(BitcoinChainhookOccurrence::Http(request)) => {
                                         requests.push(request);
                                     }
+                                    Ok(BitcoinChainhookOccurrence::Kafka(message)) => {
+                                        kafka_messages.push(message);
+                                    }
+                                    Ok(BitcoinChainhookOccurrence::Nats(messages)) => {
+                                        nats_messages.extend(messages);
+                                    }
+                                    Ok(BitcoinChainhookOccurrence::Amqp(message)) => {
+                                        amqp_messages.push(message);
+                                    }
+                                    Ok(BitcoinChainhookOccurrence::RedisStream(message)) => {
+                                        redis_stream_messages.push(message);
+                                    }
+                                    Ok(BitcoinChainhookOccurrence::PubSub(message)) => {
+                                        pubsub_messages.push(message);
+                                    }
+                                    Ok(BitcoinChainhookOccurrence::WebSocket(uuid, payload)) => {
+                                        websocket_messages.push((uuid, payload));
+                                    }
                                     Ok(BitcoinChainhookOccurrence::File(_path, _bytes)) => ctx
                                         .try_log(|logger| {
                                             slog::info!(
@@ -1068,20 +1347,56 @@ pub async fn start_observer_commands_handler(
                     }
                 }
 
+                let mut span = StageSpan::start(PipelineStage::Delivery);
+                span.set_attribute("requests", requests.len() as i64);
+                span.set_attribute("kafka_messages", kafka_messages.len() as i64);
+                span.set_attribute("nats_messages", nats_messages.len() as i64);
+                span.set_attribute("amqp_messages", amqp_messages.len() as i64);
+                span.set_attribute("redis_stream_messages", redis_stream_messages.len() as i64);
+                span.set_attribute("pubsub_messages", pubsub_messages.len() as i64);
+                span.set_attribute("websocket_messages", websocket_messages.len() as i64);
                 for request in requests.into_iter() {
-                    let _ = send_request(request, 3, 1, &ctx).await;
+                    if send_webhook_request(request, 3, 1, &ctx).await.is_err() {
+                        span.record_error("unable to deliver chainhook occurrence");
+                    }
+                }
+                for message in kafka_messages.into_iter() {
+                    if produce_kafka_message(message, &ctx).await.is_err() {
+                        span.record_error("unable to deliver chainhook occurrence");
+                    }
+                }
+                for message in nats_messages.into_iter() {
+                    if publish_nats_message(message, &ctx).await.is_err() {
+                        span.record_error("unable to deliver chainhook occurrence");
+                    }
+                }
+                for message in amqp_messages.into_iter() {
+                    if publish_amqp_message(message, &ctx).await.is_err() {
+                        span.record_error("unable to deliver chainhook occurrence");
+                    }
+                }
+                for message in redis_stream_messages.into_iter() {
+                    if append_to_redis_stream(message, &ctx).await.is_err() {
+                        span.record_error("unable to deliver chainhook occurrence");
+                    }
+                }
+                for message in pubsub_messages.into_iter() {
+                    if publish_pubsub_message(message, &ctx).await.is_err() {
+                        span.record_error("unable to deliver chainhook occurrence");
+                    }
+                }
+                for (uuid, payload) in websocket_messages.into_iter() {
+                    if send_websocket_message(&uuid, payload, &ctx).is_err() {
+                        span.record_error("unable to deliver chainhook occurrence");
+                    }
                 }
+                drop(span);
 
                 for block in confirmed_blocks.into_iter() {
                     if block.block_identifier.index % 24 == 0 {
                         ctx.try_log(|logger| {
-                            slog::info!(
-                                logger,
-                                "Flushing traversals_cache ({} entries)",
-                                traversals_cache.len()
-                            )
+                            slog::info!(logger, "Traversals cache: {}", traversals_cache.stats())
                         });
-                        traversals_cache.clear();
                     }
                 }
 
@@ -1098,6 +1413,12 @@ pub async fn start_observer_commands_handler(
                 }
                 let mut hooks_ids_to_deregister = vec![];
                 let mut requests = vec![];
+                let mut kafka_messages = vec![];
+                let mut nats_messages = vec![];
+                let mut amqp_messages = vec![];
+                let mut redis_stream_messages = vec![];
+                let mut pubsub_messages = vec![];
+                let mut websocket_messages = vec![];
                 if config.hooks_enabled {
                     match chainhook_store.read() {
                         Err(e) => {
@@ -1157,6 +1478,24 @@ pub async fn start_observer_commands_handler(
                                     Ok(StacksChainhookOccurrence::Http(request)) => {
                                         requests.push(request);
                                     }
+                                    Ok(StacksChainhookOccurrence::Kafka(message)) => {
+                                        kafka_messages.push(message);
+                                    }
+                                    Ok(StacksChainhookOccurrence::Nats(messages)) => {
+                                        nats_messages.extend(messages);
+                                    }
+                                    Ok(StacksChainhookOccurrence::Amqp(message)) => {
+                                        amqp_messages.push(message);
+                                    }
+                                    Ok(StacksChainhookOccurrence::RedisStream(message)) => {
+                                        redis_stream_messages.push(message);
+                                    }
+                                    Ok(StacksChainhookOccurrence::PubSub(message)) => {
+                                        pubsub_messages.push(message);
+                                    }
+                                    Ok(StacksChainhookOccurrence::WebSocket(uuid, payload)) => {
+                                        websocket_messages.push((uuid, payload));
+                                    }
                                     Ok(StacksChainhookOccurrence::File(_path, _bytes)) => ctx
                                         .try_log(|logger| {
                                             slog::info!(
@@ -1215,7 +1554,25 @@ pub async fn start_observer_commands_handler(
                             request
                         )
                     });
-                    let _ = send_request(request, 3, 1, &ctx).await;
+                    let _ = send_webhook_request(request, 3, 1, &ctx).await;
+                }
+                for message in kafka_messages.into_iter() {
+                    let _ = produce_kafka_message(message, &ctx).await;
+                }
+                for message in nats_messages.into_iter() {
+                    let _ = publish_nats_message(message, &ctx).await;
+                }
+                for message in amqp_messages.into_iter() {
+                    let _ = publish_amqp_message(message, &ctx).await;
+                }
+                for message in redis_stream_messages.into_iter() {
+                    let _ = append_to_redis_stream(message, &ctx).await;
+                }
+                for message in pubsub_messages.into_iter() {
+                    let _ = publish_pubsub_message(message, &ctx).await;
+                }
+                for (uuid, payload) in websocket_messages.into_iter() {
+                    let _ = send_websocket_message(&uuid, payload, &ctx);
                 }
 
                 if let Some(ref tx) = observer_events_tx {
@@ -1230,6 +1587,32 @@ pub async fn start_observer_commands_handler(
                     let _ = tx.send(ObserverEvent::StacksChainMempoolEvent(mempool_event));
                 }
             }
+            ObserverCommand::PropagateBitcoinMempoolEvent(mempool_event) => {
+                ctx.try_log(|logger| {
+                    slog::debug!(logger, "Handling PropagateBitcoinMempoolEvent command")
+                });
+                if !config.hooks_enabled {
+                    continue;
+                }
+                let (admitted_transactions, dropped_transactions) = match mempool_event {
+                    BitcoinChainMempoolEvent::TransactionsAdmitted(transactions) => {
+                        bitcoin_mempool_tracker.detect_conflicts(&transactions);
+                        (transactions, vec![])
+                    }
+                    BitcoinChainMempoolEvent::TransactionsDropped(transaction_identifiers) => (
+                        vec![],
+                        bitcoin_mempool_tracker.drop_transactions(&transaction_identifiers),
+                    ),
+                };
+                mempool::handle_unconfirmed_bitcoin_transactions(
+                    admitted_transactions,
+                    dropped_transactions,
+                    &mut bitcoin_mempool_tracker,
+                    &chainhook_store,
+                    &ctx,
+                )
+                .await;
+            }
             ObserverCommand::NotifyBitcoinTransactionProxied => {
                 ctx.try_log(|logger| {
                     slog::info!(logger, "Handling NotifyBitcoinTransactionProxied command")
@@ -1286,10 +1669,13 @@ pub async fn start_observer_commands_handler(
                             api_key
                         )
                     });
-                    if let Some(ref tx) = observer_events_tx {
-                        let _ = tx.send(ObserverEvent::HookRegistered(spec, api_key));
-                    } else {
-                        hook_formation.enable_specification(&spec);
+                    // Predicates registered over a WebSocket only live as long as their socket:
+                    // they are neither persisted nor scanned.
+                    match observer_events_tx {
+                        Some(ref tx) if !matches!(spec.action(), HookAction::WebSocket) => {
+                            let _ = tx.send(ObserverEvent::HookRegistered(spec, api_key));
+                        }
+                        _ => hook_formation.enable_specification(&spec),
                     }
                 }
             },
@@ -1425,6 +1811,7 @@ pub async fn handle_new_bitcoin_block(
     // into account the last 7 blocks.
 
     let block_hash = bitcoin_block.burn_block_hash.strip_prefix("0x").unwrap();
+    let mut span = StageSpan::start(PipelineStage::BlockDownload);
     let block = match download_and_parse_block_with_retry(block_hash, bitcoin_config, ctx).await {
         Ok(block) => block,
         Err(e) => {
@@ -1435,12 +1822,15 @@ pub async fn handle_new_bitcoin_block(
                     e.to_string()
                 )
             });
+            span.record_error(&e);
             return Json(json!({
                 "status": 500,
                 "result": "unable to retrieve_full_block",
             }));
         }
     };
+    span.set_block_height(block.height as u64);
+    drop(span);
 
     let header = block.get_block_header();
     match background_job_tx.lock() {
@@ -1986,6 +2376,182 @@ pub fn handle_delete_bitcoin_hook(
     }))
 }
 
+/// Read-only access to hord.sqlite for the ordinals endpoints. The database is only created
+/// once the first block gets indexed, so the pool is opened on first use.
+#[cfg(feature = "ordinals")]
+pub struct HordDbReader {
+    cache_path: PathBuf,
+    pool: Mutex<Option<Arc<ReadonlyHordDbPool>>>,
+}
+
+#[cfg(feature = "ordinals")]
+impl HordDbReader {
+    const POOL_SIZE: usize = 4;
+
+    pub fn new(cache_path: PathBuf) -> HordDbReader {
+        HordDbReader {
+            cache_path,
+            pool: Mutex::new(None),
+        }
+    }
+
+    pub fn query<F, R>(&self, ctx: &Context, query: F) -> Result<R, HordDbError>
+    where
+        F: FnOnce(&rusqlite::Connection) -> Result<R, HordDbError>,
+    {
+        let pool = {
+            let mut pool = self.pool.lock().unwrap();
+            match pool.as_ref() {
+                Some(pool) => pool.clone(),
+                None => {
+                    let new_pool = Arc::new(open_readonly_hord_db_pool(
+                        &self.cache_path,
+                        Self::POOL_SIZE,
+                        ctx,
+                    )?);
+                    *pool = Some(new_pool.clone());
+                    new_pool
+                }
+            }
+        };
+        let conn = pool.get(ctx)?;
+        query(&conn)
+    }
+}
+
+#[cfg(feature = "ordinals")]
+const DEFAULT_INSCRIPTIONS_PAGE_SIZE: u64 = 20;
+#[cfg(feature = "ordinals")]
+const MAX_INSCRIPTIONS_PAGE_SIZE: u64 = 100;
+
+#[cfg(feature = "ordinals")]
+fn hord_db_error_response(e: HordDbError, ctx: &Context) -> Json<JsonValue> {
+    ctx.try_log(|logger| slog::error!(logger, "Unable to query hord db: {}", e));
+    Json(json!({
+        "status": 500,
+        "error": e.to_string(),
+    }))
+}
+
+/// Inscriptions ordered by inscription number. The `next_cursor` of a full page is passed as
+/// `cursor` to get the next one.
+#[cfg(feature = "ordinals")]
+#[get(
+    "/ordinals/inscriptions?<cursor>&<limit>&<from_block>&<to_block>",
+    format = "application/json"
+)]
+pub fn handle_get_inscriptions(
+    cursor: Option<i64>,
+    limit: Option<u64>,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+    hord_db_reader: &State<HordDbReader>,
+    ctx: &State<Context>,
+    _api_key: ApiKey,
+) -> Json<JsonValue> {
+    ctx.try_log(|logger| slog::info!(logger, "GET /ordinals/inscriptions"));
+    if let (Some(from_block), Some(to_block)) = (from_block, to_block) {
+        if from_block > to_block {
+            return Json(json!({
+                "status": 422,
+                "error": format!("invalid block range {from_block}-{to_block}"),
+            }));
+        }
+    }
+    let query = InscriptionRowsQuery {
+        after_inscription_number: cursor,
+        from_block_height: from_block,
+        to_block_height: to_block,
+        limit: limit
+            .unwrap_or(DEFAULT_INSCRIPTIONS_PAGE_SIZE)
+            .clamp(1, MAX_INSCRIPTIONS_PAGE_SIZE),
+    };
+    match hord_db_reader.query(ctx, |conn| find_inscription_rows(&query, conn)) {
+        Ok(inscriptions) => {
+            // A partial page is the last one
+            let next_cursor = match inscriptions.len() as u64 == query.limit {
+                true => inscriptions.last().map(|i| i.inscription_number),
+                false => None,
+            };
+            Json(json!({
+                "status": 200,
+                "result": inscriptions,
+                "next_cursor": next_cursor,
+            }))
+        }
+        Err(e) => hord_db_error_response(e, ctx),
+    }
+}
+
+#[cfg(feature = "ordinals")]
+#[get("/ordinals/inscriptions/<inscription_id>", format = "application/json")]
+pub fn handle_get_inscription(
+    inscription_id: String,
+    hord_db_reader: &State<HordDbReader>,
+    ctx: &State<Context>,
+    _api_key: ApiKey,
+) -> Json<JsonValue> {
+    ctx.try_log(|logger| slog::info!(logger, "GET /ordinals/inscriptions/<inscription_id>"));
+    match hord_db_reader.query(ctx, |conn| {
+        find_inscription_row_with_id(&inscription_id, conn)
+    }) {
+        Ok(Some(inscription)) => Json(json!({
+            "status": 200,
+            "result": inscription,
+        })),
+        Ok(None) => Json(json!({
+            "status": 404,
+        })),
+        Err(e) => hord_db_error_response(e, ctx),
+    }
+}
+
+#[cfg(feature = "ordinals")]
+#[get("/ordinals/outpoint/<outpoint>", format = "application/json")]
+pub fn handle_get_inscriptions_at_outpoint(
+    outpoint: String,
+    hord_db_reader: &State<HordDbReader>,
+    ctx: &State<Context>,
+    _api_key: ApiKey,
+) -> Json<JsonValue> {
+    ctx.try_log(|logger| slog::info!(logger, "GET /ordinals/outpoint/<outpoint>"));
+    let outpoint = outpoint.strip_prefix("0x").unwrap_or(&outpoint);
+    match outpoint.split_once(':') {
+        Some((txid, vout)) if txid.len() == 64 && vout.parse::<u32>().is_ok() => {}
+        _ => {
+            return Json(json!({
+                "status": 422,
+                "error": format!("invalid outpoint {outpoint}, expected <txid>:<vout>"),
+            }))
+        }
+    }
+    match hord_db_reader.query(ctx, |conn| {
+        find_inscription_rows_at_outpoint(outpoint, conn)
+    }) {
+        Ok(inscriptions) => Json(json!({
+            "status": 200,
+            "result": inscriptions,
+        })),
+        Err(e) => hord_db_error_response(e, ctx),
+    }
+}
+
+/// Progress of the latest hord db sync (blocks processed, current height, rate and ETA),
+/// `null` until a sync is started.
+#[cfg(feature = "ordinals")]
+#[get("/progress", format = "application/json")]
+pub fn handle_get_progress(
+    sync_progress: &State<Arc<SyncProgressTracker>>,
+    ctx: &State<Context>,
+    _api_key: ApiKey,
+) -> Json<JsonValue> {
+    ctx.try_log(|logger| slog::info!(logger, "GET /progress"));
+    Json(json!({
+        "status": 200,
+        "result": sync_progress.progress(),
+    }))
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, OpenApiFromRequest)]
 pub struct ApiKey(pub Option<String>);
 
@@ -2032,5 +2598,12 @@ impl<'r> FromRequest<'r> for ApiKey {
     }
 }
 
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+mod mempool;
+
+pub mod websocket;
+
 #[cfg(test)]
 mod tests;
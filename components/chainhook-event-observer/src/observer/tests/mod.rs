@@ -1,3 +1,4 @@
+use crate::chainhooks::bitcoin::BitcoinMempoolDropReason;
 use crate::chainhooks::types::{
     BitcoinChainhookFullSpecification, BitcoinChainhookNetworkSpecification,
     BitcoinChainhookSpecification, BitcoinPredicateType, ChainhookConfig,
@@ -5,22 +6,43 @@ use crate::chainhooks::types::{
     OutputPredicate, StacksChainhookFullSpecification, StacksChainhookNetworkSpecification,
     StacksChainhookSpecification, StacksContractCallBasedPredicate, StacksPredicate,
 };
+#[cfg(feature = "ordinals")]
+use crate::hord::db::{BlockPruningPolicy, HordDbOptions};
+#[cfg(feature = "ordinals")]
+use crate::hord::sync_progress::SyncProgressTracker;
+use crate::hord::traversals_cache::DEFAULT_TRAVERSALS_CACHE_SIZE;
+use crate::indexer::bitcoin::BitcoinRpcRateLimits;
 use crate::indexer::tests::helpers::transactions::generate_test_tx_bitcoin_p2pkh_transfer;
 use crate::indexer::tests::helpers::{
     accounts, bitcoin_blocks, stacks_blocks, transactions::generate_test_tx_stacks_contract_call,
 };
+use crate::observer::mempool::BitcoinMempoolTracker;
+#[cfg(feature = "websocket")]
+use crate::observer::websocket::{
+    send_websocket_message, start_websocket_server_with_keepalive, WebSocketKeepalive,
+};
 use crate::observer::{
     start_observer_commands_handler, ApiKey, ChainhookStore, EventObserverConfig, ObserverCommand,
 };
 use crate::utils::{AbstractBlock, Context};
+use chainhook_types::bitcoin::{OutPoint, TxIn};
 use chainhook_types::{
-    BitcoinBlockSignaling, BitcoinNetwork, BlockchainEvent, BlockchainUpdatedWithHeaders,
-    StacksBlockUpdate, StacksChainEvent, StacksChainUpdatedWithBlocksData, StacksNetwork,
+    BitcoinBlockSignaling, BitcoinNetwork, BitcoinTransactionData, BlockchainEvent,
+    BlockchainUpdatedWithHeaders, StacksBlockUpdate, StacksChainEvent,
+    StacksChainUpdatedWithBlocksData, StacksNetwork,
 };
+#[cfg(feature = "websocket")]
+use futures::{SinkExt, StreamExt};
 use hiro_system_kit;
 use std::collections::{BTreeMap, HashMap, HashSet};
+#[cfg(feature = "websocket")]
+use std::sync::mpsc::Receiver;
 use std::sync::mpsc::{channel, Sender};
 use std::sync::{Arc, RwLock};
+#[cfg(feature = "websocket")]
+use std::time::Duration;
+#[cfg(feature = "websocket")]
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
 
 use super::ObserverEvent;
 
@@ -37,6 +59,8 @@ fn generate_test_config() -> (EventObserverConfig, ChainhookStore) {
         bitcoind_rpc_username: "user".into(),
         bitcoind_rpc_password: "user".into(),
         bitcoind_rpc_url: "http://localhost:18443".into(),
+        bitcoind_fallback_rpc_urls: vec![],
+        bitcoind_rpc_rate_limits: BitcoinRpcRateLimits::default(),
         stacks_node_rpc_url: "http://localhost:20443".into(),
         operators,
         display_logs: false,
@@ -44,6 +68,21 @@ fn generate_test_config() -> (EventObserverConfig, ChainhookStore) {
         cache_path: "cache".into(),
         bitcoin_network: BitcoinNetwork::Regtest,
         stacks_network: StacksNetwork::Devnet,
+        #[cfg(feature = "ordinals")]
+        hord_block_pruning: BlockPruningPolicy::KeepAll,
+        #[cfg(feature = "ordinals")]
+        hord_db_options: HordDbOptions::default(),
+        #[cfg(feature = "ordinals")]
+        hord_traversals_cache_size: DEFAULT_TRAVERSALS_CACHE_SIZE,
+        #[cfg(feature = "ordinals")]
+        hord_sync_progress: Arc::new(SyncProgressTracker::default()),
+        #[cfg(feature = "ordinals")]
+        inscriptions_postgres_url: None,
+        #[cfg(feature = "grpc")]
+        grpc_port: None,
+        #[cfg(feature = "websocket")]
+        websocket_port: None,
+        watch_bitcoin_mempool: false,
     };
     let mut entries = HashMap::new();
     entries.insert(ApiKey(None), ChainhookConfig::new());
@@ -997,3 +1036,248 @@ fn test_bitcoin_chainhook_auto_deregister() {
     let _ = observer_commands_tx.send(ObserverCommand::Terminate);
     handle.join().expect("unable to terminate thread");
 }
+
+fn generate_test_tx_bitcoin_spending(txid: u64, spent_vout: u32) -> BitcoinTransactionData {
+    let mut tx = generate_test_tx_bitcoin_p2pkh_transfer(
+        txid,
+        &accounts::wallet_1_btc_address(),
+        &accounts::wallet_2_btc_address(),
+        3,
+    );
+    tx.metadata.inputs.push(TxIn {
+        previous_output: OutPoint {
+            txid: "0x0000000000000000000000000000000000000000000000000000000000000001".into(),
+            vout: spent_vout,
+            value: 10,
+            block_height: 1,
+        },
+        script_sig: "0x".into(),
+        sequence: 0xfffffffd,
+        witness: vec![],
+    });
+    tx
+}
+
+#[test]
+fn test_bitcoin_mempool_tracker_replacements() {
+    let mut tracker = BitcoinMempoolTracker::default();
+    let (replaced, evicted, double_spent, confirmed) = (
+        generate_test_tx_bitcoin_spending(1, 0),
+        generate_test_tx_bitcoin_spending(2, 1),
+        generate_test_tx_bitcoin_spending(3, 2),
+        generate_test_tx_bitcoin_spending(4, 3),
+    );
+    for tx in [&replaced, &evicted, &double_spent, &confirmed] {
+        tracker.track(tx, "uuid");
+    }
+
+    // RBF bump admitted, then the original transaction leaving the mempool
+    let bump = generate_test_tx_bitcoin_spending(5, 0);
+    assert_eq!(
+        tracker.detect_conflicts(&[bump.clone()]),
+        vec![replaced.transaction_identifier.clone()]
+    );
+    let dropped = tracker.drop_transactions(&[
+        replaced.transaction_identifier.clone(),
+        evicted.transaction_identifier.clone(),
+    ]);
+    assert_eq!(dropped.len(), 1);
+    assert_eq!(dropped[0].tx, replaced);
+    assert_eq!(
+        dropped[0].reason,
+        BitcoinMempoolDropReason::Replaced(bump.transaction_identifier.clone())
+    );
+
+    // Evictions are settled with the next block, along with the double-spends it includes
+    let double_spend = generate_test_tx_bitcoin_spending(6, 2);
+    let block = bitcoin_blocks::generate_test_bitcoin_block(
+        0,
+        2,
+        vec![confirmed.clone(), double_spend.clone()],
+        None,
+    );
+    let mut dropped = tracker.confirm_blocks(&[block]);
+    dropped.sort_by(|a, b| {
+        a.tx.transaction_identifier
+            .hash
+            .cmp(&b.tx.transaction_identifier.hash)
+    });
+    assert_eq!(dropped.len(), 2);
+    assert_eq!(dropped[0].tx, evicted);
+    assert_eq!(dropped[0].reason, BitcoinMempoolDropReason::Evicted);
+    assert_eq!(dropped[1].tx, double_spent);
+    assert_eq!(
+        dropped[1].reason,
+        BitcoinMempoolDropReason::Replaced(double_spend.transaction_identifier.clone())
+    );
+    assert!(tracker.confirm_blocks(&[]).is_empty());
+}
+
+#[cfg(feature = "websocket")]
+type TestWebSocket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+#[cfg(feature = "websocket")]
+fn start_test_websocket_server(
+    port: u16,
+    keepalive: WebSocketKeepalive,
+) -> (Arc<RwLock<ChainhookStore>>, Receiver<ObserverCommand>) {
+    let (_, chainhook_store) = generate_test_config();
+    let chainhook_store = Arc::new(RwLock::new(chainhook_store));
+    let (observer_commands_tx, observer_commands_rx) = channel();
+    start_websocket_server_with_keepalive(
+        port,
+        keepalive,
+        chainhook_store.clone(),
+        observer_commands_tx,
+        &Context::empty(),
+    );
+    (chainhook_store, observer_commands_rx)
+}
+
+#[cfg(feature = "websocket")]
+async fn connect_test_websocket(port: u16) -> TestWebSocket {
+    // The server binds its port from a thread of its own
+    for _ in 0..50 {
+        if let Ok((socket, _)) =
+            tokio_tungstenite::connect_async(format!("ws://127.0.0.1:{}", port)).await
+        {
+            return socket;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("websocket server not listening on port {}", port)
+}
+
+#[cfg(feature = "websocket")]
+async fn register_over_websocket(socket: &mut TestWebSocket, id: u8) -> serde_json::Value {
+    let spec = ChainhookFullSpecification::Bitcoin(bitcoin_chainhook_p2pkh(
+        id,
+        &accounts::wallet_1_btc_address(),
+        None,
+    ));
+    socket
+        .send(Message::Text(serde_json::to_string(&spec).unwrap()))
+        .await
+        .unwrap();
+    read_websocket_text(socket).await
+}
+
+#[cfg(feature = "websocket")]
+async fn read_websocket_text(socket: &mut TestWebSocket) -> serde_json::Value {
+    loop {
+        match socket.next().await {
+            Some(Ok(Message::Text(text))) => return serde_json::from_str(&text).unwrap(),
+            Some(Ok(_)) => continue,
+            _ => panic!("websocket closed"),
+        }
+    }
+}
+
+#[cfg(feature = "websocket")]
+fn expect_websocket_registration(observer_commands_rx: &Receiver<ObserverCommand>, uuid: &str) {
+    match observer_commands_rx.recv_timeout(Duration::from_secs(5)) {
+        Ok(ObserverCommand::RegisterPredicate(
+            ChainhookFullSpecification::Bitcoin(spec),
+            ApiKey(None),
+        )) => {
+            assert_eq!(spec.uuid, uuid);
+            assert!(spec
+                .networks
+                .values()
+                .all(|network_spec| matches!(network_spec.action, HookAction::WebSocket)));
+        }
+        _ => panic!("expected predicate {} to be registered", uuid),
+    }
+}
+
+#[cfg(feature = "websocket")]
+fn expect_websocket_deregistration(observer_commands_rx: &Receiver<ObserverCommand>, uuid: &str) {
+    match observer_commands_rx.recv_timeout(Duration::from_secs(5)) {
+        Ok(ObserverCommand::DeregisterBitcoinPredicate(deregistered_uuid, ApiKey(None))) => {
+            assert_eq!(deregistered_uuid, uuid);
+        }
+        _ => panic!("expected predicate {} to be deregistered", uuid),
+    }
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn test_websocket_register_and_deliver() {
+    let (chainhook_store, observer_commands_rx) =
+        start_test_websocket_server(20551, WebSocketKeepalive::default());
+    let mut socket = connect_test_websocket(20551).await;
+
+    let status = register_over_websocket(&mut socket, 51).await;
+    assert_eq!(status["status"], json!(200));
+    assert_eq!(status["result"]["uuid"], json!("51"));
+    expect_websocket_registration(&observer_commands_rx, "51");
+
+    // Uuids registered over a WebSocket or the control API are not taken over
+    let status = register_over_websocket(&mut socket, 51).await;
+    assert_eq!(status["status"], json!(409));
+    chainhook_store
+        .write()
+        .unwrap()
+        .entries
+        .get_mut(&ApiKey(None))
+        .unwrap()
+        .register_full_specification(
+            (&BitcoinNetwork::Regtest, &StacksNetwork::Devnet),
+            ChainhookFullSpecification::Bitcoin(bitcoin_chainhook_p2pkh(
+                52,
+                &accounts::wallet_1_btc_address(),
+                None,
+            )),
+            &ApiKey(None),
+        )
+        .unwrap();
+    let status = register_over_websocket(&mut socket, 52).await;
+    assert_eq!(status["status"], json!(409));
+    assert!(observer_commands_rx.try_recv().is_err());
+
+    let ctx = Context::empty();
+    assert!(send_websocket_message("51", br#"{"apply":[]}"#.to_vec(), &ctx).is_ok());
+    assert_eq!(
+        read_websocket_text(&mut socket).await,
+        json!({ "apply": [] })
+    );
+    assert!(send_websocket_message("52", br#"{"apply":[]}"#.to_vec(), &ctx).is_err());
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn test_websocket_close_deregisters_predicates() {
+    let (_, observer_commands_rx) =
+        start_test_websocket_server(20552, WebSocketKeepalive::default());
+    let mut socket = connect_test_websocket(20552).await;
+
+    let status = register_over_websocket(&mut socket, 53).await;
+    assert_eq!(status["status"], json!(200));
+    expect_websocket_registration(&observer_commands_rx, "53");
+
+    socket.close(None).await.unwrap();
+    expect_websocket_deregistration(&observer_commands_rx, "53");
+    assert!(send_websocket_message("53", b"{}".to_vec(), &Context::empty()).is_err());
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn test_websocket_keepalive_timeout() {
+    let (_, observer_commands_rx) = start_test_websocket_server(
+        20553,
+        WebSocketKeepalive {
+            ping_interval: Duration::from_millis(100),
+            timeout: Duration::from_millis(300),
+        },
+    );
+    let mut socket = connect_test_websocket(20553).await;
+
+    let status = register_over_websocket(&mut socket, 54).await;
+    assert_eq!(status["status"], json!(200));
+    expect_websocket_registration(&observer_commands_rx, "54");
+
+    // Pings are only answered while the socket is read
+    expect_websocket_deregistration(&observer_commands_rx, "54");
+    assert!(send_websocket_message("54", b"{}".to_vec(), &Context::empty()).is_err());
+    drop(socket);
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "websocket")]
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{mpsc::Sender, RwLock},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "websocket")]
+use futures::{SinkExt, StreamExt};
+use hiro_system_kit::slog;
+#[cfg(feature = "websocket")]
+use serde_json::Value as JsonValue;
+#[cfg(feature = "websocket")]
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    Notify,
+};
+#[cfg(feature = "websocket")]
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    protocol::{frame::coding::CloseCode, CloseFrame},
+    Message,
+};
+
+#[cfg(feature = "websocket")]
+use super::{ApiKey, ChainhookStore, ObserverCommand};
+#[cfg(feature = "websocket")]
+use crate::chainhooks::types::{ChainhookFullSpecification, HookAction};
+use crate::utils::Context;
+
+/// Sockets are pinged at this interval, and closed once nothing, pongs included, was received
+/// from them for `KEEPALIVE_TIMEOUT`.
+#[cfg(feature = "websocket")]
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+#[cfg(feature = "websocket")]
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Occurrences waiting to be written to a socket. Sockets not keeping up are closed once it is
+/// full, for a slow client not to hold the occurrences in memory.
+#[cfg(feature = "websocket")]
+const OCCURRENCES_BUFFER_SIZE: usize = 256;
+
+/// Pings sent to the sockets, closed once nothing, pongs included, was received from them for
+/// `timeout`.
+#[cfg(feature = "websocket")]
+#[derive(Clone, Copy, Debug)]
+pub struct WebSocketKeepalive {
+    pub ping_interval: Duration,
+    pub timeout: Duration,
+}
+
+#[cfg(feature = "websocket")]
+impl Default for WebSocketKeepalive {
+    fn default() -> Self {
+        WebSocketKeepalive {
+            ping_interval: PING_INTERVAL,
+            timeout: KEEPALIVE_TIMEOUT,
+        }
+    }
+}
+
+/// Socket the occurrences of a predicate are streamed to, only subscribed to when the
+/// `websocket` feature is enabled.
+#[cfg_attr(not(feature = "websocket"), allow(dead_code))]
+#[derive(Clone)]
+struct Subscription {
+    occurrences_tx: mpsc::Sender<Vec<u8>>,
+    /// Notified when the buffer of the socket is full, for the socket to be closed.
+    overflow: Arc<Notify>,
+}
+
+/// Sockets the occurrences of the predicates registered over a WebSocket are streamed to, by
+/// predicate uuid.
+static SUBSCRIPTIONS: Mutex<Option<HashMap<String, Subscription>>> = Mutex::new(None);
+
+/// Streams an occurrence to the WebSocket its predicate was registered over. Fails once the
+/// socket is closed, until the predicate is deregistered, and closes the sockets not consuming
+/// their occurrences fast enough.
+pub fn send_websocket_message(
+    predicate_uuid: &str,
+    payload: Vec<u8>,
+    ctx: &Context,
+) -> Result<(), ()> {
+    let subscriptions = SUBSCRIPTIONS.lock().unwrap_or_else(|e| e.into_inner());
+    let error = match subscriptions
+        .as_ref()
+        .and_then(|subscriptions| subscriptions.get(predicate_uuid))
+    {
+        Some(subscription) => match subscription.occurrences_tx.try_send(payload) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Full(_)) => {
+                subscription.overflow.notify_one();
+                "websocket not keeping up, closing it"
+            }
+            Err(TrySendError::Closed(_)) => "websocket closed",
+        },
+        None => "websocket closed",
+    };
+    ctx.try_log(|logger| {
+        slog::warn!(
+            logger,
+            "unable to stream occurrence of predicate {}: {}",
+            predicate_uuid,
+            error
+        )
+    });
+    Err(())
+}
+
+/// Serves the WebSocket subscriptions on `port`, in a dedicated thread. Clients send predicate
+/// specifications as text frames, answered with a status frame, and then receive the
+/// occurrences of their predicates, until the socket closes and the predicates are
+/// deregistered. Clients are authorized with the `x-api-key` header of the handshake, as on
+/// the control API.
+#[cfg(feature = "websocket")]
+pub fn start_websocket_server(
+    port: u16,
+    chainhook_store: Arc<RwLock<ChainhookStore>>,
+    observer_commands_tx: Sender<ObserverCommand>,
+    ctx: &Context,
+) {
+    start_websocket_server_with_keepalive(
+        port,
+        WebSocketKeepalive::default(),
+        chainhook_store,
+        observer_commands_tx,
+        ctx,
+    )
+}
+
+#[cfg(feature = "websocket")]
+pub fn start_websocket_server_with_keepalive(
+    port: u16,
+    keepalive: WebSocketKeepalive,
+    chainhook_store: Arc<RwLock<ChainhookStore>>,
+    observer_commands_tx: Sender<ObserverCommand>,
+    ctx: &Context,
+) {
+    let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
+    let ctx = ctx.clone();
+    let _ = std::thread::spawn(move || {
+        ctx.try_log(|logger| slog::info!(logger, "Serving WebSocket subscriptions on {}", address));
+        let ctx_moved = ctx.clone();
+        let res: Result<(), std::io::Error> = hiro_system_kit::nestable_block_on(async move {
+            let listener = TcpListener::bind(address).await?;
+            loop {
+                let (stream, _) = listener.accept().await?;
+                let chainhook_store = chainhook_store.clone();
+                let observer_commands_tx = observer_commands_tx.clone();
+                let ctx = ctx_moved.clone();
+                tokio::spawn(async move {
+                    handle_connection(
+                        stream,
+                        keepalive,
+                        chainhook_store,
+                        observer_commands_tx,
+                        ctx,
+                    )
+                    .await
+                });
+            }
+        });
+        if let Err(e) = res {
+            ctx.try_log(|logger| slog::error!(logger, "WebSocket server stopped: {}", e));
+        }
+    });
+}
+
+#[cfg(feature = "websocket")]
+async fn handle_connection(
+    stream: TcpStream,
+    keepalive: WebSocketKeepalive,
+    chainhook_store: Arc<RwLock<ChainhookStore>>,
+    observer_commands_tx: Sender<ObserverCommand>,
+    ctx: Context,
+) {
+    let mut api_key = None;
+    let authorize = |request: &Request, response: Response| {
+        let key = request
+            .headers()
+            .get("x-api-key")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let authorized = chainhook_store
+            .read()
+            .map(|store| store.is_authorized(key.clone()))
+            .unwrap_or(false);
+        if authorized {
+            api_key = Some(ApiKey(key));
+            Ok(response)
+        } else {
+            let mut error = ErrorResponse::new(Some("invalid api key".into()));
+            *error.status_mut() = StatusCode::UNAUTHORIZED;
+            Err(error)
+        }
+    };
+    let socket = match tokio_tungstenite::accept_hdr_async(stream, authorize).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            ctx.try_log(|logger| slog::warn!(logger, "WebSocket handshake failed: {}", e));
+            return;
+        }
+    };
+    let api_key = match api_key {
+        Some(api_key) => api_key,
+        None => return,
+    };
+
+    let (mut sink, mut frames) = socket.split();
+    let (occurrences_tx, mut occurrences_rx) = mpsc::channel(OCCURRENCES_BUFFER_SIZE);
+    let subscription = Subscription {
+        occurrences_tx,
+        overflow: Arc::new(Notify::new()),
+    };
+    let mut registered_predicates = vec![];
+    let mut ping_interval = tokio::time::interval(keepalive.ping_interval);
+    let mut last_seen = Instant::now();
+    loop {
+        tokio::select! {
+            frame = frames.next() => {
+                let frame = match frame {
+                    Some(Ok(frame)) => frame,
+                    _ => break,
+                };
+                last_seen = Instant::now();
+                match frame {
+                    Message::Text(text) => {
+                        let status = register_predicate(
+                            &text,
+                            &api_key,
+                            &subscription,
+                            &chainhook_store,
+                            &mut registered_predicates,
+                            &observer_commands_tx,
+                            &ctx,
+                        );
+                        if sink.send(Message::Text(status.to_string())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Message::Close(_) => break,
+                    _ => {}
+                }
+            }
+            Some(payload) = occurrences_rx.recv() => {
+                let payload = String::from_utf8_lossy(&payload).to_string();
+                if sink.send(Message::Text(payload)).await.is_err() {
+                    break;
+                }
+            }
+            _ = subscription.overflow.notified() => {
+                ctx.try_log(|logger| {
+                    slog::warn!(logger, "Closing WebSocket not keeping up with its occurrences")
+                });
+                let close_frame = CloseFrame {
+                    code: CloseCode::Again,
+                    reason: "occurrences not consumed fast enough".into(),
+                };
+                let _ = sink.send(Message::Close(Some(close_frame))).await;
+                break;
+            }
+            _ = ping_interval.tick() => {
+                if last_seen.elapsed() > keepalive.timeout {
+                    ctx.try_log(|logger| slog::info!(logger, "Closing unresponsive WebSocket"));
+                    break;
+                }
+                if sink.send(Message::Ping(vec![])).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    let mut subscriptions = SUBSCRIPTIONS.lock().unwrap_or_else(|e| e.into_inner());
+    for (predicate_uuid, is_bitcoin) in registered_predicates.into_iter() {
+        if let Some(subscriptions) = subscriptions.as_mut() {
+            subscriptions.remove(&predicate_uuid);
+        }
+        ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Deregistering predicate {} registered over closed WebSocket",
+                predicate_uuid
+            )
+        });
+        let command = if is_bitcoin {
+            ObserverCommand::DeregisterBitcoinPredicate(predicate_uuid, api_key.clone())
+        } else {
+            ObserverCommand::DeregisterStacksPredicate(predicate_uuid, api_key.clone())
+        };
+        let _ = observer_commands_tx.send(command);
+    }
+}
+
+/// Registers the predicate specified by a text frame, its occurrences being streamed to
+/// `subscription`, and returns the status frame answered. Predicates already registered, over
+/// a WebSocket or not, are rejected: closing the socket deregisters its predicates.
+#[cfg(feature = "websocket")]
+fn register_predicate(
+    text: &str,
+    api_key: &ApiKey,
+    subscription: &Subscription,
+    chainhook_store: &Arc<RwLock<ChainhookStore>>,
+    registered_predicates: &mut Vec<(String, bool)>,
+    observer_commands_tx: &Sender<ObserverCommand>,
+    ctx: &Context,
+) -> JsonValue {
+    let mut spec: ChainhookFullSpecification = match serde_json::from_str(text) {
+        Ok(spec) => spec,
+        Err(e) => {
+            return json!({
+                "status": 422,
+                "error": format!("unable to parse predicate: {}", e.to_string()),
+            })
+        }
+    };
+    let (predicate_uuid, is_bitcoin) = match spec {
+        ChainhookFullSpecification::Bitcoin(ref mut data) => {
+            for (_, network_spec) in data.networks.iter_mut() {
+                network_spec.action = HookAction::WebSocket;
+            }
+            (data.uuid.clone(), true)
+        }
+        ChainhookFullSpecification::Stacks(ref mut data) => {
+            for (_, network_spec) in data.networks.iter_mut() {
+                network_spec.action = HookAction::WebSocket;
+            }
+            (data.uuid.clone(), false)
+        }
+    };
+    if let Err(e) = spec.validate() {
+        return json!({
+            "status": 422,
+            "error": e,
+        });
+    }
+    let already_registered = chainhook_store
+        .read()
+        .map(|store| store.is_registered(&predicate_uuid))
+        .unwrap_or(true);
+    {
+        let mut subscriptions = SUBSCRIPTIONS.lock().unwrap_or_else(|e| e.into_inner());
+        let subscriptions = subscriptions.get_or_insert_with(HashMap::new);
+        if already_registered || subscriptions.contains_key(&predicate_uuid) {
+            return json!({
+                "status": 409,
+                "error": format!("predicate {} already registered", predicate_uuid),
+            });
+        }
+        subscriptions.insert(predicate_uuid.clone(), subscription.clone());
+    }
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "Registering predicate {} over WebSocket",
+            predicate_uuid
+        )
+    });
+    registered_predicates.push((predicate_uuid.clone(), is_bitcoin));
+    let _ = observer_commands_tx.send(ObserverCommand::RegisterPredicate(spec, api_key.clone()));
+    json!({
+        "status": 200,
+        "result": {
+            "uuid": predicate_uuid,
+        },
+    })
+}
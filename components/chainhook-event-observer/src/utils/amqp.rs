@@ -0,0 +1,230 @@
+#[cfg(feature = "amqp")]
+use std::collections::HashMap;
+#[cfg(feature = "amqp")]
+use std::sync::Mutex;
+
+use chainhook_types::BlockIdentifier;
+use hiro_system_kit::slog;
+#[cfg(feature = "amqp")]
+use lapin::{
+    options::{BasicPublishOptions, ConfirmSelectOptions},
+    BasicProperties, Channel, Connection, ConnectionProperties,
+};
+use regex::Regex;
+
+use super::Context;
+use crate::chainhooks::types::AmqpHook;
+
+/// Placeholders available in the exchange and routing key templates of an `amqp` action.
+pub const AMQP_TEMPLATE_PLACEHOLDERS: [&str; 6] = [
+    "predicate_uuid",
+    "predicate_name",
+    "chain",
+    "network",
+    "block_height",
+    "block_hash",
+];
+
+/// Values substituted to the placeholders of the templates of an `amqp` action.
+pub struct AmqpTemplateValues<'a> {
+    pub predicate_uuid: &'a str,
+    pub predicate_name: &'a str,
+    pub chain: &'static str,
+    pub network: String,
+    /// Last block applied by the occurrence, `None` for unconfirmed transactions: the block
+    /// placeholders are then rendered as `mempool`.
+    pub block: Option<&'a BlockIdentifier>,
+}
+
+pub fn render_amqp_template(template: &str, values: &AmqpTemplateValues) -> String {
+    let (block_height, block_hash) = match values.block {
+        Some(block) => (block.index.to_string(), block.hash.clone()),
+        None => ("mempool".to_string(), "mempool".to_string()),
+    };
+    template
+        .replace("{predicate_uuid}", values.predicate_uuid)
+        .replace("{predicate_name}", values.predicate_name)
+        .replace("{chain}", values.chain)
+        .replace("{network}", &values.network)
+        .replace("{block_height}", &block_height)
+        .replace("{block_hash}", &block_hash)
+}
+
+pub fn validate_amqp_template(template: &str) -> Result<(), String> {
+    let placeholder = Regex::new(r"\{([^{}]*)\}").unwrap();
+    for captures in placeholder.captures_iter(template) {
+        if !AMQP_TEMPLATE_PLACEHOLDERS.contains(&&captures[1]) {
+            return Err(format!(
+                "unknown placeholder {{{}}} in amqp template {}",
+                &captures[1], template
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Occurrence of a chainhook, to be published to the exchange of its `amqp` action.
+#[derive(Clone, Debug)]
+pub struct AmqpMessage {
+    pub hook: AmqpHook,
+    /// Exchange and routing key, rendered from the templates of the action.
+    pub exchange: String,
+    pub routing_key: String,
+    pub payload: Vec<u8>,
+}
+
+#[cfg(feature = "amqp")]
+struct AmqpChannel {
+    // Kept for the connection to outlive the publications.
+    _connection: Connection,
+    channel: Channel,
+}
+
+/// Channels are shared by the chainhooks publishing to the same broker, and reopened once
+/// their connection is lost.
+#[cfg(feature = "amqp")]
+static CHANNELS: Mutex<Option<HashMap<String, AmqpChannel>>> = Mutex::new(None);
+
+#[cfg(feature = "amqp")]
+async fn get_channel(url: &str) -> Result<Channel, String> {
+    if let Some(entry) = CHANNELS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .get(url)
+    {
+        if entry.channel.status().connected() {
+            return Ok(entry.channel.clone());
+        }
+    }
+    let connection = Connection::connect(url, ConnectionProperties::default())
+        .await
+        .map_err(|e| format!("unable to connect to amqp broker: {}", e.to_string()))?;
+    let channel = connection
+        .create_channel()
+        .await
+        .map_err(|e| format!("unable to open amqp channel: {}", e.to_string()))?;
+    channel
+        .confirm_select(ConfirmSelectOptions::default())
+        .await
+        .map_err(|e| format!("unable to enable amqp confirms: {}", e.to_string()))?;
+    CHANNELS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .insert(
+            url.to_string(),
+            AmqpChannel {
+                _connection: connection,
+                channel: channel.clone(),
+            },
+        );
+    Ok(channel)
+}
+
+pub async fn publish_amqp_message(message: AmqpMessage, ctx: &Context) -> Result<(), ()> {
+    #[cfg(feature = "amqp")]
+    {
+        let channel = match get_channel(&message.hook.url).await {
+            Ok(channel) => channel,
+            Err(e) => {
+                ctx.try_log(|logger| slog::warn!(logger, "{}", e));
+                return Err(());
+            }
+        };
+        let properties = BasicProperties::default()
+            .with_content_type("application/json".into())
+            .with_delivery_mode(2);
+        let res = match channel
+            .basic_publish(
+                &message.exchange,
+                &message.routing_key,
+                BasicPublishOptions::default(),
+                &message.payload,
+                properties,
+            )
+            .await
+        {
+            Ok(confirm) => confirm.await,
+            Err(e) => Err(e),
+        };
+        match res {
+            Ok(confirmation) if confirmation.is_ack() => {
+                ctx.try_log(|logger| {
+                    slog::info!(
+                        logger,
+                        "Occurrence published to amqp exchange {} with routing key {}",
+                        message.exchange,
+                        message.routing_key
+                    )
+                });
+                Ok(())
+            }
+            Ok(_) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "amqp broker rejected message published to exchange {}",
+                        message.exchange
+                    )
+                });
+                Err(())
+            }
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "unable to publish message to amqp exchange {}: {}",
+                        message.exchange,
+                        e.to_string()
+                    )
+                });
+                Err(())
+            }
+        }
+    }
+    #[cfg(not(feature = "amqp"))]
+    {
+        ctx.try_log(|logger| {
+            slog::warn!(
+                logger,
+                "unable to publish message to amqp exchange {}: built without the amqp feature",
+                message.exchange
+            )
+        });
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_templates() {
+        let block = BlockIdentifier {
+            index: 780000,
+            hash: "0xab".into(),
+        };
+        let mut values = AmqpTemplateValues {
+            predicate_uuid: "1",
+            predicate_name: "inscriptions",
+            chain: "bitcoin",
+            network: "mainnet".into(),
+            block: Some(&block),
+        };
+        let template = "{chain}.{network}.{predicate_name}.{block_height}";
+        assert_eq!(
+            render_amqp_template(template, &values),
+            "bitcoin.mainnet.inscriptions.780000"
+        );
+        values.block = None;
+        assert_eq!(
+            render_amqp_template("{predicate_uuid}.{block_hash}", &values),
+            "1.mempool"
+        );
+
+        assert!(validate_amqp_template(template).is_ok());
+        assert!(validate_amqp_template("chainhook.{txid}").is_err());
+    }
+}
@@ -0,0 +1,253 @@
+#[cfg(feature = "kafka")]
+use std::collections::HashMap;
+#[cfg(feature = "kafka")]
+use std::sync::Mutex;
+#[cfg(feature = "kafka")]
+use std::time::Duration;
+
+use hiro_system_kit::slog;
+#[cfg(feature = "kafka")]
+use rdkafka::{
+    config::ClientConfig,
+    producer::{FutureProducer, FutureRecord},
+};
+
+use super::Context;
+use crate::chainhooks::types::KafkaHook;
+
+/// Occurrence of a chainhook, to be produced to the topic of its `kafka` action.
+#[derive(Clone, Debug)]
+pub struct KafkaMessage {
+    pub hook: KafkaHook,
+    /// Hash of the block the occurrence applies, or of the first transaction matched for
+    /// unconfirmed transactions.
+    pub key: String,
+    pub payload: Vec<u8>,
+}
+
+/// Producers batch the messages sent through them and are costly to create: they are shared
+/// by the chainhooks connecting to the same brokers with the same settings.
+#[cfg(feature = "kafka")]
+static PRODUCERS: Mutex<Option<HashMap<String, FutureProducer>>> = Mutex::new(None);
+
+#[cfg(feature = "kafka")]
+fn get_producer(hook: &KafkaHook) -> Result<FutureProducer, String> {
+    let producer_key = format!(
+        "{}|{}|{:?}|{}",
+        hook.brokers.join(","),
+        hook.acks.as_str(),
+        hook.sasl,
+        hook.tls
+    );
+    let mut producers = PRODUCERS.lock().unwrap_or_else(|e| e.into_inner());
+    let producers = producers.get_or_insert_with(HashMap::new);
+    if let Some(producer) = producers.get(&producer_key) {
+        return Ok(producer.clone());
+    }
+    let mut config = ClientConfig::new();
+    config
+        .set("bootstrap.servers", hook.brokers.join(","))
+        .set("acks", hook.acks.as_str())
+        .set("security.protocol", hook.security_protocol());
+    if let Some(ref sasl) = hook.sasl {
+        config
+            .set("sasl.mechanism", sasl.mechanism.as_str())
+            .set("sasl.username", &sasl.username)
+            .set("sasl.password", &sasl.password);
+    }
+    let producer: FutureProducer = config
+        .create()
+        .map_err(|e| format!("unable to create kafka producer: {}", e.to_string()))?;
+    producers.insert(producer_key, producer.clone());
+    Ok(producer)
+}
+
+pub async fn produce_kafka_message(message: KafkaMessage, ctx: &Context) -> Result<(), ()> {
+    #[cfg(feature = "kafka")]
+    {
+        let producer = match get_producer(&message.hook) {
+            Ok(producer) => producer,
+            Err(e) => {
+                ctx.try_log(|logger| slog::warn!(logger, "{}", e));
+                return Err(());
+            }
+        };
+        let record = FutureRecord::to(&message.hook.topic)
+            .key(&message.key)
+            .payload(&message.payload);
+        // Retries are handled by the producer, until the delivery timeout expires.
+        match producer.send(record, Duration::from_secs(5)).await {
+            Ok((partition, offset)) => {
+                ctx.try_log(|logger| {
+                    slog::info!(
+                        logger,
+                        "Occurrence produced to kafka topic {} (partition {}, offset {})",
+                        message.hook.topic,
+                        partition,
+                        offset
+                    )
+                });
+                Ok(())
+            }
+            Err((e, _)) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "unable to produce message to kafka topic {}: {}",
+                        message.hook.topic,
+                        e.to_string()
+                    )
+                });
+                Err(())
+            }
+        }
+    }
+    #[cfg(not(feature = "kafka"))]
+    {
+        ctx.try_log(|logger| {
+            slog::warn!(
+                logger,
+                "unable to produce message to kafka topic {}: built without the kafka feature",
+                message.hook.topic
+            )
+        });
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chainhook_types::BitcoinNetwork;
+
+    use super::*;
+    use crate::chainhooks::bitcoin::{
+        handle_bitcoin_hook_action, BitcoinChainhookOccurrence, BitcoinTriggerChainhook,
+    };
+    use crate::chainhooks::types::{
+        BitcoinChainhookSpecification, BitcoinPredicateType, HookAction, KafkaAcks,
+        KafkaSaslMechanism,
+    };
+    use crate::indexer::tests::helpers::bitcoin_blocks;
+
+    fn kafka_chainhook(action: HookAction) -> BitcoinChainhookSpecification {
+        BitcoinChainhookSpecification {
+            uuid: "kafka".into(),
+            owner_uuid: None,
+            name: "kafka".into(),
+            network: BitcoinNetwork::Regtest,
+            version: 1,
+            start_block: None,
+            end_block: None,
+            expire_after_occurrence: None,
+            predicate: BitcoinPredicateType::Block,
+            action,
+            include_proof: false,
+            include_inputs: false,
+            include_outputs: false,
+            include_witness: false,
+            enabled: true,
+        }
+    }
+
+    fn kafka_message(trigger: BitcoinTriggerChainhook) -> KafkaMessage {
+        match handle_bitcoin_hook_action(trigger, &HashMap::new()) {
+            Ok(BitcoinChainhookOccurrence::Kafka(message)) => message,
+            _ => panic!("expected a kafka occurrence"),
+        }
+    }
+
+    #[test]
+    fn kafka_actions_are_deserialized_with_defaults() {
+        let action: HookAction = serde_json::from_value(json!({
+            "kafka": {
+                "brokers": ["localhost:9092"],
+                "topic": "chainhooks",
+            }
+        }))
+        .unwrap();
+        let hook = match action {
+            HookAction::Kafka(hook) => hook,
+            _ => panic!("expected a kafka action"),
+        };
+        assert_eq!(hook.acks, KafkaAcks::All);
+        assert_eq!(hook.security_protocol(), "plaintext");
+        assert_eq!(
+            serde_json::to_value(&hook).unwrap(),
+            json!({
+                "brokers": ["localhost:9092"],
+                "topic": "chainhooks",
+                "acks": "all",
+                "tls": false,
+            })
+        );
+
+        let action: HookAction = serde_json::from_value(json!({
+            "kafka": {
+                "brokers": ["broker-1:9093", "broker-2:9093"],
+                "topic": "chainhooks",
+                "acks": "leader",
+                "sasl": {
+                    "mechanism": "SCRAM-SHA-256",
+                    "username": "chainhook",
+                    "password": "secret",
+                },
+                "tls": true,
+            }
+        }))
+        .unwrap();
+        let hook = match action {
+            HookAction::Kafka(hook) => hook,
+            _ => panic!("expected a kafka action"),
+        };
+        assert_eq!(hook.acks.as_str(), "1");
+        assert_eq!(
+            hook.sasl.as_ref().map(|sasl| sasl.mechanism),
+            Some(KafkaSaslMechanism::ScramSha256)
+        );
+        assert_eq!(hook.security_protocol(), "sasl_ssl");
+    }
+
+    #[test]
+    fn occurrences_are_keyed_by_their_tip_block() {
+        let chainhook = kafka_chainhook(HookAction::Kafka(KafkaHook {
+            brokers: vec!["localhost:9092".into()],
+            topic: "chainhooks".into(),
+            acks: KafkaAcks::All,
+            sasl: None,
+            tls: false,
+        }));
+        let a1 = bitcoin_blocks::A1(None);
+        let b1 = bitcoin_blocks::B1(Some(a1.clone()));
+        let b2 = bitcoin_blocks::B2(Some(a1.clone()));
+
+        // Occurrences of the same block share a key, and land on the same partition in order
+        let message = kafka_message(BitcoinTriggerChainhook {
+            chainhook: &chainhook,
+            apply: vec![(vec![], &a1), (vec![], &b1)],
+            rollback: vec![],
+        });
+        assert_eq!(message.key, b1.block_identifier.hash);
+        let payload: serde_json::Value = serde_json::from_slice(&message.payload).unwrap();
+        assert_eq!(payload["chainhook"]["uuid"], json!("kafka"));
+        assert_eq!(
+            payload["apply"][1]["block_identifier"]["hash"],
+            json!(b1.block_identifier.hash)
+        );
+
+        let message = kafka_message(BitcoinTriggerChainhook {
+            chainhook: &chainhook,
+            apply: vec![(vec![], &b2)],
+            rollback: vec![(vec![], &b1)],
+        });
+        assert_eq!(message.key, b2.block_identifier.hash);
+
+        let message = kafka_message(BitcoinTriggerChainhook {
+            chainhook: &chainhook,
+            apply: vec![],
+            rollback: vec![(vec![], &b1), (vec![], &a1)],
+        });
+        assert_eq!(message.key, a1.block_identifier.hash);
+    }
+}
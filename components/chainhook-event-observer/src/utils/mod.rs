@@ -1,3 +1,12 @@
+pub mod amqp;
+pub mod kafka;
+pub mod nats;
+pub mod oauth2;
+pub mod pubsub;
+pub mod redis_streams;
+pub mod telemetry;
+pub mod webhooks;
+
 use std::{fs::OpenOptions, io::Write};
 
 use chainhook_types::{
@@ -5,9 +14,15 @@ use chainhook_types::{
     StacksTransactionData,
 };
 use hiro_system_kit::slog::{self, Logger};
-use reqwest::RequestBuilder;
+use reqwest::{RequestBuilder, StatusCode};
 use serde_json::Value as JsonValue;
 
+use self::oauth2::{get_oauth2_token, invalidate_oauth2_token};
+use self::webhooks::WebhookRequest;
+
+/// Log records carry their structured fields under stable keys, for the JSON logs to be
+/// queried: `component` (see `Context::with_component`), `block_height`, `predicate_uuid`
+/// and `duration_ms`.
 #[derive(Clone)]
 pub struct Context {
     pub logger: Option<Logger>,
@@ -34,6 +49,29 @@ impl Context {
     pub fn expect_logger(&self) -> &Logger {
         self.logger.as_ref().unwrap()
     }
+
+    /// Context whose log records carry the part of chainhook emitting them, in `component`.
+    /// Meant for the entry points of the components, which are not nested.
+    pub fn with_component(&self, component: &'static str) -> Context {
+        Context {
+            logger: self
+                .logger
+                .as_ref()
+                .map(|logger| logger.new(slog::o!("component" => component))),
+            tracer: self.tracer,
+        }
+    }
+
+    /// Context whose log records carry the uuid of the predicate they relate to.
+    pub fn with_predicate_uuid(&self, predicate_uuid: &str) -> Context {
+        Context {
+            logger: self
+                .logger
+                .as_ref()
+                .map(|logger| logger.new(slog::o!("predicate_uuid" => predicate_uuid.to_string()))),
+            tracer: self.tracer,
+        }
+    }
 }
 
 pub trait AbstractStacksBlock {
@@ -144,22 +182,58 @@ pub async fn send_request(
     attempts_max: u16,
     attempts_interval_sec: u16,
     ctx: &Context,
+) -> Result<(), ()> {
+    send_webhook_request(
+        WebhookRequest::new(request_builder),
+        attempts_max,
+        attempts_interval_sec,
+        ctx,
+    )
+    .await
+}
+
+/// Sends a delivery, authorized with the oauth2 token of the client when it has one. Tokens
+/// rejected with a `401` are dropped, the next attempt being sent with a new one.
+pub async fn send_webhook_request(
+    webhook_request: WebhookRequest,
+    attempts_max: u16,
+    attempts_interval_sec: u16,
+    ctx: &Context,
 ) -> Result<(), ()> {
     let mut retry = 0;
     loop {
-        let request_builder = match request_builder.try_clone() {
+        let request_builder = match webhook_request.request.try_clone() {
             Some(rb) => rb,
             None => {
                 ctx.try_log(|logger| slog::warn!(logger, "unable to clone request builder"));
                 return Err(());
             }
         };
-        match request_builder.send().await {
-            Ok(res) => {
+        let (request_builder, token) = match webhook_request.oauth2 {
+            Some(ref credentials) => match get_oauth2_token(credentials).await {
+                Ok(token) => (Some(request_builder.bearer_auth(&token)), Some(token)),
+                Err(e) => {
+                    ctx.try_log(|logger| slog::warn!(logger, "{}", e));
+                    (None, None)
+                }
+            },
+            None => (Some(request_builder), None),
+        };
+        let response = match request_builder {
+            Some(request_builder) => Some(request_builder.send().await),
+            None => None,
+        };
+        match response {
+            Some(Ok(res)) => {
                 if res.status().is_success() {
                     ctx.try_log(|logger| slog::info!(logger, "Trigger {} successful", res.url()));
                     return Ok(());
                 } else {
+                    if let (Some(credentials), Some(token), StatusCode::UNAUTHORIZED) =
+                        (&webhook_request.oauth2, &token, res.status())
+                    {
+                        invalidate_oauth2_token(credentials, token).await;
+                    }
                     retry += 1;
                     ctx.try_log(|logger| {
                         slog::warn!(
@@ -171,12 +245,13 @@ pub async fn send_request(
                     });
                 }
             }
-            Err(e) => {
+            Some(Err(e)) => {
                 retry += 1;
                 ctx.try_log(|logger| {
                     slog::warn!(logger, "unable to send request {}", e.to_string())
                 });
             }
+            None => retry += 1,
         }
         if retry >= attempts_max {
             ctx.try_log(|logger| {
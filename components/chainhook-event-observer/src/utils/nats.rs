@@ -0,0 +1,155 @@
+#[cfg(feature = "nats")]
+use std::collections::HashMap;
+#[cfg(feature = "nats")]
+use std::sync::Mutex;
+
+#[cfg(feature = "nats")]
+use async_nats::{header::NATS_MESSAGE_ID, jetstream, Client, ConnectOptions, HeaderMap};
+use hiro_system_kit::slog;
+
+use super::Context;
+use crate::chainhooks::types::NatsHook;
+
+/// Occurrence of a chainhook for a single transaction, to be published to the subject of its
+/// `nats` action.
+#[derive(Clone, Debug)]
+pub struct NatsMessage {
+    pub hook: NatsHook,
+    /// Deduplication id of the message, see `nats_message_id`.
+    pub id: String,
+    pub payload: Vec<u8>,
+}
+
+/// Id of the message notifying the chainhook `predicate_uuid` of a transaction of a block:
+/// JetStream drops the messages published with an id seen within the duplicate window of the
+/// stream. Rollbacks get ids of their own, for a rollback not to be dropped as the duplicate
+/// of the apply it reverts.
+pub fn nats_message_id(
+    predicate_uuid: &str,
+    block_hash: &str,
+    transaction_hash: Option<&str>,
+    rollback: bool,
+) -> String {
+    let mut id = format!(
+        "{}:{}:{}",
+        predicate_uuid,
+        block_hash,
+        transaction_hash.unwrap_or_default()
+    );
+    if rollback {
+        id.push_str(":rollback");
+    }
+    id
+}
+
+/// Connections are shared by the chainhooks publishing to the same server with the same
+/// credentials.
+#[cfg(feature = "nats")]
+static CLIENTS: Mutex<Option<HashMap<String, Client>>> = Mutex::new(None);
+
+#[cfg(feature = "nats")]
+async fn get_client(hook: &NatsHook) -> Result<Client, String> {
+    let client_key = format!("{}|{:?}", hook.url, hook.credentials_file);
+    if let Some(client) = CLIENTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .get(&client_key)
+    {
+        return Ok(client.clone());
+    }
+    let options = match hook.credentials_file {
+        Some(ref path) => ConnectOptions::with_credentials_file(path.into())
+            .await
+            .map_err(|e| format!("unable to read nats credentials: {}", e.to_string()))?,
+        None => ConnectOptions::new(),
+    };
+    let client = options
+        .connect(hook.url.as_str())
+        .await
+        .map_err(|e| format!("unable to connect to nats server: {}", e.to_string()))?;
+    CLIENTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .insert(client_key, client.clone());
+    Ok(client)
+}
+
+pub async fn publish_nats_message(message: NatsMessage, ctx: &Context) -> Result<(), ()> {
+    #[cfg(feature = "nats")]
+    {
+        let client = match get_client(&message.hook).await {
+            Ok(client) => client,
+            Err(e) => {
+                ctx.try_log(|logger| slog::warn!(logger, "{}", e));
+                return Err(());
+            }
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(NATS_MESSAGE_ID, message.id.as_str());
+        let res = match jetstream::new(client)
+            .publish_with_headers(
+                message.hook.subject.clone(),
+                headers,
+                message.payload.into(),
+            )
+            .await
+        {
+            Ok(ack) => ack.await,
+            Err(e) => Err(e),
+        };
+        match res {
+            Ok(ack) => {
+                ctx.try_log(|logger| {
+                    slog::info!(
+                        logger,
+                        "Occurrence {} published to nats subject {} (stream {}, sequence {}, duplicate: {})",
+                        message.id,
+                        message.hook.subject,
+                        ack.stream,
+                        ack.sequence,
+                        ack.duplicate
+                    )
+                });
+                Ok(())
+            }
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "unable to publish message to nats subject {}: {}",
+                        message.hook.subject,
+                        e.to_string()
+                    )
+                });
+                Err(())
+            }
+        }
+    }
+    #[cfg(not(feature = "nats"))]
+    {
+        ctx.try_log(|logger| {
+            slog::warn!(
+                logger,
+                "unable to publish message to nats subject {}: built without the nats feature",
+                message.hook.subject
+            )
+        });
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollbacks_are_not_duplicates_of_applies() {
+        let apply = nats_message_id("uuid", "0xab", Some("0xcd"), false);
+        let rollback = nats_message_id("uuid", "0xab", Some("0xcd"), true);
+        assert_eq!(apply, "uuid:0xab:0xcd");
+        assert_eq!(rollback, "uuid:0xab:0xcd:rollback");
+        assert_eq!(nats_message_id("uuid", "0xab", None, false), "uuid:0xab:");
+    }
+}
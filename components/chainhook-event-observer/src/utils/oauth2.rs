@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bitcoincore_rpc::bitcoin::hashes::{sha256, Hash};
+use reqwest::Client;
+
+use crate::chainhooks::types::OAuth2ClientCredentials;
+
+/// Tokens are refreshed once they expire within this margin, for deliveries and their retries
+/// not to be sent with an expired token.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+/// Lifetime assumed for the tokens issued without `expires_in`.
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(300);
+/// Token requests are given up after this long, for the deliveries waiting on them to be
+/// retried.
+const TOKEN_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+/// Token of a client, locked while it is requested: concurrent deliveries wait for the request
+/// in flight instead of requesting tokens of their own.
+type TokenSlot = Arc<tokio::sync::Mutex<Option<CachedToken>>>;
+
+/// Tokens issued to the `oauth2_client_credentials` actions, shared by the actions requesting
+/// the same client, secret, scopes and audience from the same token endpoint.
+static TOKENS: Mutex<Option<HashMap<String, TokenSlot>>> = Mutex::new(None);
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+/// Access token of the client, requested from the token endpoint with the client credentials
+/// grant when none is cached or the cached one is about to expire.
+pub async fn get_oauth2_token(credentials: &OAuth2ClientCredentials) -> Result<String, String> {
+    let slot = token_slot(credentials);
+    let mut token = slot.lock().await;
+    if let Some(ref cached) = *token {
+        if cached.expires_at > Instant::now() + TOKEN_REFRESH_MARGIN {
+            return Ok(cached.access_token.clone());
+        }
+    }
+    let response = request_oauth2_token(credentials).await?;
+    *token = Some(CachedToken {
+        access_token: response.access_token.clone(),
+        expires_at: Instant::now() + token_lifetime(response.expires_in),
+    });
+    Ok(response.access_token)
+}
+
+/// Drops the cached token of the client when it is the one rejected, for the next delivery to
+/// request a new one. Tokens refreshed in the meantime are kept.
+pub async fn invalidate_oauth2_token(credentials: &OAuth2ClientCredentials, rejected_token: &str) {
+    let slot = token_slot(credentials);
+    let mut token = slot.lock().await;
+    if matches!(*token, Some(ref cached) if cached.access_token == rejected_token) {
+        *token = None;
+    }
+}
+
+fn token_slot(credentials: &OAuth2ClientCredentials) -> TokenSlot {
+    TOKENS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .entry(token_cache_key(credentials))
+        .or_default()
+        .clone()
+}
+
+async fn request_oauth2_token(
+    credentials: &OAuth2ClientCredentials,
+) -> Result<TokenResponse, String> {
+    let mut form = vec![("grant_type", "client_credentials".to_string())];
+    if !credentials.scopes.is_empty() {
+        form.push(("scope", credentials.scopes.join(" ")));
+    }
+    if let Some(ref audience) = credentials.audience {
+        form.push(("audience", audience.clone()));
+    }
+    let client = Client::builder()
+        .timeout(TOKEN_REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("unable to build http client: {}", e.to_string()))?;
+    let response = client
+        .post(&credentials.token_url)
+        .basic_auth(&credentials.client_id, Some(&credentials.client_secret))
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| {
+            format!(
+                "unable to request oauth2 token from {}: {}",
+                credentials.token_url,
+                e.to_string()
+            )
+        })?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "oauth2 token endpoint {} answered with status {}",
+            credentials.token_url,
+            response.status()
+        ));
+    }
+    response.json::<TokenResponse>().await.map_err(|e| {
+        format!(
+            "unable to parse oauth2 token from {}: {}",
+            credentials.token_url,
+            e.to_string()
+        )
+    })
+}
+
+/// Rotated secrets are given tokens of their own. Only a digest of the secret is kept.
+fn token_cache_key(credentials: &OAuth2ClientCredentials) -> String {
+    format!(
+        "{}|{}|{}|{}|{}",
+        credentials.token_url,
+        credentials.client_id,
+        sha256::Hash::hash(credentials.client_secret.as_bytes()),
+        credentials.scopes.join(" "),
+        credentials.audience.as_deref().unwrap_or_default()
+    )
+}
+
+fn token_lifetime(expires_in: Option<u64>) -> Duration {
+    expires_in
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TOKEN_LIFETIME)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Serves `token-1`, `token-2`... on `/token`, counting the tokens issued, and answers the
+    /// other paths with `401` when authorized with `token-1`, `200` otherwise.
+    pub async fn start_test_oauth2_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let tokens_issued = Arc::new(AtomicUsize::new(0));
+        let counter = tokens_issued.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let request = read_test_request(&mut stream).await;
+                    let (status, body) = if request.starts_with("post /token ") {
+                        // Tokens are issued slowly, for concurrent requests to overlap
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        let issued = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                        let body = json!({ "access_token": format!("token-{}", issued) });
+                        ("200 OK", body.to_string())
+                    } else if request.contains("authorization: bearer token-1\r\n") {
+                        ("401 Unauthorized", "{}".to_string())
+                    } else {
+                        ("200 OK", "{}".to_string())
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        (url, tokens_issued)
+    }
+
+    /// Head of the request, once its body was read for the connection to be closed cleanly.
+    async fn read_test_request(stream: &mut tokio::net::TcpStream) -> String {
+        let mut request = vec![];
+        let mut buffer = [0; 4096];
+        loop {
+            let len = stream.read(&mut buffer).await.unwrap_or(0);
+            request.extend_from_slice(&buffer[..len]);
+            let text = String::from_utf8_lossy(&request).to_lowercase();
+            if let Some(head_len) = text.find("\r\n\r\n") {
+                let content_length = text[..head_len]
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .and_then(|value| value.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                if len == 0 || request.len() >= head_len + 4 + content_length {
+                    return text[..head_len + 2].to_string();
+                }
+            } else if len == 0 {
+                return text;
+            }
+        }
+    }
+
+    pub fn test_credentials(url: &str, client_id: &str) -> OAuth2ClientCredentials {
+        OAuth2ClientCredentials {
+            token_url: format!("{}/token", url),
+            client_id: client_id.into(),
+            client_secret: "secret".into(),
+            scopes: vec!["events:write".into()],
+            audience: None,
+        }
+    }
+
+    #[test]
+    fn tokens_are_cached_per_client_secret_and_scopes() {
+        let mut credentials = OAuth2ClientCredentials {
+            token_url: "https://auth.example.com/oauth/token".into(),
+            client_id: "chainhook".into(),
+            client_secret: "secret".into(),
+            scopes: vec!["events:write".into()],
+            audience: None,
+        };
+        let key = token_cache_key(&credentials);
+        assert!(!key.contains("secret"));
+        credentials.client_secret = "rotated".into();
+        let rotated_key = token_cache_key(&credentials);
+        assert_ne!(key, rotated_key);
+        credentials.scopes.push("events:read".into());
+        assert_ne!(rotated_key, token_cache_key(&credentials));
+
+        assert_eq!(token_lifetime(Some(3600)), Duration::from_secs(3600));
+        assert_eq!(token_lifetime(None), DEFAULT_TOKEN_LIFETIME);
+    }
+
+    #[tokio::test]
+    async fn concurrent_deliveries_share_a_token_request() {
+        let (url, tokens_issued) = start_test_oauth2_server().await;
+        let credentials = test_credentials(&url, "single-flight");
+        let tokens =
+            futures::future::join_all((0..8).map(|_| get_oauth2_token(&credentials))).await;
+        assert!(tokens.iter().all(|token| token.as_deref() == Ok("token-1")));
+        assert_eq!(tokens_issued.load(Ordering::SeqCst), 1);
+
+        // Only the token rejected is dropped
+        invalidate_oauth2_token(&credentials, "token-0").await;
+        assert_eq!(get_oauth2_token(&credentials).await.unwrap(), "token-1");
+        invalidate_oauth2_token(&credentials, "token-1").await;
+        assert_eq!(get_oauth2_token(&credentials).await.unwrap(), "token-2");
+        assert_eq!(tokens_issued.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,98 @@
+#[cfg(feature = "pubsub")]
+use gcp_auth::AuthenticationManager;
+use hiro_system_kit::slog;
+#[cfg(feature = "pubsub")]
+use reqwest::Client;
+#[cfg(feature = "pubsub")]
+use tokio::sync::OnceCell;
+
+#[cfg(feature = "pubsub")]
+use super::send_request;
+use super::Context;
+use crate::chainhooks::types::PubSubHook;
+
+pub const PUBSUB_DEFAULT_ENDPOINT: &str = "https://pubsub.googleapis.com";
+
+#[cfg(feature = "pubsub")]
+const PUBSUB_SCOPES: &[&str] = &["https://www.googleapis.com/auth/pubsub"];
+
+/// Occurrence of a chainhook, to be published to the topic of its `pubsub` action.
+#[derive(Clone, Debug)]
+pub struct PubSubMessage {
+    pub hook: PubSubHook,
+    /// Uuid of the predicate: the occurrences of a predicate are delivered in order to the
+    /// subscriptions with message ordering enabled.
+    pub ordering_key: String,
+    pub payload: Vec<u8>,
+}
+
+/// Credentials are looked up once, from the application default credentials, and their
+/// tokens refreshed by the manager.
+#[cfg(feature = "pubsub")]
+static AUTHENTICATION_MANAGER: OnceCell<AuthenticationManager> = OnceCell::const_new();
+
+/// Publishes the occurrence with the REST API of Pub/Sub. Publications are sequential, which
+/// the ordering of the messages sharing an ordering key relies on.
+pub async fn publish_pubsub_message(message: PubSubMessage, ctx: &Context) -> Result<(), ()> {
+    #[cfg(feature = "pubsub")]
+    {
+        let token = match AUTHENTICATION_MANAGER
+            .get_or_try_init(AuthenticationManager::new)
+            .await
+        {
+            Ok(manager) => manager.get_token(PUBSUB_SCOPES).await,
+            Err(e) => Err(e),
+        };
+        let token = match token {
+            Ok(token) => token,
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "unable to retrieve gcp credentials: {}",
+                        e.to_string()
+                    )
+                });
+                return Err(());
+            }
+        };
+        let url = format!(
+            "{}/v1/projects/{}/topics/{}:publish",
+            message
+                .hook
+                .endpoint
+                .as_deref()
+                .unwrap_or(PUBSUB_DEFAULT_ENDPOINT),
+            message.hook.project_id,
+            message.hook.topic
+        );
+        let body = json!({
+            "messages": [{
+                "data": base64::encode(&message.payload),
+                "orderingKey": message.ordering_key,
+            }]
+        });
+        let client = match Client::builder().build() {
+            Ok(client) => client,
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(logger, "unable to build http client: {}", e.to_string())
+                });
+                return Err(());
+            }
+        };
+        let request = client.post(&url).bearer_auth(token.as_str()).json(&body);
+        send_request(request, 3, 1, ctx).await
+    }
+    #[cfg(not(feature = "pubsub"))]
+    {
+        ctx.try_log(|logger| {
+            slog::warn!(
+                logger,
+                "unable to publish message to pubsub topic {}: built without the pubsub feature",
+                message.hook.topic
+            )
+        });
+        Err(())
+    }
+}
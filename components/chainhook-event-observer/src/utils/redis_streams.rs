@@ -0,0 +1,113 @@
+#[cfg(feature = "redis_streams")]
+use std::collections::HashMap;
+#[cfg(feature = "redis_streams")]
+use std::sync::Mutex;
+
+use hiro_system_kit::slog;
+#[cfg(feature = "redis_streams")]
+use redis::aio::MultiplexedConnection;
+
+use super::Context;
+use crate::chainhooks::types::RedisStreamHook;
+
+/// Occurrence of a chainhook, to be appended to the stream of its `redis_stream` action.
+#[derive(Clone, Debug)]
+pub struct RedisStreamMessage {
+    pub hook: RedisStreamHook,
+    pub predicate_uuid: String,
+    /// Hash of the last block applied by the occurrence, `mempool` for unconfirmed
+    /// transactions.
+    pub block_hash: String,
+    pub payload: Vec<u8>,
+}
+
+/// Connections are multiplexed, and shared by the chainhooks writing to the same server.
+#[cfg(feature = "redis_streams")]
+static CONNECTIONS: Mutex<Option<HashMap<String, MultiplexedConnection>>> = Mutex::new(None);
+
+#[cfg(feature = "redis_streams")]
+async fn get_connection(url: &str) -> Result<MultiplexedConnection, String> {
+    if let Some(connection) = CONNECTIONS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .get(url)
+    {
+        return Ok(connection.clone());
+    }
+    let connection = redis::Client::open(url)
+        .map_err(|e| format!("unable to open redis client: {}", e.to_string()))?
+        .get_multiplexed_tokio_connection()
+        .await
+        .map_err(|e| format!("unable to connect to redis server: {}", e.to_string()))?;
+    CONNECTIONS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .insert(url.to_string(), connection.clone());
+    Ok(connection)
+}
+
+/// Appends the occurrence to the stream with `XADD`, trimming the stream to about `max_len`
+/// entries: consumers lagging further behind lose the oldest entries.
+pub async fn append_to_redis_stream(message: RedisStreamMessage, ctx: &Context) -> Result<(), ()> {
+    #[cfg(feature = "redis_streams")]
+    {
+        let mut connection = match get_connection(&message.hook.url).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                ctx.try_log(|logger| slog::warn!(logger, "{}", e));
+                return Err(());
+            }
+        };
+        let res: Result<String, _> = redis::cmd("XADD")
+            .arg(&message.hook.stream)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(message.hook.max_len)
+            .arg("*")
+            .arg("predicate_uuid")
+            .arg(&message.predicate_uuid)
+            .arg("block_hash")
+            .arg(&message.block_hash)
+            .arg("payload")
+            .arg(&message.payload)
+            .query_async(&mut connection)
+            .await;
+        match res {
+            Ok(entry_id) => {
+                ctx.try_log(|logger| {
+                    slog::info!(
+                        logger,
+                        "Occurrence appended to redis stream {} (entry {})",
+                        message.hook.stream,
+                        entry_id
+                    )
+                });
+                Ok(())
+            }
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "unable to append message to redis stream {}: {}",
+                        message.hook.stream,
+                        e.to_string()
+                    )
+                });
+                Err(())
+            }
+        }
+    }
+    #[cfg(not(feature = "redis_streams"))]
+    {
+        ctx.try_log(|logger| {
+            slog::warn!(
+                logger,
+                "unable to append message to redis stream {}: built without the redis_streams feature",
+                message.hook.stream
+            )
+        });
+        Err(())
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(feature = "otel")]
+use opentelemetry::{
+    global::{self, BoxedSpan},
+    trace::{Span, Status, Tracer},
+    KeyValue,
+};
+
+/// Name of the tracer the spans are reported with.
+pub const TRACER_NAME: &str = "chainhook";
+
+/// Stages of the indexing pipeline, from the download of a block to the delivery of the
+/// chainhooks it triggered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipelineStage {
+    BlockDownload,
+    BlockCompression,
+    Traversal,
+    DbWrite,
+    Delivery,
+}
+
+impl PipelineStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PipelineStage::BlockDownload => "block_download",
+            PipelineStage::BlockCompression => "block_compression",
+            PipelineStage::Traversal => "traversal",
+            PipelineStage::DbWrite => "db_write",
+            PipelineStage::Delivery => "delivery",
+        }
+    }
+}
+
+/// Span of a stage of the pipeline, reported to the global OpenTelemetry tracer when the
+/// `otel` feature is enabled, a no-op otherwise. The stages of a block run on different
+/// threads, so their spans are not nested: they carry the height of the block instead,
+/// for the stages of a block to be correlated. The span ends when dropped.
+pub struct StageSpan {
+    #[cfg(feature = "otel")]
+    span: BoxedSpan,
+}
+
+impl StageSpan {
+    pub fn start(stage: PipelineStage) -> StageSpan {
+        #[cfg(feature = "otel")]
+        {
+            StageSpan {
+                span: global::tracer(TRACER_NAME).start(stage.name()),
+            }
+        }
+        #[cfg(not(feature = "otel"))]
+        {
+            let _ = stage;
+            StageSpan {}
+        }
+    }
+
+    pub fn for_block(stage: PipelineStage, block_height: u64) -> StageSpan {
+        let mut span = StageSpan::start(stage);
+        span.set_block_height(block_height);
+        span
+    }
+
+    pub fn set_block_height(&mut self, block_height: u64) {
+        self.set_attribute("block.height", block_height as i64);
+    }
+
+    pub fn set_attribute(&mut self, key: &'static str, value: i64) {
+        #[cfg(feature = "otel")]
+        self.span.set_attribute(KeyValue::new(key, value));
+        #[cfg(not(feature = "otel"))]
+        let _ = (key, value);
+    }
+
+    pub fn record_error(&mut self, error: &str) {
+        #[cfg(feature = "otel")]
+        self.span.set_status(Status::error(error.to_string()));
+        #[cfg(not(feature = "otel"))]
+        let _ = error;
+    }
+}
@@ -0,0 +1,206 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcoincore_rpc::bitcoin::hashes::{
+    hmac::{Hmac, HmacEngine},
+    sha256, Hash, HashEngine,
+};
+use reqwest::{Certificate, Client, Identity, RequestBuilder};
+
+use crate::chainhooks::types::{HttpAuth, HttpHook, OAuth2ClientCredentials};
+
+pub const SIGNATURE_HEADER: &str = "X-Chainhook-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Chainhook-Timestamp";
+
+/// Signature of a delivery, `sha256=` followed by the hex encoded HMAC-SHA256, keyed with the
+/// signing secret of the predicate, of `{timestamp}.{body}`. Signing the timestamp lets
+/// receivers reject the deliveries replayed after their tolerance.
+pub fn webhook_signature(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut engine = HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+    engine.input(timestamp.to_string().as_bytes());
+    engine.input(b".");
+    engine.input(body);
+    format!("sha256={}", Hmac::<sha256::Hash>::from_engine(engine))
+}
+
+/// Adds the timestamp and signature headers of a delivery of `body` to `request`. Retries
+/// send the same headers, the timestamp being the one of the first attempt.
+pub fn sign_webhook_request(request: RequestBuilder, secret: &str, body: &[u8]) -> RequestBuilder {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    request
+        .header(TIMESTAMP_HEADER, timestamp)
+        .header(SIGNATURE_HEADER, webhook_signature(secret, timestamp, body))
+}
+
+/// Delivery of an `http_post` action. Deliveries authorized with oauth2 client credentials are
+/// sent with a bearer token requested for each attempt, for the deliveries queued or retried
+/// for a while not to be sent with an expired token.
+pub struct WebhookRequest {
+    pub request: RequestBuilder,
+    pub oauth2: Option<OAuth2ClientCredentials>,
+}
+
+impl WebhookRequest {
+    pub fn new(request: RequestBuilder) -> WebhookRequest {
+        WebhookRequest {
+            request,
+            oauth2: None,
+        }
+    }
+}
+
+/// Authorizes a delivery of an `http_post` action with the bearer token of its `auth`.
+/// Deliveries of actions without `auth` are left as is.
+pub fn authorize_webhook_request(request: RequestBuilder, http: &HttpHook) -> WebhookRequest {
+    match http.auth {
+        Some(HttpAuth::Bearer { ref token }) => WebhookRequest::new(request.bearer_auth(token)),
+        Some(HttpAuth::OAuth2ClientCredentials(ref credentials)) => WebhookRequest {
+            request,
+            oauth2: Some(credentials.clone()),
+        },
+        None => WebhookRequest::new(request),
+    }
+}
+
+/// Client delivering the occurrences of an `http_post` action, trusting the certificate
+/// authorities of its `ca_bundle_path` on top of the built-in ones, and authenticating with its
+/// client certificate. Files are read on each delivery, for renewed certificates to be picked
+/// up.
+pub fn build_webhook_client(http: &HttpHook) -> Result<Client, String> {
+    let mut builder = Client::builder();
+    if let Some(ref tls) = http.tls {
+        if let Some(ref path) = tls.ca_bundle_path {
+            let bundle = read_tls_file(path)?;
+            let certificates = split_pem_certificates(&String::from_utf8_lossy(&bundle));
+            if certificates.is_empty() {
+                return Err(format!("no certificate found in ca bundle {}", path));
+            }
+            for certificate in certificates.iter() {
+                let certificate = Certificate::from_pem(certificate.as_bytes()).map_err(|e| {
+                    format!("unable to parse ca bundle {}: {}", path, e.to_string())
+                })?;
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        if let (Some(certificate_path), Some(key_path)) =
+            (&tls.client_certificate_path, &tls.client_key_path)
+        {
+            let mut pem = read_tls_file(key_path)?;
+            pem.push(b'\n');
+            pem.extend(read_tls_file(certificate_path)?);
+            let identity = Identity::from_pem(&pem)
+                .map_err(|e| format!("unable to parse client certificate: {}", e.to_string()))?;
+            builder = builder.identity(identity);
+        }
+    }
+    builder
+        .build()
+        .map_err(|e| format!("unable to build http client: {}", e.to_string()))
+}
+
+fn read_tls_file(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("unable to read {}: {}", path, e.to_string()))
+}
+
+/// Certificates of a PEM bundle, one PEM block each.
+fn split_pem_certificates(bundle: &str) -> Vec<String> {
+    bundle
+        .split_inclusive("-----END CERTIFICATE-----")
+        .filter(|block| block.contains("-----BEGIN CERTIFICATE-----"))
+        .map(|block| block.trim().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::utils::oauth2::tests::{start_test_oauth2_server, test_credentials};
+    use crate::utils::{send_webhook_request, Context};
+
+    fn http_hook(auth: Option<HttpAuth>) -> HttpHook {
+        HttpHook {
+            url: "https://api.example.com/hook".into(),
+            authorization_header: "Bearer legacy".into(),
+            signing_secret: None,
+            tls: None,
+            auth,
+        }
+    }
+
+    #[test]
+    fn signatures_cover_body_and_timestamp() {
+        let signature = webhook_signature("secret", 1682000000, b"{}");
+        assert_eq!(
+            signature,
+            "sha256=ff963463ab93ee25c72547bbe3dda1200b3c63a196f282572117b951b3b9b446"
+        );
+        assert_ne!(signature, webhook_signature("secret", 1682000001, b"{}"));
+        assert_ne!(signature, webhook_signature("secret", 1682000000, b"[]"));
+        assert_ne!(signature, webhook_signature("other", 1682000000, b"{}"));
+    }
+
+    #[test]
+    fn split_ca_bundles() {
+        let bundle = "# root\n-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n\n-----BEGIN CERTIFICATE-----\nMIIC\n-----END CERTIFICATE-----\n";
+        assert_eq!(
+            split_pem_certificates(bundle),
+            vec![
+                "# root\n-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----",
+                "-----BEGIN CERTIFICATE-----\nMIIC\n-----END CERTIFICATE-----",
+            ]
+        );
+        assert!(split_pem_certificates("").is_empty());
+    }
+
+    #[test]
+    fn only_auth_is_added_to_requests() {
+        let authorization = |auth| {
+            let http = http_hook(auth);
+            let webhook_request = authorize_webhook_request(Client::new().post(&http.url), &http);
+            let request = webhook_request.request.build().unwrap();
+            (
+                request
+                    .headers()
+                    .get("Authorization")
+                    .map(|value| value.to_str().unwrap().to_string()),
+                webhook_request.oauth2.is_some(),
+            )
+        };
+        assert_eq!(authorization(None), (None, false));
+        assert_eq!(
+            authorization(Some(HttpAuth::Bearer {
+                token: "static".into()
+            })),
+            (Some("Bearer static".to_string()), false)
+        );
+        // OAuth2 tokens are requested when the request is sent
+        assert_eq!(
+            authorization(Some(HttpAuth::OAuth2ClientCredentials(test_credentials(
+                "https://auth.example.com",
+                "chainhook"
+            )))),
+            (None, true)
+        );
+    }
+
+    #[tokio::test]
+    async fn oauth2_tokens_rejected_are_refreshed() {
+        let (url, tokens_issued) = start_test_oauth2_server().await;
+        let webhook_request = WebhookRequest {
+            request: Client::new().post(format!("{}/hook", url)).body("{}"),
+            oauth2: Some(test_credentials(&url, "refresh")),
+        };
+        // `token-1` is rejected, the retry is sent with `token-2`
+        assert!(
+            send_webhook_request(webhook_request, 2, 0, &Context::empty())
+                .await
+                .is_ok()
+        );
+        assert_eq!(tokens_issued.load(Ordering::SeqCst), 2);
+    }
+}
@@ -295,8 +295,26 @@ pub struct BitcoinTransactionMetadata {
     pub outputs: Vec<TxOut>,
     pub stacks_operations: Vec<StacksBaseChainOperation>,
     pub ordinal_operations: Vec<OrdinalOperation>,
+    /// Only populated when the BRC-20 indexing is enabled.
+    #[serde(default)]
+    pub brc20_operations: Vec<Brc20Operation>,
+    #[serde(default)]
+    pub rune_operations: Vec<RuneOperation>,
+    #[serde(default)]
+    pub stamp_operations: Vec<StampOperation>,
+    /// Only populated when a `rare_sats` predicate is registered.
+    #[serde(default)]
+    pub sat_movements: Vec<SatMovementData>,
+    #[serde(default)]
+    pub taproot_spends: Vec<TaprootSpendData>,
     pub proof: Option<String>,
     pub fee: u64,
+    /// Virtual size of the transaction, in vbytes.
+    #[serde(default)]
+    pub vsize: u64,
+    /// Fee paid per vbyte, in satoshis.
+    #[serde(default)]
+    pub fee_rate: f64,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -308,13 +326,15 @@ pub enum OrdinalOperation {
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct OrdinalInscriptionTransferData {
-    pub inscription_number: u64,
+    pub inscription_number: i64,
     pub inscription_id: String,
     pub ordinal_number: u64,
     pub updated_address: Option<String>,
     pub satpoint_pre_transfer: String,
     pub satpoint_post_transfer: String,
     pub post_transfer_output_value: Option<u64>,
+    /// Parent of the transferred inscription, for inscriptions belonging to a collection.
+    pub parent_inscription_id: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -322,7 +342,7 @@ pub struct OrdinalInscriptionRevealData {
     pub content_bytes: String,
     pub content_type: String,
     pub content_length: usize,
-    pub inscription_number: u64,
+    pub inscription_number: i64,
     pub inscription_fee: u64,
     pub inscription_output_value: u64,
     pub inscription_id: String,
@@ -330,8 +350,188 @@ pub struct OrdinalInscriptionRevealData {
     pub ordinal_number: u64,
     pub ordinal_block_height: u64,
     pub ordinal_offset: u64,
+    /// Rarity of the inscribed sat: common, uncommon, rare, epic, legendary or mythic. Empty in
+    /// the payloads serialized before rarities were reported.
+    #[serde(default)]
+    pub ordinal_rarity: String,
     pub transfers_pre_inscription: u32,
     pub satpoint_post_inscription: String,
+    /// CBOR metadata of the envelope, decoded to JSON.
+    pub metadata: Option<serde_json::Value>,
+    /// Parent designated by the envelope, for inscriptions belonging to a collection.
+    pub parent_inscription_id: Option<String>,
+    /// Set for cursed inscriptions, which are numbered from -1 downwards.
+    pub curse_type: Option<OrdinalInscriptionCurseType>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrdinalInscriptionCurseType {
+    /// Revealed in another input than the first one.
+    NotInFirstInput,
+    /// Not the first envelope of its input.
+    NotAtOffsetZero,
+    /// Using an even tag unknown to the protocol.
+    UnrecognizedEvenField,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Brc20Operation {
+    TokenDeployed(Brc20TokenDeployData),
+    TokenMinted(Brc20BalanceData),
+    /// Amount moved from the available balance to the transferable balance of its owner.
+    TransferInscribed(Brc20BalanceData),
+    /// Amount moved once the transfer inscription is sent.
+    TransferSent(Brc20TransferData),
+}
+
+/// BRC-20 amounts are fixed-point: integers scaled by `10^dec`, `dec` being the decimals of
+/// their token (e.g. "1.5" is 1500000000000000000 for a token using 18 decimals).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Brc20TokenDeployData {
+    pub tick: String,
+    #[serde(with = "amount_as_string")]
+    pub max: u128,
+    #[serde(with = "amount_as_string")]
+    pub lim: u128,
+    pub dec: u8,
+    pub address: String,
+    pub inscription_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Brc20BalanceData {
+    pub tick: String,
+    #[serde(with = "amount_as_string")]
+    pub amt: u128,
+    pub address: String,
+    pub inscription_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Brc20TransferData {
+    pub tick: String,
+    #[serde(with = "amount_as_string")]
+    pub amt: u128,
+    pub sender_address: String,
+    pub receiver_address: String,
+    pub inscription_id: String,
+}
+
+/// BRC-20 amounts are serialized as decimal strings, most JSON parsers losing the precision of
+/// integers above 2^53.
+mod amount_as_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(amount: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(amount)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        String::deserialize(deserializer)?
+            .parse::<u128>()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuneOperation {
+    RuneEtched(RuneEtchingData),
+    RuneMinted(RuneMintData),
+    /// Runes allocated to an output of the transaction.
+    RuneTransferred(RuneTransferData),
+    /// Runes sent to an OP_RETURN output, or left unallocated by a cenotaph.
+    RuneBurned(RuneMintData),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RuneEtchingData {
+    /// `block:tx` of the etching transaction.
+    pub rune_id: String,
+    pub name: String,
+    pub spaced_name: String,
+    pub divisibility: u8,
+    pub symbol: Option<String>,
+    pub premine: u128,
+    pub cap: Option<u128>,
+    pub amount: Option<u128>,
+    pub height_start: Option<u64>,
+    pub height_end: Option<u64>,
+    pub offset_start: Option<u64>,
+    pub offset_end: Option<u64>,
+    pub turbo: bool,
+    /// Etched by a malformed runestone: the rune can't be minted.
+    pub cenotaph: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RuneMintData {
+    pub rune_id: String,
+    pub amount: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RuneTransferData {
+    pub rune_id: String,
+    pub amount: u128,
+    pub outpoint: String,
+    pub address: Option<String>,
+}
+
+/// Taproot input of a transaction, classified from its witness.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TaprootSpendData {
+    pub input_index: usize,
+    pub spend_type: TaprootSpendType,
+    /// Only provided for script path spends.
+    pub tapleaf_script: Option<String>,
+    /// Only provided for script path spends.
+    pub tapleaf_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaprootSpendType {
+    KeyPath,
+    ScriptPath,
+}
+
+/// First sat of an outpoint spent by a transaction.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SatMovementData {
+    pub ordinal_number: u64,
+    pub rarity: String,
+    pub input_index: usize,
+    pub outpoint_pre_transfer: String,
+}
+
+/// Data embedded in the bare multisig outputs of a transaction, following the Stamps protocol.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StampOperation {
+    StampRevealed(StampRevealData),
+    Src20(Src20OperationData),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct StampRevealData {
+    /// Guessed from the leading bytes of the content.
+    pub content_type: String,
+    pub content_bytes: String,
+    pub content_length: usize,
+}
+
+/// SRC-20 operation, as described by the JSON content of the stamp.
+/// The operation is not checked against the state of the token.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Src20OperationData {
+    pub op: String,
+    pub tick: String,
+    pub amt: Option<String>,
+    pub max: Option<String>,
+    pub lim: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]